@@ -4,6 +4,52 @@ mod m20250804_005445_categories_table;
 mod m20250811_011544_products_table;
 mod m20250811_024226_add_product_img_url_in_products_table;
 mod m20250819_153433_carts_table;
+mod m20261016_000001_orders_table;
+mod m20261016_000002_add_stock_quantity_in_products_table;
+mod m20261016_000003_stock_adjustments_table;
+mod m20261016_000004_add_parent_id_in_categories_table;
+mod m20261016_000005_add_is_featured_in_products_table;
+mod m20261016_000006_add_unique_user_product_in_carts_table;
+mod m20261016_000007_add_availability_window_in_products_table;
+mod m20261016_000008_add_unit_price_in_carts_table;
+mod m20261016_000009_add_version_in_products_table;
+mod m20261016_000010_add_saved_for_later_in_carts_table;
+mod m20261016_000011_coupons_table;
+mod m20261016_000012_cart_sessions_table;
+mod m20261016_000013_product_images_table;
+mod m20261016_000014_product_variants_table;
+mod m20261016_000015_add_variant_id_in_carts_table;
+mod m20261016_000016_add_search_vector_in_products_table;
+mod m20261016_000017_add_slug_in_products_table;
+mod m20261016_000018_order_status_history_table;
+mod m20261016_000019_add_note_in_carts_table;
+mod m20261016_000020_wishlists_table;
+mod m20261016_000021_addresses_table;
+mod m20261016_000022_add_min_subtotal_and_is_active_in_coupons_table;
+mod m20261016_000023_cart_coupons_table;
+mod m20261016_000024_add_product_id_foreign_key_in_carts_table;
+mod m20261016_000025_cart_shares_table;
+mod m20261016_000026_add_view_count_in_products_table;
+mod m20261016_000027_cart_deletions_table;
+mod m20261016_000028_vendors_table;
+mod m20261016_000029_add_deleted_at_in_categories_table;
+mod m20261016_000030_add_name_prefix_index_in_products_table;
+mod m20261016_000031_rename_order_statuses;
+mod m20261016_000032_restock_subscriptions_table;
+mod m20261016_000033_add_reason_in_order_status_history_table;
+mod m20261016_000034_price_history_table;
+mod m20261016_000035_product_price_tiers_table;
+mod m20261016_000036_add_order_number_in_orders_table;
+mod m20261016_000037_add_min_order_qty_in_products_table;
+mod m20261016_000038_add_charges_in_orders_table;
+mod m20261016_000039_add_payment_in_orders_table;
+mod m20261016_000040_order_addresses_table;
+mod m20261016_000041_saved_items_table;
+mod m20261016_000042_add_variant_id_in_order_items_table;
+mod m20261016_000043_add_sold_out_in_products_table;
+mod m20261016_000044_set_null_product_id_foreign_key_in_stock_adjustments_table;
+mod m20261016_000045_move_saved_for_later_carts_to_saved_items_table;
+mod m20261016_000046_set_null_product_id_foreign_key_in_price_history_table;
 
 pub struct Migrator;
 
@@ -15,6 +61,52 @@ impl MigratorTrait for Migrator {
             Box::new(m20250811_011544_products_table::Migration),
             Box::new(m20250811_024226_add_product_img_url_in_products_table::Migration),
             Box::new(m20250819_153433_carts_table::Migration),
+            Box::new(m20261016_000001_orders_table::Migration),
+            Box::new(m20261016_000002_add_stock_quantity_in_products_table::Migration),
+            Box::new(m20261016_000003_stock_adjustments_table::Migration),
+            Box::new(m20261016_000004_add_parent_id_in_categories_table::Migration),
+            Box::new(m20261016_000005_add_is_featured_in_products_table::Migration),
+            Box::new(m20261016_000006_add_unique_user_product_in_carts_table::Migration),
+            Box::new(m20261016_000007_add_availability_window_in_products_table::Migration),
+            Box::new(m20261016_000008_add_unit_price_in_carts_table::Migration),
+            Box::new(m20261016_000009_add_version_in_products_table::Migration),
+            Box::new(m20261016_000010_add_saved_for_later_in_carts_table::Migration),
+            Box::new(m20261016_000011_coupons_table::Migration),
+            Box::new(m20261016_000012_cart_sessions_table::Migration),
+            Box::new(m20261016_000013_product_images_table::Migration),
+            Box::new(m20261016_000014_product_variants_table::Migration),
+            Box::new(m20261016_000015_add_variant_id_in_carts_table::Migration),
+            Box::new(m20261016_000016_add_search_vector_in_products_table::Migration),
+            Box::new(m20261016_000017_add_slug_in_products_table::Migration),
+            Box::new(m20261016_000018_order_status_history_table::Migration),
+            Box::new(m20261016_000019_add_note_in_carts_table::Migration),
+            Box::new(m20261016_000020_wishlists_table::Migration),
+            Box::new(m20261016_000021_addresses_table::Migration),
+            Box::new(m20261016_000022_add_min_subtotal_and_is_active_in_coupons_table::Migration),
+            Box::new(m20261016_000023_cart_coupons_table::Migration),
+            Box::new(m20261016_000024_add_product_id_foreign_key_in_carts_table::Migration),
+            Box::new(m20261016_000025_cart_shares_table::Migration),
+            Box::new(m20261016_000026_add_view_count_in_products_table::Migration),
+            Box::new(m20261016_000027_cart_deletions_table::Migration),
+            Box::new(m20261016_000028_vendors_table::Migration),
+            Box::new(m20261016_000029_add_deleted_at_in_categories_table::Migration),
+            Box::new(m20261016_000030_add_name_prefix_index_in_products_table::Migration),
+            Box::new(m20261016_000031_rename_order_statuses::Migration),
+            Box::new(m20261016_000032_restock_subscriptions_table::Migration),
+            Box::new(m20261016_000033_add_reason_in_order_status_history_table::Migration),
+            Box::new(m20261016_000034_price_history_table::Migration),
+            Box::new(m20261016_000035_product_price_tiers_table::Migration),
+            Box::new(m20261016_000036_add_order_number_in_orders_table::Migration),
+            Box::new(m20261016_000037_add_min_order_qty_in_products_table::Migration),
+            Box::new(m20261016_000038_add_charges_in_orders_table::Migration),
+            Box::new(m20261016_000039_add_payment_in_orders_table::Migration),
+            Box::new(m20261016_000040_order_addresses_table::Migration),
+            Box::new(m20261016_000041_saved_items_table::Migration),
+            Box::new(m20261016_000042_add_variant_id_in_order_items_table::Migration),
+            Box::new(m20261016_000043_add_sold_out_in_products_table::Migration),
+            Box::new(m20261016_000044_set_null_product_id_foreign_key_in_stock_adjustments_table::Migration),
+            Box::new(m20261016_000045_move_saved_for_later_carts_to_saved_items_table::Migration),
+            Box::new(m20261016_000046_set_null_product_id_foreign_key_in_price_history_table::Migration),
         ]
     }
 }
@@ -1,4 +1,4 @@
-use sea_orm_migration::{prelude::*, schema::*};
+use sea_orm_migration::prelude::*;
 
 #[derive(DeriveMigrationName)]
 pub struct Migration;
@@ -33,13 +33,5 @@ impl MigrationTrait for Migration {
 #[derive(DeriveIden)]
 enum Products {
     Table,
-    Id,
-    ProductName,
-    Description,
-    Price,
-    Category,
-    IsAvailable,
-    CreatedAt,
-    UpdatedAt,
     ImgUrl, // new field
 }
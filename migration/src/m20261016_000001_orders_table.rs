@@ -0,0 +1,141 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Orders::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Orders::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(string(Orders::UserId))
+                    .col(
+                        ColumnDef::new(Orders::Status)
+                            .string()
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(
+                        ColumnDef::new(Orders::Subtotal)
+                            .decimal_len(12, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Orders::Total)
+                            .decimal_len(12, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Orders::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .col(
+                        ColumnDef::new(Orders::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderItems::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderItems::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OrderItems::OrderId).uuid().not_null())
+                    .col(ColumnDef::new(OrderItems::ProductId).uuid().not_null())
+                    .col(string(OrderItems::ProductName))
+                    .col(
+                        ColumnDef::new(OrderItems::UnitPrice)
+                            .decimal_len(10, 2)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OrderItems::Quantity).integer().not_null())
+                    .col(
+                        ColumnDef::new(OrderItems::LineTotal)
+                            .decimal_len(12, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OrderItems::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_order_items_order_id")
+                            .from(OrderItems::Table, OrderItems::OrderId)
+                            .to(Orders::Table, Orders::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_orders_user_id")
+                    .table(Orders::Table)
+                    .col(Orders::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OrderItems::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Orders::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+    UserId,
+    Status,
+    Subtotal,
+    Total,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum OrderItems {
+    Table,
+    Id,
+    OrderId,
+    ProductId,
+    ProductName,
+    UnitPrice,
+    Quantity,
+    LineTotal,
+    CreatedAt,
+}
@@ -0,0 +1,68 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(StockAdjustments::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(StockAdjustments::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(StockAdjustments::ProductId).uuid().not_null())
+                    .col(ColumnDef::new(StockAdjustments::Delta).integer().not_null())
+                    .col(string(StockAdjustments::Reason))
+                    .col(
+                        ColumnDef::new(StockAdjustments::ResultingQty)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(StockAdjustments::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_stock_adjustments_product_id")
+                            .from(StockAdjustments::Table, StockAdjustments::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StockAdjustments::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum StockAdjustments {
+    Table,
+    Id,
+    ProductId,
+    Delta,
+    Reason,
+    ResultingQty,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
@@ -0,0 +1,74 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Fold duplicate (user_id, product_id) rows into the oldest one, summing quantities
+        db.execute_unprepared(
+            r#"
+            WITH ranked AS (
+                SELECT
+                    id,
+                    ROW_NUMBER() OVER (PARTITION BY user_id, product_id ORDER BY created_at, id) AS rn,
+                    SUM(total_qty) OVER (PARTITION BY user_id, product_id) AS summed_qty,
+                    MAX(updated_at) OVER (PARTITION BY user_id, product_id) AS last_updated_at
+                FROM carts
+            )
+            UPDATE carts c
+            SET total_qty = r.summed_qty, updated_at = r.last_updated_at
+            FROM ranked r
+            WHERE c.id = r.id AND r.rn = 1;
+            "#,
+        )
+        .await?;
+
+        db.execute_unprepared(
+            r#"
+            DELETE FROM carts c
+            USING (
+                SELECT
+                    id,
+                    ROW_NUMBER() OVER (PARTITION BY user_id, product_id ORDER BY created_at, id) AS rn
+                FROM carts
+            ) d
+            WHERE c.id = d.id AND d.rn > 1;
+            "#,
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_carts_user_id_product_id")
+                    .table(Carts::Table)
+                    .col(Carts::UserId)
+                    .col(Carts::ProductId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_carts_user_id_product_id")
+                    .table(Carts::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Carts {
+    Table,
+    UserId,
+    ProductId,
+}
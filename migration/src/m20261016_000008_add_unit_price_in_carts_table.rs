@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Carts::Table)
+                    .add_column(ColumnDef::new(Carts::UnitPrice).decimal_len(10, 2).null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing rows snapshot the price the product has right now
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                UPDATE carts c
+                SET unit_price = p.price
+                FROM products p
+                WHERE c.product_id = p.id;
+                "#,
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Carts::Table)
+                    .modify_column(ColumnDef::new(Carts::UnitPrice).decimal_len(10, 2).not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Carts::Table)
+                    .drop_column(Carts::UnitPrice)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Carts {
+    Table,
+    UnitPrice,
+}
@@ -0,0 +1,75 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Coupons::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Coupons::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(string_uniq(Coupons::Code))
+                    .col(string(Coupons::DiscountType))
+                    .col(
+                        ColumnDef::new(Coupons::Value)
+                            .decimal_len(10, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Coupons::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(Coupons::MaxUses).integer().null())
+                    .col(
+                        ColumnDef::new(Coupons::UsedCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(Coupons::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .col(
+                        ColumnDef::new(Coupons::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Coupons::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Coupons {
+    Table,
+    Id,
+    Code,
+    DiscountType,
+    Value,
+    ExpiresAt,
+    MaxUses,
+    UsedCount,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,45 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CartSessions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CartSessions::UserId)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(string(CartSessions::Token))
+                    .col(
+                        ColumnDef::new(CartSessions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CartSessions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CartSessions {
+    Table,
+    UserId,
+    Token,
+    CreatedAt,
+}
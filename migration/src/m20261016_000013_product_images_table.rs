@@ -0,0 +1,78 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProductImages::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ProductImages::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ProductImages::ProductId).uuid().not_null())
+                    .col(string(ProductImages::Url))
+                    .col(
+                        ColumnDef::new(ProductImages::SortOrder)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(ProductImages::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_product_images_product_id")
+                            .from(ProductImages::Table, ProductImages::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_product_images_product_id")
+                    .table(ProductImages::Table)
+                    .col(ProductImages::ProductId)
+                    .col(ProductImages::SortOrder)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ProductImages::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ProductImages {
+    Table,
+    Id,
+    ProductId,
+    Url,
+    SortOrder,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
@@ -0,0 +1,88 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProductVariants::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ProductVariants::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ProductVariants::ProductId).uuid().not_null())
+                    .col(string(ProductVariants::Name))
+                    .col(string_uniq(ProductVariants::Sku))
+                    .col(ColumnDef::new(ProductVariants::Price).decimal_len(10, 2).null())
+                    .col(
+                        ColumnDef::new(ProductVariants::StockQuantity)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(ProductVariants::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .col(
+                        ColumnDef::new(ProductVariants::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_product_variants_product_id")
+                            .from(ProductVariants::Table, ProductVariants::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_product_variants_product_id")
+                    .table(ProductVariants::Table)
+                    .col(ProductVariants::ProductId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ProductVariants::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ProductVariants {
+    Table,
+    Id,
+    ProductId,
+    Name,
+    Sku,
+    Price,
+    StockQuantity,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
@@ -0,0 +1,99 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Carts::Table)
+                    .add_column(ColumnDef::new(Carts::VariantId).uuid().null())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_carts_variant_id")
+                            .from_tbl(Carts::Table)
+                            .from_col(Carts::VariantId)
+                            .to_tbl(ProductVariants::Table)
+                            .to_col(ProductVariants::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_carts_user_id_product_id")
+                    .table(Carts::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        // One row per (user, product, variant); NULLS NOT DISTINCT keeps base-product rows unique too
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "CREATE UNIQUE INDEX idx_carts_user_id_product_id_variant_id \
+                 ON carts (user_id, product_id, variant_id) NULLS NOT DISTINCT",
+            )
+            .await
+            .map(|_| ())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_carts_user_id_product_id_variant_id")
+                    .table(Carts::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Variant rows would collide with the base-product row under the old index
+        manager
+            .get_connection()
+            .execute_unprepared("DELETE FROM carts WHERE variant_id IS NOT NULL")
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Carts::Table)
+                    .drop_foreign_key(Alias::new("fk_carts_variant_id"))
+                    .drop_column(Carts::VariantId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_carts_user_id_product_id")
+                    .table(Carts::Table)
+                    .col(Carts::UserId)
+                    .col(Carts::ProductId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Carts {
+    Table,
+    UserId,
+    ProductId,
+    VariantId,
+}
+
+#[derive(DeriveIden)]
+enum ProductVariants {
+    Table,
+    Id,
+}
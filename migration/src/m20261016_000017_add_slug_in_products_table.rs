@@ -0,0 +1,96 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Products::Table)
+                    .add_column(ColumnDef::new(Products::Slug).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+
+        // Same rules as `slugify`: lowercase, runs of anything but letters/digits become one dash
+        db.execute_unprepared(
+            r#"
+            UPDATE products
+            SET slug = COALESCE(
+                NULLIF(trim(both '-' from regexp_replace(lower(product_name), '[^[:alnum:]]+', '-', 'g')), ''),
+                'product'
+            );
+            "#,
+        )
+        .await?;
+
+        // Deduplicate with a numeric suffix, oldest product keeps the bare slug
+        db.execute_unprepared(
+            r#"
+            WITH ranked AS (
+                SELECT
+                    id,
+                    slug,
+                    ROW_NUMBER() OVER (PARTITION BY slug ORDER BY created_at, id) AS rn
+                FROM products
+            )
+            UPDATE products p
+            SET slug = r.slug || '-' || r.rn
+            FROM ranked r
+            WHERE p.id = r.id AND r.rn > 1;
+            "#,
+        )
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Products::Table)
+                    .modify_column(ColumnDef::new(Products::Slug).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_products_slug")
+                    .table(Products::Table)
+                    .col(Products::Slug)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_products_slug")
+                    .table(Products::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Products::Table)
+                    .drop_column(Products::Slug)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Slug,
+}
@@ -0,0 +1,73 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderStatusHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderStatusHistory::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OrderStatusHistory::OrderId).uuid().not_null())
+                    .col(string(OrderStatusHistory::FromStatus))
+                    .col(string(OrderStatusHistory::ToStatus))
+                    .col(
+                        ColumnDef::new(OrderStatusHistory::ChangedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_order_status_history_order_id")
+                            .from(OrderStatusHistory::Table, OrderStatusHistory::OrderId)
+                            .to(Orders::Table, Orders::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_order_status_history_order_id")
+                    .table(OrderStatusHistory::Table)
+                    .col(OrderStatusHistory::OrderId)
+                    .col(OrderStatusHistory::ChangedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OrderStatusHistory::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum OrderStatusHistory {
+    Table,
+    Id,
+    OrderId,
+    FromStatus,
+    ToStatus,
+    ChangedAt,
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+}
@@ -0,0 +1,72 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Wishlists::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Wishlists::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(string(Wishlists::UserId))
+                    .col(ColumnDef::new(Wishlists::ProductId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(Wishlists::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_wishlists_product_id")
+                            .from(Wishlists::Table, Wishlists::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_wishlists_user_id_product_id")
+                    .table(Wishlists::Table)
+                    .col(Wishlists::UserId)
+                    .col(Wishlists::ProductId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Wishlists::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Wishlists {
+    Table,
+    Id,
+    UserId,
+    ProductId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
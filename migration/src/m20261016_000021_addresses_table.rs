@@ -0,0 +1,114 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Addresses::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Addresses::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(string(Addresses::UserId))
+                    .col(string(Addresses::Line1))
+                    .col(string_null(Addresses::Line2))
+                    .col(string(Addresses::City))
+                    .col(string(Addresses::Province))
+                    .col(string(Addresses::PostalCode))
+                    .col(
+                        ColumnDef::new(Addresses::IsDefault)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(Addresses::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_addresses_user_id")
+                    .table(Addresses::Table)
+                    .col(Addresses::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Backstop for the "one default per user" rule enforced by the handlers
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "CREATE UNIQUE INDEX idx_addresses_user_id_default ON addresses (user_id) WHERE is_default",
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Orders::Table)
+                    .add_column(ColumnDef::new(Orders::AddressId).uuid().null())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_orders_address_id")
+                            .from_tbl(Orders::Table)
+                            .from_col(Orders::AddressId)
+                            .to_tbl(Addresses::Table)
+                            .to_col(Addresses::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Orders::Table)
+                    .drop_foreign_key(Alias::new("fk_orders_address_id"))
+                    .drop_column(Orders::AddressId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Addresses::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Addresses {
+    Table,
+    Id,
+    UserId,
+    Line1,
+    Line2,
+    City,
+    Province,
+    PostalCode,
+    IsDefault,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    AddressId,
+}
@@ -0,0 +1,47 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Coupons::Table)
+                    .add_column(
+                        ColumnDef::new(Coupons::MinSubtotal)
+                            .decimal_len(10, 2)
+                            .null(),
+                    )
+                    .add_column(
+                        ColumnDef::new(Coupons::IsActive)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Coupons::Table)
+                    .drop_column(Coupons::MinSubtotal)
+                    .drop_column(Coupons::IsActive)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Coupons {
+    Table,
+    MinSubtotal,
+    IsActive,
+}
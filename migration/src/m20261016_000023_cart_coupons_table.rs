@@ -0,0 +1,54 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One coupon per cart, keyed by the cart's owner
+        manager
+            .create_table(
+                Table::create()
+                    .table(CartCoupons::Table)
+                    .if_not_exists()
+                    .col(string(CartCoupons::UserId).primary_key())
+                    .col(ColumnDef::new(CartCoupons::CouponId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(CartCoupons::AppliedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_cart_coupons_coupon_id")
+                            .from(CartCoupons::Table, CartCoupons::CouponId)
+                            .to(Coupons::Table, Coupons::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CartCoupons::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CartCoupons {
+    Table,
+    UserId,
+    CouponId,
+    AppliedAt,
+}
+
+#[derive(DeriveIden)]
+enum Coupons {
+    Table,
+    Id,
+}
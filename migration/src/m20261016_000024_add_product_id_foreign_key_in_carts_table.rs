@@ -0,0 +1,60 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Rows left behind by products deleted before the constraint existed would block it
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"
+                DELETE FROM carts
+                WHERE NOT EXISTS (SELECT 1 FROM products WHERE products.id = carts.product_id)
+                "#,
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Carts::Table)
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_carts_product_id")
+                            .from_tbl(Carts::Table)
+                            .from_col(Carts::ProductId)
+                            .to_tbl(Products::Table)
+                            .to_col(Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Carts::Table)
+                    .drop_foreign_key(Alias::new("fk_carts_product_id"))
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Carts {
+    Table,
+    ProductId,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CartShares::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CartShares::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(string(CartShares::UserId))
+                    .col(string_uniq(CartShares::Token))
+                    .col(
+                        ColumnDef::new(CartShares::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CartShares::RevokedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(CartShares::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_cart_shares_user_id")
+                    .table(CartShares::Table)
+                    .col(CartShares::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CartShares::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CartShares {
+    Table,
+    Id,
+    UserId,
+    Token,
+    ExpiresAt,
+    RevokedAt,
+    CreatedAt,
+}
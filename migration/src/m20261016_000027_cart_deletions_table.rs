@@ -0,0 +1,91 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Copies of removed cart rows, kept briefly so a removal can be undone
+        manager
+            .create_table(
+                Table::create()
+                    .table(CartDeletions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CartDeletions::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(string(CartDeletions::UserId))
+                    .col(ColumnDef::new(CartDeletions::ProductId).uuid().not_null())
+                    .col(ColumnDef::new(CartDeletions::VariantId).uuid().null())
+                    .col(ColumnDef::new(CartDeletions::TotalQty).integer().not_null())
+                    .col(
+                        ColumnDef::new(CartDeletions::UnitPrice)
+                            .decimal_len(10, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CartDeletions::SavedForLater)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(ColumnDef::new(CartDeletions::Note).text().null())
+                    .col(
+                        ColumnDef::new(CartDeletions::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_cart_deletions_product_id")
+                            .from(CartDeletions::Table, CartDeletions::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_cart_deletions_user_id_product_id")
+                    .table(CartDeletions::Table)
+                    .col(CartDeletions::UserId)
+                    .col(CartDeletions::ProductId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CartDeletions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum CartDeletions {
+    Table,
+    Id,
+    UserId,
+    ProductId,
+    VariantId,
+    TotalQty,
+    UnitPrice,
+    SavedForLater,
+    Note,
+    DeletedAt,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
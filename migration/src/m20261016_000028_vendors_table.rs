@@ -0,0 +1,80 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Vendors::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Vendors::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(string_uniq(Vendors::Name))
+                    .col(
+                        ColumnDef::new(Vendors::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Products without a vendor are sold by the store itself
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Products::Table)
+                    .add_column(ColumnDef::new(Products::VendorId).uuid().null())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_products_vendor_id")
+                            .from_tbl(Products::Table)
+                            .from_col(Products::VendorId)
+                            .to_tbl(Vendors::Table)
+                            .to_col(Vendors::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Products::Table)
+                    .drop_foreign_key(Alias::new("fk_products_vendor_id"))
+                    .drop_column(Products::VendorId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Vendors::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Vendors {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    VendorId,
+}
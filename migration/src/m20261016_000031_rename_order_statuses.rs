@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// (old, new) status names; `preparing` has no old equivalent
+const RENAMES: [(&str, &str); 3] = [
+    ("paid", "confirmed"),
+    ("shipped", "out_for_delivery"),
+    ("delivered", "completed"),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (old, new) in RENAMES {
+            rename_status(manager, old, new).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for (old, new) in RENAMES {
+            rename_status(manager, new, old).await?;
+        }
+        // Orders being prepared were already paid for
+        rename_status(manager, "preparing", "paid").await
+    }
+}
+
+async fn rename_status(manager: &SchemaManager<'_>, from: &str, to: &str) -> Result<(), DbErr> {
+    let updates = [
+        Query::update()
+            .table(Orders::Table)
+            .value(Orders::Status, to)
+            .and_where(Expr::col(Orders::Status).eq(from))
+            .to_owned(),
+        Query::update()
+            .table(OrderStatusHistory::Table)
+            .value(OrderStatusHistory::FromStatus, to)
+            .and_where(Expr::col(OrderStatusHistory::FromStatus).eq(from))
+            .to_owned(),
+        Query::update()
+            .table(OrderStatusHistory::Table)
+            .value(OrderStatusHistory::ToStatus, to)
+            .and_where(Expr::col(OrderStatusHistory::ToStatus).eq(from))
+            .to_owned(),
+    ];
+    for update in updates {
+        manager.exec_stmt(update).await?;
+    }
+    Ok(())
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Status,
+}
+
+#[derive(DeriveIden)]
+enum OrderStatusHistory {
+    Table,
+    FromStatus,
+    ToStatus,
+}
@@ -0,0 +1,81 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Shoppers waiting for an out-of-stock product to come back
+        manager
+            .create_table(
+                Table::create()
+                    .table(RestockSubscriptions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RestockSubscriptions::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(string(RestockSubscriptions::UserId))
+                    .col(ColumnDef::new(RestockSubscriptions::ProductId).uuid().not_null())
+                    .col(string(RestockSubscriptions::Email))
+                    .col(
+                        ColumnDef::new(RestockSubscriptions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .col(
+                        ColumnDef::new(RestockSubscriptions::NotifiedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_restock_subscriptions_product_id")
+                            .from(RestockSubscriptions::Table, RestockSubscriptions::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_restock_subscriptions_user_id_product_id")
+                    .table(RestockSubscriptions::Table)
+                    .col(RestockSubscriptions::UserId)
+                    .col(RestockSubscriptions::ProductId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RestockSubscriptions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum RestockSubscriptions {
+    Table,
+    Id,
+    UserId,
+    ProductId,
+    Email,
+    CreatedAt,
+    NotifiedAt,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
@@ -0,0 +1,82 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per product price change, for audits and price tracking
+        manager
+            .create_table(
+                Table::create()
+                    .table(PriceHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PriceHistory::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PriceHistory::ProductId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(PriceHistory::OldPrice)
+                            .decimal_len(10, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PriceHistory::NewPrice)
+                            .decimal_len(10, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(PriceHistory::ChangedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_price_history_product_id")
+                            .from(PriceHistory::Table, PriceHistory::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_price_history_product_id_changed_at")
+                    .table(PriceHistory::Table)
+                    .col(PriceHistory::ProductId)
+                    .col(PriceHistory::ChangedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PriceHistory::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PriceHistory {
+    Table,
+    Id,
+    ProductId,
+    OldPrice,
+    NewPrice,
+    ChangedAt,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
@@ -0,0 +1,80 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Quantity discounts: from `min_qty` units on, each unit costs `unit_price`
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProductPriceTiers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ProductPriceTiers::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ProductPriceTiers::ProductId).uuid().not_null())
+                    .col(ColumnDef::new(ProductPriceTiers::MinQty).integer().not_null())
+                    .col(
+                        ColumnDef::new(ProductPriceTiers::UnitPrice)
+                            .decimal_len(10, 2)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ProductPriceTiers::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_product_price_tiers_product_id")
+                            .from(ProductPriceTiers::Table, ProductPriceTiers::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Two tiers starting at the same quantity would make the price ambiguous
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_product_price_tiers_product_id_min_qty")
+                    .table(ProductPriceTiers::Table)
+                    .col(ProductPriceTiers::ProductId)
+                    .col(ProductPriceTiers::MinQty)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ProductPriceTiers::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ProductPriceTiers {
+    Table,
+    Id,
+    ProductId,
+    MinQty,
+    UnitPrice,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
@@ -0,0 +1,54 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared("CREATE SEQUENCE IF NOT EXISTS order_number_seq")
+            .await?;
+
+        // Numbers come from the sequence at insert time, so concurrent checkouts can't collide.
+        // Existing orders are numbered when the column is added.
+        db.execute_unprepared(
+            "ALTER TABLE orders ADD COLUMN order_number TEXT NOT NULL \
+             DEFAULT ('TP-' || to_char(NOW() AT TIME ZONE 'Asia/Manila', 'YYYY') || '-' \
+             || lpad(nextval('order_number_seq')::text, 6, '0'))",
+        )
+        .await?;
+        db.execute_unprepared("ALTER SEQUENCE order_number_seq OWNED BY orders.order_number")
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_orders_order_number")
+                    .table(Orders::Table)
+                    .col(Orders::OrderNumber)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Dropping the column drops the sequence it owns and the index
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Orders::Table)
+                    .drop_column(Orders::OrderNumber)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    OrderNumber,
+}
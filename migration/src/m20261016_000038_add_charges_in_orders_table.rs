@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The order keeps every charge it was placed with, so reads never recompute them.
+        // Orders placed before this only know their subtotal and total.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Orders::Table)
+                    .add_column(
+                        ColumnDef::new(Orders::Tax)
+                            .decimal_len(12, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(
+                        ColumnDef::new(Orders::DeliveryFee)
+                            .decimal_len(12, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(
+                        ColumnDef::new(Orders::Discount)
+                            .decimal_len(12, 2)
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Orders::Table)
+                    .drop_column(Orders::Tax)
+                    .drop_column(Orders::DeliveryFee)
+                    .drop_column(Orders::Discount)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Tax,
+    DeliveryFee,
+    Discount,
+}
@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Orders placed before payments were tracked count as unpaid cash on delivery
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Orders::Table)
+                    .add_column(
+                        ColumnDef::new(Orders::PaymentStatus)
+                            .string()
+                            .not_null()
+                            .default("unpaid"),
+                    )
+                    .add_column(
+                        ColumnDef::new(Orders::PaymentMethod)
+                            .string()
+                            .not_null()
+                            .default("cod"),
+                    )
+                    .add_column(ColumnDef::new(Orders::PaidAt).timestamp_with_time_zone().null())
+                    .add_column(ColumnDef::new(Orders::PaymentReference).text().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Orders::Table)
+                    .drop_column(Orders::PaymentStatus)
+                    .drop_column(Orders::PaymentMethod)
+                    .drop_column(Orders::PaidAt)
+                    .drop_column(Orders::PaymentReference)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    PaymentStatus,
+    PaymentMethod,
+    PaidAt,
+    PaymentReference,
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Where an order goes, copied at checkout so later address book edits don't change it
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderAddresses::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OrderAddresses::OrderId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(OrderAddresses::RecipientName).text().not_null())
+                    .col(ColumnDef::new(OrderAddresses::Phone).text().not_null())
+                    .col(ColumnDef::new(OrderAddresses::Line1).text().not_null())
+                    .col(ColumnDef::new(OrderAddresses::Barangay).text().not_null())
+                    .col(ColumnDef::new(OrderAddresses::City).text().not_null())
+                    .col(ColumnDef::new(OrderAddresses::Notes).text().null())
+                    .col(
+                        ColumnDef::new(OrderAddresses::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_order_addresses_order_id")
+                            .from(OrderAddresses::Table, OrderAddresses::OrderId)
+                            .to(Orders::Table, Orders::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OrderAddresses::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum OrderAddresses {
+    Table,
+    OrderId,
+    RecipientName,
+    Phone,
+    Line1,
+    Barangay,
+    City,
+    Notes,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+}
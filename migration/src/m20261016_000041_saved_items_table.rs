@@ -0,0 +1,96 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Cart lines set aside by the shopper, kept with their quantity, price and note
+        manager
+            .create_table(
+                Table::create()
+                    .table(SavedItems::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SavedItems::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(string(SavedItems::UserId))
+                    .col(ColumnDef::new(SavedItems::ProductId).uuid().not_null())
+                    .col(ColumnDef::new(SavedItems::VariantId).uuid().null())
+                    .col(ColumnDef::new(SavedItems::TotalQty).integer().not_null())
+                    .col(
+                        ColumnDef::new(SavedItems::UnitPrice)
+                            .decimal_len(10, 2)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(SavedItems::Note).text().null())
+                    .col(
+                        ColumnDef::new(SavedItems::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::cust("NOW()")),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_saved_items_product_id")
+                            .from(SavedItems::Table, SavedItems::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_saved_items_variant_id")
+                            .from(SavedItems::Table, SavedItems::VariantId)
+                            .to(ProductVariants::Table, ProductVariants::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // One row per (user, product, variant), as in carts
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "CREATE UNIQUE INDEX idx_saved_items_user_id_product_id_variant_id \
+                 ON saved_items (user_id, product_id, variant_id) NULLS NOT DISTINCT",
+            )
+            .await
+            .map(|_| ())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SavedItems::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SavedItems {
+    Table,
+    Id,
+    UserId,
+    ProductId,
+    VariantId,
+    TotalQty,
+    UnitPrice,
+    Note,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ProductVariants {
+    Table,
+    Id,
+}
@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // No foreign key, like product_id: order items outlive the variants they were bought as
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OrderItems::Table)
+                    .add_column(ColumnDef::new(OrderItems::VariantId).uuid().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OrderItems::Table)
+                    .drop_column(OrderItems::VariantId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum OrderItems {
+    Table,
+    VariantId,
+}
@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The audit trail outlives the product; deleting it only clears the link
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(StockAdjustments::Table)
+                    .drop_foreign_key(Alias::new("fk_stock_adjustments_product_id"))
+                    .modify_column(ColumnDef::new(StockAdjustments::ProductId).uuid().null())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_stock_adjustments_product_id")
+                            .from_tbl(StockAdjustments::Table)
+                            .from_col(StockAdjustments::ProductId)
+                            .to_tbl(Products::Table)
+                            .to_col(Products::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Adjustments of deleted products can't be linked back
+        manager
+            .get_connection()
+            .execute_unprepared("DELETE FROM stock_adjustments WHERE product_id IS NULL")
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(StockAdjustments::Table)
+                    .drop_foreign_key(Alias::new("fk_stock_adjustments_product_id"))
+                    .modify_column(ColumnDef::new(StockAdjustments::ProductId).uuid().not_null())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_stock_adjustments_product_id")
+                            .from_tbl(StockAdjustments::Table)
+                            .from_col(StockAdjustments::ProductId)
+                            .to_tbl(Products::Table)
+                            .to_col(Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum StockAdjustments {
+    Table,
+    ProductId,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
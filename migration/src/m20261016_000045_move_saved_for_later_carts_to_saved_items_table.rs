@@ -0,0 +1,86 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Lines flagged as saved for later move to `saved_items`, replacing an earlier saved copy
+        // like saving the line again would
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "INSERT INTO saved_items (id, user_id, product_id, variant_id, total_qty, unit_price, note, created_at) \
+                 SELECT id, user_id, product_id, variant_id, total_qty, unit_price, note, updated_at \
+                 FROM carts WHERE saved_for_later \
+                 ON CONFLICT (user_id, product_id, variant_id) DO UPDATE SET \
+                 total_qty = EXCLUDED.total_qty, unit_price = EXCLUDED.unit_price, \
+                 note = EXCLUDED.note, created_at = EXCLUDED.created_at",
+            )
+            .await?;
+        manager
+            .get_connection()
+            .execute_unprepared("DELETE FROM carts WHERE saved_for_later")
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Carts::Table)
+                    .drop_column(Carts::SavedForLater)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CartDeletions::Table)
+                    .drop_column(CartDeletions::SavedForLater)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Moved lines stay in `saved_items`
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CartDeletions::Table)
+                    .add_column(
+                        ColumnDef::new(CartDeletions::SavedForLater)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Carts::Table)
+                    .add_column(
+                        ColumnDef::new(Carts::SavedForLater)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Carts {
+    Table,
+    SavedForLater,
+}
+
+#[derive(DeriveIden)]
+enum CartDeletions {
+    Table,
+    SavedForLater,
+}
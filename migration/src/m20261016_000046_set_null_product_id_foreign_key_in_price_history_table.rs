@@ -0,0 +1,68 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Price changes outlive the product; deleting it only clears the link
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PriceHistory::Table)
+                    .drop_foreign_key(Alias::new("fk_price_history_product_id"))
+                    .modify_column(ColumnDef::new(PriceHistory::ProductId).uuid().null())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_price_history_product_id")
+                            .from_tbl(PriceHistory::Table)
+                            .from_col(PriceHistory::ProductId)
+                            .to_tbl(Products::Table)
+                            .to_col(Products::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Price changes of deleted products can't be linked back
+        manager
+            .get_connection()
+            .execute_unprepared("DELETE FROM price_history WHERE product_id IS NULL")
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PriceHistory::Table)
+                    .drop_foreign_key(Alias::new("fk_price_history_product_id"))
+                    .modify_column(ColumnDef::new(PriceHistory::ProductId).uuid().not_null())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_price_history_product_id")
+                            .from_tbl(PriceHistory::Table)
+                            .from_col(PriceHistory::ProductId)
+                            .to_tbl(Products::Table)
+                            .to_col(Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PriceHistory {
+    Table,
+    ProductId,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
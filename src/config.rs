@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::env;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+use sea_orm::prelude::Decimal;
+
+/// Where the hot cart endpoints keep carts, selected with the `CART_STORE` env var.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CartStoreKind {
+    /// The `carts` table; the default.
+    Postgres,
+    /// Redis, written back to the `carts` table every `CART_STORE_FLUSH_INTERVAL_SECS`.
+    Redis,
+}
+
+/// Runtime settings read from the environment once at startup.
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    /// How long the cached category list is served before hitting the database again.
+    pub category_cache_ttl: Duration,
+    /// Shared secret expected in the `X-Admin-Key` header on admin routes.
+    pub admin_api_key: Option<String>,
+    /// Maximum number of products returned by `GET /products/featured`.
+    pub featured_products_limit: u64,
+    /// Maximum number of products returned by `GET /products/new`.
+    pub new_products_limit: u64,
+    /// Cart rows not updated for this many days are purged by the background cleanup.
+    pub cart_retention_days: i64,
+    /// Whether the background stale cart cleanup runs at all.
+    pub cart_cleanup_enabled: bool,
+    /// How often the background stale cart cleanup runs.
+    pub cart_cleanup_interval: Duration,
+    /// How long a processed `Idempotency-Key` is remembered.
+    pub idempotency_key_ttl: Duration,
+    /// Tax applied to the cart subtotal at checkout, as a fraction (0.12 = 12% VAT).
+    pub tax_rate: Decimal,
+    /// Flat shipping fee charged per order.
+    pub shipping_fee: Decimal,
+    /// Subtotal at or above which shipping is free; `None` disables free shipping.
+    pub free_shipping_threshold: Option<Decimal>,
+    /// Shipping fees of delivery regions that don't use `shipping_fee`, keyed by lowercase region.
+    pub regional_shipping_fees: HashMap<String, Decimal>,
+    /// Largest quantity a single cart line may hold.
+    pub max_cart_item_qty: i32,
+    /// Most distinct products a single cart may hold.
+    pub max_cart_items: usize,
+    /// How long in-flight requests get to finish after a shutdown signal.
+    pub shutdown_timeout: Duration,
+    /// Enables `POST /admin/seed`; leave unset in production.
+    pub allow_seed: bool,
+    /// Page size of paginated lists when the request omits `per_page`.
+    pub default_page_size: u64,
+    /// Largest `per_page` a paginated list accepts; bigger requests are capped.
+    pub max_page_size: u64,
+    /// Requests per minute allowed for each signed-in user; 0 means unlimited.
+    pub user_rate_limit_per_minute: u32,
+    /// Requests per minute allowed for each anonymous client IP; 0 means unlimited.
+    pub anonymous_rate_limit_per_minute: u32,
+    /// Reverse proxies whose `X-Forwarded-For` entries are trusted for the client IP.
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Where carts live; `CART_STORE=redis` also needs `redis_url`.
+    pub cart_store: CartStoreKind,
+    /// Redis connection URL, e.g. `redis://127.0.0.1:6379`.
+    pub redis_url: Option<String>,
+    /// How often carts changed in Redis are written back to Postgres.
+    pub cart_store_flush_interval: Duration,
+    /// Business name printed at the top of order receipts.
+    pub store_name: String,
+    /// Business address printed on order receipts, when set.
+    pub store_address: Option<String>,
+    /// Taxpayer identification number printed on order receipts, when set.
+    pub store_tin: Option<String>,
+}
+
+impl AppConfig {
+    pub fn from_env() -> Self {
+        Self {
+            category_cache_ttl: Duration::from_secs(env_or("CATEGORY_CACHE_TTL_SECS", 60)),
+            admin_api_key: env::var("ADMIN_API_KEY").ok().filter(|key| !key.is_empty()),
+            featured_products_limit: env_or("FEATURED_PRODUCTS_LIMIT", 12),
+            new_products_limit: env_or("NEW_PRODUCTS_LIMIT", 12),
+            cart_retention_days: env_or("CART_RETENTION_DAYS", 30),
+            cart_cleanup_enabled: env_or("CART_CLEANUP_ENABLED", true),
+            // At least a minute, so a typo can't turn it into a busy loop
+            cart_cleanup_interval: Duration::from_secs(env_or("CART_CLEANUP_INTERVAL_SECS", 24 * 60 * 60).max(60)),
+            idempotency_key_ttl: Duration::from_secs(env_or("IDEMPOTENCY_KEY_TTL_SECS", 24 * 60 * 60)),
+            tax_rate: env_or("TAX_RATE", Decimal::new(12, 2)),
+            shipping_fee: env_or("SHIPPING_FEE", Decimal::new(50, 0)),
+            free_shipping_threshold: env::var("FREE_SHIPPING_THRESHOLD")
+                .ok()
+                .and_then(|value| value.trim().parse().ok()),
+            // `region=fee` pairs separated by commas, e.g. `ncr=50,visayas=150`; malformed pairs are ignored
+            regional_shipping_fees: env::var("REGIONAL_SHIPPING_FEES")
+                .map(|fees| {
+                    fees.split(',')
+                        .filter_map(|pair| {
+                            let (region, fee) = pair.split_once('=')?;
+                            let region = region.trim().to_lowercase();
+                            let fee = fee.trim().parse().ok()?;
+                            (!region.is_empty()).then_some((region, fee))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            max_cart_item_qty: env_or("MAX_CART_ITEM_QTY", 99),
+            max_cart_items: env_or("MAX_CART_ITEMS", 50),
+            shutdown_timeout: Duration::from_secs(env_or("SHUTDOWN_TIMEOUT_SECS", 30)),
+            allow_seed: env_or("ALLOW_SEED", false),
+            default_page_size: env_or("DEFAULT_PAGE_SIZE", 20),
+            max_page_size: env_or("MAX_PAGE_SIZE", 100),
+            user_rate_limit_per_minute: env_or("USER_RATE_LIMIT_PER_MINUTE", 120),
+            anonymous_rate_limit_per_minute: env_or("ANONYMOUS_RATE_LIMIT_PER_MINUTE", 60),
+            // Comma-separated IPs; entries that don't parse are ignored
+            trusted_proxies: env::var("TRUSTED_PROXIES")
+                .map(|proxies| proxies.split(',').filter_map(|proxy| proxy.trim().parse().ok()).collect())
+                .unwrap_or_default(),
+            cart_store: match env::var("CART_STORE") {
+                Ok(value) if value.trim().eq_ignore_ascii_case("redis") => CartStoreKind::Redis,
+                _ => CartStoreKind::Postgres,
+            },
+            redis_url: env::var("REDIS_URL").ok().filter(|url| !url.trim().is_empty()),
+            cart_store_flush_interval: Duration::from_secs(env_or("CART_STORE_FLUSH_INTERVAL_SECS", 5).max(1)),
+            store_name: env::var("STORE_NAME")
+                .ok()
+                .filter(|name| !name.trim().is_empty())
+                .unwrap_or_else(|| "TalipapaUp".to_string()),
+            store_address: env::var("STORE_ADDRESS").ok().filter(|address| !address.trim().is_empty()),
+            store_tin: env::var("STORE_TIN").ok().filter(|tin| !tin.trim().is_empty()),
+        }
+    }
+}
+
+/// Reads and parses an env var, falling back to `default` when it is missing or malformed.
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
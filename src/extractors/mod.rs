@@ -0,0 +1,3 @@
+mod pagination;
+
+pub use pagination::*;
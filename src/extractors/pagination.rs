@@ -0,0 +1,89 @@
+use crate::config::AppConfig;
+use crate::models::responses::ErrorResponse;
+use actix_web::dev::Payload;
+use actix_web::error::InternalError;
+use actix_web::{web, Error, FromRequest, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use std::future::{ready, Ready};
+
+/// `page` / `per_page` query parameters, parsed and defaulted the same way for every list endpoint.
+///
+/// `page` is 1-based and defaults to 1. `per_page` defaults to `DEFAULT_PAGE_SIZE` (20) and is
+/// capped at `MAX_PAGE_SIZE` (100). A `page` or `per_page` that isn't a positive integer answers
+/// `400 Bad Request` before the handler runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaginationParams {
+    pub page: u64,
+    pub per_page: u64,
+}
+
+impl PaginationParams {
+    /// The page as a 0-based index, for `Paginator::fetch_page`.
+    pub fn page_index(&self) -> u64 {
+        self.page - 1
+    }
+
+    /// Rows to skip before this page.
+    pub fn offset(&self) -> u64 {
+        self.page_index().saturating_mul(self.per_page)
+    }
+
+    /// This page's slice of an already loaded list; empty past the end.
+    pub fn slice<'a, T>(&self, items: &'a [T]) -> &'a [T] {
+        let start = usize::try_from(self.offset()).unwrap_or(usize::MAX).min(items.len());
+        let end = start.saturating_add(self.per_page as usize).min(items.len());
+        &items[start..end]
+    }
+
+    fn parse(query: &str, config: Option<&AppConfig>) -> Result<Self, String> {
+        let raw = web::Query::<RawPagination>::from_query(query)
+            .map_err(|_| "Invalid pagination parameters.".to_string())?
+            .into_inner();
+        let (default_per_page, max_per_page) = config
+            .map_or((DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE), |config| {
+                (config.default_page_size, config.max_page_size)
+            });
+
+        let positive = |name: &str, value: Option<String>, default: u64| match value {
+            None => Ok(default),
+            Some(value) => match value.trim().parse::<u64>() {
+                Ok(number) if number > 0 => Ok(number),
+                _ => Err(format!("{} must be a positive integer.", name)),
+            },
+        };
+
+        Ok(Self {
+            page: positive("page", raw.page, 1)?,
+            per_page: positive("per_page", raw.per_page, default_per_page)?.min(max_per_page),
+        })
+    }
+}
+
+/// Page size used when `per_page` is omitted and `DEFAULT_PAGE_SIZE` isn't configured.
+pub const DEFAULT_PAGE_SIZE: u64 = 20;
+
+/// Largest `per_page` accepted when `MAX_PAGE_SIZE` isn't configured.
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+#[derive(Deserialize)]
+struct RawPagination {
+    page: Option<String>,
+    per_page: Option<String>,
+}
+
+impl FromRequest for PaginationParams {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let config = req.app_data::<web::Data<AppConfig>>().map(|config| config.get_ref());
+
+        ready(Self::parse(req.query_string(), config).map_err(|detail| {
+            InternalError::from_response(
+                detail.clone(),
+                HttpResponse::BadRequest().json(ErrorResponse { detail }),
+            )
+            .into()
+        }))
+    }
+}
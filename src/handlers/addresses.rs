@@ -0,0 +1,202 @@
+use crate::models::addresses;
+use crate::models::addresses::NewAddress;
+use crate::models::responses::{ErrorResponse, SuccessResponse};
+use crate::services::{authorize_cart_access, create_address, delete_address, fetch_user_addresses, find_user_address, update_address, validate_address};
+use crate::utils::local_datetime;
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use sea_orm::TransactionTrait;
+use uuid::Uuid;
+
+/// Lists a user's delivery addresses.
+///
+/// # Endpoint
+/// `GET /users/{user_id}/addresses`
+///
+/// # Response
+/// - 200 OK: The addresses, default first.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/users/{user_id}/addresses")]
+pub async fn fetch_addresses(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    match fetch_user_addresses(&user_id, db.get_ref()).await {
+        Ok(addresses) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Addresses fetched successfully.".to_string(),
+            data: addresses,
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while fetching addresses: {}", e),
+        }),
+    }
+}
+
+/// Adds a delivery address.
+///
+/// # Endpoint
+/// `POST /users/{user_id}/addresses`
+///
+/// # Request
+/// `{ "line1": "...", "line2": null, "city": "...", "province": "...", "postal_code": "...", "is_default": false }`
+///
+/// # Response
+/// - 201 Created: The new address.
+/// - 400 Bad Request: If a required field is blank.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// A user's first address becomes the default. Setting `is_default` clears the
+/// flag on the user's other addresses in the same transaction.
+#[post("/users/{user_id}/addresses")]
+pub async fn add_address(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    new_address: web::Json<NewAddress>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let new_address = new_address.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    if let Err(detail) = validate_address(&new_address) {
+        return HttpResponse::BadRequest().json(ErrorResponse { detail });
+    }
+
+    let now = local_datetime();
+    let created = db
+        .transaction::<_, addresses::Model, sea_orm::DbErr>(|txn| {
+            let user_id = user_id.clone();
+            Box::pin(async move { create_address(&user_id, new_address, now, txn).await })
+        })
+        .await;
+
+    match created {
+        Ok(address) => HttpResponse::Created().json(SuccessResponse {
+            success: true,
+            message: "Address added successfully.".to_string(),
+            data: address,
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to add address: {}", e),
+        }),
+    }
+}
+
+/// Replaces a delivery address.
+///
+/// # Endpoint
+/// `PUT /users/{user_id}/addresses/{address_id}`
+///
+/// # Response
+/// - 200 OK: The updated address.
+/// - 400 Bad Request: If a required field is blank.
+/// - 404 Not Found: If the address doesn't belong to the user.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// `is_default: true` makes this the user's only default; the current default
+/// can't be unset this way, only replaced by another address.
+#[put("/users/{user_id}/addresses/{address_id}")]
+pub async fn edit_address(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(String, Uuid)>,
+    payload: web::Json<NewAddress>,
+) -> impl Responder {
+    let (user_id, address_id) = path.into_inner();
+    let payload = payload.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    if let Err(detail) = validate_address(&payload) {
+        return HttpResponse::BadRequest().json(ErrorResponse { detail });
+    }
+
+    let updated = db
+        .transaction::<_, Option<addresses::Model>, sea_orm::DbErr>(|txn| {
+            let user_id = user_id.clone();
+            Box::pin(async move {
+                match find_user_address(&user_id, address_id, txn).await? {
+                    Some(existing) => update_address(existing, payload, txn).await.map(Some),
+                    None => Ok(None),
+                }
+            })
+        })
+        .await;
+
+    match updated {
+        Ok(Some(address)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Address updated successfully.".to_string(),
+            data: address,
+        }),
+        Ok(None) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: "Address not found.".to_string(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to update address: {}", e),
+        }),
+    }
+}
+
+/// Deletes a delivery address.
+///
+/// # Endpoint
+/// `DELETE /users/{user_id}/addresses/{address_id}`
+///
+/// # Response
+/// - 200 OK: The address was deleted.
+/// - 404 Not Found: If the address doesn't belong to the user.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// Deleting the default promotes the user's oldest remaining address. Orders
+/// that shipped to the address keep their other details but lose the reference.
+#[delete("/users/{user_id}/addresses/{address_id}")]
+pub async fn remove_address(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(String, Uuid)>,
+) -> impl Responder {
+    let (user_id, address_id) = path.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    let deleted = db
+        .transaction::<_, bool, sea_orm::DbErr>(|txn| {
+            let user_id = user_id.clone();
+            Box::pin(async move {
+                match find_user_address(&user_id, address_id, txn).await? {
+                    Some(existing) => delete_address(existing, txn).await.map(|_| true),
+                    None => Ok(false),
+                }
+            })
+        })
+        .await;
+
+    match deleted {
+        Ok(true) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Address deleted successfully.".to_string(),
+            data: "None",
+        }),
+        Ok(false) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: "Address not found.".to_string(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to delete address: {}", e),
+        }),
+    }
+}
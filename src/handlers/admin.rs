@@ -0,0 +1,485 @@
+use crate::config::AppConfig;
+use crate::extractors::PaginationParams;
+use crate::middleware::require_admin;
+use crate::models::carts::{AbandonedCartsPage, AbandonedCartsQuery, AdminCartResponse, AdminCartsPage, AdminCartsQuery, CartContents, CartListOptions};
+use crate::models::order_items::TopProduct;
+use crate::models::orders::{AdminStatsResponse, OrderStatus, OrderTotals};
+use crate::models::prelude::{OrderItems, Orders, Products};
+use crate::models::products::{CategoryStockValue, CategoryValuation, InventoryValuationResponse, LowStockQuery, ProductsResponse, StockValueTotals};
+use crate::models::responses::{ErrorResponse, SuccessResponse};
+use crate::models::{order_items, orders, products};
+use crate::services::{apply_cart_coupon, fetch_abandoned_cart_page, fetch_all_abandoned_carts, fetch_cart_owners, fetch_cart_with_products, seed_demo_data, write_abandoned_carts_csv, CategoryCache, SeedSummary};
+use crate::utils::{format_money, local_datetime};
+use actix_web::middleware::from_fn;
+use actix_web::http::header::{self, ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use chrono::Duration;
+use sea_orm::prelude::Decimal;
+use sea_orm::sea_query::{Expr, Func, SimpleExpr};
+use sea_orm::{ColumnTrait, EntityTrait, JoinType, QueryFilter, QueryOrder, QuerySelect, RelationTrait, TransactionTrait};
+use sea_orm::Order;
+
+/// Summary figures for the seller dashboard.
+///
+/// # Endpoint
+/// `GET /admin/stats`
+///
+/// # Response
+/// - 200 OK: Total orders, revenue, distinct customers and the top 5 products by quantity.
+/// - 401 Unauthorized: If the admin key is missing or wrong.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// Cancelled orders are excluded from every figure.
+#[get("/admin/stats", wrap = "from_fn(require_admin)")]
+pub async fn get_admin_stats(db: web::Data<sea_orm::DatabaseConnection>) -> impl Responder {
+    let totals = match Orders::find()
+        .select_only()
+        .column_as(Expr::col(orders::Column::Id).count(), "total_orders")
+        .column_as(Expr::col(orders::Column::Total).sum(), "total_revenue")
+        .column_as(Expr::col(orders::Column::UserId).count_distinct(), "distinct_customers")
+        .filter(orders::Column::Status.ne(OrderStatus::Cancelled))
+        .into_model::<OrderTotals>()
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(totals)) => totals,
+        Ok(None) => OrderTotals {
+            total_orders: 0,
+            total_revenue: None,
+            distinct_customers: 0,
+        },
+        Err(e) => {
+            eprintln!("❌ Error computing order stats: {}", e);
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to compute order stats: {}", e),
+            });
+        }
+    };
+
+    let top_products = match OrderItems::find()
+        .select_only()
+        .column(order_items::Column::ProductId)
+        .column(order_items::Column::ProductName)
+        .column_as(
+            Expr::col((order_items::Entity, order_items::Column::Quantity)).sum(),
+            "total_quantity",
+        )
+        .join(JoinType::InnerJoin, order_items::Relation::Orders.def())
+        .filter(orders::Column::Status.ne(OrderStatus::Cancelled))
+        .group_by(order_items::Column::ProductId)
+        .group_by(order_items::Column::ProductName)
+        .order_by(Expr::col((order_items::Entity, order_items::Column::Quantity)).sum(), Order::Desc)
+        .limit(5)
+        .into_model::<TopProduct>()
+        .all(db.get_ref())
+        .await
+    {
+        Ok(top_products) => top_products,
+        Err(e) => {
+            eprintln!("❌ Error computing top products: {}", e);
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to compute top products: {}", e),
+            });
+        }
+    };
+
+    let total_revenue = totals.total_revenue.unwrap_or(Decimal::ZERO);
+
+    HttpResponse::Ok().json(SuccessResponse {
+        success: true,
+        message: "Stats fetched successfully.".to_string(),
+        data: AdminStatsResponse {
+            total_orders: totals.total_orders,
+            total_revenue,
+            total_revenue_display: format_money(f64::try_from(total_revenue).unwrap_or_default()),
+            distinct_customers: totals.distinct_customers,
+            top_products,
+        },
+    })
+}
+
+/// Retail value of the stock on hand, for accounting.
+///
+/// # Endpoint
+/// `GET /admin/inventory/valuation`
+///
+/// # Response
+/// - 200 OK: Units in stock and their value (`stock_quantity * price`, raw and formatted),
+///   overall and per category, most valuable category first.
+/// - 401 Unauthorized: If the admin key is missing or wrong.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// Every product counts, available or not. Variant stock and prices are not included.
+#[get("/admin/inventory/valuation", wrap = "from_fn(require_admin)")]
+pub async fn get_inventory_valuation(db: web::Data<sea_orm::DatabaseConnection>) -> impl Responder {
+    let stock_value = || {
+        SimpleExpr::from(Func::sum(
+            Expr::col(products::Column::StockQuantity).mul(Expr::col(products::Column::Price)),
+        ))
+    };
+
+    let totals = match Products::find()
+        .select_only()
+        .column_as(Expr::col(products::Column::StockQuantity).sum(), "total_units")
+        .column_as(stock_value(), "total_value")
+        .into_model::<StockValueTotals>()
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(totals)) => totals,
+        Ok(None) => StockValueTotals {
+            total_units: None,
+            total_value: None,
+        },
+        Err(e) => {
+            eprintln!("❌ Error computing inventory value: {}", e);
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to compute inventory value: {}", e),
+            });
+        }
+    };
+
+    let categories = match Products::find()
+        .select_only()
+        .column(products::Column::Category)
+        .column_as(Expr::col(products::Column::StockQuantity).sum(), "total_units")
+        .column_as(stock_value(), "total_value")
+        .group_by(products::Column::Category)
+        .order_by(stock_value(), Order::Desc)
+        .order_by_asc(products::Column::Category)
+        .into_model::<CategoryStockValue>()
+        .all(db.get_ref())
+        .await
+    {
+        Ok(categories) => categories,
+        Err(e) => {
+            eprintln!("❌ Error computing inventory value by category: {}", e);
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to compute inventory value by category: {}", e),
+            });
+        }
+    };
+
+    let total_value = totals.total_value.unwrap_or(Decimal::ZERO);
+
+    HttpResponse::Ok().json(SuccessResponse {
+        success: true,
+        message: "Inventory valuation computed successfully.".to_string(),
+        data: InventoryValuationResponse {
+            total_units: totals.total_units.unwrap_or(0),
+            total_value,
+            total_value_display: format_money(f64::try_from(total_value).unwrap_or_default()),
+            categories: categories
+                .into_iter()
+                .map(CategoryValuation::from_stock_value)
+                .collect(),
+        },
+    })
+}
+
+/// Products whose stock is at or below a threshold, lowest stock first.
+///
+/// # Endpoint
+/// `GET /admin/products/low-stock?threshold=N`
+///
+/// # Response
+/// - 200 OK: Matching products (possibly empty). `threshold` defaults to 5.
+/// - 400 Bad Request: If `threshold` is negative.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/admin/products/low-stock", wrap = "from_fn(require_admin)")]
+pub async fn fetch_low_stock_products(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    query: web::Query<LowStockQuery>,
+) -> impl Responder {
+    let threshold = query.threshold.unwrap_or(5);
+    if threshold < 0 {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Threshold cannot be negative.".to_string(),
+        });
+    }
+
+    fetch_products_by_max_stock(db.get_ref(), threshold, "Low-stock products fetched successfully.").await
+}
+
+/// Products with no stock left.
+///
+/// # Endpoint
+/// `GET /admin/products/out-of-stock`
+///
+/// # Response
+/// - 200 OK: Matching products (possibly empty).
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/admin/products/out-of-stock", wrap = "from_fn(require_admin)")]
+pub async fn fetch_out_of_stock_products(db: web::Data<sea_orm::DatabaseConnection>) -> impl Responder {
+    fetch_products_by_max_stock(db.get_ref(), 0, "Out-of-stock products fetched successfully.").await
+}
+
+/// Browses every user's cart, for support staff and abandoned-cart reports.
+///
+/// # Endpoint
+/// `GET /admin/carts?page=1&per_page=20&user_id=&updated_before=&updated_after=`
+///
+/// # Response
+/// - 200 OK: One page of carts, most recently changed first, each with its lines (product
+///   names included) and totals as returned by `GET /carts/{user_id}`, plus `total_carts`.
+///   `updated_before`/`updated_after` (RFC 3339) bound a cart's latest change.
+/// - 400 Bad Request: If `page` or `per_page` isn't a positive integer, or a timestamp is
+///   malformed.
+/// - 401 Unauthorized: If the admin key is missing or wrong.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/admin/carts", wrap = "from_fn(require_admin)")]
+pub async fn fetch_admin_carts(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    query: web::Query<AdminCartsQuery>,
+    pagination: PaginationParams,
+) -> impl Responder {
+    let filter = match query.filter() {
+        Ok(filter) => filter,
+        Err(detail) => return HttpResponse::BadRequest().json(ErrorResponse { detail }),
+    };
+
+    let (owners, total_carts) = match fetch_cart_owners(&filter, pagination.page_index(), pagination.per_page, db.get_ref()).await {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("❌ Error listing carts: {}", e);
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to list carts: {}", e),
+            });
+        }
+    };
+
+    let now = local_datetime();
+    let mut carts = Vec::with_capacity(owners.len());
+    for owner in owners {
+        let loaded = match fetch_cart_with_products(&owner.user_id, &CartListOptions::default(), db.get_ref()).await {
+            Ok(lines) => apply_cart_coupon(&owner.user_id, CartContents::from_lines(lines), now, db.get_ref()).await,
+            Err(e) => Err(e),
+        };
+        match loaded {
+            Ok(cart) => carts.push(AdminCartResponse {
+                user_id: owner.user_id,
+                last_updated_at: owner.last_updated_at,
+                cart,
+            }),
+            Err(e) => {
+                eprintln!("❌ Error fetching cart for {}: {}", owner.user_id, e);
+                return HttpResponse::InternalServerError().json(ErrorResponse {
+                    detail: format!("Failed to fetch carts: {}", e),
+                });
+            }
+        }
+    }
+
+    HttpResponse::Ok().json(SuccessResponse {
+        success: true,
+        message: "Carts fetched successfully.".to_string(),
+        data: AdminCartsPage {
+            page: pagination.page,
+            per_page: pagination.per_page,
+            total_carts,
+            carts,
+        },
+    })
+}
+
+/// Reports registered users' carts that have gone quiet, for the abandoned-cart emails.
+///
+/// # Endpoint
+/// `GET /admin/carts/abandoned?inactive_hours=24&page=1&per_page=50`
+///
+/// # Response
+/// - 200 OK: One page of carts with no change in the last `inactive_hours` (or `hours`),
+///   most valuable first, each with its value and `cart_value_display`, item count, product
+///   names and last `updated_at`. Only
+///   active lines count; guest carts are left out. With `Accept: text/csv`, every matching
+///   cart is returned as CSV instead, same as `GET /admin/carts/abandoned.csv`.
+/// - 400 Bad Request: If `inactive_hours` is not positive or `page` is 0.
+///
+/// `?hours=N` is accepted as an alias for `inactive_hours`.
+/// - 401 Unauthorized: If the admin key is missing or wrong.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/admin/carts/abandoned", wrap = "from_fn(require_admin)")]
+pub async fn fetch_abandoned_carts(
+    req: HttpRequest,
+    db: web::Data<sea_orm::DatabaseConnection>,
+    query: web::Query<AbandonedCartsQuery>,
+) -> impl Responder {
+    let wants_csv = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/csv"));
+    if wants_csv {
+        return abandoned_carts_csv(db.get_ref(), &query).await;
+    }
+
+    let inactive_hours = match abandoned_cart_window(&query) {
+        Ok(hours) => hours,
+        Err(response) => return response,
+    };
+    let page = query.page.unwrap_or(1);
+    if page == 0 {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Page numbers start at 1.".to_string(),
+        });
+    }
+    let per_page = query.per_page.unwrap_or(50).clamp(1, 500);
+    let cutoff = local_datetime() - Duration::hours(inactive_hours);
+
+    match fetch_abandoned_cart_page(cutoff, page - 1, per_page, db.get_ref()).await {
+        Ok((carts, total_carts)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Abandoned carts fetched successfully.".to_string(),
+            data: AbandonedCartsPage {
+                inactive_hours,
+                page,
+                per_page,
+                total_carts,
+                carts,
+            },
+        }),
+        Err(e) => {
+            eprintln!("❌ Error listing abandoned carts: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to list abandoned carts: {}", e),
+            })
+        }
+    }
+}
+
+/// Exports the abandoned cart report as CSV for the mail tool.
+///
+/// # Endpoint
+/// `GET /admin/carts/abandoned.csv?inactive_hours=24`
+///
+/// # Response
+/// - 200 OK: Every matching cart (pagination is ignored), with columns user_id, cart_value,
+///   item_count, product_names, last_updated_at.
+/// - 400 Bad Request: If `inactive_hours` is not positive.
+/// - 401 Unauthorized: If the admin key is missing or wrong.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/admin/carts/abandoned.csv", wrap = "from_fn(require_admin)")]
+pub async fn export_abandoned_carts_csv(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    query: web::Query<AbandonedCartsQuery>,
+) -> impl Responder {
+    abandoned_carts_csv(db.get_ref(), &query).await
+}
+
+fn abandoned_cart_window(query: &AbandonedCartsQuery) -> Result<i64, HttpResponse> {
+    let hours = query.inactive_hours.unwrap_or(24);
+    if hours <= 0 {
+        return Err(HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "inactive_hours must be a positive number of hours.".to_string(),
+        }));
+    }
+    Ok(hours)
+}
+
+async fn abandoned_carts_csv(db: &sea_orm::DatabaseConnection, query: &AbandonedCartsQuery) -> HttpResponse {
+    let inactive_hours = match abandoned_cart_window(query) {
+        Ok(hours) => hours,
+        Err(response) => return response,
+    };
+    let cutoff = local_datetime() - Duration::hours(inactive_hours);
+
+    let body = match fetch_all_abandoned_carts(cutoff, db).await {
+        Ok(carts) => write_abandoned_carts_csv(&carts).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    match body {
+        Ok(body) => HttpResponse::Ok()
+            .content_type("text/csv; charset=utf-8")
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename("abandoned-carts.csv".to_string())],
+            })
+            .body(body),
+        Err(e) => {
+            eprintln!("❌ Error exporting abandoned carts: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to export abandoned carts: {}", e),
+            })
+        }
+    }
+}
+
+/// Fills an empty database with demo data for local development.
+///
+/// # Endpoint
+/// `POST /admin/seed`
+///
+/// # Response
+/// - 200 OK: How many categories, products and cart rows were created. Rows that
+///   already exist (matched by name) are skipped, so repeated calls create nothing.
+/// - 401 Unauthorized: If the admin key is missing or wrong.
+/// - 404 Not Found: Unless `ALLOW_SEED=true` is set.
+/// - 500 Internal Server Error: On database-related failures; nothing is inserted.
+///
+/// The demo cart belongs to the `demo-user` cart id.
+#[post("/admin/seed", wrap = "from_fn(require_admin)")]
+pub async fn seed_database(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    config: web::Data<AppConfig>,
+    cache: web::Data<CategoryCache>,
+) -> impl Responder {
+    if !config.allow_seed {
+        return HttpResponse::NotFound().json(ErrorResponse {
+            detail: "Seeding is disabled. Set ALLOW_SEED=true to enable it.".to_string(),
+        });
+    }
+
+    let now = local_datetime();
+    let max_qty = config.max_cart_item_qty;
+    let seeded = db
+        .transaction::<_, SeedSummary, sea_orm::DbErr>(|txn| {
+            Box::pin(async move { seed_demo_data(now, max_qty, txn).await })
+        })
+        .await;
+
+    match seeded {
+        Ok(summary) => {
+            if summary.categories_created > 0 {
+                cache.invalidate();
+            }
+            HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message: "Demo data seeded.".to_string(),
+                data: summary,
+            })
+        }
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to seed demo data: {}", e),
+        }),
+    }
+}
+
+async fn fetch_products_by_max_stock(
+    db: &sea_orm::DatabaseConnection,
+    max_stock: i32,
+    message: &str,
+) -> HttpResponse {
+    match Products::find()
+        .filter(products::Column::StockQuantity.lte(max_stock))
+        .order_by(products::Column::StockQuantity, Order::Asc)
+        .order_by(products::Column::ProductName, Order::Asc)
+        .all(db)
+        .await
+    {
+        Ok(products) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: message.to_string(),
+            data: products
+                .into_iter()
+                .map(ProductsResponse::from_model)
+                .collect::<Vec<_>>(),
+        }),
+        Err(e) => {
+            eprintln!("❌ Error fetching stock report: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch stock report: {}", e),
+            })
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::models::carts::{CartContents, CartListOptions};
+use crate::models::cart_shares::{CartShareResponse, NewCartShare, SharedCartResponse};
+use crate::models::responses::{ErrorResponse, SuccessResponse};
+use crate::services::{apply_cart_coupon, authorize_cart_access, create_cart_share, find_cart_share_by_token, revoke_cart_share, sync_cart_store, CartBackend, CartStore, DEFAULT_CART_SHARE_HOURS, MAX_CART_SHARE_HOURS};
+use crate::utils::{format_datetime, local_datetime};
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+use uuid::Uuid;
+
+/// Creates a read-only link to a user's cart.
+///
+/// # Endpoint
+/// `POST /carts/{user_id}/share`
+///
+/// # Request
+/// Optional: `{ "expires_in_hours": 72 }`
+///
+/// # Response
+/// - 201 Created: The share `id` (for revoking), its secret `token` and `expires_at`.
+/// - 400 Bad Request: If `expires_in_hours` is not between 1 and 720.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// Anyone holding the token can view the cart through `GET /carts/shared/{token}`
+/// until it expires or is revoked; they can't change it.
+#[post("/carts/{user_id}/share")]
+pub async fn share_cart(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    payload: Option<web::Json<NewCartShare>>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let expires_in_hours = payload
+        .and_then(|payload| payload.expires_in_hours)
+        .unwrap_or(DEFAULT_CART_SHARE_HOURS);
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &user_id).await {
+        return response;
+    }
+
+    if !(1..=MAX_CART_SHARE_HOURS).contains(&expires_in_hours) {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: format!("expires_in_hours must be between 1 and {}.", MAX_CART_SHARE_HOURS),
+        });
+    }
+
+    match create_cart_share(&user_id, expires_in_hours, local_datetime(), db.get_ref()).await {
+        Ok(share) => HttpResponse::Created().json(SuccessResponse {
+            success: true,
+            message: "Cart share link created.".to_string(),
+            data: CartShareResponse::from_model(share),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to create cart share link: {}", e),
+        }),
+    }
+}
+
+/// Shows a shared cart.
+///
+/// # Endpoint
+/// `GET /carts/shared/{token}`
+///
+/// # Response
+/// - 200 OK: The cart in the same shape as `GET /carts/{user_id}`, without the
+///   owner's id, plus the link's `expires_at`.
+/// - 404 Not Found: If no share link has this token.
+/// - 410 Gone: If the link has expired or was revoked.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/carts/shared/{token}")]
+pub async fn get_shared_cart(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let token = path.into_inner();
+    let now = local_datetime();
+
+    let share = match find_cart_share_by_token(&token, db.get_ref()).await {
+        Ok(Some(share)) => share,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: "Shared cart not found.".to_string(),
+            });
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while finding shared cart: {}", e),
+            });
+        }
+    };
+
+    if !share.is_active_at(now) {
+        return HttpResponse::Gone().json(ErrorResponse {
+            detail: "This cart link has expired or was revoked.".to_string(),
+        });
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &share.user_id).await {
+        return response;
+    }
+
+    let cart_contents = match store.cart_lines(&share.user_id, &CartListOptions::default()).await {
+        Ok(carts_responses) => CartContents::from_lines(carts_responses),
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while fetching shared cart: {}", e),
+            });
+        }
+    };
+
+    match apply_cart_coupon(&share.user_id, cart_contents, now, db.get_ref()).await {
+        Ok(cart) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Shared cart fetched successfully.".to_string(),
+            data: SharedCartResponse {
+                cart,
+                expires_at: format_datetime(share.expires_at),
+            },
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while applying cart coupon: {}", e),
+        }),
+    }
+}
+
+/// Revokes a cart share link.
+///
+/// # Endpoint
+/// `DELETE /carts/{user_id}/share/{share_id}`
+///
+/// # Response
+/// - 200 OK: The link was revoked (or already was); it now answers `410 Gone`.
+/// - 404 Not Found: If the user has no share link with this id.
+/// - 500 Internal Server Error: On database-related failures.
+#[delete("/carts/{user_id}/share/{share_id}")]
+pub async fn revoke_shared_cart(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(String, Uuid)>,
+) -> impl Responder {
+    let (user_id, share_id) = path.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    match revoke_cart_share(&user_id, share_id, local_datetime(), db.get_ref()).await {
+        Ok(Some(_)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Cart share link revoked.".to_string(),
+            data: "None",
+        }),
+        Ok(None) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: "Cart share link not found.".to_string(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to revoke cart share link: {}", e),
+        }),
+    }
+}
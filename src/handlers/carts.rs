@@ -1,31 +1,175 @@
-use sea_orm::{FromQueryResult, ModelTrait};
-use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, Set, Statement, TryGetableMany};
+use sea_orm::{ColumnTrait, TransactionTrait};
 use sea_orm::QueryFilter;
+use actix_web::middleware::from_fn;
+use actix_web::http::StatusCode;
 use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
 use sea_orm::EntityTrait;
-use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::prelude::{DateTimeWithTimeZone, Decimal};
+use std::collections::HashMap;
 use serde_json::json;
-use uuid::Uuid;
-use crate::models::carts::{CartsResponse, NewCart};
+use crate::config::AppConfig;
+use crate::middleware::{has_admin_key, require_admin};
+use crate::models::carts::{BatchCartQuery, BatchItemOutcome, BatchItemStatus, BulkRemoveCartItems, BulkRemovedCartItems, CartAddMode, CartAddResult, CartContents, CartEventKind, CartItemQuery, CartItemUpdateResult, CartLineStatus, CartListOptions, CartListQuery, CartValidationQuery, CartValidationResponse, CheckoutSummaryQuery, CheckoutSummaryResponse, CartUpdateMode, MergeCarts, NewCart, NewCartBatch, RemovedCartItem, StaleCartsPurged, StaleCartsQuery, UpdateCartItem};
+use crate::models::cart_sessions::{CartSessionResponse, NewCartSession};
 use crate::models::carts;
-use crate::models::prelude::{Carts, Products};
-use crate::models::products;
-use crate::models::products::ProductsResponse;
-use crate::models::responses::{ErrorResponse, SuccessResponse};
-use crate::services::{create_new_cart_item, find_existing_cart_item, update_cart_quantity, validate_product_exists};
+use crate::models::prelude::Products;
+use crate::models::responses::{ErrorResponse, SuccessResponse, SuccessResponseWithMeta};
+use crate::services::{apply_cart_coupon, find_latest_cart_deletion, remove_cart_item_with_undo, restore_cart_deletion, CART_RESTORE_WINDOW_MINUTES, cart_items_limit_detail, check_cart_items_limit, fetch_cart_product_ids, remove_cart_items, check_purchasable, check_quantity_limit, clear_cart, compute_checkout_summary, create_new_cart_item, fetch_cart_total, CheckoutRates, delete_stale_carts, fetch_cart_with_products, find_existing_cart_item, find_product_by_id, fix_cart_lines, merge_cart_items, normalize_cart_note, remove_cart_item, set_cart_note, set_cart_quantity, update_cart_quantity, validate_cart_line_purchasable, validate_cart_lines, validate_product_exists, validate_product_purchasable};
+use crate::services::{authorize_cart_access, authorize_cart_token, create_cart_session, issue_user_cart_session, publish_cart_event, publish_cart_store_event, sync_cart_store, CartAddOutcome, CartBackend, CartEventHub, CartStore, TARGET_CART_TOKEN_HEADER};
+use crate::services::{IdempotencyCheck, IdempotencyStore, IDEMPOTENCY_KEY_HEADER};
 use crate::utils::local_datetime;
+use uuid::Uuid;
+
+/// Starts a guest cart, or issues the cart token of a signed-in user.
+///
+/// # Endpoint
+/// `POST /carts/session`
+///
+/// # Request
+/// No body for a guest cart. Trusted servers send `{ "user_id": "..." }` with the
+/// `X-Admin-Key` header to get the token of a signed-in user's cart, e.g. at login.
+///
+/// # Response
+/// - 201 Created: A random cart `user_id` and its secret `token`.
+/// - 200 OK: The signed-in user's `user_id` and cart `token`; the same token on every call.
+/// - 400 Bad Request: If `user_id` is empty.
+/// - 403 Forbidden: If `user_id` is sent without the admin key.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// Once a cart has a session, every cart endpoint requires its token in the
+/// `X-Cart-Token` header and answers `403 Forbidden` without it; carts of users who were
+/// never issued a token stay open. Once the shopper logs in, `POST /carts/merge` moves
+/// the guest cart into their own.
+#[post("/carts/session")]
+pub async fn create_guest_cart_session(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    payload: Option<web::Json<NewCartSession>>,
+) -> impl Responder {
+    let now = local_datetime();
+    let Some(user_id) = payload.and_then(|payload| payload.into_inner().user_id) else {
+        return match create_cart_session(now, db.get_ref()).await {
+            Ok(session) => HttpResponse::Created().json(SuccessResponse {
+                success: true,
+                message: "Guest cart created successfully.".to_string(),
+                data: CartSessionResponse {
+                    user_id: session.user_id,
+                    token: session.token,
+                },
+            }),
+            Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to create guest cart: {}", e),
+            }),
+        };
+    };
+
+    if !has_admin_key(&req) {
+        return HttpResponse::Forbidden().json(ErrorResponse {
+            detail: "Only trusted servers can issue a cart token for a user id.".to_string(),
+        });
+    }
+    let user_id = user_id.trim();
+    if user_id.is_empty() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "user_id must not be empty.".to_string(),
+        });
+    }
 
+    match issue_user_cart_session(user_id, now, db.get_ref()).await {
+        Ok(session) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Cart token issued successfully.".to_string(),
+            data: CartSessionResponse {
+                user_id: session.user_id,
+                token: session.token,
+            },
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to issue cart token: {}", e),
+        }),
+    }
+}
+
+/// Adds a product to a user's cart, or increases its quantity if it's already there.
+///
+/// Pass `variant_id` to add a specific variant; its stock is checked and its price
+/// (or the product price, when it has no override) is snapshotted. Each variant is
+/// its own cart line.
+///
+/// An optional `note` (up to 250 characters) carries preparation instructions; adding
+/// to an existing line keeps its note unless a new one is sent.
+///
+/// `mode` decides what happens when the line already exists: `add` (default) sums the
+/// quantities, `set` replaces the quantity with `total_qty`. Each returned line carries
+/// the `mode`, its `previous_qty` and the resulting `total_qty`.
+///
+/// A cart holds at most `MAX_CART_ITEMS` distinct products (default 50). Adding a new
+/// product beyond that answers `400 Bad Request` with the limit in `max_items`; lines
+/// already in the cart can always be increased.
+///
+/// Products sold in packs have a `min_order_qty`; a line that would end up with fewer
+/// units answers `400 Bad Request` with the required minimum in `min_order_qty`.
+///
+/// # Idempotency
+/// Send an `Idempotency-Key` header to make retries safe: a repeated key for the
+/// same user returns the original response (with `Idempotent-Replayed: true`)
+/// without touching the cart again. A retry that arrives while the first request
+/// is still running gets `409 Conflict`. Server errors are not remembered.
 #[post("/carts/")]
 pub async fn add_to_cart(
     db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    idempotency: web::Data<IdempotencyStore>,
+    events: web::Data<CartEventHub>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
     new_cart: web::Json<NewCart>,
 ) -> impl Responder {
-    let now: DateTimeWithTimeZone = local_datetime();
+    if let Err(response) = authorize_cart_access(&new_cart.user_id.to_string(), &req, db.get_ref()).await {
+        return response;
+    }
 
-    // Validate product exists
-    if let Err(response) = validate_product_exists(new_cart.product_id.clone(), db.get_ref()).await {
+    let idempotency_key = req
+        .headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|key| !key.is_empty());
+
+    let Some(idempotency_key) = idempotency_key else {
+        let response = add_cart_item(store.get_ref(), &new_cart, config.max_cart_item_qty, config.max_cart_items).await;
+        publish_cart_add(&events, &new_cart, &response, store.get_ref()).await;
         return response;
+    };
+
+    let scope = (new_cart.user_id.to_string(), idempotency_key.to_string());
+    match idempotency.begin(&scope) {
+        IdempotencyCheck::Replay(stored) => stored.into_response(),
+        IdempotencyCheck::InProgress => HttpResponse::Conflict().json(ErrorResponse {
+            detail: "A request with this Idempotency-Key is still being processed.".to_string(),
+        }),
+        IdempotencyCheck::Started => {
+            let response = add_cart_item(store.get_ref(), &new_cart, config.max_cart_item_qty, config.max_cart_items).await;
+            publish_cart_add(&events, &new_cart, &response, store.get_ref()).await;
+            idempotency.finish(scope, response).await
+        }
     }
+}
+
+// Tells the user's event listeners about a successful add: 201 means a new line, 200 a bigger one
+async fn publish_cart_add(events: &CartEventHub, new_cart: &NewCart, response: &HttpResponse, store: &CartBackend) {
+    let kind = match response.status() {
+        StatusCode::CREATED => CartEventKind::ItemAdded,
+        StatusCode::OK => CartEventKind::QtyChanged,
+        _ => return,
+    };
+    publish_cart_store_event(events, &new_cart.user_id.to_string(), kind, new_cart.product_id, store).await;
+}
+
+// Checks the quantity and note, then adds through the cart store, which serializes concurrent
+// adds to the same line so they can't both pass the stock check against the old quantity.
+async fn add_cart_item(store: &CartBackend, new_cart: &NewCart, max_qty: i32, max_items: usize) -> HttpResponse {
+    let now: DateTimeWithTimeZone = local_datetime();
 
     // Validate quantity
     if new_cart.total_qty <= 0 {
@@ -34,64 +178,320 @@ pub async fn add_to_cart(
         });
     }
 
-    // Check if a product already exists in the user's cart
-    match find_existing_cart_item(String::from(new_cart.user_id), new_cart.product_id, db.get_ref()).await {
-        Ok(Some(existing_cart)) => {
-            // Update existing cart item
-            match update_cart_quantity(existing_cart, new_cart.total_qty, now, db.get_ref()).await {
-                Ok(updated_cart) => {
-                    HttpResponse::Ok().json(SuccessResponse {
-                        success: true,
-                        message: format!(
-                            "Product quantity updated in cart. Added {} items.",
-                            new_cart.total_qty
-                        ),
-                        data: vec![updated_cart],
-                    })
-                }
+    let note = match normalize_cart_note(new_cart.note.as_deref()) {
+        Ok(note) => note,
+        Err(response) => return response,
+    };
+
+    match store.add_item(new_cart, note, max_qty, max_items, now).await {
+        Ok(CartAddOutcome { cart, previous_qty: Some(previous_qty) }) => {
+            let message = match new_cart.mode {
+                CartAddMode::Add => format!("Added {}, now {}.", new_cart.total_qty, cart.total_qty),
+                CartAddMode::Set => format!("Quantity set to {}.", cart.total_qty),
+            };
+            HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message,
+                data: vec![CartAddResult {
+                    cart,
+                    mode: new_cart.mode,
+                    previous_qty,
+                }],
+            })
+        }
+        Ok(CartAddOutcome { cart, previous_qty: None }) => {
+            HttpResponse::Created().json(SuccessResponse {
+                success: true,
+                message: "The product was successfully added to the cart.".to_string(),
+                data: vec![CartAddResult {
+                    cart,
+                    mode: new_cart.mode,
+                    previous_qty: 0,
+                }],
+            })
+        }
+        Err(response) => response,
+    }
+}
+
+
+/// Adds several products to a user's cart in one transaction.
+///
+/// # Endpoint
+/// `POST /carts/batch?partial=false`
+///
+/// # Request
+/// `{ "user_id": "...", "items": [{ "product_id": "...", "total_qty": 2 }] }`
+///
+/// Every product is validated (existence, availability, stock and the distinct-product
+/// limit of `POST /carts/`) before anything is written. Each valid item is then
+/// added with the same add-or-increment behaviour as `POST /carts/`.
+///
+/// # Response
+/// - 200 OK: Per-item outcomes (`created`, `updated` or `failed` with a reason).
+/// - 400 Bad Request: If the batch is empty, or any item fails validation and
+///   `partial=true` was not passed. Nothing is written in that case.
+/// - 500 Internal Server Error: On database-related failures; the batch is rolled back.
+#[post("/carts/batch")]
+pub async fn add_to_cart_batch(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
+    query: web::Query<BatchCartQuery>,
+    batch: web::Json<NewCartBatch>,
+) -> impl Responder {
+    let batch = batch.into_inner();
+    let now = local_datetime();
+
+    if let Err(response) = authorize_cart_access(&batch.user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &batch.user_id).await {
+        return response;
+    }
+
+    if batch.items.is_empty() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "At least one item is required.".to_string(),
+        });
+    }
+
+    let mut cart_product_ids = match fetch_cart_product_ids(&batch.user_id, db.get_ref()).await {
+        Ok(cart_product_ids) => cart_product_ids,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while counting cart items: {}", e),
+            });
+        }
+    };
+
+    // Validate every item before touching the cart
+    let mut failures: Vec<BatchItemOutcome> = Vec::new();
+    let mut unit_prices: HashMap<Uuid, Decimal> = HashMap::new();
+    let max_qty = config.max_cart_item_qty;
+    let max_items = config.max_cart_items;
+    for item in &batch.items {
+        let reason = if item.total_qty <= 0 {
+            Some("Quantity must be greater than 0.".to_string())
+        } else {
+            let existing_qty = match find_existing_cart_item(batch.user_id.clone(), item.product_id, None, db.get_ref()).await {
+                Ok(existing_cart) => existing_cart.map_or(0, |cart| cart.total_qty),
                 Err(e) => {
-                    HttpResponse::InternalServerError().json(ErrorResponse {
-                        detail: format!("Unable to update cart quantity: {}", e),
-                    })
+                    return HttpResponse::InternalServerError().json(ErrorResponse {
+                        detail: format!("Database error while checking existing cart: {}", e),
+                    });
                 }
-            }
-        }
-        Ok(None) => {
-            // Create a new cart item
-            match create_new_cart_item(
-                String::from(new_cart.user_id),
-                new_cart.product_id,
-                new_cart.total_qty,
-                now,
-                db.get_ref(),
-            ).await {
-                Ok(created_cart) => {
-                    HttpResponse::Created().json(SuccessResponse {
-                        success: true,
-                        message: "The product was successfully added to the cart.".to_string(),
-                        data: vec![created_cart],
-                    })
+            };
+            match find_product_by_id(item.product_id, db.get_ref()).await {
+                Ok(Some(_)) if existing_qty.saturating_add(item.total_qty) > max_qty => {
+                    Some(format!("You can have at most {} of a product in your cart.", max_qty))
+                }
+                Ok(Some(product)) => {
+                    unit_prices.insert(product.id, product.price);
+                    check_purchasable(&product, existing_qty + item.total_qty)
+                        .err()
+                        .map(|e| e.detail())
                 }
+                Ok(None) => Some("No product found with this ID.".to_string()),
                 Err(e) => {
-                    HttpResponse::InternalServerError().json(ErrorResponse {
-                        detail: format!("Unable to add product to cart: {}", e),
-                    })
+                    return HttpResponse::InternalServerError().json(ErrorResponse {
+                        detail: format!("Database error while checking product: {}", e),
+                    });
                 }
             }
+        };
+
+        // Products new to the cart count against the limit in request order
+        let reason = reason.or_else(|| {
+            if check_cart_items_limit(&cart_product_ids, item.product_id, max_items).is_err() {
+                return Some(cart_items_limit_detail(max_items));
+            }
+            cart_product_ids.insert(item.product_id);
+            None
+        });
+
+        if let Some(reason) = reason {
+            failures.push(BatchItemOutcome {
+                product_id: item.product_id,
+                status: BatchItemStatus::Failed,
+                total_qty: None,
+                reason: Some(reason),
+            });
         }
-        Err(e) => {
-            HttpResponse::InternalServerError().json(ErrorResponse {
-                detail: format!("Database error while checking existing cart: {}", e),
+    }
+
+    if !failures.is_empty() && !query.partial {
+        return HttpResponse::BadRequest().json(json!({
+            "detail": "Some items failed validation; no items were added.",
+            "items": failures,
+        }));
+    }
+
+    let user_id = batch.user_id.clone();
+    let valid_items: Vec<(Uuid, i32, Decimal)> = batch
+        .items
+        .iter()
+        .filter(|item| !failures.iter().any(|failure| failure.product_id == item.product_id))
+        .filter_map(|item| {
+            unit_prices
+                .get(&item.product_id)
+                .map(|unit_price| (item.product_id, item.total_qty, *unit_price))
+        })
+        .collect();
+
+    let applied = db
+        .transaction::<_, Vec<BatchItemOutcome>, sea_orm::DbErr>(|txn| {
+            Box::pin(async move {
+                let mut outcomes = Vec::with_capacity(valid_items.len());
+                for (product_id, total_qty, unit_price) in valid_items {
+                    let (status, total_qty) = match find_existing_cart_item(user_id.clone(), product_id, None, txn).await? {
+                        Some(existing_cart) => {
+                            let updated = update_cart_quantity(existing_cart, total_qty, max_qty, now, txn).await?;
+                            (BatchItemStatus::Updated, updated.total_qty)
+                        }
+                        None => {
+                            let created = create_new_cart_item(user_id.clone(), product_id, None, total_qty, unit_price, None, max_qty, now, txn).await?;
+                            (BatchItemStatus::Created, created.total_qty)
+                        }
+                    };
+                    outcomes.push(BatchItemOutcome {
+                        product_id,
+                        status,
+                        total_qty: Some(total_qty),
+                        reason: None,
+                    });
+                }
+                Ok(outcomes)
+            })
+        })
+        .await;
+
+    match applied {
+        Ok(mut outcomes) => {
+            outcomes.extend(failures);
+            HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message: "Batch processed successfully.".to_string(),
+                data: outcomes,
             })
         }
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while adding items to cart: {}", e),
+        }),
     }
 }
 
+/// Merges a guest cart into a user's cart, typically right after login.
+///
+/// # Endpoint
+/// `POST /carts/merge`
+///
+/// # Request
+/// `{ "from_user_id": "...", "to_user_id": "..." }`, with the source cart's token in
+/// `X-Cart-Token` and the target cart's token in `X-Target-Cart-Token`.
+///
+/// # Response
+/// - 200 OK: The merged target cart, in the same shape as `GET /carts/{user_id}`.
+/// - 400 Bad Request: If both ids are the same or empty.
+/// - 403 Forbidden: If either token is missing or doesn't match its cart.
+/// - 404 Not Found: If the source cart is empty.
+/// - 500 Internal Server Error: On database-related failures; nothing is merged.
+#[post("/carts/merge")]
+pub async fn merge_carts(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
+    payload: web::Json<MergeCarts>,
+) -> impl Responder {
+    let from_user_id = payload.from_user_id.trim().to_string();
+    let to_user_id = payload.to_user_id.trim().to_string();
+
+    if from_user_id.is_empty() || to_user_id.is_empty() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Both from_user_id and to_user_id are required.".to_string(),
+        });
+    }
+
+    if from_user_id == to_user_id {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Cannot merge a cart into itself.".to_string(),
+        });
+    }
+
+    // Both carts change, so the caller needs the token of each
+    if let Err(response) = authorize_cart_access(&from_user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &from_user_id).await {
+        return response;
+    }
+    let target_token = req
+        .headers()
+        .get(TARGET_CART_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok());
+    if let Err(response) = authorize_cart_token(&to_user_id, target_token, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &to_user_id).await {
+        return response;
+    }
 
+    let now = local_datetime();
+    let max_qty = config.max_cart_item_qty;
+    let merge_result = db
+        .transaction::<_, usize, sea_orm::DbErr>(|txn| {
+            let from_user_id = from_user_id.clone();
+            let to_user_id = to_user_id.clone();
+            Box::pin(async move { merge_cart_items(&from_user_id, &to_user_id, max_qty, now, txn).await })
+        })
+        .await;
+
+    match merge_result {
+        Ok(0) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: format!("No cart items found for user '{}'.", from_user_id),
+        }),
+        Ok(_) => match fetch_cart_with_products(&to_user_id, &CartListOptions::default(), db.get_ref()).await {
+            Ok(carts_responses) => HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message: "Carts merged successfully.".to_string(),
+                data: CartContents::from_lines(carts_responses),
+            }),
+            Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Carts merged but failed to fetch the result: {}", e),
+            }),
+        },
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while merging carts: {}", e),
+        }),
+    }
+}
+
+/// Fetches a user's cart.
+///
+/// # Endpoint
+/// `GET /carts/{user_id}?sort_by=added&order=desc&grouped=false`
+///
+/// `sort_by` is one of `added` (default), `updated`, `name` or `subtotal`; newest-added
+/// lines come first unless told otherwise. Lines saved for later live in `GET /saved/{user_id}`.
+///
+/// # Response
+/// - 200 OK: The cart `items` with their `total_price` and `total_items`, plus the
+///   attached `coupon`, its `discount_amount` and the `grand_total` after the discount.
+///   Every line has a `sub_total_display` string and the total a `grand_total_display`;
+///   the numeric fields stay numbers. With `grouped=true`, `data` is instead a list of
+///   `{ vendor_id, vendor_name, items, vendor_subtotal }` in the order of the sort, and
+///   the cart-wide totals move to `meta`.
+/// - 400 Bad Request: If `sort_by` or `order` isn't one of the valid options.
+/// - 404 Not Found: If the user has no cart rows.
+/// - 500 Internal Server Error: On database-related failures.
 #[get("/carts/{user_id}")]
 pub async fn get_cart_by_user_id(
     db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
     req: HttpRequest,
+    query: web::Query<CartListQuery>,
 ) -> impl Responder {
     // 🛠 Extract product_id from a request path
     let user_id_str = match req.match_info().get("user_id") {
@@ -103,78 +503,234 @@ pub async fn get_cart_by_user_id(
         }
     };
 
-    match Carts::find()
-        .filter(carts::Column::UserId.eq(user_id_str.to_string()))
-        .one(db.get_ref())
-        .await
-    {
-        Ok(Some(carts)) => {
-
-
-            // Raw SQL query joining carts and products
-            let sql = r#"
-                SELECT
-                    (array_agg(c.id ORDER BY c.created_at))[1] AS id,
-                    c.product_id,
-                    SUM(c.total_qty)::INTEGER AS total_qty,
-                    MIN(c.created_at) AS created_at,
-                    MAX(c.updated_at) AS updated_at,
-                    p.product_name,
-                    p.description,
-                    p.price as product_price,
-                    (SUM(c.total_qty) * p.price)::NUMERIC AS sub_total_price,
-                    p.img_url
-                FROM carts c
-                INNER JOIN products p ON c.product_id = p.id
-                WHERE c.user_id = $1
-                GROUP BY c.product_id, p.product_name, p.description, p.price, p.img_url
-                ORDER BY c.product_id;
-            "#;
-
-            match CartsResponse::find_by_statement(Statement::from_sql_and_values(
-                db.get_database_backend(),
-                sql,
-                vec![user_id_str.into()], // Use parsed user_id
-            ))
-                .all(db.get_ref())
-                .await
-            {
-                Ok(carts_responses) => {
-                    if carts_responses.is_empty() {
-                        return HttpResponse::NotFound().json(ErrorResponse {
-                            detail: "No carts found for this user.".to_string(),
-                        });
-                    }
+    if let Err(response) = authorize_cart_access(user_id_str, &req, db.get_ref()).await {
+        return response;
+    }
 
-                    HttpResponse::Ok().json(SuccessResponse {
-                        success: true,
-                        message: "Carts fetched successfully.".to_string(),
-                        data: carts_responses,
-                    })
-                }
-                Err(e) => {
-                    eprintln!("❌ Error fetching carts: {}", e);
-                    HttpResponse::InternalServerError().json(json!({
+    let options = match query.options() {
+        Ok(options) => options,
+        Err(detail) => return HttpResponse::BadRequest().json(ErrorResponse { detail }),
+    };
+
+    // Join carts with products, folding duplicate rows for the same product into one line.
+    // An empty result means the user has no cart rows.
+    let carts_responses = match store.cart_lines(user_id_str, &options).await {
+        Ok(carts_responses) => carts_responses,
+        Err(e) => {
+            eprintln!("❌ Error fetching carts: {}", e);
+            return HttpResponse::InternalServerError().json(json!({
                 "detail": "Failed to fetch carts."
-            }))
-                }
-            }
+            }));
         }
-        Ok(None) => HttpResponse::NotFound().json(ErrorResponse {
-            detail: "Carts not found.".to_string(),
-        }),
+    };
+
+    let cart_contents = CartContents::from_lines(carts_responses);
+    if cart_contents.is_empty() {
+        return HttpResponse::NotFound().json(ErrorResponse {
+            detail: "No carts found for this user.".to_string(),
+        });
+    }
+
+    let cart_contents = match apply_cart_coupon(user_id_str, cart_contents, local_datetime(), db.get_ref()).await {
+        Ok(cart_contents) => cart_contents,
         Err(e) => {
-            eprintln!("❌ Error fetching carts: {}", e);
-            HttpResponse::InternalServerError().json(json!({
-                "detail": e.to_string()
-            }))
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while applying cart coupon: {}", e),
+            });
+        }
+    };
+
+    if query.grouped {
+        let (vendor_groups, totals) = cart_contents.into_vendor_groups();
+        return HttpResponse::Ok().json(SuccessResponseWithMeta {
+            success: true,
+            message: "Carts fetched successfully.".to_string(),
+            data: vendor_groups,
+            meta: totals,
+        });
+    }
+
+    HttpResponse::Ok().json(SuccessResponse {
+        success: true,
+        message: "Carts fetched successfully.".to_string(),
+        data: cart_contents,
+    })
+}
+
+/// Streams changes to a user's cart as server-sent events.
+///
+/// # Endpoint
+/// `GET /carts/{user_id}/events`
+///
+/// # Response
+/// - 200 OK: A `text/event-stream` that stays open. Adding, changing, removing and
+///   clearing items send `item_added`, `qty_changed` or `item_removed` events whose
+///   `data` is `{ kind, product_id, total_items, total_price }` with the cart's new
+///   totals. A `: keep-alive` comment goes out every 15 seconds.
+///
+/// Events only reach listeners connected to the same server instance.
+#[get("/carts/{user_id}/events")]
+pub async fn stream_cart_events(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    events: web::Data<CartEventHub>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    let subscription = events.into_inner().subscribe(&user_id);
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(subscription.into_sse_stream())
+}
+
+/// Totals a user's cart for checkout.
+///
+/// # Endpoint
+/// `GET /carts/{user_id}/checkout-summary?region=`
+///
+/// # Response
+/// - 200 OK: Subtotal, tax, shipping fee and grand total, raw and formatted, plus
+///   the free-shipping threshold and how far the cart is from it.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// Only active cart items count. Tax comes from `TAX_RATE`; shipping is the `region`'s
+/// fee from `REGIONAL_SHIPPING_FEES`, else the flat `SHIPPING_FEE`, and is waived at or
+/// above `FREE_SHIPPING_THRESHOLD`.
+#[get("/carts/{user_id}/checkout-summary")]
+pub async fn get_checkout_summary(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<CheckoutSummaryQuery>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &user_id).await {
+        return response;
+    }
+
+    let subtotal = match fetch_cart_total(&user_id, db.get_ref()).await {
+        Ok(subtotal) => subtotal,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while computing cart total: {}", e),
+            });
         }
+    };
+
+    let rates = CheckoutRates::for_region(config.get_ref(), query.region.as_deref());
+    let summary = compute_checkout_summary(subtotal, &rates);
+
+    HttpResponse::Ok().json(SuccessResponse {
+        success: true,
+        message: "Checkout summary computed successfully.".to_string(),
+        data: CheckoutSummaryResponse::from_summary(summary),
+    })
+}
+
+/// Checks a cart before checkout.
+///
+/// # Endpoint
+/// `POST /carts/{user_id}/validate?fix=false`
+///
+/// # Response
+/// - 200 OK: A `CartValidationResponse` with one entry per active item (`ok`,
+///   `unavailable`, `insufficient_stock` or `price_changed`) and `can_checkout`.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// The cart is left untouched unless `fix=true` is passed; then unavailable items
+/// are removed, quantities are clamped to the stock on hand and changed prices are
+/// accepted, all in one transaction, and `changes` lists what was done.
+#[post("/carts/{user_id}/validate")]
+pub async fn validate_cart(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<CartValidationQuery>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let now = local_datetime();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &user_id).await {
+        return response;
+    }
+
+    let fix = query.fix;
+    let validation = db
+        .transaction::<_, CartValidationResponse, sea_orm::DbErr>(|txn| {
+            let user_id = user_id.clone();
+            Box::pin(async move {
+                let lines = validate_cart_lines(&user_id, now, txn).await?;
+                let changes = if fix {
+                    fix_cart_lines(&lines, now, txn).await?
+                } else {
+                    Vec::new()
+                };
+
+                // After fixing, judge the cart as it is now rather than as it was
+                let remaining: Vec<CartLineStatus> = if changes.is_empty() {
+                    lines.iter().map(|(_, line)| line.status.clone()).collect()
+                } else {
+                    validate_cart_lines(&user_id, now, txn)
+                        .await?
+                        .into_iter()
+                        .map(|(_, line)| line.status)
+                        .collect()
+                };
+                let can_checkout = !remaining.is_empty()
+                    && remaining.iter().all(|status| *status == CartLineStatus::Ok);
+
+                Ok(CartValidationResponse {
+                    can_checkout,
+                    items: lines.into_iter().map(|(_, line)| line).collect(),
+                    changes,
+                })
+            })
+        })
+        .await;
+
+    match validation {
+        Ok(report) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: if report.can_checkout {
+                "Cart is ready for checkout.".to_string()
+            } else {
+                "Cart has items that need attention before checkout.".to_string()
+            },
+            data: report,
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while validating cart: {}", e),
+        }),
     }
 }
 
+/// Sets the quantity of a cart item from path parameters.
+///
+/// Deprecated in favour of `PUT /carts/items`; kept working for older clients and
+/// flagged with a `Deprecation` response header. Quantities below the product's
+/// `min_order_qty` answer `400 Bad Request` with the required minimum.
 #[put("/carts/qty/{user_id}/{product_id}/{qty}/")]
 pub async fn update_cart_qty(
     db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    events: web::Data<CartEventHub>,
+    config: web::Data<AppConfig>,
     req: HttpRequest,
 ) -> impl Responder {
     // 🛠 Extract user_id, product_id and qty from a request path
@@ -205,6 +761,10 @@ pub async fn update_cart_qty(
         }
     };
 
+    if let Err(response) = authorize_cart_access(user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
     // Parse qty to integer
     let qty: i32 = match qty_str.parse() {
         Ok(q) => q,
@@ -222,8 +782,12 @@ pub async fn update_cart_qty(
         });
     }
 
+    if let Err(response) = check_quantity_limit(qty, config.max_cart_item_qty) {
+        return response;
+    }
+
     // Parse product_id (assuming it's a string or UUID)
-    let parsed_product_id = match product_id.parse() {
+    let parsed_product_id = match product_id.parse::<Uuid>() {
         Ok(id) => id,
         Err(_) => {
             return HttpResponse::BadRequest().json(ErrorResponse {
@@ -232,35 +796,24 @@ pub async fn update_cart_qty(
         }
     };
 
-    // Validate product exists
-    if let Err(response) = validate_product_exists(*&parsed_product_id, db.get_ref()).await {
+    // Validate product exists and has enough stock for the new quantity
+    if let Err(response) = validate_product_purchasable(parsed_product_id, qty, db.get_ref()).await {
         return response;
     }
 
 
     // Find and update cart item
-    match find_existing_cart_item(user_id.parse().unwrap(), parsed_product_id, db.get_ref()).await {
-        Ok(Some(cart_item)) => {
-            // Update the cart item
-            let now = local_datetime();
-            let mut cart_active_model: carts::ActiveModel = cart_item.into();
-            cart_active_model.total_qty = Set(qty);
-            cart_active_model.updated_at = Set(now);
-
-            match cart_active_model.update(db.get_ref()).await {
-                Ok(updated_cart) => {
-                    HttpResponse::Ok().json(SuccessResponse {
-                        success: true,
-                        message: "Cart quantity updated successfully.".to_string(),
-                        data: updated_cart,
-                    })
-                }
-                Err(e) => {
-                    HttpResponse::InternalServerError().json(ErrorResponse {
-                        detail: format!("Database error while updating cart: {}", e),
-                    })
-                }
-            }
+    match store.set_item_qty(user_id, parsed_product_id, qty, local_datetime()).await {
+        Ok(Some(updated_cart)) => {
+            publish_cart_store_event(&events, user_id, CartEventKind::QtyChanged, parsed_product_id, store.get_ref()).await;
+            HttpResponse::Ok()
+                .insert_header(("Deprecation", "true"))
+                .insert_header(("Link", "</api/v1/carts/items>; rel=\"successor-version\""))
+                .json(SuccessResponse {
+                success: true,
+                message: "Cart quantity updated successfully.".to_string(),
+                data: updated_cart,
+            })
         }
         Ok(None) => {
             HttpResponse::NotFound().json(ErrorResponse {
@@ -273,16 +826,376 @@ pub async fn update_cart_qty(
         }
         Err(e) => {
             HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while updating cart: {}", e),
+            })
+        }
+    }
+}
+
+/// Undoes the removal of a cart line.
+///
+/// # Endpoint
+/// `POST /carts/{user_id}/{product_id}/restore?variant_id=...`
+///
+/// # Response
+/// - 200 OK: The restored cart line. If the product was added again since the removal,
+///   the removed quantity is added to that line (up to the per-item limit).
+/// - 400 Bad Request: If restoring would exceed the distinct-product limit.
+/// - 404 Not Found: If no removal of this line is on record.
+/// - 410 Gone: If the last removal was more than 10 minutes ago.
+/// - 500 Internal Server Error: On database-related failures.
+#[post("/carts/{user_id}/{product_id}/restore")]
+pub async fn restore_cart_item(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
+    path: web::Path<(String, Uuid)>,
+    query: web::Query<CartItemQuery>,
+) -> impl Responder {
+    let (user_id, product_id) = path.into_inner();
+    let now = local_datetime();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &user_id).await {
+        return response;
+    }
+
+    let deletion = match find_latest_cart_deletion(&user_id, product_id, query.variant_id, db.get_ref()).await {
+        Ok(Some(deletion)) => deletion,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: "No removed cart item to restore.".to_string(),
+            });
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while finding removed cart item: {}", e),
+            });
+        }
+    };
+
+    if deletion.deleted_at < now - chrono::Duration::minutes(CART_RESTORE_WINDOW_MINUTES) {
+        return HttpResponse::Gone().json(ErrorResponse {
+            detail: format!(
+                "Removed cart items can only be restored within {} minutes.",
+                CART_RESTORE_WINDOW_MINUTES
+            ),
+        });
+    }
+
+    let cart_product_ids = match fetch_cart_product_ids(&user_id, db.get_ref()).await {
+        Ok(cart_product_ids) => cart_product_ids,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while counting cart items: {}", e),
+            });
+        }
+    };
+    if let Err(response) = check_cart_items_limit(&cart_product_ids, product_id, config.max_cart_items) {
+        return response;
+    }
+
+    let max_qty = config.max_cart_item_qty;
+    let restored = db
+        .transaction::<_, carts::Model, sea_orm::DbErr>(|txn| {
+            Box::pin(async move { restore_cart_deletion(deletion, max_qty, now, txn).await })
+        })
+        .await;
+
+    match restored {
+        Ok(cart) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Cart item restored.".to_string(),
+            data: cart,
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while restoring cart item: {}", e),
+        }),
+    }
+}
+
+/// Updates the quantity of a cart item from a JSON body.
+///
+/// # Endpoint
+/// `PUT /carts/items`
+///
+/// # Request
+/// `{ "user_id": "...", "product_id": "...", "variant_id": null, "qty": 3, "mode": "set", "note": "please fillet" }`
+///
+/// `mode` is one of `add`, `set` (default) or `subtract`. Subtracting down to
+/// zero or below removes the row instead of leaving a non-positive quantity.
+/// `note` replaces the line's note when present; an empty string clears it.
+///
+/// # Response
+/// - 200 OK: A `CartItemUpdateResult` with the resulting quantity, or `removed: true`.
+/// - 400 Bad Request: If `qty` is not greater than 0, or the note is too long.
+/// - 404 Not Found: If the product is not in the user's cart.
+/// - 409 Conflict: If the product does not exist.
+#[put("/carts/items")]
+pub async fn update_cart_item(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    events: web::Data<CartEventHub>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
+    payload: web::Json<UpdateCartItem>,
+) -> impl Responder {
+    let payload = payload.into_inner();
+    let now = local_datetime();
+
+    if let Err(response) = authorize_cart_access(&payload.user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &payload.user_id).await {
+        return response;
+    }
+
+    // Validate qty is positive
+    if payload.qty <= 0 {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Quantity must be greater than 0.".to_string(),
+        });
+    }
+
+    let note = match payload.note.as_deref().map(|note| normalize_cart_note(Some(note))).transpose() {
+        Ok(note) => note,
+        Err(response) => return response,
+    };
+
+    let cart_item = match find_existing_cart_item(payload.user_id.clone(), payload.product_id, payload.variant_id, db.get_ref()).await {
+        Ok(Some(cart_item)) => cart_item,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: format!(
+                    "No cart item found for user '{}' with product_id '{}'.",
+                    payload.user_id, payload.product_id
+                ),
+            });
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
                 detail: format!("Database error while finding cart item: {}", e),
+            });
+        }
+    };
+
+    let max_qty = config.max_cart_item_qty;
+    let limit_check = match payload.mode {
+        CartUpdateMode::Add => check_quantity_limit(cart_item.total_qty.saturating_add(payload.qty), max_qty),
+        CartUpdateMode::Set => check_quantity_limit(payload.qty, max_qty),
+        CartUpdateMode::Subtract => Ok(()),
+    };
+    if let Err(response) = limit_check {
+        return response;
+    }
+
+    // Validate product exists and, unless the quantity is shrinking, that there is enough stock
+    let validation = match payload.mode {
+        CartUpdateMode::Add => validate_cart_line_purchasable(payload.product_id, payload.variant_id, cart_item.total_qty + payload.qty, db.get_ref())
+            .await
+            .map(|_| ()),
+        CartUpdateMode::Set => validate_cart_line_purchasable(payload.product_id, payload.variant_id, payload.qty, db.get_ref())
+            .await
+            .map(|_| ()),
+        CartUpdateMode::Subtract => validate_product_exists(payload.product_id, db.get_ref()).await,
+    };
+    if let Err(response) = validation {
+        return response;
+    }
+
+    let cart_item = match note {
+        Some(note) => match set_cart_note(cart_item, note, now, db.get_ref()).await {
+            Ok(cart_item) => cart_item,
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(ErrorResponse {
+                    detail: format!("Database error while updating cart item: {}", e),
+                });
+            }
+        },
+        None => cart_item,
+    };
+
+    let result = match payload.mode {
+        CartUpdateMode::Add => update_cart_quantity(cart_item, payload.qty, max_qty, now, db.get_ref()).await,
+        CartUpdateMode::Set => set_cart_quantity(cart_item, payload.qty, now, db.get_ref()).await,
+        CartUpdateMode::Subtract => {
+            let remaining_qty = cart_item.total_qty - payload.qty;
+            if remaining_qty < 1 {
+                // Subtracting past the last unit clamps to removing the row
+                return match remove_cart_item(cart_item, db.get_ref()).await {
+                    Ok(()) => {
+                        publish_cart_event(&events, &payload.user_id, CartEventKind::ItemRemoved, payload.product_id, db.get_ref()).await;
+                        HttpResponse::Ok().json(SuccessResponse {
+                            success: true,
+                            message: "Quantity reached zero; item removed from cart.".to_string(),
+                            data: CartItemUpdateResult {
+                                product_id: payload.product_id,
+                                total_qty: 0,
+                                removed: true,
+                                cart: None,
+                            },
+                        })
+                    }
+                    Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+                        detail: format!("Database error while removing cart item: {}", e),
+                    }),
+                };
+            }
+            set_cart_quantity(cart_item, remaining_qty, now, db.get_ref()).await
+        }
+    };
+
+    match result {
+        Ok(updated_cart) => {
+            publish_cart_event(&events, &payload.user_id, CartEventKind::QtyChanged, payload.product_id, db.get_ref()).await;
+            HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message: format!("Cart quantity updated to {}.", updated_cart.total_qty),
+                data: CartItemUpdateResult {
+                    product_id: updated_cart.product_id,
+                    total_qty: updated_cart.total_qty,
+                    removed: false,
+                    cart: Some(updated_cart),
+                },
+            })
+        }
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while updating cart: {}", e),
+        }),
+    }
+}
+
+/// Purges cart rows that haven't been updated for a while.
+///
+/// # Endpoint
+/// `DELETE /carts/stale?older_than_days=30`
+///
+/// # Response
+/// - 200 OK: The retention window used and how many rows were removed.
+/// - 400 Bad Request: If `older_than_days` is less than 1.
+/// - 401 Unauthorized: If the admin key is missing or wrong.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// `older_than_days` defaults to `CART_RETENTION_DAYS`; the same cleanup also runs in the background
+/// every `CART_CLEANUP_INTERVAL_SECS` (daily by default) unless `CART_CLEANUP_ENABLED=false`.
+#[delete("/carts/stale", wrap = "from_fn(require_admin)")]
+pub async fn delete_stale_cart_items(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    config: web::Data<AppConfig>,
+    query: web::Query<StaleCartsQuery>,
+) -> impl Responder {
+    let older_than_days = query.older_than_days.unwrap_or(config.cart_retention_days);
+    if older_than_days < 1 {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "older_than_days must be at least 1.".to_string(),
+        });
+    }
+
+    let cutoff = local_datetime() - chrono::Duration::days(older_than_days);
+    match delete_stale_carts(cutoff, db.get_ref()).await {
+        Ok(removed) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: format!("Removed {} stale cart items.", removed),
+            data: StaleCartsPurged {
+                older_than_days,
+                removed,
+            },
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while removing stale carts: {}", e),
+        }),
+    }
+}
+
+/// Most product ids `DELETE /carts/{user_id}/items` accepts in one call.
+const MAX_BULK_REMOVE_IDS: usize = 100;
+
+/// Removes several products from a user's cart at once.
+///
+/// # Endpoint
+/// `DELETE /carts/{user_id}/items`
+///
+/// # Request
+/// `{ "product_ids": ["...", "..."] }`
+///
+/// # Response
+/// - 200 OK: How many rows were `removed` and which requested ids were `not_found`
+///   in the cart. Every line of a product is removed, variant lines included.
+/// - 400 Bad Request: If `product_ids` is empty or has more than 100 ids.
+/// - 500 Internal Server Error: On database-related failures.
+#[delete("/carts/{user_id}/items")]
+pub async fn delete_cart_items(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    payload: web::Json<BulkRemoveCartItems>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &user_id).await {
+        return response;
+    }
+
+    let mut product_ids = payload.into_inner().product_ids;
+    product_ids.sort();
+    product_ids.dedup();
+
+    if product_ids.is_empty() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "At least one product_id is required.".to_string(),
+        });
+    }
+    if product_ids.len() > MAX_BULK_REMOVE_IDS {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: format!("At most {} product_ids can be removed at once.", MAX_BULK_REMOVE_IDS),
+        });
+    }
+
+    match remove_cart_items(&user_id, &product_ids, db.get_ref()).await {
+        Ok(removed_rows) => {
+            let not_found = product_ids
+                .into_iter()
+                .filter(|product_id| !removed_rows.iter().any(|row| row.product_id == *product_id))
+                .collect();
+            HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message: "Selected cart items removed.".to_string(),
+                data: BulkRemovedCartItems {
+                    removed: removed_rows.len(),
+                    not_found,
+                },
             })
         }
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while removing cart items: {}", e),
+        }),
     }
 }
 
+/// Removes one product from a user's cart.
+///
+/// Pass `?variant_id=...` to remove a variant line; otherwise the base product line is removed.
+///
+/// # Response
+/// - 200 OK: The removed line (product id and name, quantity) so the client can offer an undo
+///   through `POST /carts/{user_id}/{product_id}/restore` for the next 10 minutes.
+/// - 400 Bad Request: If `product_id` is not a valid UUID.
+/// - 404 Not Found: If the product isn't in the user's cart.
+/// - 500 Internal Server Error: On database-related failures.
 #[delete("/carts/{user_id}/{product_id}")]
 pub async fn delete_cart_item(
     db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    events: web::Data<CartEventHub>,
     req: HttpRequest,
+    query: web::Query<CartItemQuery>,
 ) -> impl Responder {
     // 🛠 Extract user_id and product_id from a request path
     let user_id = match req.match_info().get("user_id") {
@@ -303,8 +1216,15 @@ pub async fn delete_cart_item(
         }
     };
 
+    if let Err(response) = authorize_cart_access(user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), user_id).await {
+        return response;
+    }
+
     // Parse product_id (assuming it's a string or UUID)
-    let parsed_product_id = match product_id.parse() {
+    let parsed_product_id = match product_id.parse::<Uuid>() {
         Ok(id) => id,
         Err(_) => {
             return HttpResponse::BadRequest().json(ErrorResponse {
@@ -313,24 +1233,33 @@ pub async fn delete_cart_item(
         }
     };
 
-    // Optional: Validate product exists (you might skip this for delete operations)
-    if let Err(response) = validate_product_exists(*&parsed_product_id, db.get_ref()).await {
-        return response;
-    }
+    let variant_filter = match query.variant_id {
+        Some(variant_id) => carts::Column::VariantId.eq(variant_id),
+        None => carts::Column::VariantId.is_null(),
+    };
 
-    // Find the cart item to delete
+    // Find the cart item to delete, along with its product for the response
     match carts::Entity::find()
         .filter(carts::Column::UserId.eq(user_id))
-        .filter(carts::Column::ProductId.eq(*&parsed_product_id))
+        .filter(carts::Column::ProductId.eq(parsed_product_id))
+        .filter(variant_filter)
+        .find_also_related(Products)
         .one(db.get_ref())
         .await
     {
-        Ok(Some(cart_item)) => {
-            // Store the item details before deletion (for response)
+        Ok(Some((cart_item, product))) => {
+            let removed_item = RemovedCartItem::from_model(&cart_item, product.as_ref());
 
-            // Delete the cart item
-            match cart_item.delete(db.get_ref()).await {
-                Ok(_delete_result) => {
+            // Delete the cart item, keeping a copy for undo in the same transaction
+            let now = local_datetime();
+            let deleted = db
+                .transaction::<_, (), sea_orm::DbErr>(|txn| {
+                    Box::pin(async move { remove_cart_item_with_undo(cart_item, now, txn).await })
+                })
+                .await;
+            match deleted {
+                Ok(()) => {
+                    publish_cart_event(&events, user_id, CartEventKind::ItemRemoved, parsed_product_id, db.get_ref()).await;
                     HttpResponse::Ok().json(SuccessResponse {
                         success: true,
                         message: format!(
@@ -338,7 +1267,7 @@ pub async fn delete_cart_item(
                             user_id,
                             product_id
                         ),
-                        data: "None",
+                        data: removed_item,
                     })
                 }
                 Err(e) => {
@@ -366,13 +1295,21 @@ pub async fn delete_cart_item(
 }
 
 
+/// Clears a user's cart.
+///
+/// # Response
+/// - 200 OK: Every removed line (product id and name, quantity).
+/// - 404 Not Found: If the cart is already empty.
+/// - 500 Internal Server Error: On database-related failures; nothing is removed.
 #[delete("/carts/{user_id}")]
 pub async fn delete_all_cart_item_per_user_id(
     db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    events: web::Data<CartEventHub>,
     req: HttpRequest,
 ) -> impl Responder {
     let user_id = match req.match_info().get("user_id") {
-        Some(id) => id,
+        Some(id) => id.to_string(),
         None => {
             return HttpResponse::BadRequest().json(ErrorResponse {
                 detail: "Invalid or missing user_id.".to_string(),
@@ -380,35 +1317,22 @@ pub async fn delete_all_cart_item_per_user_id(
         }
     };
 
-    // Delete using bulk delete operation
-    match carts::Entity::find()
-        .filter(carts::Column::UserId.eq(user_id))
-        .one(db.get_ref())
-        .await
-    {
-        Ok(Some(cart_item)) => {
-            // Store the item details before deletion (for response)
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &user_id).await {
+        return response;
+    }
 
-            // Delete the cart item
-            match cart_item.delete(db.get_ref()).await {
-                Ok(_delete_result) => {
-                    HttpResponse::Ok().json(SuccessResponse {
-                        success: true,
-                        message: format!(
-                            "Cart item successfully deleted for user '{}'.",
-                            user_id,
-                        ),
-                        data: "None",
-                    })
-                }
-                Err(e) => {
-                    HttpResponse::InternalServerError().json(ErrorResponse {
-                        detail: format!("Database error while deleting cart item: {}", e),
-                    })
-                }
-            }
-        }
-        Ok(None) => {
+    let owner = user_id.clone();
+    let cleared = db
+        .transaction::<_, Vec<RemovedCartItem>, sea_orm::DbErr>(|txn| {
+            Box::pin(async move { clear_cart(&owner, txn).await })
+        })
+        .await;
+
+    match cleared {
+        Ok(removed_items) if removed_items.is_empty() => {
             HttpResponse::NotFound().json(ErrorResponse {
                 detail: format!(
                     "No cart item found for user '{}'.",
@@ -416,10 +1340,249 @@ pub async fn delete_all_cart_item_per_user_id(
                 ),
             })
         }
+        Ok(removed_items) => {
+            for removed_item in &removed_items {
+                publish_cart_event(&events, &user_id, CartEventKind::ItemRemoved, removed_item.product_id, db.get_ref()).await;
+            }
+            HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message: format!(
+                    "Removed {} cart items for user '{}'.",
+                    removed_items.len(),
+                    user_id,
+                ),
+                data: removed_items,
+            })
+        }
         Err(e) => {
             HttpResponse::InternalServerError().json(ErrorResponse {
-                detail: format!("Database error while finding cart item: {}", e),
+                detail: format!("Database error while deleting cart items: {}", e),
             })
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{PostgresCartStore, CART_TOKEN_HEADER};
+    use crate::test_utils::{insert_cart_item, insert_product, test_db};
+    use actix_web::{test, App};
+    use futures_util::future::join_all;
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn concurrent_adds_of_the_same_product_leave_one_summed_row() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 100).await;
+        let session = create_cart_session(local_datetime(), &db).await.expect("create cart session");
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(CartBackend::Postgres(PostgresCartStore::new(db.clone()))))
+                .app_data(web::Data::new(IdempotencyStore::new(Duration::from_secs(60))))
+                .app_data(web::Data::new(CartEventHub::new()))
+                .app_data(web::Data::new(AppConfig::from_env()))
+                .service(add_to_cart),
+        )
+        .await;
+
+        let adds = (0..8).map(|_| {
+            let request = test::TestRequest::post()
+                .uri("/carts/")
+                .insert_header((CART_TOKEN_HEADER, session.token.clone()))
+                .set_json(json!({
+                    "user_id": session.user_id,
+                    "product_id": product.id,
+                    "total_qty": 2,
+                }))
+                .to_request();
+            test::call_service(&app, request)
+        });
+        for response in join_all(adds).await {
+            assert!(response.status().is_success(), "add failed with {}", response.status());
+        }
+
+        let rows = carts::Entity::find()
+            .filter(carts::Column::UserId.eq(session.user_id.as_str()))
+            .all(&db)
+            .await
+            .expect("load cart rows");
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].total_qty, 16);
+    }
+
+    #[actix_web::test]
+    async fn a_replayed_idempotency_key_adds_only_once() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 100).await;
+        let session = create_cart_session(local_datetime(), &db).await.expect("create cart session");
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(CartBackend::Postgres(PostgresCartStore::new(db.clone()))))
+                .app_data(web::Data::new(IdempotencyStore::new(Duration::from_secs(60))))
+                .app_data(web::Data::new(CartEventHub::new()))
+                .app_data(web::Data::new(AppConfig::from_env()))
+                .service(add_to_cart),
+        )
+        .await;
+        let add = || {
+            test::TestRequest::post()
+                .uri("/carts/")
+                .insert_header((CART_TOKEN_HEADER, session.token.clone()))
+                .insert_header((IDEMPOTENCY_KEY_HEADER, "retry-1"))
+                .set_json(json!({
+                    "user_id": session.user_id,
+                    "product_id": product.id,
+                    "total_qty": 3,
+                }))
+                .to_request()
+        };
+
+        let first = test::call_service(&app, add()).await;
+        assert_eq!(first.status(), StatusCode::CREATED);
+        let first_body = test::read_body(first).await;
+
+        let replayed = test::call_service(&app, add()).await;
+        assert_eq!(replayed.status(), StatusCode::CREATED);
+        assert_eq!(
+            replayed.headers().get("idempotent-replayed").and_then(|value| value.to_str().ok()),
+            Some("true")
+        );
+        assert_eq!(test::read_body(replayed).await, first_body);
+
+        let rows = carts::Entity::find()
+            .filter(carts::Column::UserId.eq(session.user_id.as_str()))
+            .all(&db)
+            .await
+            .expect("load cart rows");
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].total_qty, 3);
+    }
+
+    #[actix_web::test]
+    async fn merging_needs_the_target_cart_token() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Tilapia", Decimal::new(9000, 2), 50).await;
+        let guest = create_cart_session(local_datetime(), &db).await.expect("create guest session");
+        let user = issue_user_cart_session("user-7", local_datetime(), &db)
+            .await
+            .expect("issue user session");
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(CartBackend::Postgres(PostgresCartStore::new(db.clone()))))
+                .app_data(web::Data::new(IdempotencyStore::new(Duration::from_secs(60))))
+                .app_data(web::Data::new(CartEventHub::new()))
+                .app_data(web::Data::new(AppConfig::from_env()))
+                .service(add_to_cart)
+                .service(merge_carts),
+        )
+        .await;
+
+        let add = test::TestRequest::post()
+            .uri("/carts/")
+            .insert_header((CART_TOKEN_HEADER, guest.token.clone()))
+            .set_json(json!({ "user_id": guest.user_id, "product_id": product.id, "total_qty": 2 }))
+            .to_request();
+        assert!(test::call_service(&app, add).await.status().is_success());
+
+        let merge = |target_token: Option<&str>| {
+            let mut request = test::TestRequest::post()
+                .uri("/carts/merge")
+                .insert_header((CART_TOKEN_HEADER, guest.token.clone()))
+                .set_json(json!({ "from_user_id": guest.user_id, "to_user_id": user.user_id }));
+            if let Some(token) = target_token {
+                request = request.insert_header((TARGET_CART_TOKEN_HEADER, token.to_string()));
+            }
+            request.to_request()
+        };
+
+        let response = test::call_service(&app, merge(None)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = test::call_service(&app, merge(Some("not-the-token"))).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = test::call_service(&app, merge(Some(&user.token))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let rows = carts::Entity::find()
+            .filter(carts::Column::UserId.eq(user.user_id.clone()))
+            .all(&db)
+            .await
+            .expect("load cart rows");
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].total_qty, 2);
+    }
+
+    #[actix_web::test]
+    async fn odd_user_ids_on_the_qty_route_get_client_errors() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Pusit", Decimal::new(32000, 2), 20).await;
+        let unicode_session = issue_user_cart_session("mamimili-魚-ñ", local_datetime(), &db)
+            .await
+            .expect("issue unicode session");
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(CartBackend::Postgres(PostgresCartStore::new(db.clone()))))
+                .app_data(web::Data::new(CartEventHub::new()))
+                .app_data(web::Data::new(AppConfig::from_env()))
+                .service(update_cart_qty),
+        )
+        .await;
+
+        let long_id = "x".repeat(10_000);
+        let odd_ids = ["", "%E9%AD%9A%20%F0%9F%90%9F", "..%2F..%2Fetc", "%00", long_id.as_str()];
+        for user_id in odd_ids {
+            let request = test::TestRequest::put()
+                .uri(&format!("/carts/qty/{}/{}/2/", user_id, product.id))
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert!(
+                response.status().is_client_error(),
+                "user_id {:?} answered {}",
+                user_id.chars().take(40).collect::<String>(),
+                response.status()
+            );
+        }
+
+        // A unicode id with its own token gets through authorization; the product just isn't in that cart
+        let request = test::TestRequest::put()
+            .uri(&format!("/carts/qty/{}/{}/2/", "mamimili-%E9%AD%9A-%C3%B1", product.id))
+            .insert_header((CART_TOKEN_HEADER, unicode_session.token.clone()))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn fetching_a_cart_without_rows_answers_not_found() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Alimango", Decimal::new(45000, 2), 10).await;
+        let empty = create_cart_session(local_datetime(), &db).await.expect("create empty session");
+        let filled = create_cart_session(local_datetime(), &db).await.expect("create filled session");
+        insert_cart_item(&db, &filled.user_id, &product, 2).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(CartBackend::Postgres(PostgresCartStore::new(db.clone()))))
+                .service(get_cart_by_user_id),
+        )
+        .await;
+        let fetch = |session: &crate::models::cart_sessions::Model| {
+            test::TestRequest::get()
+                .uri(&format!("/carts/{}", session.user_id))
+                .insert_header((CART_TOKEN_HEADER, session.token.clone()))
+                .to_request()
+        };
+
+        let response = test::call_service(&app, fetch(&empty)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = test::call_service(&app, fetch(&filled)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(body["data"]["items"].as_array().map(Vec::len), Some(1));
+    }
+}
@@ -1,11 +1,14 @@
 use crate::models::categories;
-use crate::models::categories::{CategoryResponse, NewCategory};
+use crate::extractors::PaginationParams;
+use crate::models::categories::{CategoriesPage, CategoryResponse, DeleteCategoryQuery, NewCategory};
 use crate::models::prelude::Categories;
 use crate::models::responses::{ErrorResponse, SuccessResponse};
+use crate::services::{build_category_tree, creates_category_cycle, find_active_categories, CategoryCache};
 use crate::utils::local_datetime;
-use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
 use sea_orm::prelude::DateTimeWithTimeZone;
-use sea_orm::{ActiveModelTrait, DeleteResult, EntityTrait, Set};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ActiveModelTrait, EntityTrait, Set, TransactionTrait, UpdateResult};
 use sea_orm::{ColumnTrait, Order, QueryOrder};
 use sea_orm::{DatabaseConnection, QueryFilter};
 use serde_json::json;
@@ -21,11 +24,13 @@ use uuid::Uuid;
 ///
 /// # Response
 /// - 201 Created: If the category is successfully created.
+/// - 400 Bad Request: If `parent_id` doesn't refer to an existing category.
 /// - 409 Conflict: If a category with the same name already exists.
 /// - 500 Internal Server Error: On database-related failures.
 #[post("/category/")]
 pub async fn add_category(
     db: web::Data<sea_orm::DatabaseConnection>,
+    cache: web::Data<CategoryCache>,
     new_category: web::Json<NewCategory>,
 ) -> impl Responder {
     let now: DateTimeWithTimeZone = local_datetime();
@@ -37,6 +42,12 @@ pub async fn add_category(
         .one(db.get_ref())
         .await
     {
+        Ok(Some(existing)) if existing.deleted_at.is_some() => {
+            // Names stay taken by deleted categories, which can be restored instead
+            return HttpResponse::Conflict().json(ErrorResponse {
+                detail: "A deleted category has this name; restore it instead".to_string(),
+            });
+        }
         Ok(Some(_)) => {
             // Category already exists, return 409 Conflict
             return HttpResponse::Conflict().json(ErrorResponse {
@@ -52,18 +63,28 @@ pub async fn add_category(
         Ok(None) => {} // Category doesn't exist, proceed to creation
     }
 
+    // Make sure the parent category exists
+    if let Some(parent_id) = new_category.parent_id
+        && let Err(response) = validate_parent_exists(db.get_ref(), parent_id).await
+    {
+        return response;
+    }
+
     // Construct a new category ActiveModel
     let new_category_model = categories::ActiveModel {
         id: Set(Uuid::new_v4()),
         name: Set(normalized_name),
+        parent_id: Set(new_category.parent_id),
         created_at: Set(now),
         updated_at: Set(now),
+        deleted_at: Set(None),
     };
 
     // Attempt to insert the new category into the database
     match new_category_model.insert(db.get_ref()).await {
         Ok(created_category) => {
             // Successfully created category, return 201 Created
+            cache.invalidate();
             let category_response = CategoryResponse::from_model(created_category);
             HttpResponse::Created().json(SuccessResponse {
                 success: true,
@@ -81,17 +102,33 @@ pub async fn add_category(
 }
 /// Fetches all categories from the database.
 ///
+/// Results are served from the in-memory `CategoryCache` while it is fresh
+/// (`CATEGORY_CACHE_TTL_SECS`, 60 seconds by default); the cache holds the full list
+/// and each request takes its page from it.
+///
 /// # Endpoint
-/// `GET /category`
+/// `GET /category?page=1&per_page=20`
 ///
 /// # Response
-/// - 200 OK: Returns a list of categories.
+/// - 200 OK: One page of categories, newest first, plus `total_categories`.
+/// - 400 Bad Request: If `page` or `per_page` isn't a positive integer.
 /// - 404 Not Found: If no categories are found.
 /// - 500 Internal Server Error: If a database error occurs.
 #[get("/category")]
-pub async fn fetch_categories(db: web::Data<sea_orm::DatabaseConnection>) -> impl Responder {
+pub async fn fetch_categories(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    cache: web::Data<CategoryCache>,
+    pagination: PaginationParams,
+) -> impl Responder {
+    if let Some(category_responses) = cache.get() {
+        return categories_page_response(&category_responses, pagination);
+    }
+
+    // Taken before querying, so a write that lands mid-query keeps this result out of the cache
+    let generation = cache.generation();
+
     // Query the database for all categories, ordered by creation date descending
-    match Categories::find()
+    match find_active_categories()
         .order_by(categories::Column::CreatedAt, Order::Desc)
         .all(db.get_ref())
         .await
@@ -109,16 +146,189 @@ pub async fn fetch_categories(db: web::Data<sea_orm::DatabaseConnection>) -> imp
                 .into_iter()
                 .map(CategoryResponse::from_model)
                 .collect();
+            let response = categories_page_response(&category_responses, pagination);
+            cache.set(generation, category_responses);
+            response
+        }
+        Err(e) => {
+            // Log and return 500 error on failure
+            eprintln!("❌ Error fetching categories: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch categories: {}", e),
+            })
+        }
+    }
+}
+
+fn categories_page_response(categories: &[CategoryResponse], pagination: PaginationParams) -> HttpResponse {
+    HttpResponse::Ok().json(SuccessResponse {
+        success: true,
+        message: "Categories fetched successfully".to_string(),
+        data: CategoriesPage {
+            page: pagination.page,
+            per_page: pagination.per_page,
+            total_categories: categories.len() as u64,
+            categories: pagination.slice(categories).to_vec(),
+        },
+    })
+}
 
+/// Updates a category's name and parent.
+///
+/// # Endpoint
+/// `PUT /category/{category_id}/`
+///
+/// # Request
+/// Accepts a JSON body conforming to `NewCategory`.
+///
+/// # Response
+/// - 200 OK: The updated category.
+/// - 400 Bad Request: If the id is invalid, the parent doesn't exist, or the new
+///   parent would make the category its own ancestor.
+/// - 404 Not Found: If the category doesn't exist.
+/// - 409 Conflict: If another category already has this name.
+/// - 500 Internal Server Error: On database-related failures.
+#[put("/category/{category_id}/")]
+pub async fn update_category(
+    db: web::Data<DatabaseConnection>,
+    cache: web::Data<CategoryCache>,
+    path: web::Path<String>,
+    updated_category: web::Json<NewCategory>,
+) -> impl Responder {
+    let category_id = match Uuid::parse_str(&path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(json!({
+                "detail": "Invalid UUID format for category_id"
+            }));
+        }
+    };
+    let normalized_name = updated_category.name.trim().to_lowercase();
+
+    let all_categories = match Categories::find().all(db.get_ref()).await {
+        Ok(all_categories) => all_categories,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error: {}", e),
+            });
+        }
+    };
+
+    let existing_category = match all_categories
+        .iter()
+        .find(|category| category.id == category_id && category.deleted_at.is_none())
+    {
+        Some(category) => category.clone(),
+        None => {
+            return HttpResponse::NotFound().json(json!({
+                "detail": "Category record not found"
+            }));
+        }
+    };
+
+    if all_categories
+        .iter()
+        .any(|category| category.id != category_id && category.name == normalized_name)
+    {
+        return HttpResponse::Conflict().json(ErrorResponse {
+            detail: "Category with this name already exists".to_string(),
+        });
+    }
+
+    if let Some(parent_id) = updated_category.parent_id {
+        if !all_categories
+            .iter()
+            .any(|category| category.id == parent_id && category.deleted_at.is_none())
+        {
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                detail: "Parent category not found".to_string(),
+            });
+        }
+        if creates_category_cycle(&all_categories, category_id, parent_id) {
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                detail: "A category cannot be its own ancestor".to_string(),
+            });
+        }
+    }
+
+    let mut category_active_model: categories::ActiveModel = existing_category.into();
+    category_active_model.name = Set(normalized_name);
+    category_active_model.parent_id = Set(updated_category.parent_id);
+    category_active_model.updated_at = Set(local_datetime());
+
+    match category_active_model.update(db.get_ref()).await {
+        Ok(updated) => {
+            cache.invalidate();
             HttpResponse::Ok().json(SuccessResponse {
                 success: true,
-                message: "Categories fetched successfully".to_string(),
-                data: category_responses,
+                message: "Category updated successfully".to_string(),
+                data: vec![CategoryResponse::from_model(updated)],
             })
         }
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to update category: {}", e),
+        }),
+    }
+}
+
+/// Fetches the direct children of a category.
+///
+/// # Endpoint
+/// `GET /category/{category_id}/children`
+///
+/// # Response
+/// - 200 OK: The child categories, ordered by name (possibly empty).
+/// - 400 Bad Request: If the id is not a valid UUID.
+/// - 404 Not Found: If the category doesn't exist.
+/// - 500 Internal Server Error: If a database error occurs.
+#[get("/category/{category_id}/children")]
+pub async fn fetch_category_children(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let category_id = match Uuid::parse_str(&path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(json!({
+                "detail": "Invalid UUID format for category_id"
+            }));
+        }
+    };
+
+    match find_active_categories()
+        .filter(categories::Column::Id.eq(category_id))
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json(json!({
+                "detail": "Category record not found"
+            }));
+        }
         Err(e) => {
-            // Log and return 500 error on failure
-            eprintln!("❌ Error fetching categories: {}", e);
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error: {}", e),
+            });
+        }
+    }
+
+    match find_active_categories()
+        .filter(categories::Column::ParentId.eq(category_id))
+        .order_by(categories::Column::Name, Order::Asc)
+        .all(db.get_ref())
+        .await
+    {
+        Ok(children) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Categories fetched successfully".to_string(),
+            data: children
+                .into_iter()
+                .map(CategoryResponse::from_model)
+                .collect::<Vec<_>>(),
+        }),
+        Err(e) => {
+            eprintln!("❌ Error fetching child categories: {}", e);
             HttpResponse::InternalServerError().json(ErrorResponse {
                 detail: format!("Failed to fetch categories: {}", e),
             })
@@ -126,9 +336,51 @@ pub async fn fetch_categories(db: web::Data<sea_orm::DatabaseConnection>) -> imp
     }
 }
 
+/// Fetches every category as a nested tree.
+///
+/// # Endpoint
+/// `GET /category/tree`
+///
+/// # Response
+/// - 200 OK: Root categories, each with a recursive `children` array, ordered by name.
+/// - 500 Internal Server Error: If a database error occurs.
+#[get("/category/tree")]
+pub async fn fetch_category_tree(db: web::Data<DatabaseConnection>) -> impl Responder {
+    match find_active_categories().all(db.get_ref()).await {
+        Ok(categories) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Category tree fetched successfully".to_string(),
+            data: build_category_tree(categories),
+        }),
+        Err(e) => {
+            eprintln!("❌ Error fetching category tree: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch categories: {}", e),
+            })
+        }
+    }
+}
+
+/// Deletes a category.
+///
+/// The row is kept with `deleted_at` set, so it disappears from every listing but can be
+/// brought back with `POST /category/{category_id}/restore`.
+///
+/// # Endpoint
+/// `DELETE /category/{category_id}?force=false`
+///
+/// # Response
+/// - 200 OK: If the category was deleted.
+/// - 400 Bad Request: If the id is missing or invalid.
+/// - 404 Not Found: If the category doesn't exist.
+/// - 409 Conflict: If the category has children and `force` was not passed. With
+///   `force=true` the children are moved up to the deleted category's parent.
+/// - 500 Internal Server Error: On database-related failures.
 #[delete("/category/{category_id}")]
 pub async fn delete_category(
     db: web::Data<DatabaseConnection>,
+    cache: web::Data<CategoryCache>,
+    query: web::Query<DeleteCategoryQuery>,
     req: HttpRequest,
 ) -> impl Responder {
     let category_id = match req.match_info().get("category_id") {
@@ -147,10 +399,68 @@ pub async fn delete_category(
         }
     };
 
-    let res: DeleteResult = match Categories::delete_by_id(category_id)
-        .exec(db.get_ref())
+    let category = match find_active_categories()
+        .filter(categories::Column::Id.eq(category_id))
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(category)) => category,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(json!({
+                "detail": "Category record not found"
+            }));
+        }
+        Err(e) => {
+            eprintln!("❌ Error deleting category record: {}", e);
+            return HttpResponse::InternalServerError().json(json!({
+                "detail": format!("Failed to delete category record: {}", e)
+            }));
+        }
+    };
+
+    let has_children = match find_active_categories()
+        .filter(categories::Column::ParentId.eq(category_id))
+        .one(db.get_ref())
         .await
     {
+        Ok(child) => child.is_some(),
+        Err(e) => {
+            eprintln!("❌ Error deleting category record: {}", e);
+            return HttpResponse::InternalServerError().json(json!({
+                "detail": format!("Failed to delete category record: {}", e)
+            }));
+        }
+    };
+
+    if has_children && !query.force {
+        return HttpResponse::Conflict().json(json!({
+            "detail": "Category has child categories. Pass ?force=true to move them to its parent."
+        }));
+    }
+
+    // Re-parent any children onto the grandparent, then mark it deleted, atomically
+    let grandparent_id = category.parent_id;
+    let now = local_datetime();
+    let result = db
+        .transaction::<_, UpdateResult, sea_orm::DbErr>(|txn| {
+            Box::pin(async move {
+                Categories::update_many()
+                    .col_expr(categories::Column::ParentId, Expr::value(grandparent_id))
+                    .filter(categories::Column::ParentId.eq(category_id))
+                    .exec(txn)
+                    .await?;
+                Categories::update_many()
+                    .col_expr(categories::Column::DeletedAt, Expr::value(now))
+                    .col_expr(categories::Column::UpdatedAt, Expr::value(now))
+                    .filter(categories::Column::Id.eq(category_id))
+                    .filter(categories::Column::DeletedAt.is_null())
+                    .exec(txn)
+                    .await
+            })
+        })
+        .await;
+
+    let res: UpdateResult = match result {
         Ok(result) => result,
         Err(e) => {
             eprintln!("❌ Error deleting category record: {}", e);
@@ -165,9 +475,238 @@ pub async fn delete_category(
             "detail": "Category record not found"
         }));
     }
+    cache.invalidate();
 
     // Return success response
     HttpResponse::Ok().json(json!({
         "detail": "Category record deleted successfully"
     }))
 }
+
+/// Restores a deleted category.
+///
+/// # Endpoint
+/// `POST /category/{category_id}/restore`
+///
+/// # Response
+/// - 200 OK: The restored category. Children moved away by the delete stay where they
+///   are; if its parent is gone or deleted too, the category comes back as a root.
+/// - 400 Bad Request: If the id is not a valid UUID.
+/// - 404 Not Found: If the category doesn't exist.
+/// - 409 Conflict: If the category isn't deleted.
+/// - 500 Internal Server Error: On database-related failures.
+#[post("/category/{category_id}/restore")]
+pub async fn restore_category(
+    db: web::Data<DatabaseConnection>,
+    cache: web::Data<CategoryCache>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let category_id = match Uuid::parse_str(&path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(json!({
+                "detail": "Invalid UUID format for category_id"
+            }));
+        }
+    };
+
+    let category = match Categories::find_by_id(category_id).one(db.get_ref()).await {
+        Ok(Some(category)) => category,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(json!({
+                "detail": "Category record not found"
+            }));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error: {}", e),
+            });
+        }
+    };
+
+    if category.deleted_at.is_none() {
+        return HttpResponse::Conflict().json(ErrorResponse {
+            detail: "Category is not deleted".to_string(),
+        });
+    }
+
+    let parent_id = match category.parent_id {
+        Some(parent_id) => match find_active_categories()
+            .filter(categories::Column::Id.eq(parent_id))
+            .one(db.get_ref())
+            .await
+        {
+            Ok(parent) => parent.map(|parent| parent.id),
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(ErrorResponse {
+                    detail: format!("Database error: {}", e),
+                });
+            }
+        },
+        None => None,
+    };
+
+    let mut category_active_model: categories::ActiveModel = category.into();
+    category_active_model.parent_id = Set(parent_id);
+    category_active_model.deleted_at = Set(None);
+    category_active_model.updated_at = Set(local_datetime());
+
+    match category_active_model.update(db.get_ref()).await {
+        Ok(restored) => {
+            cache.invalidate();
+            HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message: "Category restored successfully".to_string(),
+                data: vec![CategoryResponse::from_model(restored)],
+            })
+        }
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to restore category: {}", e),
+        }),
+    }
+}
+
+async fn validate_parent_exists(db: &DatabaseConnection, parent_id: Uuid) -> Result<(), HttpResponse> {
+    match find_active_categories()
+        .filter(categories::Column::Id.eq(parent_id))
+        .one(db)
+        .await
+    {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Parent category not found".to_string(),
+        })),
+        Err(e) => Err(HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error: {}", e),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::fetch_category_by_id;
+    use crate::test_utils::test_db;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use std::time::Duration;
+
+    async fn insert_category(db: &DatabaseConnection, name: &str, parent_id: Option<Uuid>) -> categories::Model {
+        let now = local_datetime();
+        categories::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            name: Set(name.to_string()),
+            parent_id: Set(parent_id),
+            created_at: Set(now),
+            updated_at: Set(now),
+            deleted_at: Set(None),
+        }
+        .insert(db)
+        .await
+        .expect("insert category")
+    }
+
+    // Sorted names from a `GET /category` response; none when it answers 404
+    async fn category_names(response: actix_web::dev::ServiceResponse) -> Vec<String> {
+        if response.status() == StatusCode::NOT_FOUND {
+            return Vec::new();
+        }
+        let body: serde_json::Value = test::read_body_json(response).await;
+        let mut names: Vec<String> = body["data"]["categories"]
+            .as_array()
+            .expect("categories list")
+            .iter()
+            .map(|category| category["name"].as_str().expect("category name").to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[actix_web::test]
+    async fn deleting_keeps_the_row_but_hides_it() {
+        let Some(db) = test_db().await else { return };
+        let seafood = insert_category(&db, "Seafood", None).await;
+        insert_category(&db, "Vegetables", None).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(CategoryCache::new(Duration::from_secs(60))))
+                .service(fetch_categories)
+                .service(delete_category),
+        )
+        .await;
+        let list = || test::TestRequest::get().uri("/category").to_request();
+        assert_eq!(category_names(test::call_service(&app, list()).await).await, ["Seafood", "Vegetables"]);
+
+        let delete = test::TestRequest::delete().uri(&format!("/category/{}", seafood.id)).to_request();
+        assert_eq!(test::call_service(&app, delete).await.status(), StatusCode::OK);
+
+        let stored = Categories::find_by_id(seafood.id).one(&db).await.expect("load category").expect("row kept");
+        assert!(stored.deleted_at.is_some());
+        assert_eq!(category_names(test::call_service(&app, list()).await).await, ["Vegetables"]);
+        assert_eq!(fetch_category_by_id(web::Data::new(db.clone()), seafood.id.to_string()).await, "");
+
+        // Already deleted, so there is nothing left to delete
+        let again = test::TestRequest::delete().uri(&format!("/category/{}", seafood.id)).to_request();
+        assert_eq!(test::call_service(&app, again).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn restoring_brings_a_deleted_category_back() {
+        let Some(db) = test_db().await else { return };
+        let seafood = insert_category(&db, "Seafood", None).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(CategoryCache::new(Duration::from_secs(60))))
+                .service(fetch_categories)
+                .service(delete_category)
+                .service(restore_category),
+        )
+        .await;
+        let list = || test::TestRequest::get().uri("/category").to_request();
+        let restore_uri = format!("/category/{}/restore", seafood.id);
+
+        // Not deleted yet
+        let early = test::TestRequest::post().uri(&restore_uri).to_request();
+        assert_eq!(test::call_service(&app, early).await.status(), StatusCode::CONFLICT);
+
+        let delete = test::TestRequest::delete().uri(&format!("/category/{}", seafood.id)).to_request();
+        assert_eq!(test::call_service(&app, delete).await.status(), StatusCode::OK);
+        assert_eq!(category_names(test::call_service(&app, list()).await).await, Vec::<String>::new());
+
+        let restore = test::TestRequest::post().uri(&restore_uri).to_request();
+        assert_eq!(test::call_service(&app, restore).await.status(), StatusCode::OK);
+        assert_eq!(category_names(test::call_service(&app, list()).await).await, ["Seafood"]);
+        assert_eq!(fetch_category_by_id(web::Data::new(db.clone()), seafood.id.to_string()).await, "Seafood");
+
+        let unknown = test::TestRequest::post().uri(&format!("/category/{}/restore", Uuid::new_v4())).to_request();
+        assert_eq!(test::call_service(&app, unknown).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn a_child_restored_after_its_parent_was_deleted_comes_back_as_a_root() {
+        let Some(db) = test_db().await else { return };
+        let seafood = insert_category(&db, "Seafood", None).await;
+        let shellfish = insert_category(&db, "Shellfish", Some(seafood.id)).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(CategoryCache::new(Duration::from_secs(60))))
+                .service(delete_category)
+                .service(restore_category),
+        )
+        .await;
+
+        let child = test::TestRequest::delete().uri(&format!("/category/{}", shellfish.id)).to_request();
+        assert_eq!(test::call_service(&app, child).await.status(), StatusCode::OK);
+        let parent = test::TestRequest::delete().uri(&format!("/category/{}", seafood.id)).to_request();
+        assert_eq!(test::call_service(&app, parent).await.status(), StatusCode::OK);
+
+        let restore = test::TestRequest::post().uri(&format!("/category/{}/restore", shellfish.id)).to_request();
+        let response = test::call_service(&app, restore).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(body["data"][0]["parent_id"], serde_json::Value::Null);
+    }
+}
@@ -0,0 +1,334 @@
+use crate::middleware::require_admin;
+use crate::models::coupons;
+use crate::models::coupons::{ApplyCoupon, CouponQuote, CouponResponse, DiscountType, NewCoupon};
+use crate::models::prelude::Coupons;
+use crate::models::responses::{ErrorResponse, SuccessResponse};
+use crate::services::{attach_cart_coupon, authorize_cart_access, calculate_discount, check_coupon_applies, detach_cart_coupon, fetch_cart_total, find_coupon_by_code, normalize_coupon_code, sync_cart_store, CartBackend};
+use crate::utils::{format_money, local_datetime};
+use actix_web::middleware::from_fn;
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+use sea_orm::prelude::{DateTimeWithTimeZone, Decimal};
+use sea_orm::{ActiveModelTrait, EntityTrait, Order, QueryOrder, Set};
+use uuid::Uuid;
+
+/// Creates a discount coupon.
+///
+/// # Endpoint
+/// `POST /admin/coupons`
+///
+/// # Request
+/// `{ "code": "WEEKEND10", "discount_type": "percentage", "value": 10, "expires_at": null, "max_uses": 100, "min_subtotal": 500 }`
+///
+/// # Response
+/// - 201 Created: The new coupon, active. Codes are stored upper-case.
+/// - 400 Bad Request: If the code is empty, the value isn't positive, a percentage
+///   exceeds 100, `max_uses` is less than 1, or `min_subtotal` is negative.
+/// - 401 Unauthorized: If the admin key is missing or wrong.
+/// - 409 Conflict: If a coupon with the same code already exists.
+/// - 500 Internal Server Error: On database-related failures.
+#[post("/admin/coupons", wrap = "from_fn(require_admin)")]
+pub async fn create_coupon(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    new_coupon: web::Json<NewCoupon>,
+) -> impl Responder {
+    let code = normalize_coupon_code(&new_coupon.code);
+    if code.is_empty() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Coupon code is required.".to_string(),
+        });
+    }
+    if new_coupon.value <= Decimal::ZERO {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Coupon value must be greater than 0.".to_string(),
+        });
+    }
+    if new_coupon.discount_type == DiscountType::Percentage && new_coupon.value > Decimal::ONE_HUNDRED {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "A percentage discount cannot exceed 100.".to_string(),
+        });
+    }
+    if new_coupon.max_uses.is_some_and(|max_uses| max_uses < 1) {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "max_uses must be at least 1.".to_string(),
+        });
+    }
+    if new_coupon.min_subtotal.is_some_and(|min_subtotal| min_subtotal < Decimal::ZERO) {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "min_subtotal cannot be negative.".to_string(),
+        });
+    }
+
+    match find_coupon_by_code(&code, db.get_ref()).await {
+        Ok(Some(_)) => {
+            return HttpResponse::Conflict().json(ErrorResponse {
+                detail: "A coupon with this code already exists.".to_string(),
+            });
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while checking for duplicate: {}", e),
+            });
+        }
+        Ok(None) => {}
+    }
+
+    let now: DateTimeWithTimeZone = local_datetime();
+    let new_coupon_model = coupons::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        code: Set(code),
+        discount_type: Set(new_coupon.discount_type),
+        value: Set(new_coupon.value),
+        expires_at: Set(new_coupon.expires_at),
+        max_uses: Set(new_coupon.max_uses),
+        used_count: Set(0),
+        created_at: Set(now),
+        updated_at: Set(now),
+        min_subtotal: Set(new_coupon.min_subtotal),
+        is_active: Set(true),
+    };
+
+    match new_coupon_model.insert(db.get_ref()).await {
+        Ok(created_coupon) => HttpResponse::Created().json(SuccessResponse {
+            success: true,
+            message: "Coupon created successfully.".to_string(),
+            data: CouponResponse::from_model(created_coupon),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to create coupon: {}", e),
+        }),
+    }
+}
+
+/// Lists every coupon, newest first.
+///
+/// # Endpoint
+/// `GET /admin/coupons`
+///
+/// # Response
+/// - 200 OK: All coupons (possibly empty).
+/// - 401 Unauthorized: If the admin key is missing or wrong.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/admin/coupons", wrap = "from_fn(require_admin)")]
+pub async fn fetch_coupons(db: web::Data<sea_orm::DatabaseConnection>) -> impl Responder {
+    match Coupons::find()
+        .order_by(coupons::Column::CreatedAt, Order::Desc)
+        .all(db.get_ref())
+        .await
+    {
+        Ok(coupons) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Coupons fetched successfully.".to_string(),
+            data: coupons
+                .into_iter()
+                .map(CouponResponse::from_model)
+                .collect::<Vec<_>>(),
+        }),
+        Err(e) => {
+            eprintln!("❌ Error fetching coupons: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch coupons: {}", e),
+            })
+        }
+    }
+}
+
+/// Previews a coupon against a user's cart.
+///
+/// # Endpoint
+/// `POST /carts/{user_id}/apply-coupon`
+///
+/// # Request
+/// `{ "code": "WEEKEND10" }`
+///
+/// # Response
+/// - 200 OK: The cart total before and after the discount, raw and formatted.
+/// - 400 Bad Request: If the coupon doesn't apply, with a `reason` of `inactive`,
+///   `expired`, `exhausted` or `below_minimum`.
+/// - 404 Not Found: If no coupon has this code.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// Only active cart items count toward the total. Nothing is written; the coupon's
+/// `used_count` is untouched.
+#[post("/carts/{user_id}/apply-coupon")]
+pub async fn apply_coupon(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    payload: web::Json<ApplyCoupon>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &user_id).await {
+        return response;
+    }
+
+    let coupon = match find_coupon_by_code(&payload.code, db.get_ref()).await {
+        Ok(Some(coupon)) => coupon,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: "Coupon not found.".to_string(),
+            });
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while finding coupon: {}", e),
+            });
+        }
+    };
+
+    let subtotal = match fetch_cart_total(&user_id, db.get_ref()).await {
+        Ok(subtotal) => subtotal,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while computing cart total: {}", e),
+            });
+        }
+    };
+
+    if let Err(e) = check_coupon_applies(&coupon, subtotal, local_datetime()) {
+        return e.into_response();
+    }
+
+    let discount = calculate_discount(subtotal, coupon.discount_type, coupon.value);
+    let total = subtotal - discount;
+
+    HttpResponse::Ok().json(SuccessResponse {
+        success: true,
+        message: "Coupon applied successfully.".to_string(),
+        data: CouponQuote {
+            code: coupon.code,
+            subtotal,
+            subtotal_display: format_money(f64::try_from(subtotal).unwrap_or_default()),
+            discount,
+            discount_display: format_money(f64::try_from(discount).unwrap_or_default()),
+            total,
+            total_display: format_money(f64::try_from(total).unwrap_or_default()),
+        },
+    })
+}
+
+/// Attaches a coupon to a user's cart, replacing any coupon already attached.
+///
+/// # Endpoint
+/// `POST /carts/{user_id}/coupon`
+///
+/// # Request
+/// `{ "code": "WEEKEND10" }`
+///
+/// # Response
+/// - 200 OK: The discounted quote, as for `POST /carts/{user_id}/apply-coupon`.
+/// - 400 Bad Request: If the coupon doesn't apply, with a `reason` of `inactive`,
+///   `expired`, `exhausted` or `below_minimum`.
+/// - 404 Not Found: If no coupon has this code.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// The coupon is re-checked every time the cart is read; `GET /carts/{user_id}` reports
+/// a coupon that stopped applying in `coupon.issue` instead of discounting.
+#[post("/carts/{user_id}/coupon")]
+pub async fn attach_coupon(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    payload: web::Json<ApplyCoupon>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let now = local_datetime();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &user_id).await {
+        return response;
+    }
+
+    let coupon = match find_coupon_by_code(&payload.code, db.get_ref()).await {
+        Ok(Some(coupon)) => coupon,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: "Coupon not found.".to_string(),
+            });
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while finding coupon: {}", e),
+            });
+        }
+    };
+
+    let subtotal = match fetch_cart_total(&user_id, db.get_ref()).await {
+        Ok(subtotal) => subtotal,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while computing cart total: {}", e),
+            });
+        }
+    };
+
+    if let Err(e) = check_coupon_applies(&coupon, subtotal, now) {
+        return e.into_response();
+    }
+
+    if let Err(e) = attach_cart_coupon(&user_id, coupon.id, now, db.get_ref()).await {
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to attach coupon: {}", e),
+        });
+    }
+
+    let discount = calculate_discount(subtotal, coupon.discount_type, coupon.value);
+    let total = subtotal - discount;
+
+    HttpResponse::Ok().json(SuccessResponse {
+        success: true,
+        message: "Coupon attached to cart.".to_string(),
+        data: CouponQuote {
+            code: coupon.code,
+            subtotal,
+            subtotal_display: format_money(f64::try_from(subtotal).unwrap_or_default()),
+            discount,
+            discount_display: format_money(f64::try_from(discount).unwrap_or_default()),
+            total,
+            total_display: format_money(f64::try_from(total).unwrap_or_default()),
+        },
+    })
+}
+
+/// Removes the coupon from a user's cart.
+///
+/// # Endpoint
+/// `DELETE /carts/{user_id}/coupon`
+///
+/// # Response
+/// - 200 OK: The coupon was removed.
+/// - 404 Not Found: If no coupon was attached.
+/// - 500 Internal Server Error: On database-related failures.
+#[delete("/carts/{user_id}/coupon")]
+pub async fn remove_coupon(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    match detach_cart_coupon(&user_id, db.get_ref()).await {
+        Ok(true) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Coupon removed from cart.".to_string(),
+            data: "None",
+        }),
+        Ok(false) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: "No coupon is attached to this cart.".to_string(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to remove coupon: {}", e),
+        }),
+    }
+}
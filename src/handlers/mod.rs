@@ -1,7 +1,26 @@
+// A panic here takes down the worker; answer with a 4xx/5xx instead
+#![deny(clippy::unwrap_used)]
+
+mod addresses;
+mod cart_shares;
+pub mod admin;
 pub mod categories;
+mod coupons;
+mod orders;
 mod products;
+mod restock;
 mod carts;
+mod saved_items;
+mod wishlists;
 
+pub use addresses::*;
+pub use cart_shares::*;
+pub use admin::*;
 pub use categories::*;
+pub use coupons::*;
+pub use orders::*;
 pub use products::*;
+pub use restock::*;
 pub use carts::*;
+pub use saved_items::*;
+pub use wishlists::*;
@@ -0,0 +1,890 @@
+use crate::config::AppConfig;
+use crate::extractors::PaginationParams;
+use crate::middleware::require_admin;
+use crate::models::carts::{CartContents, CartEventKind, CartListOptions};
+use crate::models::orders::{AdminOrdersQuery, CancelOrder, CheckoutBlockedResponse, CheckoutRequest, IllegalTransitionResponse, MarkOrderPaid, OrderFilter, OrderListQuery, OrderStatusChange, OrdersPage, ReceiptStore, ReorderResponse, UpdateOrderStatus};
+use crate::models::orders;
+use crate::models::prelude::Orders;
+use crate::models::responses::{ErrorResponse, SuccessResponse};
+use crate::services::{apply_cart_coupon, authorize_cart_access, cancel_customer_order, checkout_cart, fetch_order_detail, fetch_order_receipt, fetch_order_timeline, fetch_orders, find_order_by_number, mark_order_paid, normalize_payment_reference, MarkPaidOutcome, normalize_cancel_reason, publish_cart_event, publish_cart_store_event, render_receipt_html, reorder_into_cart, sync_cart_store, transition_order_status, CartBackend, CartEventHub, CartStore, CheckoutError, CheckoutRates, OrderStatusError};
+use crate::utils::local_datetime;
+use actix_web::http::header;
+use actix_web::middleware::from_fn;
+use actix_web::{get, patch, post, web, HttpRequest, HttpResponse, Responder};
+use sea_orm::{ActiveEnum, ColumnTrait, EntityTrait, QueryFilter, TransactionError, TransactionTrait};
+use uuid::Uuid;
+
+/// Lists a user's orders.
+///
+/// # Endpoint
+/// `GET /orders/{user_id}?page=1&per_page=20&status=`
+///
+/// # Response
+/// - 200 OK: One page of orders, newest first, each with its status, totals and `item_count`
+///   (units ordered) but not its items, plus `total_orders`. Users without orders get an empty list.
+///   `status` (`pending`, `confirmed`, `preparing`, `out_for_delivery`, `completed` or
+///   `cancelled`) keeps only orders in that status.
+/// - 400 Bad Request: If `page` or `per_page` isn't a positive integer, or `status` is unknown.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/orders/{user_id}")]
+pub async fn fetch_user_orders_page(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    query: web::Query<OrderListQuery>,
+    pagination: PaginationParams,
+) -> impl Responder {
+    let user_id = path.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    let filter = OrderFilter {
+        user_id: Some(user_id),
+        status: query.status,
+        ..Default::default()
+    };
+
+    match fetch_orders(&filter, pagination.page_index(), pagination.per_page, db.get_ref()).await {
+        Ok((orders, total_orders)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Orders fetched successfully.".to_string(),
+            data: OrdersPage {
+                page: pagination.page,
+                per_page: pagination.per_page,
+                total_orders,
+                orders,
+            },
+        }),
+        Err(e) => {
+            eprintln!("❌ Error listing orders: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to list orders: {}", e),
+            })
+        }
+    }
+}
+
+/// Looks up an order by its number, for support staff taking calls.
+///
+/// # Endpoint
+/// `GET /orders/number/{order_number}`
+///
+/// # Response
+/// - 200 OK: The order with its items, as in `GET /orders/{user_id}/{order_id}`. The number is
+///   matched ignoring case, so `tp-2026-000123` finds `TP-2026-000123`.
+/// - 401 Unauthorized: If the admin key is missing or wrong; numbers are sequential, so they are
+///   not enough on their own to see an order.
+/// - 404 Not Found: If no order has that number.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/orders/number/{order_number}", wrap = "from_fn(require_admin)")]
+pub async fn fetch_order_by_number(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let order = match find_order_by_number(&path.into_inner(), db.get_ref()).await {
+        Ok(Some(order)) => order,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: "Order not found.".to_string(),
+            });
+        }
+        Err(e) => {
+            eprintln!("❌ Error looking up order number: {}", e);
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch order: {}", e),
+            });
+        }
+    };
+
+    match fetch_order_detail(&order.user_id, order.id, db.get_ref()).await {
+        Ok(Some(detail)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Order fetched successfully.".to_string(),
+            data: detail,
+        }),
+        Ok(None) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: "Order not found.".to_string(),
+        }),
+        Err(e) => {
+            eprintln!("❌ Error fetching order: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch order: {}", e),
+            })
+        }
+    }
+}
+
+/// Shows one of a user's orders with its items.
+///
+/// # Endpoint
+/// `GET /orders/{user_id}/{order_id}`
+///
+/// # Response
+/// - 200 OK: The order with every item at the name and price it was ordered at, each with
+///   the product's current `img_url` (null once the product is deleted), plus `item_count`
+///   and `items_total`. `payment_status` and `payment_method` are included, and
+///   `awaiting_payment` is true while a `gcash` or `card` order is still unpaid.
+///   `delivery_address` is the address copied at checkout, null for older orders.
+/// - 400 Bad Request: If `order_id` is not a valid UUID.
+/// - 404 Not Found: If the order doesn't exist or belongs to another user.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/orders/{user_id}/{order_id}")]
+pub async fn fetch_order_detail_by_id(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (user_id, order_id) = path.into_inner();
+
+    let Ok(order_id) = Uuid::parse_str(&order_id) else {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Invalid order_id format. Must be a valid UUID.".to_string(),
+        });
+    };
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    match fetch_order_detail(&user_id, order_id, db.get_ref()).await {
+        Ok(Some(detail)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Order fetched successfully.".to_string(),
+            data: detail,
+        }),
+        Ok(None) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: OrderStatusError::NotFound.detail(),
+        }),
+        Err(e) => {
+            eprintln!("❌ Error fetching order: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch order: {}", e),
+            })
+        }
+    }
+}
+
+/// Shows how an order has moved through its statuses, for delivery tracking.
+///
+/// # Endpoint
+/// `GET /orders/{user_id}/{order_id}/timeline`
+///
+/// # Response
+/// - 200 OK: `{ status, changed_at, note }` entries, oldest first. The first is always
+///   `pending` at the time the order was placed; `note` carries the recorded reason, if any.
+/// - 400 Bad Request: If `order_id` is not a valid UUID.
+/// - 404 Not Found: If the order doesn't exist or belongs to another user.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/orders/{user_id}/{order_id}/timeline")]
+pub async fn fetch_order_timeline_by_id(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (user_id, order_id) = path.into_inner();
+
+    let Ok(order_id) = Uuid::parse_str(&order_id) else {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Invalid order_id format. Must be a valid UUID.".to_string(),
+        });
+    };
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    match fetch_order_timeline(&user_id, order_id, db.get_ref()).await {
+        Ok(Some(timeline)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Order timeline fetched successfully.".to_string(),
+            data: timeline,
+        }),
+        Ok(None) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: OrderStatusError::NotFound.detail(),
+        }),
+        Err(e) => {
+            eprintln!("❌ Error fetching order timeline: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch order timeline: {}", e),
+            })
+        }
+    }
+}
+
+/// Produces a receipt for an order, for accounting or for the customer to print.
+///
+/// # Endpoint
+/// `GET /orders/{user_id}/{order_id}/receipt`
+///
+/// # Response
+/// - 200 OK: The store details (`STORE_NAME`, `STORE_ADDRESS`, `STORE_TIN`), `order_number`,
+///   `placed_at`/`paid_at`/`issued_at` in store time, the items with their unit prices and
+///   line totals, the `vat` breakdown, delivery fee, discount, total, payment method and
+///   status, and the delivery address. Amounts come with `*_display` strings. With
+///   `Accept: text/html`, a printable HTML page of the same receipt instead.
+/// - 400 Bad Request: If `order_id` is not a valid UUID.
+/// - 404 Not Found: If the order doesn't exist or belongs to another user.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// Everything is read from the order as stored at checkout, never from the current products.
+#[get("/orders/{user_id}/{order_id}/receipt")]
+pub async fn fetch_order_receipt_by_id(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (user_id, order_id) = path.into_inner();
+
+    let Ok(order_id) = Uuid::parse_str(&order_id) else {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Invalid order_id format. Must be a valid UUID.".to_string(),
+        });
+    };
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    let store = ReceiptStore {
+        name: config.store_name.clone(),
+        address: config.store_address.clone(),
+        tin: config.store_tin.clone(),
+    };
+    let receipt = match fetch_order_receipt(&user_id, order_id, store, local_datetime(), db.get_ref()).await {
+        Ok(Some(receipt)) => receipt,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: OrderStatusError::NotFound.detail(),
+            });
+        }
+        Err(e) => {
+            eprintln!("❌ Error fetching order receipt: {}", e);
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch order receipt: {}", e),
+            });
+        }
+    };
+
+    let wants_html = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    if wants_html {
+        return HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(render_receipt_html(&receipt));
+    }
+
+    HttpResponse::Ok().json(SuccessResponse {
+        success: true,
+        message: "Order receipt fetched successfully.".to_string(),
+        data: receipt,
+    })
+}
+
+/// Turns a user's cart into an order.
+///
+/// # Endpoint
+/// `POST /checkout`
+///
+/// # Request
+/// ```json
+/// {
+///   "user_id": "...",
+///   "payment_method": "gcash",
+///   "address": {
+///     "recipient_name": "Juan Dela Cruz",
+///     "phone": "0917 123 4567",
+///     "line1": "123 Rizal St.",
+///     "barangay": "San Antonio",
+///     "city": "Pasig",
+///     "notes": "Blue gate"
+///   }
+/// }
+/// ```
+/// `payment_method` (`cod`, `gcash` or `card`) defaults to `cod`. Instead of `line1`,
+/// `barangay` and `city`, `address` may give the `address_id` of one of the user's saved
+/// addresses; its `line1` and `city` are copied, and its `line2` stands in for a missing
+/// `barangay`. `recipient_name` and a Philippine mobile `phone` are always required.
+///
+/// # Response
+/// - 201 Created: The pending order with its items. Product names and prices are copied
+///   from the cart, stock is reserved and the active cart lines are removed.
+/// - 400 Bad Request: If the cart has no active items, or the address is incomplete or its
+///   phone number isn't a Philippine mobile number.
+/// - 404 Not Found: If `address_id` isn't one of the user's addresses.
+/// - 409 Conflict: If any item is unavailable, short on stock or has changed price; `items`
+///   lists those lines with the same statuses as `POST /carts/{user_id}/validate`. Also when
+///   the cart's coupon ran out of uses in the meantime.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// Everything happens in one transaction, so a blocked checkout changes nothing. Stock is
+/// decremented with conditional updates: of two checkouts racing for the last unit, one
+/// succeeds and the other gets 409. A product that sells out is marked unavailable.
+///
+/// The order stores `subtotal`, `tax` and `delivery_fee` as in
+/// `GET /carts/{user_id}/checkout-summary`, the attached coupon's `discount` (the coupon
+/// is used up once and detached) and the resulting `total`; each comes with a `*_display`
+/// string. Order reads return these stored amounts, never recomputed ones. New orders start
+/// `unpaid`; see `POST /admin/orders/{order_id}/mark-paid`.
+///
+/// The address is copied onto the order as `delivery_address` (phone as `+639XXXXXXXXX`),
+/// so editing or deleting the saved address later doesn't change it.
+#[post("/checkout")]
+pub async fn checkout(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    config: web::Data<AppConfig>,
+    events: web::Data<CartEventHub>,
+    req: HttpRequest,
+    payload: web::Json<CheckoutRequest>,
+) -> impl Responder {
+    let CheckoutRequest { user_id, payment_method, address } = payload.into_inner();
+    let now = local_datetime();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &user_id).await {
+        return response;
+    }
+
+    let rates = CheckoutRates::for_region(config.get_ref(), None);
+    let owner = user_id.clone();
+    let result = db
+        .transaction::<_, _, CheckoutError>(|txn| {
+            Box::pin(async move { checkout_cart(&owner, payment_method, address, &rates, now, txn).await })
+        })
+        .await;
+
+    match result {
+        Ok(placed) => {
+            for item in &placed.items {
+                publish_cart_event(&events, &user_id, CartEventKind::ItemRemoved, item.product_id, db.get_ref()).await;
+            }
+            HttpResponse::Created().json(SuccessResponse {
+                success: true,
+                message: "Order placed successfully.".to_string(),
+                data: placed,
+            })
+        }
+        Err(TransactionError::Transaction(e)) => {
+            let detail = e.detail();
+            match e {
+                CheckoutError::EmptyCart => HttpResponse::BadRequest().json(ErrorResponse { detail }),
+                CheckoutError::Blocked(items) => HttpResponse::Conflict().json(CheckoutBlockedResponse { detail, items }),
+                CheckoutError::CouponExhausted => HttpResponse::Conflict().json(ErrorResponse { detail }),
+                CheckoutError::InvalidAddress(_) => HttpResponse::BadRequest().json(ErrorResponse { detail }),
+                CheckoutError::AddressNotFound => HttpResponse::NotFound().json(ErrorResponse { detail }),
+                CheckoutError::Db(_) => HttpResponse::InternalServerError().json(ErrorResponse { detail }),
+            }
+        }
+        Err(TransactionError::Connection(e)) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error during checkout: {}", e),
+        }),
+    }
+}
+
+/// Lists orders across all users for fulfillment.
+///
+/// # Endpoint
+/// `GET /admin/orders?page=1&per_page=50&status=&user_id=&created_from=&created_to=&min_total=&sort_by=created_at`
+///
+/// # Response
+/// - 200 OK: One page of orders, each with its `user_id`, status, totals and `item_count`, plus
+///   `total_orders`. Newest first by default; `sort_by=total` puts the largest orders first.
+///   `created_from`/`created_to` take `YYYY-MM-DD` dates (whole days, store time) or RFC 3339
+///   timestamps; `min_total` keeps orders totalling at least that amount.
+/// - 400 Bad Request: If `page` is 0, or a filter or `sort_by` is malformed.
+/// - 401 Unauthorized: If the admin key is missing or wrong.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/admin/orders", wrap = "from_fn(require_admin)")]
+pub async fn fetch_admin_orders(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    query: web::Query<AdminOrdersQuery>,
+) -> impl Responder {
+    let page = query.page.unwrap_or(1);
+    if page == 0 {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Page numbers start at 1.".to_string(),
+        });
+    }
+    let per_page = query.per_page.unwrap_or(50).clamp(1, 200);
+
+    let filter = match query.filter() {
+        Ok(filter) => filter,
+        Err(detail) => return HttpResponse::BadRequest().json(ErrorResponse { detail }),
+    };
+
+    match fetch_orders(&filter, page - 1, per_page, db.get_ref()).await {
+        Ok((orders, total_orders)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Orders fetched successfully.".to_string(),
+            data: OrdersPage {
+                page,
+                per_page,
+                total_orders,
+                orders,
+            },
+        }),
+        Err(e) => {
+            eprintln!("❌ Error listing admin orders: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to list orders: {}", e),
+            })
+        }
+    }
+}
+
+/// Moves an order to a new status.
+///
+/// # Endpoint
+/// `PATCH /admin/orders/{order_id}/status`
+///
+/// # Request
+/// `{ "status": "confirmed" }`
+///
+/// # Response
+/// - 200 OK: The updated order and the recorded transition.
+/// - 401 Unauthorized: If the admin key is missing or wrong.
+/// - 404 Not Found: If the order doesn't exist.
+/// - 409 Conflict: If the order can't move from its current status to the requested one;
+///   `allowed` lists where it can go instead.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// Allowed moves: pending → confirmed → preparing → out_for_delivery → completed, and
+/// pending/confirmed/preparing → cancelled. Orders paid by `gcash` or `card` can only move to
+/// `preparing` once marked paid; otherwise 409. Every change is timestamped in
+/// `order_status_history`; cancelling restores the items' stock.
+#[patch("/admin/orders/{order_id}/status", wrap = "from_fn(require_admin)")]
+pub async fn update_order_status(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<Uuid>,
+    payload: web::Json<UpdateOrderStatus>,
+) -> impl Responder {
+    let order_id = path.into_inner();
+    let next = payload.status;
+    let now = local_datetime();
+
+    let result = db
+        .transaction::<_, _, OrderStatusError>(|txn| {
+            Box::pin(async move { transition_order_status(order_id, next, None, now, txn).await })
+        })
+        .await;
+
+    match result {
+        Ok((order, transition)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Order status updated successfully.".to_string(),
+            data: OrderStatusChange { order, transition },
+        }),
+        Err(TransactionError::Transaction(e)) => {
+            let detail = e.detail();
+            match e {
+                OrderStatusError::NotFound => HttpResponse::NotFound().json(ErrorResponse { detail }),
+                OrderStatusError::IllegalTransition { from, .. } => HttpResponse::Conflict().json(IllegalTransitionResponse {
+                    detail,
+                    allowed: from.next_statuses(),
+                }),
+                OrderStatusError::AwaitingPayment(_) => HttpResponse::Conflict().json(ErrorResponse { detail }),
+                OrderStatusError::Db(_) => HttpResponse::InternalServerError().json(ErrorResponse { detail }),
+            }
+        }
+        Err(TransactionError::Connection(e)) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while updating order status: {}", e),
+        }),
+    }
+}
+
+/// Records an order's payment.
+///
+/// # Endpoint
+/// `POST /admin/orders/{order_id}/mark-paid`
+///
+/// # Request
+/// `{ "reference": "GCASH-0012345" }`; the body and `reference` are optional.
+///
+/// # Response
+/// - 200 OK: The order, now `paid` with `paid_at` and `payment_reference` set. An order that
+///   was already paid is returned unchanged, keeping its original `paid_at` and reference.
+/// - 400 Bad Request: If `reference` is longer than 200 characters.
+/// - 401 Unauthorized: If the admin key is missing or wrong.
+/// - 404 Not Found: If the order doesn't exist.
+/// - 409 Conflict: If the order was refunded or cancelled.
+/// - 500 Internal Server Error: On database-related failures.
+#[post("/admin/orders/{order_id}/mark-paid", wrap = "from_fn(require_admin)")]
+pub async fn mark_order_as_paid(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<Uuid>,
+    payload: Option<web::Json<MarkOrderPaid>>,
+) -> impl Responder {
+    let order_id = path.into_inner();
+    let payload = payload.map(web::Json::into_inner).unwrap_or_default();
+    let now = local_datetime();
+
+    let reference = match normalize_payment_reference(payload.reference.as_deref()) {
+        Ok(reference) => reference,
+        Err(detail) => return HttpResponse::BadRequest().json(ErrorResponse { detail }),
+    };
+
+    let result = db
+        .transaction::<_, _, sea_orm::DbErr>(|txn| {
+            Box::pin(async move { mark_order_paid(order_id, reference, now, txn).await })
+        })
+        .await;
+
+    match result {
+        Ok(MarkPaidOutcome::Paid(order)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Order marked as paid.".to_string(),
+            data: order,
+        }),
+        Ok(MarkPaidOutcome::AlreadyPaid(order)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Order was already paid.".to_string(),
+            data: order,
+        }),
+        Ok(MarkPaidOutcome::NotFound) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: "Order not found.".to_string(),
+        }),
+        Ok(MarkPaidOutcome::Refunded) => HttpResponse::Conflict().json(ErrorResponse {
+            detail: "This order was refunded and can't be marked as paid.".to_string(),
+        }),
+        Ok(MarkPaidOutcome::Cancelled) => HttpResponse::Conflict().json(ErrorResponse {
+            detail: "This order was cancelled and can't be marked as paid.".to_string(),
+        }),
+        Err(e) => {
+            eprintln!("❌ Error marking order paid: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to mark order as paid: {}", e),
+            })
+        }
+    }
+}
+
+/// Cancels one of the customer's own orders and puts its items back in stock.
+///
+/// # Endpoint
+/// `POST /orders/{user_id}/{order_id}/cancel`
+///
+/// # Request
+/// Optional `{ "reason": "Ordered the wrong size" }` (up to 500 characters).
+///
+/// # Response
+/// - 200 OK: The cancelled order and the recorded transition, with the reason. Cancelling
+///   an order that is already cancelled returns it unchanged with `already_cancelled: true`.
+/// - 400 Bad Request: If the reason is too long.
+/// - 404 Not Found: If the order doesn't exist or belongs to another user.
+/// - 409 Conflict: If the order is past `confirmed`; it can no longer be cancelled by the customer.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// The status change and the stock restore happen in one transaction.
+#[post("/orders/{user_id}/{order_id}/cancel")]
+pub async fn cancel_order(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(String, Uuid)>,
+    payload: Option<web::Json<CancelOrder>>,
+) -> impl Responder {
+    let (user_id, order_id) = path.into_inner();
+    let payload = payload.map(web::Json::into_inner).unwrap_or_default();
+    let now = local_datetime();
+
+    let reason = match normalize_cancel_reason(payload.reason.as_deref()) {
+        Ok(reason) => reason,
+        Err(detail) => return HttpResponse::BadRequest().json(ErrorResponse { detail }),
+    };
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    let owner = user_id.clone();
+    let result = db
+        .transaction::<_, _, OrderStatusError>(|txn| {
+            Box::pin(async move { cancel_customer_order(&owner, order_id, reason, now, txn).await })
+        })
+        .await;
+
+    match result {
+        Ok(cancellation) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: if cancellation.already_cancelled {
+                "Order was already cancelled.".to_string()
+            } else {
+                "Order cancelled successfully.".to_string()
+            },
+            data: cancellation,
+        }),
+        Err(TransactionError::Transaction(e)) => match e {
+            OrderStatusError::NotFound => HttpResponse::NotFound().json(ErrorResponse { detail: e.detail() }),
+            OrderStatusError::IllegalTransition { from, .. } => HttpResponse::Conflict().json(ErrorResponse {
+                detail: format!(
+                    "This order is already '{}' and can no longer be cancelled. Orders can only be cancelled while pending or confirmed.",
+                    from.to_value()
+                ),
+            }),
+            OrderStatusError::AwaitingPayment(_) => HttpResponse::Conflict().json(ErrorResponse { detail: e.detail() }),
+            OrderStatusError::Db(_) => HttpResponse::InternalServerError().json(ErrorResponse { detail: e.detail() }),
+        },
+        Err(TransactionError::Connection(e)) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while cancelling order: {}", e),
+        }),
+    }
+}
+
+/// Puts a past order's items back into its owner's cart.
+///
+/// # Endpoint
+/// `POST /orders/{user_id}/{order_id}/reorder`
+///
+/// # Response
+/// - 200 OK: The items that were `added` and those `skipped` with a reason
+///   (`deleted`, `unavailable`, `out_of_stock`, `quantity_limit`, `below_minimum`,
+///   `cart_full` or `conflict`), plus the refreshed `cart` as in `GET /carts/{user_id}`.
+/// - 400 Bad Request: If `order_id` is not a valid UUID, or the order's cart id isn't a UUID
+///   (`POST /carts/` can't add to it either).
+/// - 404 Not Found: If the order doesn't exist or belongs to another user.
+/// - 500 Internal Server Error: On database-related failures; items added before the
+///   failure stay in the cart.
+///
+/// Each item is added like `POST /carts/` with its variant, at today's prices, adding to the
+/// quantities of existing cart lines. A quantity is cut down when there isn't enough stock
+/// left for all of it; an item is skipped when what's left is below its `min_order_qty`, or
+/// when it is new to a cart that already holds `MAX_CART_ITEMS` products. Listeners of
+/// `GET /carts/{user_id}/events` hear about every added line.
+#[post("/orders/{user_id}/{order_id}/reorder")]
+pub async fn reorder_from_order(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    events: web::Data<CartEventHub>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> impl Responder {
+    let (user_id, order_id) = path.into_inner();
+    let now = local_datetime();
+
+    let Ok(order_id) = Uuid::parse_str(&order_id) else {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Invalid order_id format. Must be a valid UUID.".to_string(),
+        });
+    };
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    let order = match Orders::find_by_id(order_id)
+        .filter(orders::Column::UserId.eq(&user_id))
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(order)) => order,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: OrderStatusError::NotFound.detail(),
+            });
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while finding order: {}", e),
+            });
+        }
+    };
+
+    let summary = match reorder_into_cart(
+        &order,
+        store.get_ref(),
+        config.max_cart_item_qty,
+        config.max_cart_items,
+        now,
+        db.get_ref(),
+    )
+    .await
+    {
+        Ok(summary) => summary,
+        Err(response) => return response,
+    };
+
+    for item in &summary.added {
+        let kind = if item.total_qty == item.added_qty {
+            CartEventKind::ItemAdded
+        } else {
+            CartEventKind::QtyChanged
+        };
+        publish_cart_store_event(&events, &user_id, kind, item.product_id, store.get_ref()).await;
+    }
+
+    let cart = match store.cart_lines(&user_id, &CartListOptions::default()).await {
+        Ok(lines) => apply_cart_coupon(&user_id, CartContents::from_lines(lines), now, db.get_ref()).await,
+        Err(e) => Err(e),
+    };
+    match cart {
+        Ok(cart) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Order items added to cart.".to_string(),
+            data: ReorderResponse { summary, cart },
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while fetching cart: {}", e),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{product_variants, products};
+    use crate::services::{create_cart_session, PostgresCartStore, CART_TOKEN_HEADER};
+    use crate::test_utils::{insert_cart_item, insert_product, test_db};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use sea_orm::prelude::Decimal;
+    use sea_orm::{ActiveModelTrait, IntoActiveModel, Set};
+    use serde_json::json;
+
+    #[actix_web::test]
+    async fn a_placed_order_keeps_its_prices_when_the_product_is_repriced() {
+        let Some(db) = test_db().await else { return };
+        let session = create_cart_session(local_datetime(), &db).await.expect("create cart session");
+        let bangus = insert_product(&db, "Bangus", Decimal::new(15000, 2), 10).await;
+        insert_cart_item(&db, &session.user_id, &bangus, 2).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(CartBackend::Postgres(PostgresCartStore::new(db.clone()))))
+                .app_data(web::Data::new(AppConfig::from_env()))
+                .app_data(web::Data::new(CartEventHub::new()))
+                .service(checkout)
+                .service(fetch_order_detail_by_id),
+        )
+        .await;
+
+        let placed = test::TestRequest::post()
+            .uri("/checkout")
+            .insert_header((CART_TOKEN_HEADER, session.token.as_str()))
+            .set_json(json!({
+                "user_id": session.user_id,
+                "address": {
+                    "recipient_name": "Juan dela Cruz",
+                    "phone": "09171234567",
+                    "line1": "12 Mabini St.",
+                    "barangay": "Poblacion",
+                    "city": "Makati",
+                },
+            }))
+            .to_request();
+        let placed = test::call_service(&app, placed).await;
+        assert_eq!(placed.status(), StatusCode::CREATED);
+        let placed: serde_json::Value = test::read_body_json(placed).await;
+        let order_uri = format!("/orders/{}/{}", session.user_id, placed["data"]["id"].as_str().expect("order id"));
+
+        let fetch_order = || {
+            test::TestRequest::get()
+                .uri(&order_uri)
+                .insert_header((CART_TOKEN_HEADER, session.token.as_str()))
+                .to_request()
+        };
+        let before: serde_json::Value = test::call_and_read_body_json(&app, fetch_order()).await;
+        assert_eq!(before["data"]["subtotal"], placed["data"]["subtotal"]);
+        assert_eq!(before["data"]["total"], placed["data"]["total"]);
+
+        let mut repriced: products::ActiveModel = bangus.into_active_model();
+        repriced.price = Set(Decimal::new(99900, 2));
+        repriced.update(&db).await.expect("reprice product");
+
+        let after: serde_json::Value = test::call_and_read_body_json(&app, fetch_order()).await;
+        assert_eq!(after["data"], before["data"]);
+        assert_eq!(after["data"]["items"][0]["unit_price"], "150.00");
+        assert_eq!(after["data"]["items"][0]["line_total_display"], "300.00");
+        assert_eq!(after["data"]["subtotal_display"], "300.00");
+    }
+
+    #[actix_web::test]
+    async fn reorder_adds_the_variant_and_skips_what_is_below_the_minimum() {
+        let Some(db) = test_db().await else { return };
+        let now = local_datetime();
+        let session = create_cart_session(now, &db).await.expect("create cart session");
+        // Only the variant has stock; tilapia is sold in threes
+        let bangus = insert_product(&db, "Bangus", Decimal::new(15000, 2), 0).await;
+        let variant = product_variants::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            product_id: Set(bangus.id),
+            name: Set("1kg".to_string()),
+            sku: Set(format!("SKU-{}", Uuid::new_v4().simple())),
+            price: Set(None),
+            stock_quantity: Set(5),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(&db)
+        .await
+        .expect("insert variant");
+        let mut tilapia = insert_product(&db, "Tilapia", Decimal::new(9000, 2), 10).await.into_active_model();
+        tilapia.min_order_qty = Set(3);
+        let tilapia = tilapia.update(&db).await.expect("set minimum");
+        let mut line = insert_cart_item(&db, &session.user_id, &bangus, 3).await.into_active_model();
+        line.variant_id = Set(Some(variant.id));
+        line.update(&db).await.expect("pick variant");
+        insert_cart_item(&db, &session.user_id, &tilapia, 3).await;
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(CartBackend::Postgres(PostgresCartStore::new(db.clone()))))
+                .app_data(web::Data::new(AppConfig::from_env()))
+                .app_data(web::Data::new(CartEventHub::new()))
+                .service(checkout)
+                .service(reorder_from_order),
+        )
+        .await;
+
+        let placed = test::TestRequest::post()
+            .uri("/checkout")
+            .insert_header((CART_TOKEN_HEADER, session.token.as_str()))
+            .set_json(json!({
+                "user_id": session.user_id,
+                "address": {
+                    "recipient_name": "Juan dela Cruz",
+                    "phone": "09171234567",
+                    "line1": "12 Mabini St.",
+                    "barangay": "Poblacion",
+                    "city": "Makati",
+                },
+            }))
+            .to_request();
+        let placed = test::call_service(&app, placed).await;
+        assert_eq!(placed.status(), StatusCode::CREATED);
+        let placed: serde_json::Value = test::read_body_json(placed).await;
+
+        // 2 of the variant are left; 2 tilapia is below the minimum of 3
+        let tilapia_id = tilapia.id;
+        let mut tilapia = tilapia.into_active_model();
+        tilapia.stock_quantity = Set(2);
+        tilapia.update(&db).await.expect("set stock");
+
+        let reorder = test::TestRequest::post()
+            .uri(&format!("/orders/{}/{}/reorder", session.user_id, placed["data"]["id"].as_str().expect("order id")))
+            .insert_header((CART_TOKEN_HEADER, session.token.as_str()))
+            .to_request();
+        let reordered = test::call_service(&app, reorder).await;
+        assert_eq!(reordered.status(), StatusCode::OK);
+        let reordered: serde_json::Value = test::read_body_json(reordered).await;
+
+        let added = reordered["data"]["added"].as_array().expect("added items");
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0]["variant_id"], json!(variant.id));
+        assert_eq!(added[0]["ordered_qty"], 3);
+        assert_eq!(added[0]["added_qty"], 2);
+        let skipped = reordered["data"]["skipped"].as_array().expect("skipped items");
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0]["product_id"], json!(tilapia_id));
+        assert_eq!(skipped[0]["reason"], "below_minimum");
+        assert_eq!(reordered["data"]["cart"]["items"][0]["variant_id"], json!(variant.id));
+    }
+}
@@ -1,20 +1,39 @@
-use crate::models::prelude::Products;
-use crate::models::products;
-use crate::models::products::{NewProduct, ProductsResponse};
+use crate::config::AppConfig;
+use crate::extractors::PaginationParams;
+use crate::models::carts;
+use crate::models::prelude::{ProductImages, ProductVariants, Products};
+use crate::models::product_images::{NewProductImage, ReorderProductImages};
+use crate::models::product_variants::NewProductVariant;
+use crate::models::{product_images, product_variants, products};
+use crate::models::products::{BulkProductAvailability, ComparedAttribute, CompareProducts, ImportQuery, PriceUpdateRequest, ImportRowOutcome, ImportRowStatus, NewProduct, NewProductsQuery, ProductDateRange, ProductDeleted, ProductComparison, ProductImportRow, ProductListQuery, ProductSearchQuery, ProductSearchResult, ProductSuggestQuery, ProductSuggestion, ProductSummary, ProductViewQuery, ProductsAvailabilityUpdated, ProductsPage, ProductsResponse, TrendingQuery, TrendingRank};
+use crate::models::price_history::PriceHistoryResponse;
+use crate::models::product_price_tiers::ReplacePriceTiers;
+use crate::models::stock_adjustments::{NewStockAdjustment, StockAdjustmentResponse};
+use crate::middleware::{has_admin_key, require_admin};
+use crate::services::{add_product_image, adjust_product_stock, apply_price_update, fetch_price_history, fetch_price_tiers, replace_price_tiers, validate_price_tiers, record_price_change, attach_product_galleries, available_at, fetch_product_images, fetch_product_variants, find_product_by_id, find_product_variant, record_product_view, search_products, suggest_products, sku_exists, slugify_unique, set_product_image_order, fetch_category_names, validate_product_price, validate_vendor_exists, upsert_product_by_name, validate_import_row, write_products_csv, PriceUpdateOutcome, StockAdjustmentOutcome, ValidatedImportRow};
 use crate::models::responses::{ErrorResponse, SuccessResponse};
-use crate::utils::local_datetime;
-use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
-use sea_orm::prelude::DateTimeWithTimeZone;
-use sea_orm::{ActiveModelTrait, ColumnTrait, QueryOrder};
+use crate::utils::{is_valid_image_url, local_datetime};
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::middleware::from_fn;
+use actix_web::web::Bytes;
+use actix_web::{delete, get, patch, post, put, web, HttpRequest, HttpResponse, Responder};
+use actix_multipart::Multipart;
+use futures_util::{stream, TryStreamExt};
+use chrono::DateTime;
+use sea_orm::prelude::{DateTimeWithTimeZone, Decimal};
+use sea_orm::sea_query::{Expr, Func};
+use sea_orm::{ActiveModelTrait, ColumnTrait, JoinType, PaginatorTrait, QueryOrder, QuerySelect, RelationTrait, Select, TransactionError, TransactionTrait};
 use sea_orm::{EntityTrait, Set};
 use sea_orm::{Order, QueryFilter};
 use serde_json::json;
 use uuid::Uuid;
+use std::collections::{HashMap, HashSet};
 
 /// Create a new product
 ///
 /// - Validates that no product with the same name exists (case-insensitive).
 /// - Normalizes the product name to lowercase before saving.
+/// - Generates a unique `slug` from the name.
 /// - Inserts the product with current timestamps.
 /// - Returns `201 Created` with product details if successful.
 #[post("/products/")]
@@ -25,9 +44,35 @@ pub async fn create_product(
     let now: DateTimeWithTimeZone = local_datetime();
     let normalized_name = new_product.product_name.trim();
 
+    if let Err(reason) = validate_product_price(new_product.price) {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: format!("Price {}.", reason),
+        });
+    }
+
+    if new_product.stock_quantity.is_some_and(|qty| qty < 0) {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Stock quantity cannot be negative.".to_string(),
+        });
+    }
+
+    if new_product.min_order_qty.is_some_and(|qty| qty < 1) {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Minimum order quantity must be at least 1.".to_string(),
+        });
+    }
+
+    if let Err(response) = validate_availability_window(new_product.available_from, new_product.available_until) {
+        return response;
+    }
+
+    if let Err(response) = validate_vendor_exists(new_product.vendor_id.flatten(), db.get_ref()).await {
+        return response;
+    }
+
     // 🔍 Check if a product with the same normalized name already exists
     match products::Entity::find()
-        .filter(products::Column::ProductName.eq(normalized_name.clone()))
+        .filter(products::Column::ProductName.eq(normalized_name))
         .one(db.get_ref())
         .await
     {
@@ -44,15 +89,34 @@ pub async fn create_product(
         Ok(None) => {}
     }
 
+    let slug = match slugify_unique(normalized_name, None, db.get_ref()).await {
+        Ok(slug) => slug,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while generating slug: {}", e),
+            });
+        }
+    };
+
     // 🏗️ Construct the new product ActiveModel
     let new_product_model = products::ActiveModel {
         id: Set(Uuid::new_v4()),
-        product_name: Set(normalized_name.parse().unwrap()),
+        product_name: Set(normalized_name.to_string()),
+        slug: Set(slug),
         description: Set(new_product.description.clone()),
         price: Set(new_product.price),
         category: Set(new_product.category.clone()),
         img_url: Set(new_product.img_url.clone()),
         is_available: Set(new_product.is_available),
+        sold_out: Set(false),
+        stock_quantity: Set(new_product.stock_quantity.unwrap_or(0)),
+        min_order_qty: Set(new_product.min_order_qty.unwrap_or(1)),
+        is_featured: Set(new_product.is_featured.unwrap_or(false)),
+        available_from: Set(new_product.available_from),
+        available_until: Set(new_product.available_until),
+        version: Set(1),
+        view_count: Set(0),
+        vendor_id: Set(new_product.vendor_id.flatten()),
         created_at: Set(now),
         updated_at: Set(now),
     };
@@ -70,40 +134,582 @@ pub async fn create_product(
     }
 }
 
-/// Fetch all products
+/// Fetch a page of products
 ///
-/// - Returns products ordered by creation date (descending).
-/// - Returns `404 Not Found` if there are no products.
-/// - On success, returns a list of products.
+/// - Returns products ordered by creation date (descending), `?page=` / `?per_page=` at a time
+///   (see `PaginationParams`), with `total_products` counting every match.
+/// - `is_available` is only true when the flag is set and now is inside the product's sale window.
+/// - `?now=` (RFC 3339) overrides the current time used for that check.
+/// - `?category=` restricts the list to a single category (case-insensitive).
+/// - `?created_after=`, `?created_before=`, `?updated_after=` and `?updated_before=` (RFC 3339)
+///   bound `created_at` / `updated_at`; `_after` is inclusive and `_before` exclusive.
+/// - Returns `400 Bad Request` for a malformed `now`, date bound, `page` or `per_page`.
+/// - Returns `404 Not Found` if there are no products; a page past the end is just empty.
 #[get("/products")]
-pub async fn fetch_products(db: web::Data<sea_orm::DatabaseConnection>) -> impl Responder {
+pub async fn fetch_products(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    query: web::Query<ProductListQuery>,
+    pagination: PaginationParams,
+) -> impl Responder {
+    let now: DateTimeWithTimeZone = match &query.now {
+        Some(now) => match DateTime::parse_from_rfc3339(now) {
+            Ok(now) => now,
+            Err(_) => {
+                return HttpResponse::BadRequest().json(ErrorResponse {
+                    detail: "Invalid now format. Must be an RFC 3339 timestamp.".to_string(),
+                });
+            }
+        },
+        None => local_datetime(),
+    };
+
+    let date_range = match query.date_range() {
+        Ok(date_range) => date_range,
+        Err(detail) => return HttpResponse::BadRequest().json(ErrorResponse { detail }),
+    };
+
+    let paginator = filter_by_date_range(filter_by_category(Products::find(), query.category.as_deref()), &date_range)
+        .order_by(products::Column::CreatedAt, Order::Desc)
+        .order_by_asc(products::Column::Id)
+        .paginate(db.get_ref(), pagination.per_page);
+
+    let total_products = match paginator.num_items().await {
+        Ok(0) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: "No products found.".to_string(),
+            });
+        }
+        Ok(total_products) => total_products,
+        Err(e) => {
+            eprintln!("❌ Error counting products: {}", e);
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch products: {}", e),
+            });
+        }
+    };
+
+    match paginator.fetch_page(pagination.page_index()).await {
+        Ok(products) => {
+
+            let products_responses: Vec<ProductsResponse> = products
+                .into_iter()
+                .map(|product| ProductsResponse::from_model_at(product, now))
+                .collect();
+
+            let products_responses = match attach_product_galleries(products_responses, db.get_ref()).await {
+                Ok(products_responses) => products_responses,
+                Err(e) => {
+                    return HttpResponse::InternalServerError().json(ErrorResponse {
+                        detail: format!("Failed to fetch product images: {}", e),
+                    });
+                }
+            };
+
+            HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message: "Products fetched successfully.".to_string(),
+                data: ProductsPage {
+                    page: pagination.page,
+                    per_page: pagination.per_page,
+                    total_products,
+                    products: products_responses,
+                },
+            })
+        }
+        Err(e) => {
+            eprintln!("❌ Error fetching products: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch products: {}", e),
+            })
+        }
+    }
+}
+
+/// Fetch a lightweight product list for grid views
+///
+/// - Same order and `?category=` / `?now=` handling as `GET /products`.
+/// - Only selects `id`, `product_name`, `price`, `img_url` and `is_available`; use
+///   `GET /products` or `GET /products/{product_id}` for the full record.
+/// - Returns `400 Bad Request` for a malformed `now`.
+/// - Returns `404 Not Found` if there are no products.
+#[get("/products/summary")]
+pub async fn fetch_product_summaries(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    query: web::Query<ProductListQuery>,
+) -> impl Responder {
+    let now: DateTimeWithTimeZone = match &query.now {
+        Some(now) => match DateTime::parse_from_rfc3339(now) {
+            Ok(now) => now,
+            Err(_) => {
+                return HttpResponse::BadRequest().json(ErrorResponse {
+                    detail: "Invalid now format. Must be an RFC 3339 timestamp.".to_string(),
+                });
+            }
+        },
+        None => local_datetime(),
+    };
+
+    match filter_by_category(Products::find(), query.category.as_deref())
+        .select_only()
+        .columns([
+            products::Column::Id,
+            products::Column::ProductName,
+            products::Column::Price,
+            products::Column::ImgUrl,
+        ])
+        .column_as(
+            Expr::cust_with_values(
+                "products.is_available \
+                 AND (products.available_from IS NULL OR products.available_from <= $1) \
+                 AND (products.available_until IS NULL OR products.available_until > $1)",
+                [now],
+            ),
+            "is_available",
+        )
+        .order_by(products::Column::CreatedAt, Order::Desc)
+        .into_model::<ProductSummary>()
+        .all(db.get_ref())
+        .await
+    {
+        Ok(summaries) if summaries.is_empty() => HttpResponse::NotFound().json(ErrorResponse {
+            detail: "No products found.".to_string(),
+        }),
+        Ok(summaries) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Products fetched successfully.".to_string(),
+            data: summaries,
+        }),
+        Err(e) => {
+            eprintln!("❌ Error fetching product summaries: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch products: {}", e),
+            })
+        }
+    }
+}
+
+/// Export the product catalog as CSV
+///
+/// - Streams rows page by page instead of buffering the whole catalog.
+/// - Columns: id, product_name, description, price, category, is_available, img_url, created_at.
+/// - `price` is formatted with `format_money`.
+/// - `?category=` applies the same filter as `GET /products`.
+/// - Requires the `X-Admin-Key` header.
+#[get("/products/export.csv", wrap = "from_fn(require_admin)")]
+pub async fn export_products_csv(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    query: web::Query<ProductListQuery>,
+) -> impl Responder {
+    let db = db.get_ref().clone();
+    let category = query.into_inner().category;
+
+    // Each step fetches one page; `None` means the previous page was the last one
+    let rows = stream::unfold(Some(0u64), move |page| {
+        let db = db.clone();
+        let category = category.clone();
+        async move {
+            let page = page?;
+            let result = filter_by_category(Products::find(), category.as_deref())
+                .order_by(products::Column::CreatedAt, Order::Desc)
+                .order_by(products::Column::Id, Order::Asc)
+                .paginate(&db, CSV_EXPORT_PAGE_SIZE)
+                .fetch_page(page)
+                .await;
+
+            match result {
+                Ok(products) => {
+                    let next_page = (products.len() as u64 == CSV_EXPORT_PAGE_SIZE).then_some(page + 1);
+                    let chunk = write_products_csv(&products, page == 0)
+                        .map(Bytes::from)
+                        .map_err(actix_web::error::ErrorInternalServerError);
+                    Some((chunk, next_page))
+                }
+                Err(e) => {
+                    eprintln!("❌ Error exporting products: {}", e);
+                    Some((Err(actix_web::error::ErrorInternalServerError(e)), None))
+                }
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename("products.csv".to_string())],
+        })
+        .streaming(rows)
+}
+
+/// Import products from an uploaded CSV
+///
+/// - Expects a multipart upload with the CSV in a `file` field.
+/// - Columns: product_name, price, category, and optionally description, img_url,
+///   is_available and stock_quantity.
+/// - Rows are matched to existing products by name; matches are updated, the rest created.
+/// - Every row is validated (name, positive price, known category) before anything is written.
+/// - Returns `400 Bad Request` if the CSV can't be parsed, or if any row fails validation
+///   and `?partial=true` was not passed. Nothing is written in either case.
+/// - Writes happen in one transaction and are rolled back on a database error.
+/// - Requires the `X-Admin-Key` header.
+#[post("/products/import", wrap = "from_fn(require_admin)")]
+pub async fn import_products_csv(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    query: web::Query<ImportQuery>,
+    payload: Multipart,
+) -> impl Responder {
+    let upload = match read_csv_upload(payload).await {
+        Ok(upload) => upload,
+        Err(response) => return response,
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(upload.as_slice());
+    let mut rows: Vec<ProductImportRow> = Vec::new();
+    for (index, record) in reader.deserialize::<ProductImportRow>().enumerate() {
+        match record {
+            Ok(row) => rows.push(row),
+            Err(e) => {
+                return HttpResponse::BadRequest().json(ErrorResponse {
+                    detail: format!("Failed to parse CSV at row {}: {}", index + 1, e),
+                });
+            }
+        }
+    }
+
+    if rows.is_empty() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "The CSV contains no product rows.".to_string(),
+        });
+    }
+
+    let category_names = match fetch_category_names(db.get_ref()).await {
+        Ok(category_names) => category_names,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while loading categories: {}", e),
+            });
+        }
+    };
+
+    // Validate every row before touching the catalog
+    let mut failures: Vec<ImportRowOutcome> = Vec::new();
+    let mut valid_rows: Vec<(usize, ValidatedImportRow)> = Vec::new();
+    for (index, row) in rows.into_iter().enumerate() {
+        let product_name = row.product_name.trim().to_string();
+        match validate_import_row(row, &category_names) {
+            Ok(valid_row) => valid_rows.push((index + 1, valid_row)),
+            Err(reason) => failures.push(ImportRowOutcome {
+                row: index + 1,
+                product_name,
+                status: ImportRowStatus::Failed,
+                reason: Some(reason),
+            }),
+        }
+    }
+
+    if !failures.is_empty() && !query.partial {
+        return HttpResponse::BadRequest().json(json!({
+            "detail": "Some rows failed validation; no products were imported.",
+            "items": failures,
+        }));
+    }
+
+    let now: DateTimeWithTimeZone = local_datetime();
+    let applied = db
+        .transaction::<_, Vec<ImportRowOutcome>, sea_orm::DbErr>(|txn| {
+            Box::pin(async move {
+                let mut outcomes = Vec::with_capacity(valid_rows.len());
+                for (row, valid_row) in valid_rows {
+                    let product_name = valid_row.product_name.clone();
+                    let status = upsert_product_by_name(valid_row, now, txn).await?;
+                    outcomes.push(ImportRowOutcome {
+                        row,
+                        product_name,
+                        status,
+                        reason: None,
+                    });
+                }
+                Ok(outcomes)
+            })
+        })
+        .await;
+
+    match applied {
+        Ok(mut outcomes) => {
+            outcomes.extend(failures);
+            outcomes.sort_by_key(|outcome| outcome.row);
+            HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message: "Import processed successfully.".to_string(),
+                data: outcomes,
+            })
+        }
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while importing products: {}", e),
+        }),
+    }
+}
+
+/// Fetch featured products for the homepage shelf
+///
+/// - Returns only products that are both featured and available, including their sale window.
+/// - Ordered by `updated_at` (most recently touched first).
+/// - Capped at `FEATURED_PRODUCTS_LIMIT` (12 by default).
+#[get("/products/featured")]
+pub async fn fetch_featured_products(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    config: web::Data<AppConfig>,
+) -> impl Responder {
     match Products::find()
+        .filter(products::Column::IsFeatured.eq(true))
+        .filter(available_at(local_datetime()))
+        .order_by(products::Column::UpdatedAt, Order::Desc)
+        .limit(config.featured_products_limit)
+        .all(db.get_ref())
+        .await
+    {
+        Ok(products) => {
+            let products_responses: Vec<ProductsResponse> = products
+                .into_iter()
+                .map(ProductsResponse::from_model)
+                .collect();
+
+            let products_responses = match attach_product_galleries(products_responses, db.get_ref()).await {
+                Ok(products_responses) => products_responses,
+                Err(e) => {
+                    return HttpResponse::InternalServerError().json(ErrorResponse {
+                        detail: format!("Failed to fetch product images: {}", e),
+                    });
+                }
+            };
+
+            HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message: "Featured products fetched successfully.".to_string(),
+                data: products_responses,
+            })
+        }
+        Err(e) => {
+            eprintln!("❌ Error fetching featured products: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch featured products: {}", e),
+            })
+        }
+    }
+}
+
+/// Fetch recently added products for the homepage shelf
+///
+/// - `?days=` (default 7, at most 365) limits the listing to products created in that many days.
+/// - Returns only available products, newest first, capped at `NEW_PRODUCTS_LIMIT` (12 by default).
+/// - Returns `400 Bad Request` unless `days` is a positive integer.
+#[get("/products/new")]
+pub async fn fetch_new_products(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    config: web::Data<AppConfig>,
+    query: web::Query<NewProductsQuery>,
+) -> impl Responder {
+    let days = query.days.unwrap_or(7);
+    if !(1..=365).contains(&days) {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "days must be a whole number between 1 and 365.".to_string(),
+        });
+    }
+
+    let now = local_datetime();
+    match Products::find()
+        .filter(products::Column::CreatedAt.gt(now - chrono::Duration::days(days)))
+        .filter(available_at(now))
         .order_by(products::Column::CreatedAt, Order::Desc)
+        .limit(config.new_products_limit)
         .all(db.get_ref())
         .await
     {
         Ok(products) => {
-            if products.is_empty() {
-                return HttpResponse::NotFound().json(ErrorResponse {
-                    detail: "No products found.".to_string(),
+            let products_responses: Vec<ProductsResponse> = products
+                .into_iter()
+                .map(|product| ProductsResponse::from_model_at(product, now))
+                .collect();
+
+            let products_responses = match attach_product_galleries(products_responses, db.get_ref()).await {
+                Ok(products_responses) => products_responses,
+                Err(e) => {
+                    return HttpResponse::InternalServerError().json(ErrorResponse {
+                        detail: format!("Failed to fetch product images: {}", e),
+                    });
+                }
+            };
+
+            HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message: "New products fetched successfully.".to_string(),
+                data: products_responses,
+            })
+        }
+        Err(e) => {
+            eprintln!("❌ Error fetching new products: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch new products: {}", e),
+            })
+        }
+    }
+}
+
+/// Fetch trending products ranked by cart activity
+///
+/// - Ranks by total quantity in carts, or by distinct users with `?rank_by=users`.
+/// - `?since=` (RFC 3339) restricts the ranking to cart rows created after that instant.
+/// - `?limit=` defaults to 10 and is capped at 50.
+/// - Returns `400 Bad Request` for a malformed `since`.
+#[get("/products/trending")]
+pub async fn fetch_trending_products(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    query: web::Query<TrendingQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(10).clamp(1, 50);
+
+    let mut select = Products::find()
+        .join(JoinType::InnerJoin, products::Relation::Carts.def())
+        .group_by(products::Column::Id);
+
+    if let Some(since) = &query.since {
+        match DateTime::parse_from_rfc3339(since) {
+            Ok(since) => select = select.filter(carts::Column::CreatedAt.gte(since)),
+            Err(_) => {
+                return HttpResponse::BadRequest().json(ErrorResponse {
+                    detail: "Invalid since format. Must be an RFC 3339 timestamp.".to_string(),
                 });
             }
+        }
+    }
 
+    let score = match query.rank_by {
+        TrendingRank::Quantity => Expr::col((carts::Entity, carts::Column::TotalQty)).sum(),
+        TrendingRank::Users => Expr::col((carts::Entity, carts::Column::UserId)).count_distinct(),
+    };
+
+    match select
+        .order_by(score, Order::Desc)
+        .order_by(products::Column::ProductName, Order::Asc)
+        .limit(limit)
+        .all(db.get_ref())
+        .await
+    {
+        Ok(products) => {
             let products_responses: Vec<ProductsResponse> = products
                 .into_iter()
                 .map(ProductsResponse::from_model)
                 .collect();
 
+            let products_responses = match attach_product_galleries(products_responses, db.get_ref()).await {
+                Ok(products_responses) => products_responses,
+                Err(e) => {
+                    return HttpResponse::InternalServerError().json(ErrorResponse {
+                        detail: format!("Failed to fetch product images: {}", e),
+                    });
+                }
+            };
+
             HttpResponse::Ok().json(SuccessResponse {
                 success: true,
-                message: "Products fetched successfully.".to_string(),
+                message: "Trending products fetched successfully.".to_string(),
                 data: products_responses,
             })
         }
         Err(e) => {
-            eprintln!("❌ Error fetching products: {}", e);
+            eprintln!("❌ Error fetching trending products: {}", e);
             HttpResponse::InternalServerError().json(ErrorResponse {
-                detail: format!("Failed to fetch products: {}", e),
+                detail: format!("Failed to fetch trending products: {}", e),
+            })
+        }
+    }
+}
+
+/// Search products by name and description
+///
+/// - Ranks full-text matches (`ts_rank`) with names weighing more than descriptions; every
+///   word is matched as a prefix, so `?q=mang` finds "mango".
+/// - Queries under 3 characters fall back to a product name prefix match with `rank` 0.
+/// - `?limit=` defaults to 20 and is capped at 100.
+/// - Returns `400 Bad Request` for an empty `q`.
+#[get("/products/search")]
+pub async fn search_products_by_text(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    query: web::Query<ProductSearchQuery>,
+) -> impl Responder {
+    let q = query.q.trim();
+    if q.is_empty() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Search query must not be empty.".to_string(),
+        });
+    }
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+
+    let results = match search_products(q, limit, db.get_ref()).await {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("❌ Error searching products: {}", e);
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to search products: {}", e),
+            });
+        }
+    };
+
+    let (products, ranks): (Vec<products::Model>, Vec<f64>) = results.into_iter().unzip();
+    let products_responses: Vec<ProductsResponse> = products
+        .into_iter()
+        .map(ProductsResponse::from_model)
+        .collect();
+
+    match attach_product_galleries(products_responses, db.get_ref()).await {
+        Ok(products_responses) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Products searched successfully.".to_string(),
+            data: products_responses
+                .into_iter()
+                .zip(ranks)
+                .map(|(product, rank)| ProductSearchResult { product, rank })
+                .collect::<Vec<_>>(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to fetch product images: {}", e),
+        }),
+    }
+}
+
+/// Suggest product names as the user types
+///
+/// - `?q=` is a name prefix, matched case-insensitively; `%` and `_` match literally.
+/// - Returns up to 10 available products as `{ id, product_name }`, most viewed first,
+///   then alphabetically.
+/// - A blank `q` returns an empty list rather than an error, so clients can call it on every keystroke.
+/// - Use `GET /products/search` for full-text search.
+#[get("/products/suggest")]
+pub async fn suggest_product_names(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    query: web::Query<ProductSuggestQuery>,
+) -> impl Responder {
+    let q = query.q.trim();
+    if q.is_empty() {
+        return HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Product suggestions fetched successfully.".to_string(),
+            data: Vec::<ProductSuggestion>::new(),
+        });
+    }
+
+    match suggest_products(q, local_datetime(), db.get_ref()).await {
+        Ok(suggestions) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Product suggestions fetched successfully.".to_string(),
+            data: suggestions,
+        }),
+        Err(e) => {
+            eprintln!("❌ Error suggesting products: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to suggest products: {}", e),
             })
         }
     }
@@ -113,11 +719,14 @@ pub async fn fetch_products(db: web::Data<sea_orm::DatabaseConnection>) -> impl
 ///
 /// - Validates the UUID format.
 /// - Returns `404 Not Found` if the product doesn't exist.
+/// - With `?track=true`, counts a page view by incrementing `view_count` in the same
+///   `UPDATE ... RETURNING` that loads the product; other fetches don't write.
 /// - On success, returns the product details.
 #[get("/products/{product_id}")]
 pub async fn fetch_product_by_id(
     db: web::Data<sea_orm::DatabaseConnection>,
     req: HttpRequest,
+    query: web::Query<ProductViewQuery>,
 ) -> impl Responder {
     // 🛠 Extract product_id from a request path
     let product_id_str = match req.match_info().get("product_id") {
@@ -140,14 +749,28 @@ pub async fn fetch_product_by_id(
     };
 
     // 📦 Fetch the product from the database
-    match Products::find()
-        .filter(products::Column::Id.eq(product_uuid))
-        .one(db.get_ref())
-        .await
-    {
+    let product = if query.track {
+        record_product_view(product_uuid, db.get_ref()).await
+    } else {
+        Products::find()
+            .filter(products::Column::Id.eq(product_uuid))
+            .one(db.get_ref())
+            .await
+    };
+
+    match product {
         Ok(Some(product)) => {
             let products_responses = vec![ProductsResponse::from_model(product)];
 
+            let products_responses = match attach_product_galleries(products_responses, db.get_ref()).await {
+                Ok(products_responses) => products_responses,
+                Err(e) => {
+                    return HttpResponse::InternalServerError().json(ErrorResponse {
+                        detail: format!("Failed to fetch product images: {}", e),
+                    });
+                }
+            };
+
             HttpResponse::Ok().json(SuccessResponse {
                 success: true,
                 message: "Product fetched successfully.".to_string(),
@@ -168,35 +791,313 @@ pub async fn fetch_product_by_id(
 
 
 
-#[put("/products/{product_id}/")]
-pub async fn update_product(
+/// Fetch a single product by its slug
+///
+/// - Same response as `GET /products/{product_id}`.
+/// - Returns `404 Not Found` if no product has this slug.
+#[get("/products/slug/{slug}")]
+pub async fn fetch_product_by_slug(
     db: web::Data<sea_orm::DatabaseConnection>,
     path: web::Path<String>,
-    updated_product: web::Json<NewProduct>,
 ) -> impl Responder {
-    // 🛠 Extract product_id from path parameters
-    let product_id_str = path.into_inner();
-
-    // Parse the product_id string to Uuid
-    let product_id = match Uuid::parse_str(&product_id_str) {
-        Ok(id) => id,
-        Err(_) => {
-            return HttpResponse::BadRequest().json(json!({
-                "detail": "Invalid product_id format. Must be a valid UUID."
-            }));
-        }
-    };
+    let slug = path.into_inner();
 
-    // 🔍 First, check if the product exists
-    let existing_product = match Products::find_by_id(product_id)
+    match Products::find()
+        .filter(products::Column::Slug.eq(slug.to_lowercase()))
         .one(db.get_ref())
         .await
     {
-        Ok(Some(product)) => product,
-        Ok(None) => {
-            return HttpResponse::NotFound().json(json!({
-                "detail": "Product not found."
-            }));
+        Ok(Some(product)) => {
+            let products_responses = vec![ProductsResponse::from_model(product)];
+
+            let products_responses = match attach_product_galleries(products_responses, db.get_ref()).await {
+                Ok(products_responses) => products_responses,
+                Err(e) => {
+                    return HttpResponse::InternalServerError().json(ErrorResponse {
+                        detail: format!("Failed to fetch product images: {}", e),
+                    });
+                }
+            };
+
+            HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message: "Product fetched successfully.".to_string(),
+                data: products_responses,
+            })
+        }
+        Ok(None) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: "Product not found.".to_string(),
+        }),
+        Err(e) => {
+            eprintln!("❌ Error fetching product: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch product: {}", e),
+            })
+        }
+    }
+}
+
+/// Most product ids `PATCH /products/availability` accepts in one call.
+const MAX_BULK_AVAILABILITY_IDS: usize = 500;
+
+/// Show or hide many products at once
+///
+/// - Body: `{ "ids": ["...", "..."], "is_available": false }`.
+/// - Runs as a single update, so either every listed product changes or none does.
+/// - Bumps `version` on each updated product, like any other edit.
+/// - Returns the number of products updated; ids that don't exist are ignored.
+/// - Returns `400 Bad Request` if `ids` is empty, holds more than 500 ids, or isn't a list of UUIDs.
+/// - Requires the `X-Admin-Key` header.
+#[patch("/products/availability", wrap = "from_fn(require_admin)")]
+pub async fn update_products_availability(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    payload: web::Json<BulkProductAvailability>,
+) -> impl Responder {
+    let BulkProductAvailability { mut ids, is_available } = payload.into_inner();
+    ids.sort();
+    ids.dedup();
+
+    if ids.is_empty() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "At least one product id is required.".to_string(),
+        });
+    }
+    if ids.len() > MAX_BULK_AVAILABILITY_IDS {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: format!("At most {} products can be updated at once.", MAX_BULK_AVAILABILITY_IDS),
+        });
+    }
+
+    let result = Products::update_many()
+        .col_expr(products::Column::IsAvailable, Expr::value(is_available))
+        .col_expr(products::Column::SoldOut, Expr::value(false))
+        .col_expr(products::Column::Version, Expr::col(products::Column::Version).add(1))
+        .col_expr(products::Column::UpdatedAt, Expr::value(local_datetime()))
+        .filter(products::Column::Id.is_in(ids))
+        .exec(db.get_ref())
+        .await;
+
+    match result {
+        Ok(result) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Product availability updated.".to_string(),
+            data: ProductsAvailabilityUpdated {
+                updated: result.rows_affected,
+            },
+        }),
+        Err(e) => {
+            eprintln!("❌ Error updating product availability: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to update product availability: {}", e),
+            })
+        }
+    }
+}
+
+/// How many products `POST /products/compare` puts side by side.
+const COMPARED_PRODUCTS: std::ops::RangeInclusive<usize> = 2..=4;
+
+/// Compare a few products side by side
+///
+/// - Body: `{ "ids": ["...", "..."] }` with 2 to 4 distinct product ids.
+/// - Returns the full `products` in the requested order, plus an `attributes` matrix with one
+///   row each for `price`, `category`, `is_available`, `stock_quantity` and `min_order_qty`,
+///   whose `values` follow the same order and whose `differs` flags rows that don't all match.
+/// - Returns `400 Bad Request` for fewer than 2 or more than 4 ids, or a repeated id.
+/// - Returns `404 Not Found` naming any id that doesn't exist.
+#[post("/products/compare")]
+pub async fn compare_products(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    payload: web::Json<CompareProducts>,
+) -> impl Responder {
+    let CompareProducts { ids } = payload.into_inner();
+
+    if !COMPARED_PRODUCTS.contains(&ids.len()) {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: format!(
+                "Between {} and {} product ids can be compared.",
+                COMPARED_PRODUCTS.start(),
+                COMPARED_PRODUCTS.end()
+            ),
+        });
+    }
+    if ids.iter().collect::<HashSet<_>>().len() != ids.len() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Each product can only be compared once.".to_string(),
+        });
+    }
+
+    let found = match Products::find()
+        .filter(products::Column::Id.is_in(ids.clone()))
+        .all(db.get_ref())
+        .await
+    {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("❌ Error fetching products to compare: {}", e);
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch products: {}", e),
+            });
+        }
+    };
+
+    let mut by_id: HashMap<Uuid, products::Model> =
+        found.into_iter().map(|product| (product.id, product)).collect();
+    let missing: Vec<String> = ids
+        .iter()
+        .filter(|id| !by_id.contains_key(id))
+        .map(|id| id.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return HttpResponse::NotFound().json(ErrorResponse {
+            detail: format!("Product(s) not found: {}.", missing.join(", ")),
+        });
+    }
+
+    let now = local_datetime();
+    let compared: Vec<products::Model> = ids.iter().filter_map(|id| by_id.remove(id)).collect();
+    let attributes = ComparedAttribute::matrix(&compared, now);
+    let products_responses = compared
+        .into_iter()
+        .map(|product| ProductsResponse::from_model_at(product, now))
+        .collect();
+
+    match attach_product_galleries(products_responses, db.get_ref()).await {
+        Ok(products_responses) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Products compared successfully.".to_string(),
+            data: ProductComparison {
+                products: products_responses,
+                attributes,
+            },
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to fetch product images: {}", e),
+        }),
+    }
+}
+
+/// Most `{ id, price }` pairs `POST /products/price-update` accepts in one call.
+const MAX_BULK_PRICE_UPDATES: usize = 500;
+
+/// Reprice many products at once
+///
+/// - Body: a list of `{ "id": "...", "price": "12.50" }` pairs, or
+///   `{ "category": "Fruits", "percent_change": -20 }` to change every product in a category
+///   (case-insensitive) by a percentage, rounded to cents.
+/// - Runs in one transaction: new prices must be positive, have at most two decimal places and
+///   stay below 100,000,000, and any violation rejects the whole batch.
+/// - Bumps `version` on each repriced product, like any other edit.
+/// - Returns each product's `old_price` and `new_price`.
+/// - Returns `400 Bad Request` for an empty or oversized list, a repeated id or an invalid price.
+/// - Returns `404 Not Found` if a listed product doesn't exist or the category has no products.
+/// - Requires the `X-Admin-Key` header.
+#[post("/products/price-update", wrap = "from_fn(require_admin)")]
+pub async fn update_product_prices(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    payload: web::Json<PriceUpdateRequest>,
+) -> impl Responder {
+    let request = payload.into_inner();
+
+    if let PriceUpdateRequest::Prices(prices) = &request {
+        if prices.is_empty() {
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                detail: "At least one price change is required.".to_string(),
+            });
+        }
+        if prices.len() > MAX_BULK_PRICE_UPDATES {
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                detail: format!("At most {} prices can be updated at once.", MAX_BULK_PRICE_UPDATES),
+            });
+        }
+        let mut seen = HashSet::new();
+        if let Some(change) = prices.iter().find(|change| !seen.insert(change.id)) {
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                detail: format!("Product {} is listed more than once.", change.id),
+            });
+        }
+    }
+
+    let now = local_datetime();
+    let result = db
+        .transaction::<_, PriceUpdateOutcome, sea_orm::DbErr>(|txn| {
+            Box::pin(async move { apply_price_update(request, now, txn).await })
+        })
+        .await;
+
+    match result {
+        Ok(PriceUpdateOutcome::Applied(changes)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: format!("{} product prices updated.", changes.len()),
+            data: changes,
+        }),
+        Ok(PriceUpdateOutcome::ProductsNotFound(ids)) => HttpResponse::NotFound().json(json!({
+            "detail": "Some products were not found; no prices were changed.",
+            "product_ids": ids,
+        })),
+        Ok(PriceUpdateOutcome::CategoryEmpty) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: "No products found in that category.".to_string(),
+        }),
+        Ok(PriceUpdateOutcome::Invalid(detail)) => HttpResponse::BadRequest().json(ErrorResponse {
+            detail: format!("{} No prices were changed.", detail),
+        }),
+        Err(e) => {
+            eprintln!("❌ Error updating product prices: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to update product prices: {}", e),
+            })
+        }
+    }
+}
+
+/// Update a product
+///
+/// - The payload should carry the `version` the edit was based on; without it the update
+///   applies over whatever is stored.
+/// - Stock can't be changed here: a `stock_quantity` other than the stored one is refused with
+///   `400 Bad Request`; use `POST /products/{product_id}/stock` so the change is audited.
+/// - Changing `price` requires the `X-Admin-Key` header (`401 Unauthorized` without it), and
+///   the new price must be positive with at most two decimal places.
+/// - Renaming the product regenerates its `slug`. A `null` `vendor_id` clears the vendor.
+/// - Returns `409 Conflict` if the product was changed since the sent version, so
+///   concurrent edits can't silently overwrite each other.
+/// - Returns `404 Not Found` if the product doesn't exist.
+#[put("/products/{product_id}/")]
+pub async fn update_product(
+    req: HttpRequest,
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<String>,
+    updated_product: web::Json<NewProduct>,
+) -> impl Responder {
+    // 🛠 Extract product_id from path parameters
+    let product_id_str = path.into_inner();
+
+    // Parse the product_id string to Uuid
+    let product_id = match Uuid::parse_str(&product_id_str) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(json!({
+                "detail": "Invalid product_id format. Must be a valid UUID."
+            }));
+        }
+    };
+
+    if updated_product.min_order_qty.is_some_and(|qty| qty < 1) {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Minimum order quantity must be at least 1.".to_string(),
+        });
+    }
+
+    // 🔍 First, check if the product exists
+    let existing_product = match Products::find_by_id(product_id)
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(product)) => product,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(json!({
+                "detail": "Product not found."
+            }));
         }
         Err(e) => {
             return HttpResponse::InternalServerError().json(ErrorResponse {
@@ -205,34 +1106,290 @@ pub async fn update_product(
         }
     };
 
+    let expected_version = updated_product.version;
+    if expected_version.is_some_and(|version| version != existing_product.version) {
+        return stale_product_response();
+    }
+
+    // Repricing is held to the same rules as `POST /products/price-update`
+    if updated_product.price != existing_product.price {
+        if !has_admin_key(&req) {
+            return HttpResponse::Unauthorized().json(ErrorResponse {
+                detail: "Changing a product's price requires the admin key.".to_string(),
+            });
+        }
+        if let Err(reason) = validate_product_price(updated_product.price) {
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                detail: format!("Price {}.", reason),
+            });
+        }
+    }
+
+    // Echoing the current stock back is fine; changing it has to leave an audit record
+    if updated_product
+        .stock_quantity
+        .is_some_and(|qty| qty != existing_product.stock_quantity)
+    {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: format!(
+                "Stock can't be changed when updating a product. Use POST /products/{}/stock instead.",
+                product_id
+            ),
+        });
+    }
+
+    let available_from = updated_product.available_from.or(existing_product.available_from);
+    let available_until = updated_product.available_until.or(existing_product.available_until);
+    if let Err(response) = validate_availability_window(available_from, available_until) {
+        return response;
+    }
+
+    if let Err(response) = validate_vendor_exists(updated_product.vendor_id.flatten(), db.get_ref()).await {
+        return response;
+    }
+
     let now: DateTimeWithTimeZone = local_datetime();
     let normalized_name = updated_product.product_name.trim();
 
+    let slug = if normalized_name == existing_product.product_name {
+        existing_product.slug.clone()
+    } else {
+        match slugify_unique(normalized_name, Some(product_id), db.get_ref()).await {
+            Ok(slug) => slug,
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(ErrorResponse {
+                    detail: format!("Database error while generating slug: {}", e),
+                });
+            }
+        }
+    };
+
     // 🏗️ Create ActiveModel for updating (keeping existing id and created_at)
     let mut product_active_model: products::ActiveModel = existing_product.into();
 
     // Update only the fields that should change
-    product_active_model.product_name = Set(normalized_name.parse().unwrap());
+    product_active_model.product_name = Set(normalized_name.to_string());
+    product_active_model.slug = Set(slug);
     product_active_model.description = Set(updated_product.description.clone());
     product_active_model.price = Set(updated_product.price);
     product_active_model.category = Set(updated_product.category.clone());
     product_active_model.img_url = Set(updated_product.img_url.clone());
+    // Showing or hiding the product by hand overrides a sell-out
+    if product_active_model.is_available.as_ref() != &updated_product.is_available {
+        product_active_model.sold_out = Set(false);
+    }
     product_active_model.is_available = Set(updated_product.is_available);
+    if let Some(min_order_qty) = updated_product.min_order_qty {
+        product_active_model.min_order_qty = Set(min_order_qty);
+    }
+    if let Some(is_featured) = updated_product.is_featured {
+        product_active_model.is_featured = Set(is_featured);
+    }
+    if let Some(vendor_id) = updated_product.vendor_id {
+        product_active_model.vendor_id = Set(vendor_id);
+    }
+    product_active_model.available_from = Set(available_from);
+    product_active_model.available_until = Set(available_until);
     product_active_model.updated_at = Set(now);
 
-    // 💾 Update the product in the database
-    match product_active_model.update(db.get_ref()).await {
+    // 💾 Update the product, but only if nobody else bumped the version in the meantime
+    // (unless no version was sent), and record a price change with it
+    let updated = db
+        .transaction::<_, products::Model, sea_orm::DbErr>(|txn| {
+            Box::pin(async move {
+                let current = Products::find_by_id(product_id)
+                    .lock_exclusive()
+                    .one(txn)
+                    .await?
+                    .ok_or(sea_orm::DbErr::RecordNotFound("Product not found.".to_string()))?;
+                if expected_version.is_some_and(|version| version != current.version) {
+                    return Err(sea_orm::DbErr::RecordNotUpdated);
+                }
+
+                let mut product_active_model = product_active_model;
+                product_active_model.version = Set(current.version + 1);
+                let updated_product = Products::update(product_active_model).exec(txn).await?;
+                record_price_change(product_id, current.price, updated_product.price, now, txn).await?;
+                Ok(updated_product)
+            })
+        })
+        .await;
+
+    match updated {
         Ok(updated_product) => HttpResponse::Ok().json(SuccessResponse {
             success: true,
             message: "Product updated successfully.".to_string(),
             data: vec![updated_product],
         }),
+        Err(TransactionError::Transaction(sea_orm::DbErr::RecordNotUpdated)) => stale_product_response(),
+        Err(TransactionError::Transaction(sea_orm::DbErr::RecordNotFound(_))) => HttpResponse::NotFound().json(json!({
+            "detail": "Product not found."
+        })),
         Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
             detail: format!("Failed to update product: {}", e),
         }),
     }
 }
 
+/// Fetch a product's price history
+///
+/// - Every change made through `PUT /products/{product_id}/`, `POST /products/price-update`
+///   or the CSV import, newest first.
+/// - `old_price` and `new_price` are formatted with `format_money`.
+/// - Returns `404 Not Found` if the product doesn't exist.
+#[get("/products/{product_id}/price-history")]
+pub async fn fetch_product_price_history(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let Ok(product_id) = Uuid::parse_str(&path.into_inner()) else {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Invalid product_id format. Must be a valid UUID.".to_string(),
+        });
+    };
+
+    match find_product_by_id(product_id, db.get_ref()).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: "Product not found.".to_string(),
+            });
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch product: {}", e),
+            });
+        }
+    }
+
+    match fetch_price_history(product_id, db.get_ref()).await {
+        Ok(entries) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Price history fetched successfully.".to_string(),
+            data: entries
+                .into_iter()
+                .map(PriceHistoryResponse::from_model)
+                .collect::<Vec<_>>(),
+        }),
+        Err(e) => {
+            eprintln!("❌ Error fetching price history: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch price history: {}", e),
+            })
+        }
+    }
+}
+
+/// Fetch a product's quantity discount tiers
+///
+/// - Lowest `min_qty` first; from `min_qty` units on, each unit costs the tier's `unit_price`.
+/// - Returns `404 Not Found` if the product doesn't exist.
+#[get("/products/{product_id}/price-tiers")]
+pub async fn fetch_product_price_tiers(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let Ok(product_id) = Uuid::parse_str(&path.into_inner()) else {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Invalid product_id format. Must be a valid UUID.".to_string(),
+        });
+    };
+
+    match find_product_by_id(product_id, db.get_ref()).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: "Product not found.".to_string(),
+            });
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch product: {}", e),
+            });
+        }
+    }
+
+    match fetch_price_tiers(product_id, db.get_ref()).await {
+        Ok(tiers) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Price tiers fetched successfully.".to_string(),
+            data: tiers,
+        }),
+        Err(e) => {
+            eprintln!("❌ Error fetching price tiers: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch price tiers: {}", e),
+            })
+        }
+    }
+}
+
+/// Replace a product's quantity discount tiers
+///
+/// - Body: `{ "tiers": [{ "min_qty": 10, "unit_price": "45.00" }, ...] }`; an empty list removes them all.
+/// - Each `min_qty` must be at least 2 and unique, and each `unit_price` a valid price below the
+///   product price; returns `400 Bad Request` otherwise.
+/// - Carts use the tier matching each line's `total_qty` (lines without a variant only).
+/// - Returns `404 Not Found` if the product doesn't exist, else the new tiers, lowest `min_qty` first.
+/// - Requires the `X-Admin-Key` header.
+#[put("/products/{product_id}/price-tiers", wrap = "from_fn(require_admin)")]
+pub async fn replace_product_price_tiers(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<String>,
+    payload: web::Json<ReplacePriceTiers>,
+) -> impl Responder {
+    let Ok(product_id) = Uuid::parse_str(&path.into_inner()) else {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Invalid product_id format. Must be a valid UUID.".to_string(),
+        });
+    };
+
+    let product = match find_product_by_id(product_id, db.get_ref()).await {
+        Ok(Some(product)) => product,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: "Product not found.".to_string(),
+            });
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch product: {}", e),
+            });
+        }
+    };
+
+    if let Err(detail) = validate_price_tiers(&payload.tiers, product.price) {
+        return HttpResponse::BadRequest().json(ErrorResponse { detail });
+    }
+
+    let now = local_datetime();
+    let tiers = payload.into_inner().tiers;
+    let result = db
+        .transaction::<_, _, sea_orm::DbErr>(|txn| {
+            Box::pin(async move { replace_price_tiers(product_id, &tiers, now, txn).await })
+        })
+        .await;
+
+    match result {
+        Ok(tiers) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Price tiers updated successfully.".to_string(),
+            data: tiers,
+        }),
+        Err(e) => {
+            eprintln!("❌ Error replacing price tiers: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to update price tiers: {}", e),
+            })
+        }
+    }
+}
+
+/// Delete a product
+///
+/// - Cart lines holding the product are deleted with it (`ON DELETE CASCADE`);
+///   `cart_items_removed` reports how many.
+/// - Returns `404 Not Found` if the product doesn't exist.
 #[delete("/products/{product_id}")]
 pub async fn delete_product(
     db: web::Data<sea_orm::DatabaseConnection>,
@@ -251,19 +1408,30 @@ pub async fn delete_product(
         }
     };
 
+    // 🗑️ Count the cart lines the cascade will take, then delete the product, in one transaction
+    let deleted = db
+        .transaction::<_, (u64, u64), sea_orm::DbErr>(|txn| {
+            Box::pin(async move {
+                let cart_items_removed = carts::Entity::find()
+                    .filter(carts::Column::ProductId.eq(product_id))
+                    .count(txn)
+                    .await?;
+                let delete_result = Products::delete_by_id(product_id).exec(txn).await?;
+                Ok((delete_result.rows_affected, cart_items_removed))
+            })
+        })
+        .await;
 
-
-    // 🗑️ Delete the product from the database
-    match Products::delete_by_id(product_id)
-        .exec(db.get_ref())
-        .await
-    {
-        Ok(delete_result) => {
-            if delete_result.rows_affected > 0 {
+    match deleted {
+        Ok((rows_affected, cart_items_removed)) => {
+            if rows_affected > 0 {
                 HttpResponse::Ok().json(SuccessResponse {
                     success: true,
                     message: "Product deleted successfully.".to_string(),
-                    data: "None",
+                    data: ProductDeleted {
+                        product_id,
+                        cart_items_removed,
+                    },
                 })
             } else {
                 HttpResponse::NotFound().json(json!({
@@ -275,4 +1443,911 @@ pub async fn delete_product(
             detail: format!("Failed to delete product: {}", e),
         }),
     }
-}
\ No newline at end of file
+}
+
+/// Adjust a product's stock by a signed delta
+///
+/// - Applies the delta atomically and rejects it with `409 Conflict` if stock would go negative.
+/// - Records the change in `stock_adjustments` with the given reason, in the same transaction.
+/// - Returns `400 Bad Request` for a zero delta or an empty reason.
+/// - Returns `404 Not Found` if the product doesn't exist.
+/// - Requires the `X-Admin-Key` header.
+#[post("/products/{product_id}/stock", wrap = "from_fn(require_admin)")]
+pub async fn adjust_stock(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<String>,
+    adjustment: web::Json<NewStockAdjustment>,
+) -> impl Responder {
+    let product_id = match Uuid::parse_str(&path.into_inner()) {
+        Ok(id) => id,
+        Err(_) => {
+            return HttpResponse::BadRequest().json(json!({
+                "detail": "Invalid product_id format. Must be a valid UUID."
+            }));
+        }
+    };
+
+    let adjustment = adjustment.into_inner();
+    let reason = adjustment.reason.trim().to_string();
+    if adjustment.delta == 0 {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Delta must not be zero.".to_string(),
+        });
+    }
+    if reason.is_empty() {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "A reason is required for stock adjustments.".to_string(),
+        });
+    }
+
+    let now: DateTimeWithTimeZone = local_datetime();
+    let delta = adjustment.delta;
+    let result = db
+        .transaction::<_, StockAdjustmentOutcome, sea_orm::DbErr>(|txn| {
+            Box::pin(async move { adjust_product_stock(product_id, delta, reason, now, txn).await })
+        })
+        .await;
+
+    match result {
+        Ok(StockAdjustmentOutcome::Applied(adjustment)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Stock adjusted successfully.".to_string(),
+            data: StockAdjustmentResponse {
+                product_id,
+                stock_quantity: adjustment.resulting_qty,
+                adjustment,
+            },
+        }),
+        Ok(StockAdjustmentOutcome::ProductNotFound) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: "Product not found.".to_string(),
+        }),
+        Ok(StockAdjustmentOutcome::InsufficientStock(current)) => HttpResponse::Conflict().json(ErrorResponse {
+            detail: format!(
+                "Adjustment would make stock negative (current stock: {}).",
+                current
+            ),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to adjust stock: {}", e),
+        }),
+    }
+}
+
+// Rejects a sale window whose start is not before its end
+fn validate_availability_window(
+    available_from: Option<DateTimeWithTimeZone>,
+    available_until: Option<DateTimeWithTimeZone>,
+) -> Result<(), HttpResponse> {
+    match (available_from, available_until) {
+        (Some(from), Some(until)) if from >= until => Err(HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "available_from must be before available_until.".to_string(),
+        })),
+        _ => Ok(()),
+    }
+}
+
+// Number of products fetched per chunk of the CSV export
+const CSV_EXPORT_PAGE_SIZE: u64 = 500;
+
+// Restricts a product query to one category, matched case-insensitively
+fn filter_by_category(select: Select<Products>, category: Option<&str>) -> Select<Products> {
+    match category.map(str::trim).filter(|category| !category.is_empty()) {
+        Some(category) => select.filter(
+            Expr::expr(Func::lower(Expr::col(products::Column::Category))).eq(category.to_lowercase()),
+        ),
+        None => select,
+    }
+}
+
+fn filter_by_date_range(mut select: Select<Products>, range: &ProductDateRange) -> Select<Products> {
+    if let Some(created_after) = range.created_after {
+        select = select.filter(products::Column::CreatedAt.gte(created_after));
+    }
+    if let Some(created_before) = range.created_before {
+        select = select.filter(products::Column::CreatedAt.lt(created_before));
+    }
+    if let Some(updated_after) = range.updated_after {
+        select = select.filter(products::Column::UpdatedAt.gte(updated_after));
+    }
+    if let Some(updated_before) = range.updated_before {
+        select = select.filter(products::Column::UpdatedAt.lt(updated_before));
+    }
+    select
+}
+
+// Largest CSV upload accepted by the product import
+const MAX_IMPORT_BYTES: usize = 5 * 1024 * 1024;
+
+// Reads the `file` field of a multipart upload into memory
+async fn read_csv_upload(mut payload: Multipart) -> Result<Vec<u8>, HttpResponse> {
+    let mut upload: Option<Vec<u8>> = None;
+
+    loop {
+        let mut field = match payload.try_next().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => {
+                return Err(HttpResponse::BadRequest().json(ErrorResponse {
+                    detail: format!("Invalid multipart upload: {}", e),
+                }));
+            }
+        };
+
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let mut contents = Vec::new();
+        loop {
+            match field.try_next().await {
+                Ok(Some(chunk)) => {
+                    if contents.len() + chunk.len() > MAX_IMPORT_BYTES {
+                        return Err(HttpResponse::PayloadTooLarge().json(ErrorResponse {
+                            detail: format!("CSV uploads are limited to {} bytes.", MAX_IMPORT_BYTES),
+                        }));
+                    }
+                    contents.extend_from_slice(&chunk);
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    return Err(HttpResponse::BadRequest().json(ErrorResponse {
+                        detail: format!("Invalid multipart upload: {}", e),
+                    }));
+                }
+            }
+        }
+        upload = Some(contents);
+    }
+
+    upload.ok_or_else(|| {
+        HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "A CSV file is required in the `file` field.".to_string(),
+        })
+    })
+}
+
+// Response for an update based on an outdated product version
+fn stale_product_response() -> HttpResponse {
+    HttpResponse::Conflict().json(ErrorResponse {
+        detail: "The product was modified by someone else. Reload it and try again.".to_string(),
+    })
+}
+
+/// Add an image to a product's gallery
+///
+/// - The URL must be an absolute `http(s)` URL.
+/// - The image is appended after the existing ones.
+/// - Returns `404 Not Found` if the product doesn't exist.
+/// - Requires the `X-Admin-Key` header.
+#[post("/products/{product_id}/images", wrap = "from_fn(require_admin)")]
+pub async fn add_product_gallery_image(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<Uuid>,
+    new_image: web::Json<NewProductImage>,
+) -> impl Responder {
+    let product_id = path.into_inner();
+    let url = new_image.url.trim().to_string();
+
+    if !is_valid_image_url(&url) {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Image URL must be a valid http(s) URL.".to_string(),
+        });
+    }
+
+    if let Err(response) = ensure_product_exists(product_id, db.get_ref()).await {
+        return response;
+    }
+
+    match add_product_image(product_id, url, local_datetime(), db.get_ref()).await {
+        Ok(image) => HttpResponse::Created().json(SuccessResponse {
+            success: true,
+            message: "Product image added successfully.".to_string(),
+            data: image,
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to add product image: {}", e),
+        }),
+    }
+}
+
+/// Remove an image from a product's gallery
+///
+/// - Returns `404 Not Found` if the image doesn't belong to the product.
+/// - Requires the `X-Admin-Key` header.
+#[delete("/products/{product_id}/images/{image_id}", wrap = "from_fn(require_admin)")]
+pub async fn delete_product_gallery_image(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> impl Responder {
+    let (product_id, image_id) = path.into_inner();
+
+    match ProductImages::delete_many()
+        .filter(product_images::Column::Id.eq(image_id))
+        .filter(product_images::Column::ProductId.eq(product_id))
+        .exec(db.get_ref())
+        .await
+    {
+        Ok(delete_result) if delete_result.rows_affected > 0 => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Product image deleted successfully.".to_string(),
+            data: "None",
+        }),
+        Ok(_) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: "Product image not found.".to_string(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to delete product image: {}", e),
+        }),
+    }
+}
+
+/// Reorder a product's gallery
+///
+/// - `image_ids` must list every image of the product exactly once, in the new order.
+/// - Returns the gallery in its new order.
+/// - Returns `400 Bad Request` if the list doesn't match the product's images.
+/// - Returns `404 Not Found` if the product doesn't exist.
+/// - Requires the `X-Admin-Key` header.
+#[put("/products/{product_id}/images/order", wrap = "from_fn(require_admin)")]
+pub async fn reorder_product_gallery_images(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<Uuid>,
+    payload: web::Json<ReorderProductImages>,
+) -> impl Responder {
+    let product_id = path.into_inner();
+
+    if let Err(response) = ensure_product_exists(product_id, db.get_ref()).await {
+        return response;
+    }
+
+    let images = match fetch_product_images(product_id, db.get_ref()).await {
+        Ok(images) => images,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch product images: {}", e),
+            });
+        }
+    };
+
+    let mut requested_ids = payload.image_ids.clone();
+    requested_ids.sort();
+    requested_ids.dedup();
+    let mut current_ids: Vec<Uuid> = images.iter().map(|image| image.id).collect();
+    current_ids.sort();
+    if requested_ids.len() != payload.image_ids.len() || requested_ids != current_ids {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "image_ids must list every image of this product exactly once.".to_string(),
+        });
+    }
+
+    let image_ids = payload.into_inner().image_ids;
+    let reordered = db
+        .transaction::<_, Vec<product_images::Model>, sea_orm::DbErr>(|txn| {
+            Box::pin(async move {
+                set_product_image_order(product_id, &image_ids, txn).await?;
+                fetch_product_images(product_id, txn).await
+            })
+        })
+        .await;
+
+    match reordered {
+        Ok(images) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Product images reordered successfully.".to_string(),
+            data: images,
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to reorder product images: {}", e),
+        }),
+    }
+}
+
+/// List a product's variants
+///
+/// - Returns `404 Not Found` if the product doesn't exist.
+#[get("/products/{product_id}/variants")]
+pub async fn fetch_product_variants_by_product_id(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let product_id = path.into_inner();
+
+    if let Err(response) = ensure_product_exists(product_id, db.get_ref()).await {
+        return response;
+    }
+
+    match fetch_product_variants(product_id, db.get_ref()).await {
+        Ok(variants) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Product variants fetched successfully.".to_string(),
+            data: variants,
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to fetch product variants: {}", e),
+        }),
+    }
+}
+
+/// Add a variant (e.g. a size or color) to a product
+///
+/// - `price` overrides the product price; the product price is used when omitted.
+/// - The variant keeps its own `stock_quantity`, defaulting to 0.
+/// - Returns `409 Conflict` if the SKU is already used (case-insensitive).
+/// - Returns `404 Not Found` if the product doesn't exist.
+/// - Requires the `X-Admin-Key` header.
+#[post("/products/{product_id}/variants", wrap = "from_fn(require_admin)")]
+pub async fn add_product_variant(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<Uuid>,
+    new_variant: web::Json<NewProductVariant>,
+) -> impl Responder {
+    let product_id = path.into_inner();
+
+    if let Err(response) = validate_variant(&new_variant) {
+        return response;
+    }
+
+    if let Err(response) = ensure_product_exists(product_id, db.get_ref()).await {
+        return response;
+    }
+
+    if let Err(response) = ensure_sku_available(new_variant.sku.trim(), None, db.get_ref()).await {
+        return response;
+    }
+
+    let now = local_datetime();
+    let variant = product_variants::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        product_id: Set(product_id),
+        name: Set(new_variant.name.trim().to_string()),
+        sku: Set(new_variant.sku.trim().to_string()),
+        price: Set(new_variant.price),
+        stock_quantity: Set(new_variant.stock_quantity.unwrap_or(0)),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    match variant.insert(db.get_ref()).await {
+        Ok(variant) => HttpResponse::Created().json(SuccessResponse {
+            success: true,
+            message: "Product variant added successfully.".to_string(),
+            data: variant,
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to add product variant: {}", e),
+        }),
+    }
+}
+
+/// Update a product variant
+///
+/// - Replaces the name, SKU, price override and stock; an omitted `stock_quantity` is left unchanged.
+/// - Returns `409 Conflict` if the SKU is used by another variant.
+/// - Returns `404 Not Found` if the variant doesn't belong to the product.
+/// - Requires the `X-Admin-Key` header.
+#[put("/products/{product_id}/variants/{variant_id}", wrap = "from_fn(require_admin)")]
+pub async fn update_product_variant(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<NewProductVariant>,
+) -> impl Responder {
+    let (product_id, variant_id) = path.into_inner();
+
+    if let Err(response) = validate_variant(&payload) {
+        return response;
+    }
+
+    let variant = match find_product_variant(product_id, variant_id, db.get_ref()).await {
+        Ok(Some(variant)) => variant,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: "Product variant not found.".to_string(),
+            });
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while finding product variant: {}", e),
+            });
+        }
+    };
+
+    if let Err(response) = ensure_sku_available(payload.sku.trim(), Some(variant_id), db.get_ref()).await {
+        return response;
+    }
+
+    let mut variant: product_variants::ActiveModel = variant.into();
+    variant.name = Set(payload.name.trim().to_string());
+    variant.sku = Set(payload.sku.trim().to_string());
+    variant.price = Set(payload.price);
+    if let Some(stock_quantity) = payload.stock_quantity {
+        variant.stock_quantity = Set(stock_quantity);
+    }
+    variant.updated_at = Set(local_datetime());
+
+    match variant.update(db.get_ref()).await {
+        Ok(variant) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Product variant updated successfully.".to_string(),
+            data: variant,
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to update product variant: {}", e),
+        }),
+    }
+}
+
+/// Delete a product variant
+///
+/// - Cart lines for the variant are removed with it.
+/// - Returns `404 Not Found` if the variant doesn't belong to the product.
+/// - Requires the `X-Admin-Key` header.
+#[delete("/products/{product_id}/variants/{variant_id}", wrap = "from_fn(require_admin)")]
+pub async fn delete_product_variant(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> impl Responder {
+    let (product_id, variant_id) = path.into_inner();
+
+    match ProductVariants::delete_many()
+        .filter(product_variants::Column::Id.eq(variant_id))
+        .filter(product_variants::Column::ProductId.eq(product_id))
+        .exec(db.get_ref())
+        .await
+    {
+        Ok(delete_result) if delete_result.rows_affected > 0 => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Product variant deleted successfully.".to_string(),
+            data: "None",
+        }),
+        Ok(_) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: "Product variant not found.".to_string(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to delete product variant: {}", e),
+        }),
+    }
+}
+
+// Validates the fields of a variant create/update payload
+fn validate_variant(variant: &NewProductVariant) -> Result<(), HttpResponse> {
+    let detail = if variant.name.trim().is_empty() {
+        Some("Variant name must not be empty.")
+    } else if variant.sku.trim().is_empty() {
+        Some("Variant SKU must not be empty.")
+    } else if variant.price.is_some_and(|price| price <= Decimal::ZERO) {
+        Some("Variant price must be greater than 0.")
+    } else if variant.stock_quantity.is_some_and(|stock_quantity| stock_quantity < 0) {
+        Some("Variant stock quantity must not be negative.")
+    } else {
+        None
+    };
+
+    match detail {
+        Some(detail) => Err(HttpResponse::BadRequest().json(ErrorResponse {
+            detail: detail.to_string(),
+        })),
+        None => Ok(()),
+    }
+}
+
+// Answers `409 Conflict` if another variant already uses the SKU
+async fn ensure_sku_available(
+    sku: &str,
+    exclude_id: Option<Uuid>,
+    db: &sea_orm::DatabaseConnection,
+) -> Result<(), HttpResponse> {
+    match sku_exists(sku, exclude_id, db).await {
+        Ok(false) => Ok(()),
+        Ok(true) => Err(HttpResponse::Conflict().json(ErrorResponse {
+            detail: format!("A variant with SKU '{}' already exists.", sku),
+        })),
+        Err(e) => Err(HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while checking SKU: {}", e),
+        })),
+    }
+}
+
+// Answers `404 Not Found` unless the product exists
+async fn ensure_product_exists(
+    product_id: Uuid,
+    db: &sea_orm::DatabaseConnection,
+) -> Result<(), HttpResponse> {
+    match find_product_by_id(product_id, db).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(HttpResponse::NotFound().json(ErrorResponse {
+            detail: "Product not found.".to_string(),
+        })),
+        Err(e) => Err(HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while checking product: {}", e),
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{insert_cart_item, insert_product, test_db};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use futures_util::future::join_all;
+
+    const ADMIN_KEY: &str = "test-admin-key";
+
+    fn admin_config() -> web::Data<AppConfig> {
+        web::Data::new(AppConfig {
+            admin_api_key: Some(ADMIN_KEY.to_string()),
+            ..AppConfig::from_env()
+        })
+    }
+
+    fn product_update(name: &str, version: i32) -> serde_json::Value {
+        json!({
+            "product_name": name,
+            "description": "Fresh milkfish",
+            "price": "150.00",
+            "category": "Seafood",
+            "img_url": "",
+            "is_available": true,
+            "version": version,
+        })
+    }
+
+    #[actix_web::test]
+    async fn rejects_an_update_based_on_a_stale_version() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 10).await;
+        let app = test::init_service(App::new().app_data(web::Data::new(db.clone())).service(update_product)).await;
+        let uri = format!("/products/{}/", product.id);
+
+        let first = test::TestRequest::put().uri(&uri).set_json(product_update("Bangus Belly", 1)).to_request();
+        assert_eq!(test::call_service(&app, first).await.status(), StatusCode::OK);
+
+        // A second admin still editing version 1
+        let stale = test::TestRequest::put().uri(&uri).set_json(product_update("Daing na Bangus", 1)).to_request();
+        assert_eq!(test::call_service(&app, stale).await.status(), StatusCode::CONFLICT);
+
+        let stored = Products::find_by_id(product.id).one(&db).await.expect("load product").expect("product exists");
+        assert_eq!(stored.product_name, "Bangus Belly");
+        assert_eq!(stored.version, 2);
+    }
+
+    #[actix_web::test]
+    async fn only_one_of_two_concurrent_updates_wins() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 10).await;
+        let app = test::init_service(App::new().app_data(web::Data::new(db.clone())).service(update_product)).await;
+        let uri = format!("/products/{}/", product.id);
+
+        let updates = ["Bangus Belly", "Daing na Bangus"].map(|name| {
+            test::call_service(&app, test::TestRequest::put().uri(&uri).set_json(product_update(name, 1)).to_request())
+        });
+        let mut statuses: Vec<StatusCode> = join_all(updates).await.iter().map(|response| response.status()).collect();
+        statuses.sort();
+
+        assert_eq!(statuses, [StatusCode::OK, StatusCode::CONFLICT]);
+    }
+
+    #[actix_web::test]
+    async fn an_update_without_a_version_applies_and_a_null_vendor_clears_it() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 10).await;
+        let vendor = crate::models::vendors::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            name: Set("Aling Nena".to_string()),
+            created_at: Set(local_datetime()),
+        }
+        .insert(&db)
+        .await
+        .expect("insert vendor");
+        let app = test::init_service(App::new().app_data(web::Data::new(db.clone())).service(update_product)).await;
+        let uri = format!("/products/{}/", product.id);
+
+        let mut with_vendor = product_update("Bangus Belly", 1);
+        with_vendor["vendor_id"] = json!(vendor.id);
+        let response = test::call_service(&app, test::TestRequest::put().uri(&uri).set_json(with_vendor).to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Leaving the vendor out keeps it; sending no version applies over version 2
+        let mut unversioned = product_update("Daing na Bangus", 0);
+        unversioned.as_object_mut().expect("update body is an object").remove("version");
+        let response = test::call_service(&app, test::TestRequest::put().uri(&uri).set_json(unversioned).to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let stored = Products::find_by_id(product.id).one(&db).await.expect("load product").expect("product exists");
+        assert_eq!(stored.product_name, "Daing na Bangus");
+        assert_eq!(stored.version, 3);
+        assert_eq!(stored.vendor_id, Some(vendor.id));
+
+        let mut without_vendor = product_update("Daing na Bangus", 3);
+        without_vendor["vendor_id"] = serde_json::Value::Null;
+        let response = test::call_service(&app, test::TestRequest::put().uri(&uri).set_json(without_vendor).to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let stored = Products::find_by_id(product.id).one(&db).await.expect("load product").expect("product exists");
+        assert_eq!(stored.vendor_id, None);
+    }
+
+    #[actix_web::test]
+    async fn deleting_a_product_cascades_to_its_cart_rows() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Tahong", Decimal::new(12000, 2), 30).await;
+        let kept = insert_product(&db, "Talaba", Decimal::new(18000, 2), 30).await;
+        insert_cart_item(&db, "shopper-a", &product, 2).await;
+        insert_cart_item(&db, "shopper-b", &product, 1).await;
+        insert_cart_item(&db, "shopper-a", &kept, 4).await;
+        let app = test::init_service(App::new().app_data(web::Data::new(db.clone())).service(delete_product)).await;
+
+        let request = test::TestRequest::delete().uri(&format!("/products/{}", product.id)).to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(body["data"]["cart_items_removed"], 2);
+
+        let remaining = carts::Entity::find().all(&db).await.expect("load cart rows");
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].product_id, kept.id);
+
+        let request = test::TestRequest::delete().uri(&format!("/products/{}", product.id)).to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn updates_leave_stock_to_the_audited_adjustment_endpoint() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 10).await;
+        let app = test::init_service(App::new().app_data(web::Data::new(db.clone())).service(update_product)).await;
+        let uri = format!("/products/{}/", product.id);
+
+        let mut restock = product_update("Bangus", 1);
+        restock["stock_quantity"] = json!(50);
+        let request = test::TestRequest::put().uri(&uri).set_json(&restock).to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::BAD_REQUEST);
+
+        // Sending the stored stock back unchanged is still accepted
+        let mut echo = product_update("Bangus Belly", 1);
+        echo["stock_quantity"] = json!(10);
+        let request = test::TestRequest::put().uri(&uri).set_json(&echo).to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::OK);
+
+        let stored = Products::find_by_id(product.id).one(&db).await.expect("load product").expect("product exists");
+        assert_eq!(stored.stock_quantity, 10);
+    }
+
+    #[actix_web::test]
+    async fn repricing_through_an_update_needs_the_admin_key_and_a_valid_price() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 10).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(admin_config())
+                .service(update_product),
+        )
+        .await;
+        let uri = format!("/products/{}/", product.id);
+        let priced = |price: &str| {
+            let mut body = product_update("Bangus", 1);
+            body["price"] = json!(price);
+            body
+        };
+
+        let anonymous = test::TestRequest::put().uri(&uri).set_json(priced("120.00")).to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+
+        for invalid in ["0", "-5.00", "12.345"] {
+            let request = test::TestRequest::put()
+                .uri(&uri)
+                .insert_header(("x-admin-key", ADMIN_KEY))
+                .set_json(priced(invalid))
+                .to_request();
+            assert_eq!(test::call_service(&app, request).await.status(), StatusCode::BAD_REQUEST);
+        }
+        let stored = Products::find_by_id(product.id).one(&db).await.expect("load product").expect("product exists");
+        assert_eq!(stored.price, Decimal::new(15000, 2));
+
+        let admin = test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(("x-admin-key", ADMIN_KEY))
+            .set_json(priced("120.00"))
+            .to_request();
+        assert_eq!(test::call_service(&app, admin).await.status(), StatusCode::OK);
+        let stored = Products::find_by_id(product.id).one(&db).await.expect("load product").expect("product exists");
+        assert_eq!(stored.price, Decimal::new(12000, 2));
+    }
+
+    #[actix_web::test]
+    async fn gallery_changes_need_the_admin_key() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 10).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(admin_config())
+                .service(add_product_gallery_image)
+                .service(delete_product_gallery_image)
+                .service(reorder_product_gallery_images),
+        )
+        .await;
+        let images_uri = format!("/products/{}/images", product.id);
+        let body = json!({ "url": "https://example.com/bangus.jpg" });
+
+        let anonymous = test::TestRequest::post().uri(&images_uri).set_json(&body).to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+        assert!(ProductImages::find().all(&db).await.expect("load images").is_empty());
+
+        let admin = test::TestRequest::post()
+            .uri(&images_uri)
+            .insert_header(("x-admin-key", ADMIN_KEY))
+            .set_json(&body)
+            .to_request();
+        let added = test::call_service(&app, admin).await;
+        assert_eq!(added.status(), StatusCode::CREATED);
+        let added: serde_json::Value = test::read_body_json(added).await;
+        let image_id = added["data"]["id"].as_str().expect("image id").to_string();
+
+        let anonymous = test::TestRequest::put()
+            .uri(&format!("{}/order", images_uri))
+            .set_json(json!({ "image_ids": [image_id] }))
+            .to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+        let anonymous = test::TestRequest::delete().uri(&format!("{}/{}", images_uri, image_id)).to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(ProductImages::find().all(&db).await.expect("load images").len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn variant_changes_need_the_admin_key() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 10).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(admin_config())
+                .service(add_product_variant)
+                .service(update_product_variant)
+                .service(delete_product_variant),
+        )
+        .await;
+        let variants_uri = format!("/products/{}/variants", product.id);
+        let body = json!({ "name": "1kg", "sku": "BANGUS-1KG", "price": "1.00", "stock_quantity": 500 });
+
+        let anonymous = test::TestRequest::post().uri(&variants_uri).set_json(&body).to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+        assert!(ProductVariants::find().all(&db).await.expect("load variants").is_empty());
+
+        let admin = test::TestRequest::post()
+            .uri(&variants_uri)
+            .insert_header(("x-admin-key", ADMIN_KEY))
+            .set_json(&body)
+            .to_request();
+        let created = test::call_service(&app, admin).await;
+        assert_eq!(created.status(), StatusCode::CREATED);
+        let created: serde_json::Value = test::read_body_json(created).await;
+        let variant_uri = format!("{}/{}", variants_uri, created["data"]["id"].as_str().expect("variant id"));
+
+        let anonymous = test::TestRequest::put().uri(&variant_uri).set_json(&body).to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+        let anonymous = test::TestRequest::delete().uri(&variant_uri).to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(ProductVariants::find().all(&db).await.expect("load variants").len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn deleting_a_product_keeps_its_stock_adjustments_and_price_history() {
+        use crate::models::{price_history, stock_adjustments};
+
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Tahong", Decimal::new(12000, 2), 30).await;
+        adjust_product_stock(product.id, -5, "Spoilage".to_string(), local_datetime(), &db)
+            .await
+            .expect("adjust stock");
+        record_price_change(product.id, product.price, Decimal::new(13500, 2), local_datetime(), &db)
+            .await
+            .expect("record price change");
+        let app = test::init_service(App::new().app_data(web::Data::new(db.clone())).service(delete_product)).await;
+
+        let request = test::TestRequest::delete().uri(&format!("/products/{}", product.id)).to_request();
+        assert_eq!(test::call_service(&app, request).await.status(), StatusCode::OK);
+
+        let adjustments = stock_adjustments::Entity::find().all(&db).await.expect("load adjustments");
+        assert_eq!(adjustments.len(), 1);
+        assert_eq!(adjustments[0].product_id, None);
+        assert_eq!(adjustments[0].delta, -5);
+        let price_changes = price_history::Entity::find().all(&db).await.expect("load price history");
+        assert_eq!(price_changes.len(), 1);
+        assert_eq!(price_changes[0].product_id, None);
+        assert_eq!(price_changes[0].new_price, Decimal::new(13500, 2));
+    }
+
+    #[actix_web::test]
+    async fn bulk_availability_needs_the_admin_key() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 10).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(admin_config())
+                .service(update_products_availability),
+        )
+        .await;
+        let body = json!({ "ids": [product.id], "is_available": false });
+
+        let anonymous = test::TestRequest::patch().uri("/products/availability").set_json(&body).to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+        let stored = Products::find_by_id(product.id).one(&db).await.expect("load product").expect("product exists");
+        assert!(stored.is_available);
+
+        let admin = test::TestRequest::patch()
+            .uri("/products/availability")
+            .insert_header(("x-admin-key", ADMIN_KEY))
+            .set_json(&body)
+            .to_request();
+        let response = test::call_service(&app, admin).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(response["data"]["updated"], 1);
+        let stored = Products::find_by_id(product.id).one(&db).await.expect("load product").expect("product exists");
+        assert!(!stored.is_available);
+    }
+
+    #[actix_web::test]
+    async fn price_updates_need_the_admin_key() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 10).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(admin_config())
+                .service(update_product_prices),
+        )
+        .await;
+        let body = json!([{ "id": product.id, "price": "120.00" }]);
+
+        let anonymous = test::TestRequest::post().uri("/products/price-update").set_json(&body).to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+        let wrong_key = test::TestRequest::post()
+            .uri("/products/price-update")
+            .insert_header(("x-admin-key", "not-the-key"))
+            .set_json(&body)
+            .to_request();
+        assert_eq!(test::call_service(&app, wrong_key).await.status(), StatusCode::UNAUTHORIZED);
+        let stored = Products::find_by_id(product.id).one(&db).await.expect("load product").expect("product exists");
+        assert_eq!(stored.price, Decimal::new(15000, 2));
+
+        let admin = test::TestRequest::post()
+            .uri("/products/price-update")
+            .insert_header(("x-admin-key", ADMIN_KEY))
+            .set_json(&body)
+            .to_request();
+        assert_eq!(test::call_service(&app, admin).await.status(), StatusCode::OK);
+        let stored = Products::find_by_id(product.id).one(&db).await.expect("load product").expect("product exists");
+        assert_eq!(stored.price, Decimal::new(12000, 2));
+    }
+
+    #[actix_web::test]
+    async fn replacing_price_tiers_needs_the_admin_key() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 10).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(admin_config())
+                .service(replace_product_price_tiers),
+        )
+        .await;
+        let uri = format!("/products/{}/price-tiers", product.id);
+        let body = json!({ "tiers": [{ "min_qty": 10, "unit_price": "130.00" }] });
+
+        let anonymous = test::TestRequest::put().uri(&uri).set_json(&body).to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+        assert!(fetch_price_tiers(product.id, &db).await.expect("load tiers").is_empty());
+
+        let admin = test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(("x-admin-key", ADMIN_KEY))
+            .set_json(&body)
+            .to_request();
+        assert_eq!(test::call_service(&app, admin).await.status(), StatusCode::OK);
+        let tiers = fetch_price_tiers(product.id, &db).await.expect("load tiers");
+        assert_eq!(tiers.len(), 1);
+        assert_eq!(tiers[0].unit_price, Decimal::new(13000, 2));
+    }
+}
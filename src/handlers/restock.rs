@@ -0,0 +1,115 @@
+use crate::models::restock_subscriptions::NewRestockSubscription;
+use crate::models::responses::{ErrorResponse, SuccessResponse};
+use crate::services::{authorize_cart_access, find_product_by_id, subscribe_to_restock, unsubscribe_from_restock, RestockSubscribeOutcome};
+use crate::utils::{is_valid_email, local_datetime};
+use actix_web::{delete, post, web, HttpRequest, HttpResponse, Responder};
+use uuid::Uuid;
+
+/// Asks to be told when a product is back in stock.
+///
+/// # Endpoint
+/// `POST /products/{product_id}/restock-subscriptions`
+///
+/// # Request
+/// `{ "user_id": "...", "email": "shopper@example.com" }`
+///
+/// # Response
+/// - 201 Created: The subscription.
+/// - 400 Bad Request: If the email is malformed or the product is in stock.
+/// - 404 Not Found: If the product doesn't exist.
+/// - 409 Conflict: If the user is already waiting on this product.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// When the product's stock goes from 0 to positive, through `POST /products/{product_id}/stock`
+/// or `PUT /products/{product_id}/`, a notice is queued for every waiting subscriber. A
+/// subscriber who was already notified can subscribe again.
+#[post("/products/{product_id}/restock-subscriptions")]
+pub async fn subscribe_restock(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    payload: web::Json<NewRestockSubscription>,
+) -> impl Responder {
+    let product_id = path.into_inner();
+    let NewRestockSubscription { user_id, email } = payload.into_inner();
+    let email = email.trim().to_lowercase();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    if !is_valid_email(&email) {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Invalid email address.".to_string(),
+        });
+    }
+
+    match find_product_by_id(product_id, db.get_ref()).await {
+        Ok(Some(product)) if product.stock_quantity > 0 => {
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                detail: "This product is in stock.".to_string(),
+            });
+        }
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: "Product not found.".to_string(),
+            });
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while finding product: {}", e),
+            });
+        }
+    }
+
+    match subscribe_to_restock(&user_id, product_id, &email, local_datetime(), db.get_ref()).await {
+        Ok(RestockSubscribeOutcome::Subscribed(subscription)) => HttpResponse::Created().json(SuccessResponse {
+            success: true,
+            message: "You'll be notified when this product is back in stock.".to_string(),
+            data: subscription,
+        }),
+        Ok(RestockSubscribeOutcome::AlreadySubscribed) => HttpResponse::Conflict().json(ErrorResponse {
+            detail: "Already subscribed to restock notices for this product.".to_string(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while subscribing: {}", e),
+        }),
+    }
+}
+
+/// Stops restock notices for a product.
+///
+/// # Endpoint
+/// `DELETE /products/{product_id}/restock-subscriptions/{user_id}`
+///
+/// # Response
+/// - 200 OK: The subscription was removed.
+/// - 404 Not Found: If the user wasn't subscribed.
+/// - 500 Internal Server Error: On database-related failures.
+#[delete("/products/{product_id}/restock-subscriptions/{user_id}")]
+pub async fn unsubscribe_restock(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, String)>,
+) -> impl Responder {
+    let (product_id, user_id) = path.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    match unsubscribe_from_restock(&user_id, product_id, db.get_ref()).await {
+        Ok(true) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Restock notices stopped.".to_string(),
+            data: "None",
+        }),
+        Ok(false) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: "No restock subscription found for this product.".to_string(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while unsubscribing: {}", e),
+        }),
+    }
+}
@@ -0,0 +1,375 @@
+use crate::config::AppConfig;
+use crate::models::carts::{CartEventKind, CartItemQuery, RemovedCartItem};
+use crate::models::responses::{ErrorResponse, SuccessResponse};
+use crate::models::saved_items::{MovedToSaved, SavedItemResponse};
+use crate::services::{authorize_cart_access, check_cart_items_limit, check_quantity_limit, create_new_cart_item, fetch_cart_product_ids, fetch_saved_items, find_existing_cart_item, find_product_by_id, insert_saved_item, publish_cart_event, sync_cart_store, take_saved_item, validate_cart_line_purchasable, CartBackend, CartEventHub};
+use crate::utils::local_datetime;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use sea_orm::{ModelTrait, TransactionTrait};
+use uuid::Uuid;
+
+/// Moves a cart line to the user's saved-for-later list.
+///
+/// # Endpoint
+/// `POST /carts/{user_id}/{product_id}/save-for-later?variant_id=...`
+///
+/// # Response
+/// - 200 OK: The removed cart line and the saved item, which keeps the line's quantity,
+///   price and note.
+/// - 404 Not Found: If the product isn't in the user's cart.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// The cart row is deleted and the saved item written in one transaction. Saving a product
+/// that is already saved replaces the earlier copy.
+#[post("/carts/{user_id}/{product_id}/save-for-later")]
+pub async fn save_cart_item_for_later(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    events: web::Data<CartEventHub>,
+    req: HttpRequest,
+    path: web::Path<(String, Uuid)>,
+    query: web::Query<CartItemQuery>,
+) -> impl Responder {
+    let (user_id, product_id) = path.into_inner();
+    let variant_id = query.variant_id;
+    let now = local_datetime();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &user_id).await {
+        return response;
+    }
+
+    let moved = db
+        .transaction::<_, Option<MovedToSaved>, sea_orm::DbErr>(|txn| {
+            let user_id = user_id.clone();
+            Box::pin(async move {
+                let Some(cart_item) = find_existing_cart_item(user_id, product_id, variant_id, txn).await? else {
+                    return Ok(None);
+                };
+                let product = find_product_by_id(product_id, txn).await?;
+                let removed = RemovedCartItem::from_model(&cart_item, product.as_ref());
+
+                let saved_item = insert_saved_item(&cart_item, now, txn).await?;
+                cart_item.delete(txn).await?;
+
+                Ok(Some(MovedToSaved { removed, saved_item }))
+            })
+        })
+        .await;
+
+    match moved {
+        Ok(Some(moved)) => {
+            publish_cart_event(&events, &user_id, CartEventKind::ItemRemoved, product_id, db.get_ref()).await;
+            HttpResponse::Ok().json(SuccessResponse {
+                success: true,
+                message: "Cart item saved for later.".to_string(),
+                data: moved,
+            })
+        }
+        Ok(None) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: format!(
+                "No cart item found for user '{}' with product_id '{}'.",
+                user_id, product_id
+            ),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while saving cart item for later: {}", e),
+        }),
+    }
+}
+
+/// Lists a user's saved-for-later items.
+///
+/// # Endpoint
+/// `GET /saved/{user_id}`
+///
+/// # Response
+/// - 200 OK: Saved items with their product details, most recently saved first; empty when
+///   nothing is saved.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/saved/{user_id}")]
+pub async fn get_saved_items(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    match fetch_saved_items(&user_id, db.get_ref()).await {
+        Ok(items) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Saved items fetched successfully.".to_string(),
+            data: items
+                .into_iter()
+                .map(|(item, product)| SavedItemResponse::from_model(item, product))
+                .collect::<Vec<_>>(),
+        }),
+        Err(e) => {
+            eprintln!("❌ Error fetching saved items: {}", e);
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Failed to fetch saved items: {}", e),
+            })
+        }
+    }
+}
+
+/// Moves a saved item back into the user's cart.
+///
+/// # Endpoint
+/// `POST /saved/{user_id}/{product_id}/move-to-cart?variant_id=...`
+///
+/// # Response
+/// - 200 OK: The cart row, created or with the saved quantity added to an existing line,
+///   at the product's current price.
+/// - 400 Bad Request: If the quantity would exceed `MAX_CART_ITEM_QTY`, or the product would
+///   be one more than `MAX_CART_ITEMS` distinct products.
+/// - 404 Not Found: If the product isn't saved, including when an earlier move already took it.
+/// - 409 Conflict: If the product is unavailable or out of stock.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// The saved item is deleted in the same transaction before the cart is touched, so of two
+/// concurrent moves only one adds to the cart, and a refused move leaves it saved.
+#[post("/saved/{user_id}/{product_id}/move-to-cart")]
+#[allow(clippy::too_many_arguments)]
+pub async fn move_saved_item_to_cart(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    events: web::Data<CartEventHub>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
+    path: web::Path<(String, Uuid)>,
+    query: web::Query<CartItemQuery>,
+) -> impl Responder {
+    let (user_id, product_id) = path.into_inner();
+    let variant_id = query.variant_id;
+    let max_qty = config.max_cart_item_qty;
+    let now = local_datetime();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &user_id).await {
+        return response;
+    }
+
+    // Returning early drops the transaction, which rolls it back
+    let txn = match db.begin().await {
+        Ok(txn) => txn,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while starting transaction: {}", e),
+            });
+        }
+    };
+
+    let saved_item = match take_saved_item(&user_id, product_id, variant_id, &txn).await {
+        Ok(Some(saved_item)) => saved_item,
+        Ok(None) => {
+            return HttpResponse::NotFound().json(ErrorResponse {
+                detail: format!(
+                    "No saved item found for user '{}' with product_id '{}'.",
+                    user_id, product_id
+                ),
+            });
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while removing saved item: {}", e),
+            });
+        }
+    };
+
+    let existing_qty = match find_existing_cart_item(user_id.clone(), product_id, variant_id, &txn).await {
+        Ok(existing_cart) => existing_cart.map_or(0, |cart| cart.total_qty),
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while checking existing cart: {}", e),
+            });
+        }
+    };
+    let total_qty = existing_qty.saturating_add(saved_item.total_qty);
+    if let Err(response) = check_quantity_limit(total_qty, max_qty) {
+        return response;
+    }
+    if existing_qty == 0 {
+        let cart_product_ids = match fetch_cart_product_ids(&user_id, &txn).await {
+            Ok(cart_product_ids) => cart_product_ids,
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(ErrorResponse {
+                    detail: format!("Database error while counting cart items: {}", e),
+                });
+            }
+        };
+        if let Err(response) = check_cart_items_limit(&cart_product_ids, product_id, config.max_cart_items) {
+            return response;
+        }
+    }
+    let unit_price = match validate_cart_line_purchasable(product_id, variant_id, total_qty, &txn).await {
+        Ok(unit_price) => unit_price,
+        Err(response) => return response,
+    };
+
+    let cart = match create_new_cart_item(
+        user_id.clone(),
+        product_id,
+        variant_id,
+        saved_item.total_qty,
+        unit_price,
+        saved_item.note,
+        max_qty,
+        now,
+        &txn,
+    )
+    .await
+    {
+        Ok(cart) => cart,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while moving saved item to cart: {}", e),
+            });
+        }
+    };
+
+    if let Err(e) = txn.commit().await {
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while committing transaction: {}", e),
+        });
+    }
+
+    let kind = if existing_qty == 0 {
+        CartEventKind::ItemAdded
+    } else {
+        CartEventKind::QtyChanged
+    };
+    publish_cart_event(&events, &user_id, kind, product_id, db.get_ref()).await;
+
+    HttpResponse::Ok().json(SuccessResponse {
+        success: true,
+        message: "Saved item moved to cart.".to_string(),
+        data: vec![cart],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::carts;
+    use crate::services::{create_cart_session, PostgresCartStore, CART_TOKEN_HEADER};
+    use crate::test_utils::{insert_cart_item, insert_product, test_db};
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use sea_orm::prelude::Decimal;
+    use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+
+    async fn cart_rows(user_id: &str, db: &DatabaseConnection) -> Vec<carts::Model> {
+        carts::Entity::find()
+            .filter(carts::Column::UserId.eq(user_id))
+            .all(db)
+            .await
+            .expect("load cart rows")
+    }
+
+    #[actix_web::test]
+    async fn a_saved_line_keeps_its_quantity_out_of_the_cart_and_back() {
+        let Some(db) = test_db().await else { return };
+        let session = create_cart_session(local_datetime(), &db).await.expect("create cart session");
+        let bangus = insert_product(&db, "Bangus", Decimal::new(15000, 2), 10).await;
+        insert_cart_item(&db, &session.user_id, &bangus, 3).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(CartBackend::Postgres(PostgresCartStore::new(db.clone()))))
+                .app_data(web::Data::new(AppConfig::from_env()))
+                .app_data(web::Data::new(CartEventHub::new()))
+                .service(save_cart_item_for_later)
+                .service(get_saved_items)
+                .service(move_saved_item_to_cart),
+        )
+        .await;
+        let token = (CART_TOKEN_HEADER, session.token.as_str());
+        let list_saved = || test::TestRequest::get().uri(&format!("/saved/{}", session.user_id)).insert_header(token).to_request();
+        let move_back = || {
+            test::TestRequest::post()
+                .uri(&format!("/saved/{}/{}/move-to-cart", session.user_id, bangus.id))
+                .insert_header(token)
+                .to_request()
+        };
+
+        let save = test::TestRequest::post()
+            .uri(&format!("/carts/{}/{}/save-for-later", session.user_id, bangus.id))
+            .insert_header(token)
+            .to_request();
+        assert_eq!(test::call_service(&app, save).await.status(), StatusCode::OK);
+        assert!(cart_rows(&session.user_id, &db).await.is_empty());
+
+        let saved: serde_json::Value = test::call_and_read_body_json(&app, list_saved()).await;
+        assert_eq!(saved["data"].as_array().map(Vec::len), Some(1));
+        assert_eq!(saved["data"][0]["product_name"], "Bangus");
+        assert_eq!(saved["data"][0]["total_qty"], 3);
+        assert_eq!(saved["data"][0]["unit_price"], "150.00");
+
+        assert_eq!(test::call_service(&app, move_back()).await.status(), StatusCode::OK);
+        let cart = cart_rows(&session.user_id, &db).await;
+        assert_eq!(cart.len(), 1);
+        assert_eq!(cart[0].total_qty, 3);
+        let saved: serde_json::Value = test::call_and_read_body_json(&app, list_saved()).await;
+        assert_eq!(saved["data"], serde_json::json!([]));
+
+        // Already back in the cart
+        assert_eq!(test::call_service(&app, move_back()).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn a_refused_move_leaves_the_item_saved() {
+        let Some(db) = test_db().await else { return };
+        let session = create_cart_session(local_datetime(), &db).await.expect("create cart session");
+        let bangus = insert_product(&db, "Bangus", Decimal::new(15000, 2), 10).await;
+        insert_cart_item(&db, &session.user_id, &bangus, 3).await;
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(CartBackend::Postgres(PostgresCartStore::new(db.clone()))))
+                .app_data(web::Data::new(AppConfig {
+                    max_cart_item_qty: 4,
+                    ..AppConfig::from_env()
+                }))
+                .app_data(web::Data::new(CartEventHub::new()))
+                .service(save_cart_item_for_later)
+                .service(get_saved_items)
+                .service(move_saved_item_to_cart),
+        )
+        .await;
+        let token = (CART_TOKEN_HEADER, session.token.as_str());
+
+        let missing = test::TestRequest::post()
+            .uri(&format!("/carts/{}/{}/save-for-later", session.user_id, Uuid::new_v4()))
+            .insert_header(token)
+            .to_request();
+        assert_eq!(test::call_service(&app, missing).await.status(), StatusCode::NOT_FOUND);
+
+        let save = test::TestRequest::post()
+            .uri(&format!("/carts/{}/{}/save-for-later", session.user_id, bangus.id))
+            .insert_header(token)
+            .to_request();
+        assert_eq!(test::call_service(&app, save).await.status(), StatusCode::OK);
+
+        // Two more went into the cart meanwhile, so the saved three would pass the limit of four
+        insert_cart_item(&db, &session.user_id, &bangus, 2).await;
+        let move_back = test::TestRequest::post()
+            .uri(&format!("/saved/{}/{}/move-to-cart", session.user_id, bangus.id))
+            .insert_header(token)
+            .to_request();
+        assert_eq!(test::call_service(&app, move_back).await.status(), StatusCode::BAD_REQUEST);
+
+        assert_eq!(cart_rows(&session.user_id, &db).await[0].total_qty, 2);
+        let list_saved = test::TestRequest::get().uri(&format!("/saved/{}", session.user_id)).insert_header(token).to_request();
+        let saved: serde_json::Value = test::call_and_read_body_json(&app, list_saved).await;
+        assert_eq!(saved["data"][0]["total_qty"], 3);
+    }
+}
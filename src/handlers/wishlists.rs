@@ -0,0 +1,305 @@
+use crate::config::AppConfig;
+use crate::models::carts::{CartEventKind, CartItemQuery, RemovedCartItem};
+use crate::models::responses::{ErrorResponse, SuccessResponse};
+use crate::models::wishlists;
+use crate::models::wishlists::{MoveToCart, MoveToCartQuery, MovedToWishlist, WishlistItemResponse};
+use crate::services::{add_to_wishlist, authorize_cart_access, check_cart_items_limit, check_quantity_limit, create_new_cart_item, fetch_cart_product_ids, fetch_wishlist, find_existing_cart_item, find_product_by_id, publish_cart_event, validate_cart_line_purchasable, validate_product_exists, sync_cart_store, CartBackend, CartEventHub, IdempotencyCheck, IdempotencyStore, IDEMPOTENCY_KEY_HEADER};
+use crate::utils::local_datetime;
+use actix_web::{get, post, web, HttpRequest, HttpResponse, Responder};
+use sea_orm::{ColumnTrait, EntityTrait, ModelTrait, QueryFilter, TransactionTrait};
+use uuid::Uuid;
+
+/// Moves a cart line to the user's wishlist.
+///
+/// # Endpoint
+/// `POST /carts/{user_id}/{product_id}/move-to-wishlist?variant_id=...`
+///
+/// # Response
+/// - 200 OK: The removed cart line and the wishlist entry.
+/// - 404 Not Found: If the product isn't in the user's cart.
+/// - 409 Conflict: If the product no longer exists.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// The cart row is deleted and the wishlist entry added in one transaction; a
+/// product that is already on the wishlist keeps its original entry.
+#[post("/carts/{user_id}/{product_id}/move-to-wishlist")]
+pub async fn move_cart_item_to_wishlist(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    req: HttpRequest,
+    path: web::Path<(String, Uuid)>,
+    query: web::Query<CartItemQuery>,
+) -> impl Responder {
+    let (user_id, product_id) = path.into_inner();
+    let variant_id = query.variant_id;
+    let now = local_datetime();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &user_id).await {
+        return response;
+    }
+
+    if let Err(response) = validate_product_exists(product_id, db.get_ref()).await {
+        return response;
+    }
+
+    let moved = db
+        .transaction::<_, Option<MovedToWishlist>, sea_orm::DbErr>(|txn| {
+            let user_id = user_id.clone();
+            Box::pin(async move {
+                let Some(cart_item) = find_existing_cart_item(user_id.clone(), product_id, variant_id, txn).await? else {
+                    return Ok(None);
+                };
+                let product = find_product_by_id(product_id, txn).await?;
+                let removed = RemovedCartItem::from_model(&cart_item, product.as_ref());
+
+                cart_item.delete(txn).await?;
+                let wishlist_item = add_to_wishlist(&user_id, product_id, now, txn).await?;
+
+                Ok(Some(MovedToWishlist {
+                    removed,
+                    wishlist_item,
+                }))
+            })
+        })
+        .await;
+
+    match moved {
+        Ok(Some(moved)) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Cart item moved to wishlist.".to_string(),
+            data: moved,
+        }),
+        Ok(None) => HttpResponse::NotFound().json(ErrorResponse {
+            detail: format!(
+                "No cart item found for user '{}' with product_id '{}'.",
+                user_id, product_id
+            ),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while moving cart item to wishlist: {}", e),
+        }),
+    }
+}
+
+/// Lists a user's wishlist.
+///
+/// # Endpoint
+/// `GET /wishlist/{user_id}`
+///
+/// # Response
+/// - 200 OK: Wishlist entries with product details, most recently added first.
+/// - 500 Internal Server Error: On database-related failures.
+#[get("/wishlist/{user_id}")]
+pub async fn get_wishlist(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+
+    match fetch_wishlist(&user_id, db.get_ref()).await {
+        Ok(items) => HttpResponse::Ok().json(SuccessResponse {
+            success: true,
+            message: "Wishlist fetched successfully.".to_string(),
+            data: items
+                .into_iter()
+                .map(|(item, product)| WishlistItemResponse::from_model(item, product))
+                .collect::<Vec<_>>(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while fetching wishlist: {}", e),
+        }),
+    }
+}
+
+/// Moves a wishlist entry into the user's cart.
+///
+/// # Endpoint
+/// `POST /wishlist/{user_id}/{product_id}/move-to-cart?qty=2`
+///
+/// # Request
+/// The quantity comes from `?qty=`, or from an optional `{ "total_qty": 2 }` body; defaults to 1.
+///
+/// # Response
+/// - 200 OK: The cart row, created or with the quantity added to an existing line.
+/// - 400 Bad Request: If the quantity is not greater than 0, exceeds `MAX_CART_ITEM_QTY`,
+///   or the product would be one more than `MAX_CART_ITEMS` distinct products.
+/// - 404 Not Found: If the product isn't on the user's wishlist, including when an earlier
+///   move already took it off.
+/// - 409 Conflict: If the product is unavailable or out of stock.
+/// - 500 Internal Server Error: On database-related failures.
+///
+/// Uses the same add-or-increment upsert as `POST /carts/`. The wishlist entry is deleted
+/// in the same transaction before the cart is touched, so of two concurrent moves only one
+/// adds to the cart.
+///
+/// # Idempotency
+/// Send an `Idempotency-Key` header, as for `POST /carts/`, to have a retry replay the
+/// original response instead of answering `404 Not Found`.
+#[post("/wishlist/{user_id}/{product_id}/move-to-cart")]
+#[allow(clippy::too_many_arguments)]
+pub async fn move_wishlist_item_to_cart(
+    db: web::Data<sea_orm::DatabaseConnection>,
+    store: web::Data<CartBackend>,
+    idempotency: web::Data<IdempotencyStore>,
+    events: web::Data<CartEventHub>,
+    config: web::Data<AppConfig>,
+    req: HttpRequest,
+    path: web::Path<(String, Uuid)>,
+    query: web::Query<MoveToCartQuery>,
+    payload: Option<web::Json<MoveToCart>>,
+) -> impl Responder {
+    let (user_id, product_id) = path.into_inner();
+    let total_qty = query
+        .qty
+        .or_else(|| payload.and_then(|payload| payload.total_qty))
+        .unwrap_or(1);
+
+    if let Err(response) = authorize_cart_access(&user_id, &req, db.get_ref()).await {
+        return response;
+    }
+    if let Err(response) = sync_cart_store(store.get_ref(), &user_id).await {
+        return response;
+    }
+
+    let idempotency_key = req
+        .headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|key| !key.is_empty());
+
+    let Some(idempotency_key) = idempotency_key else {
+        return move_to_cart(db.get_ref(), &events, &config, &user_id, product_id, total_qty).await;
+    };
+
+    let scope = (user_id.clone(), idempotency_key.to_string());
+    match idempotency.begin(&scope) {
+        IdempotencyCheck::Replay(stored) => stored.into_response(),
+        IdempotencyCheck::InProgress => HttpResponse::Conflict().json(ErrorResponse {
+            detail: "A request with this Idempotency-Key is still being processed.".to_string(),
+        }),
+        IdempotencyCheck::Started => {
+            let response = move_to_cart(db.get_ref(), &events, &config, &user_id, product_id, total_qty).await;
+            idempotency.finish(scope, response).await
+        }
+    }
+}
+
+// Validates the move, then deletes the wishlist entry and adds to the cart in one transaction.
+// Returning early drops the transaction, which rolls it back.
+async fn move_to_cart(
+    db: &sea_orm::DatabaseConnection,
+    events: &CartEventHub,
+    config: &AppConfig,
+    user_id: &str,
+    product_id: Uuid,
+    total_qty: i32,
+) -> HttpResponse {
+    let max_qty = config.max_cart_item_qty;
+    let now = local_datetime();
+
+    if total_qty <= 0 {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Quantity must be greater than 0.".to_string(),
+        });
+    }
+
+    let not_found = || {
+        HttpResponse::NotFound().json(ErrorResponse {
+            detail: format!(
+                "No wishlist item found for user '{}' with product_id '{}'.",
+                user_id, product_id
+            ),
+        })
+    };
+
+    let txn = match db.begin().await {
+        Ok(txn) => txn,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while starting transaction: {}", e),
+            });
+        }
+    };
+
+    // Only the move that actually deletes the entry goes on to touch the cart
+    let deleted = wishlists::Entity::delete_many()
+        .filter(wishlists::Column::UserId.eq(user_id))
+        .filter(wishlists::Column::ProductId.eq(product_id))
+        .exec(&txn)
+        .await;
+    match deleted {
+        Ok(result) if result.rows_affected == 0 => return not_found(),
+        Ok(_) => {}
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while removing wishlist item: {}", e),
+            });
+        }
+    }
+
+    let existing_qty = match find_existing_cart_item(user_id.to_string(), product_id, None, &txn).await {
+        Ok(existing_cart) => existing_cart.map_or(0, |cart| cart.total_qty),
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while checking existing cart: {}", e),
+            });
+        }
+    };
+    if let Err(response) = check_quantity_limit(existing_qty.saturating_add(total_qty), max_qty) {
+        return response;
+    }
+    if existing_qty == 0 {
+        let cart_product_ids = match fetch_cart_product_ids(user_id, &txn).await {
+            Ok(cart_product_ids) => cart_product_ids,
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(ErrorResponse {
+                    detail: format!("Database error while counting cart items: {}", e),
+                });
+            }
+        };
+        if let Err(response) = check_cart_items_limit(&cart_product_ids, product_id, config.max_cart_items) {
+            return response;
+        }
+    }
+    let unit_price = match validate_cart_line_purchasable(product_id, None, existing_qty + total_qty, &txn).await {
+        Ok(unit_price) => unit_price,
+        Err(response) => return response,
+    };
+
+    let cart = match create_new_cart_item(user_id.to_string(), product_id, None, total_qty, unit_price, None, max_qty, now, &txn).await {
+        Ok(cart) => cart,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while moving wishlist item to cart: {}", e),
+            });
+        }
+    };
+
+    if let Err(e) = txn.commit().await {
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while committing transaction: {}", e),
+        });
+    }
+
+    let kind = if existing_qty == 0 {
+        CartEventKind::ItemAdded
+    } else {
+        CartEventKind::QtyChanged
+    };
+    publish_cart_event(events, user_id, kind, product_id, db).await;
+
+    HttpResponse::Ok().json(SuccessResponse {
+        success: true,
+        message: "Wishlist item moved to cart.".to_string(),
+        data: vec![cart],
+    })
+}
@@ -1,16 +1,25 @@
 mod services;
 
-use crate::handlers::categories::delete_category;
-use crate::handlers::{add_category, add_to_cart, create_product, delete_all_cart_item_per_user_id, delete_cart_item, delete_product, fetch_categories, fetch_product_by_id, fetch_products, get_cart_by_user_id, update_cart_qty, update_product};
-use crate::services::establish_connection;
+use crate::handlers::categories::{delete_category, fetch_category_children, fetch_category_tree, restore_category, update_category};
+use crate::handlers::{add_category, add_product_gallery_image, delete_product_gallery_image, reorder_product_gallery_images, fetch_product_variants_by_product_id, add_product_variant, update_product_variant, delete_product_variant, create_guest_cart_session, share_cart, get_shared_cart, revoke_shared_cart, apply_coupon, attach_coupon, remove_coupon, create_coupon, fetch_coupons, checkout, fetch_user_orders_page, fetch_admin_orders, fetch_order_by_number, fetch_order_detail_by_id, fetch_order_timeline_by_id, fetch_order_receipt_by_id, update_order_status, mark_order_as_paid, cancel_order, reorder_from_order, move_cart_item_to_wishlist, restore_cart_item, get_wishlist, move_wishlist_item_to_cart, fetch_addresses, add_address, edit_address, remove_address, add_to_cart_batch, adjust_stock, subscribe_restock, unsubscribe_restock, fetch_low_stock_products, fetch_out_of_stock_products, get_admin_stats, get_inventory_valuation, fetch_admin_carts, fetch_abandoned_carts, export_abandoned_carts_csv, seed_database, add_to_cart, create_product, export_products_csv, import_products_csv, delete_all_cart_item_per_user_id, delete_cart_item, delete_cart_items, delete_stale_cart_items, delete_product, fetch_categories, fetch_product_by_id, fetch_product_by_slug, fetch_featured_products, fetch_new_products, fetch_products, fetch_product_summaries, fetch_trending_products, compare_products, search_products_by_text, suggest_product_names, get_cart_by_user_id, merge_carts, get_checkout_summary, stream_cart_events, validate_cart, get_saved_items, save_cart_item_for_later, move_saved_item_to_cart, update_cart_item, update_cart_qty, update_product, fetch_product_price_history, fetch_product_price_tiers, replace_product_price_tiers, update_product_prices, update_products_availability};
+use crate::services::{establish_connection, spawn_cart_store_flush, spawn_stale_cart_cleanup};
 use actix_cors::Cors;
-use actix_web::{get, middleware::Logger as ActixLogger, web, HttpResponse, Responder};
-use colourful_logger::Logger;
-use shuttle_actix_web::ShuttleActixWeb;
+use crate::config::AppConfig;
+use crate::middleware::{rate_limit, request_logger};
+use crate::services::{CartBackend, CartEventHub, CategoryCache, IdempotencyStore, RateLimiter};
+use crate::utils::{init_tracing, AppLogger, LogFormat};
+use actix_web::{get, middleware::from_fn, web, HttpResponse, Responder};
+use crate::server::GracefulActixWeb;
 
+mod config;
+mod extractors;
 mod handlers;
+mod middleware;
 mod models;
+mod server;
 mod utils;
+#[cfg(test)]
+mod test_utils;
 
 #[get("/healthz")]
 async fn healthz() -> impl Responder {
@@ -18,15 +27,42 @@ async fn healthz() -> impl Responder {
 }
 
 #[shuttle_runtime::main]
-async fn main() -> ShuttleActixWeb<impl FnOnce(&mut web::ServiceConfig) + Send + Clone + 'static> {
+async fn main() -> Result<GracefulActixWeb<impl FnOnce(&mut web::ServiceConfig) + Send + Clone + 'static>, shuttle_runtime::Error> {
     // Remove dotenv - Shuttle handles environment variables
-    let logger = Logger::default();
+    init_tracing(LogFormat::from_env());
+    let logger = AppLogger::default();
 
     logger.info_single("🚀 Starting Actix server on Shuttle", "SERVER");
 
     // 💾 Connect to the database
     let db = establish_connection().await;
 
+    let app_config = AppConfig::from_env();
+    let category_cache = web::Data::new(CategoryCache::new(app_config.category_cache_ttl));
+    let idempotency_store = web::Data::new(IdempotencyStore::new(app_config.idempotency_key_ttl));
+    let cart_events = web::Data::new(CartEventHub::new());
+    let rate_limiter = web::Data::new(RateLimiter::new());
+
+    // 🛒 Keep hot carts in Redis when CART_STORE=redis, writing them back periodically
+    let cart_store = CartBackend::from_config(&app_config, db.clone())
+        .await
+        .expect("❌ Failed to connect to the cart store");
+    if let CartBackend::Redis(redis_store) = &cart_store {
+        spawn_cart_store_flush(redis_store.clone(), app_config.cart_store_flush_interval);
+        logger.info_single("🛒 Carts are kept in Redis", "CART STORE");
+    }
+    let cart_store = web::Data::new(cart_store);
+
+    // 🧹 Purge idle carts periodically unless CART_CLEANUP_ENABLED=false
+    if app_config.cart_cleanup_enabled {
+        spawn_stale_cart_cleanup(db.clone(), app_config.cart_retention_days, app_config.cart_cleanup_interval);
+    } else {
+        logger.info_single("🧹 Stale cart cleanup is disabled", "CLEANUP");
+    }
+
+    let shutdown_timeout = app_config.shutdown_timeout;
+    let pool = db.clone();
+
     let config = move |cfg: &mut web::ServiceConfig| {
         let cors = Cors::default()
             .allow_any_origin()
@@ -37,27 +73,124 @@ async fn main() -> ShuttleActixWeb<impl FnOnce(&mut web::ServiceConfig) + Send +
         cfg.service(
             web::scope("/api/v1")
                 .app_data(web::Data::new(db.clone()))
-                .wrap(ActixLogger::default())
+                .app_data(web::Data::new(app_config.clone()))
+                .app_data(category_cache.clone())
+                .app_data(idempotency_store.clone())
+                .app_data(cart_events.clone())
+                .app_data(cart_store.clone())
+                .app_data(rate_limiter.clone())
+                .wrap(from_fn(rate_limit))
+                .wrap(from_fn(request_logger))
                 .wrap(cors)
                 .service(healthz)
                 // Categories endpoints
                 .service(add_category)
                 .service(fetch_categories)
+                .service(fetch_category_tree)
+                .service(fetch_category_children)
+                .service(update_category)
                 .service(delete_category)
+                .service(restore_category)
                 // Products endpoints
                 .service(create_product)
                 .service(fetch_products)
+                // Static product routes must be registered before `/products/{product_id}`
+                .service(fetch_product_summaries)
+                .service(fetch_trending_products)
+                .service(compare_products)
+                .service(search_products_by_text)
+                .service(suggest_product_names)
+                .service(export_products_csv)
+                .service(import_products_csv)
+                .service(fetch_featured_products)
+                .service(fetch_new_products)
+                .service(fetch_product_by_slug)
                 .service(fetch_product_by_id)
+                .service(update_products_availability)
+                .service(update_product_prices)
                 .service(update_product)
+                .service(fetch_product_price_history)
+                .service(fetch_product_price_tiers)
+                .service(replace_product_price_tiers)
                 .service(delete_product)
+                .service(adjust_stock)
+                .service(subscribe_restock)
+                .service(unsubscribe_restock)
+                .service(add_product_gallery_image)
+                .service(delete_product_gallery_image)
+                .service(reorder_product_gallery_images)
+                .service(fetch_product_variants_by_product_id)
+                .service(add_product_variant)
+                .service(update_product_variant)
+                .service(delete_product_variant)
                 // Carts endpoints
+                .service(create_guest_cart_session)
                 .service(add_to_cart)
+                .service(add_to_cart_batch)
+                .service(merge_carts)
                 .service(get_cart_by_user_id)
+                .service(get_checkout_summary)
+                .service(stream_cart_events)
+                .service(validate_cart)
                 .service(update_cart_qty)
+                .service(update_cart_item)
+                // Must be registered before `/carts/{user_id}`
+                .service(delete_stale_cart_items)
+                // Must be registered before `/carts/{user_id}/{product_id}`
+                .service(remove_coupon)
+                .service(delete_cart_items)
                 .service(delete_cart_item)
                 .service(delete_all_cart_item_per_user_id)
+                .service(apply_coupon)
+                .service(attach_coupon)
+                .service(move_cart_item_to_wishlist)
+                .service(save_cart_item_for_later)
+                .service(restore_cart_item)
+                .service(share_cart)
+                .service(get_shared_cart)
+                .service(revoke_shared_cart)
+                // Wishlist endpoints
+                .service(get_wishlist)
+                .service(move_wishlist_item_to_cart)
+                // Saved-for-later endpoints
+                .service(get_saved_items)
+                .service(move_saved_item_to_cart)
+                // Address endpoints
+                .service(fetch_addresses)
+                .service(add_address)
+                .service(edit_address)
+                .service(remove_address)
+                // Admin endpoints
+                .service(get_admin_stats)
+                .service(get_inventory_valuation)
+                .service(fetch_abandoned_carts)
+                .service(export_abandoned_carts_csv)
+                .service(fetch_admin_carts)
+                .service(seed_database)
+                .service(fetch_low_stock_products)
+                .service(fetch_out_of_stock_products)
+                .service(create_coupon)
+                .service(fetch_coupons)
+                // Orders endpoints
+                .service(checkout)
+                .service(fetch_admin_orders)
+                .service(fetch_user_orders_page)
+                // Before `/orders/{user_id}/{order_id}`, which would otherwise match it
+                .service(fetch_order_by_number)
+                .service(fetch_order_detail_by_id)
+                .service(fetch_order_timeline_by_id)
+                .service(fetch_order_receipt_by_id)
+                .service(update_order_status)
+                .service(mark_order_as_paid)
+                .service(cancel_order)
+                .service(reorder_from_order)
         );
     };
 
-    Ok(config.into())
+    // 🛑 Drain in-flight requests on SIGTERM before closing the pool
+    Ok(GracefulActixWeb {
+        config,
+        db: pool,
+        shutdown_timeout,
+    })
 }
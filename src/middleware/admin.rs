@@ -0,0 +1,57 @@
+use crate::config::AppConfig;
+use crate::models::responses::ErrorResponse;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+
+const ADMIN_KEY_HEADER: &str = "x-admin-key";
+
+/// Whether the request carries the configured `ADMIN_API_KEY` in an `X-Admin-Key` header.
+///
+/// Always false when no key is configured.
+pub fn has_admin_key(req: &HttpRequest) -> bool {
+    let expected_key = req
+        .app_data::<web::Data<AppConfig>>()
+        .and_then(|config| config.admin_api_key.as_deref());
+    let provided_key = req
+        .headers()
+        .get(ADMIN_KEY_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    expected_key.is_some() && expected_key == provided_key
+}
+
+/// Rejects requests that don't carry the configured `ADMIN_API_KEY` in an `X-Admin-Key` header.
+///
+/// When no key is configured every admin request is refused, so admin routes are
+/// never accidentally left open.
+pub async fn require_admin(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let expected_key = req
+        .app_data::<web::Data<AppConfig>>()
+        .and_then(|config| config.admin_api_key.clone());
+
+    let provided_key = req
+        .headers()
+        .get(ADMIN_KEY_HEADER)
+        .and_then(|value| value.to_str().ok());
+
+    match (expected_key, provided_key) {
+        (Some(expected), Some(provided)) if expected == provided => {
+            next.call(req).await.map(ServiceResponse::map_into_left_body)
+        }
+        (None, _) => Ok(req
+            .into_response(HttpResponse::Forbidden().json(ErrorResponse {
+                detail: "Admin access is not configured.".to_string(),
+            }))
+            .map_into_right_body()),
+        _ => Ok(req
+            .into_response(HttpResponse::Unauthorized().json(ErrorResponse {
+                detail: "Missing or invalid admin key.".to_string(),
+            }))
+            .map_into_right_body()),
+    }
+}
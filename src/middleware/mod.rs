@@ -0,0 +1,7 @@
+mod admin;
+mod rate_limit;
+mod request_logger;
+
+pub use admin::*;
+pub use rate_limit::*;
+pub use request_logger::*;
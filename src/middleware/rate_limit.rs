@@ -0,0 +1,207 @@
+use crate::config::AppConfig;
+use crate::models::responses::ErrorResponse;
+use crate::services::{RateLimitKey, RateLimiter};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::RETRY_AFTER;
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpResponse};
+use std::net::{IpAddr, SocketAddr};
+
+use super::admin::has_admin_key;
+
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// The signed-in user behind a request, put into the request extensions by authentication.
+///
+/// Requests without it are treated as anonymous.
+// Nothing signs users in yet; the limiter already keys on it so quotas follow accounts once it does
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub user_id: String,
+}
+
+/// Limits how many requests a client makes per minute, answering `429 Too Many Requests`
+/// with a `Retry-After` header once the limit is used up.
+///
+/// Requests are counted per `AuthenticatedUser` when there is one, so users behind a shared
+/// IP don't use up each other's quota, and per client IP otherwise. Authenticated users get
+/// `USER_RATE_LIMIT_PER_MINUTE` and anonymous clients `ANONYMOUS_RATE_LIMIT_PER_MINUTE`;
+/// 0 turns a limit off. Requests with the admin key aren't limited.
+///
+/// The client IP is the connection's peer address, or the nearest untrusted `X-Forwarded-For`
+/// hop when the peer is one of the `TRUSTED_PROXIES`. Anonymous requests without a known
+/// address share a single quota.
+pub async fn rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let (Some(config), Some(limiter)) = (
+        req.app_data::<web::Data<AppConfig>>().cloned(),
+        req.app_data::<web::Data<RateLimiter>>().cloned(),
+    ) else {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    };
+
+    if has_admin_key(req.request()) {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    }
+
+    let user_id = req.extensions().get::<AuthenticatedUser>().map(|user| user.user_id.clone());
+    let (key, limit) = match user_id {
+        Some(user_id) => (RateLimitKey::User(user_id), config.user_rate_limit_per_minute),
+        None => {
+            let key = client_ip(&req, &config.trusted_proxies).map_or(RateLimitKey::UnknownClient, RateLimitKey::Ip);
+            (key, config.anonymous_rate_limit_per_minute)
+        }
+    };
+
+    if limit > 0
+        && let Err(retry_after) = limiter.check(key, limit)
+    {
+        // Round up so clients never retry a moment too early
+        let retry_after_secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        return Ok(req
+            .into_response(
+                HttpResponse::TooManyRequests()
+                    .insert_header((RETRY_AFTER, retry_after_secs.to_string()))
+                    .json(ErrorResponse {
+                        detail: format!("Too many requests. Try again in {} seconds.", retry_after_secs),
+                    }),
+            )
+            .map_into_right_body());
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+/// The IP a request is counted against. Behind trusted proxies `X-Forwarded-For` is read from
+/// the right, since each proxy appends the address it received the request from: the first
+/// hop that isn't a trusted proxy is the client. Entries further left are whatever the client
+/// sent, so they are never used. `None` when there is no peer address, as in some tests.
+fn client_ip(req: &ServiceRequest, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let mut client_ip = req.peer_addr()?.ip();
+    if !trusted_proxies.contains(&client_ip) {
+        return Some(client_ip);
+    }
+
+    let forwarded: Vec<&str> = req
+        .headers()
+        .get_all(FORWARDED_FOR_HEADER)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+    for hop in forwarded.into_iter().rev() {
+        let hop = hop.trim();
+        // A hop that doesn't parse can't be trusted or counted; the proxy that passed it on is
+        let Some(hop_ip) = hop
+            .parse::<IpAddr>()
+            .ok()
+            .or_else(|| hop.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        else {
+            break;
+        };
+        client_ip = hop_ip;
+        if !trusted_proxies.contains(&hop_ip) {
+            break;
+        }
+    }
+    Some(client_ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    const PROXY: &str = "10.0.0.2:443";
+
+    fn trusted() -> Vec<IpAddr> {
+        vec!["10.0.0.2".parse().unwrap(), "10.0.0.3".parse().unwrap()]
+    }
+
+    fn forwarded_request(peer: &str, forwarded_for: &str) -> ServiceRequest {
+        TestRequest::default()
+            .peer_addr(peer.parse().unwrap())
+            .insert_header((FORWARDED_FOR_HEADER, forwarded_for))
+            .to_srv_request()
+    }
+
+    #[test]
+    fn ignores_forwarded_headers_from_untrusted_peers() {
+        let req = forwarded_request("203.0.113.9:5000", "198.51.100.7");
+        assert_eq!(client_ip(&req, &[]), Some("203.0.113.9".parse().unwrap()));
+
+        let req = forwarded_request("203.0.113.9:5000", "198.51.100.7");
+        assert_eq!(client_ip(&req, &trusted()), Some("203.0.113.9".parse().unwrap()));
+    }
+
+    #[test]
+    fn uses_the_forwarded_ip_behind_a_trusted_proxy() {
+        let req = forwarded_request(PROXY, "198.51.100.7");
+        assert_eq!(client_ip(&req, &trusted()), Some("198.51.100.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn ignores_entries_the_client_put_in_front() {
+        // The client sent "1.2.3.4"; the proxy appended the address it actually saw
+        let req = forwarded_request(PROXY, "1.2.3.4, 198.51.100.7");
+        assert_eq!(client_ip(&req, &trusted()), Some("198.51.100.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn skips_trusted_proxies_in_a_chain() {
+        let req = forwarded_request(PROXY, "1.2.3.4, 198.51.100.7, 10.0.0.3");
+        assert_eq!(client_ip(&req, &trusted()), Some("198.51.100.7".parse().unwrap()));
+    }
+
+    #[test]
+    fn stops_at_an_entry_that_does_not_parse() {
+        let req = forwarded_request(PROXY, "198.51.100.7, not-an-ip, 10.0.0.3");
+        assert_eq!(client_ip(&req, &trusted()), Some("10.0.0.3".parse().unwrap()));
+    }
+
+    #[test]
+    fn falls_back_to_the_proxy_when_it_forwards_nothing() {
+        let req = TestRequest::default().peer_addr(PROXY.parse().unwrap()).to_srv_request();
+        assert_eq!(client_ip(&req, &trusted()), Some("10.0.0.2".parse().unwrap()));
+    }
+
+    #[actix_web::test]
+    async fn limits_requests_per_user_and_shares_a_quota_without_a_peer_address() {
+        use actix_web::middleware::from_fn;
+        use actix_web::{test, App};
+
+        let config = AppConfig {
+            user_rate_limit_per_minute: 2,
+            anonymous_rate_limit_per_minute: 1,
+            ..AppConfig::from_env()
+        };
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(RateLimiter::new()))
+                .wrap(from_fn(rate_limit))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let as_user = |user_id: &str| {
+            let req = test::TestRequest::get().uri("/").to_request();
+            req.extensions_mut().insert(AuthenticatedUser {
+                user_id: user_id.to_string(),
+            });
+            req
+        };
+        let status = |req| async { test::call_service(&app, req).await.status().as_u16() };
+
+        assert_eq!(status(as_user("ana")).await, 200);
+        assert_eq!(status(as_user("ana")).await, 200);
+        assert_eq!(status(as_user("ana")).await, 429);
+        assert_eq!(status(as_user("ben")).await, 200);
+
+        // No peer address: anonymous requests share one bucket instead of going unlimited
+        assert_eq!(status(test::TestRequest::get().uri("/").to_request()).await, 200);
+        assert_eq!(status(test::TestRequest::get().uri("/").to_request()).await, 429);
+    }
+}
@@ -0,0 +1,62 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::Error;
+use std::time::Instant;
+use uuid::Uuid;
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Logs one structured event per request with its ID, method, path, status and latency.
+///
+/// Reuses an incoming `X-Request-Id` header when present and echoes the ID back
+/// on the response so clients can correlate their calls with server logs.
+pub async fn request_logger(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let started_at = Instant::now();
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+
+    let result = next.call(req).await;
+    let latency_ms = started_at.elapsed().as_secs_f64() * 1000.0;
+
+    match result {
+        Ok(mut res) => {
+            let status = res.status().as_u16();
+            tracing::info!(
+                request_id = %request_id,
+                method = %method,
+                path = %path,
+                status,
+                latency_ms,
+                "request completed"
+            );
+
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                res.headers_mut()
+                    .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+            Ok(res)
+        }
+        Err(e) => {
+            tracing::error!(
+                request_id = %request_id,
+                method = %method,
+                path = %path,
+                latency_ms,
+                error = %e,
+                "request failed"
+            );
+            Err(e)
+        }
+    }
+}
@@ -1,8 +1,12 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
 use sea_orm::entity::prelude::*;
+use crate::models::coupons::CartCouponResponse;
+use crate::services::CheckoutSummary;
+use crate::utils::format_money;
 use sea_orm::FromQueryResult;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
 #[sea_orm(table_name = "carts")]
@@ -11,13 +15,48 @@ pub struct Model {
     pub id: Uuid,
     pub user_id: String,
     pub product_id: Uuid,
+    /// `None` for the base product.
+    pub variant_id: Option<Uuid>,
     pub total_qty: i32,
+    /// Product (or variant) price at the time the item was first added.
+    #[sea_orm(column_type = "Decimal(Some((10, 2)))")]
+    pub unit_price: Decimal,
+    /// Preparation instructions from the customer, e.g. "please clean and fillet".
+    #[sea_orm(column_type = "Text", nullable)]
+    pub note: Option<String>,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::products::Entity",
+        from = "Column::ProductId",
+        to = "super::products::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Products,
+    #[sea_orm(
+        belongs_to = "super::product_variants::Entity",
+        from = "Column::VariantId",
+        to = "super::product_variants::Column::Id",
+        on_delete = "Cascade"
+    )]
+    ProductVariants,
+}
+
+impl Related<super::products::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Products.def()
+    }
+}
+
+impl Related<super::product_variants::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProductVariants.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
 
@@ -25,20 +64,709 @@ impl ActiveModelBehavior for ActiveModel {}
 #[derive(Deserialize)]
 pub struct NewCart {
     pub user_id: Uuid,
+    pub product_id: Uuid,
+    /// Adds the base product when omitted.
+    pub variant_id: Option<Uuid>,
+    pub total_qty: i32,
+    /// Replaces the note of an existing line when given; otherwise the existing note is kept.
+    pub note: Option<String>,
+    #[serde(default)]
+    pub mode: CartAddMode,
+}
+
+/// How `POST /carts/` applies `total_qty` when the product is already in the cart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CartAddMode {
+    /// Add `total_qty` to the quantity already in the cart.
+    #[default]
+    Add,
+    /// Replace the quantity already in the cart with `total_qty`.
+    Set,
+}
+
+/// A cart line written by `POST /carts/`, with how its quantity changed.
+#[derive(Debug, Serialize)]
+pub struct CartAddResult {
+    #[serde(flatten)]
+    pub cart: Model,
+    pub mode: CartAddMode,
+    /// Quantity of the line before the request; 0 for a new line.
+    pub previous_qty: i32,
+}
+
+#[derive(Deserialize)]
+pub struct BatchCartItem {
+    pub product_id: Uuid,
+    pub total_qty: i32,
+}
+
+#[derive(Deserialize)]
+pub struct NewCartBatch {
+    pub user_id: String,
+    pub items: Vec<BatchCartItem>,
+}
+
+#[derive(Deserialize)]
+pub struct BatchCartQuery {
+    /// Apply the valid items even when some fail validation.
+    #[serde(default)]
+    pub partial: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchItemStatus {
+    Created,
+    Updated,
+    Failed,
+}
+
+/// Per-item result of `POST /carts/batch`.
+#[derive(Debug, Serialize)]
+pub struct BatchItemOutcome {
+    pub product_id: Uuid,
+    pub status: BatchItemStatus,
+    pub total_qty: Option<i32>,
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct StaleCartsQuery {
+    /// Defaults to `CART_RETENTION_DAYS` when omitted.
+    pub older_than_days: Option<i64>,
+}
+
+/// Result of a stale cart purge.
+#[derive(Debug, Serialize)]
+pub struct StaleCartsPurged {
+    pub older_than_days: i64,
+    pub removed: u64,
+}
+
+#[derive(Deserialize)]
+pub struct CheckoutSummaryQuery {
+    /// Delivery region; reserved for region-specific tax and shipping rates.
+    pub region: Option<String>,
+}
+
+/// Cart totals with tax and shipping, as returned by `GET /carts/{user_id}/checkout-summary`.
+#[derive(Debug, Serialize)]
+pub struct CheckoutSummaryResponse {
+    pub subtotal: Decimal,
+    pub subtotal_display: String,
+    pub tax: Decimal,
+    pub tax_display: String,
+    pub shipping_fee: Decimal,
+    pub shipping_fee_display: String,
+    pub grand_total: Decimal,
+    pub grand_total_display: String,
+    /// `None` when free shipping isn't offered.
+    pub free_shipping_threshold: Option<Decimal>,
+    /// Zero once the cart ships for free; `None` when free shipping isn't offered.
+    pub amount_to_free_shipping: Option<Decimal>,
+    pub amount_to_free_shipping_display: Option<String>,
+}
+
+impl CheckoutSummaryResponse {
+    pub fn from_summary(summary: CheckoutSummary) -> Self {
+        let display = |amount: Decimal| format_money(f64::try_from(amount).unwrap_or_default());
+        Self {
+            subtotal: summary.subtotal,
+            subtotal_display: display(summary.subtotal),
+            tax: summary.tax,
+            tax_display: display(summary.tax),
+            shipping_fee: summary.shipping_fee,
+            shipping_fee_display: display(summary.shipping_fee),
+            grand_total: summary.grand_total,
+            grand_total_display: display(summary.grand_total),
+            free_shipping_threshold: summary.free_shipping_threshold,
+            amount_to_free_shipping: summary.amount_to_free_shipping,
+            amount_to_free_shipping_display: summary.amount_to_free_shipping.map(display),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CartValidationQuery {
+    /// Clamp quantities, drop unavailable items and accept changed prices.
+    #[serde(default)]
+    pub fix: bool,
+}
+
+/// What `POST /carts/{user_id}/validate` found for one cart line, most severe issue first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CartLineStatus {
+    Ok,
+    /// The product (or variant) is gone, switched off, or outside its sale window.
+    Unavailable,
+    InsufficientStock { available_qty: i32 },
+    /// The snapshotted `unit_price` no longer matches the live price.
+    PriceChanged { old_price: Decimal, new_price: Decimal },
+}
+
+#[derive(Debug, Serialize)]
+pub struct CartLineValidation {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub product_name: String,
+    pub total_qty: i32,
+    #[serde(flatten)]
+    pub status: CartLineStatus,
+}
+
+/// A change made to a cart line by `?fix=true`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum CartLineFix {
+    Removed,
+    QuantityClamped { from_qty: i32, to_qty: i32 },
+    PriceUpdated { old_price: Decimal, new_price: Decimal },
+}
+
+#[derive(Debug, Serialize)]
+pub struct CartLineChange {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    #[serde(flatten)]
+    pub fix: CartLineFix,
+}
+
+/// Pre-checkout report for the active items of a cart.
+///
+/// With `?fix=true`, `items` describes the cart before the fixes and `can_checkout`
+/// the cart after them.
+#[derive(Debug, Serialize)]
+pub struct CartValidationResponse {
+    pub can_checkout: bool,
+    pub items: Vec<CartLineValidation>,
+    pub changes: Vec<CartLineChange>,
+}
+
+#[derive(Deserialize)]
+pub struct MergeCarts {
+    pub from_user_id: String,
+    pub to_user_id: String,
+}
+
+/// How a cart update's `qty` is applied to the existing row.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CartUpdateMode {
+    /// Increase the current quantity by `qty`.
+    Add,
+    /// Replace the current quantity with `qty`.
+    #[default]
+    Set,
+    /// Decrease the current quantity by `qty`, removing the row once it drops below 1.
+    Subtract,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateCartItem {
+    pub user_id: String,
+    pub product_id: Uuid,
+    /// Targets the base product when omitted.
+    pub variant_id: Option<Uuid>,
+    pub qty: i32,
+    #[serde(default)]
+    pub mode: CartUpdateMode,
+    /// Left unchanged when omitted; an empty string clears it.
+    pub note: Option<String>,
+}
+
+/// Query parameters for `GET /carts/{user_id}`.
+#[derive(Deserialize)]
+pub struct CartListQuery {
+    /// One of `added` (default), `updated`, `name` or `subtotal`.
+    pub sort_by: Option<String>,
+    /// `asc` or `desc`; defaults to `asc` for `name` and `desc` otherwise.
+    pub order: Option<String>,
+    /// Group the lines by vendor instead of returning one flat list.
+    #[serde(default)]
+    pub grouped: bool,
+}
+
+/// Cart line ordering for `fetch_cart_with_products`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CartSortKey {
+    /// When the line was first added.
+    #[default]
+    Added,
+    /// When the line was last changed.
+    Updated,
+    Name,
+    Subtotal,
+}
+
+impl CartSortKey {
+    pub const OPTIONS: [&'static str; 4] = ["added", "updated", "name", "subtotal"];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "added" => Some(CartSortKey::Added),
+            "updated" => Some(CartSortKey::Updated),
+            "name" => Some(CartSortKey::Name),
+            "subtotal" => Some(CartSortKey::Subtotal),
+            _ => None,
+        }
+    }
+}
+
+/// Parsed form of `CartListQuery`; the default lists newest-added lines first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CartListOptions {
+    pub sort_by: CartSortKey,
+    pub descending: bool,
+}
+
+impl Default for CartListOptions {
+    fn default() -> Self {
+        Self {
+            sort_by: CartSortKey::Added,
+            descending: true,
+        }
+    }
+}
+
+impl CartListQuery {
+    /// Validates the sort parameters, describing the valid options on failure.
+    pub fn options(&self) -> Result<CartListOptions, String> {
+        let sort_by = match self.sort_by.as_deref() {
+            None => CartSortKey::default(),
+            Some(value) => CartSortKey::parse(value).ok_or_else(|| {
+                format!(
+                    "Invalid sort_by '{}'. Valid options: {}.",
+                    value,
+                    CartSortKey::OPTIONS.join(", ")
+                )
+            })?,
+        };
+        let descending = match self.order.as_deref() {
+            None => sort_by != CartSortKey::Name,
+            Some("asc") => false,
+            Some("desc") => true,
+            Some(value) => {
+                return Err(format!("Invalid order '{}'. Valid options: asc, desc.", value));
+            }
+        };
+
+        Ok(CartListOptions { sort_by, descending })
+    }
+}
+
+/// Query parameters for `GET /admin/carts`; `page` / `per_page` are read by `PaginationParams`.
+#[derive(Deserialize)]
+pub struct AdminCartsQuery {
+    pub user_id: Option<String>,
+    /// RFC 3339 timestamp; only carts last changed before it.
+    pub updated_before: Option<String>,
+    /// RFC 3339 timestamp; only carts last changed at or after it.
+    pub updated_after: Option<String>,
+}
+
+/// Parsed filters of `AdminCartsQuery`; the time bounds apply to a cart's latest `updated_at`.
+#[derive(Debug, Clone, Default)]
+pub struct CartOwnerFilter {
+    pub user_id: Option<String>,
+    pub updated_before: Option<DateTimeWithTimeZone>,
+    pub updated_after: Option<DateTimeWithTimeZone>,
+}
+
+impl AdminCartsQuery {
+    /// Parses the time bounds, describing the malformed one on failure.
+    pub fn filter(&self) -> Result<CartOwnerFilter, String> {
+        let parse = |name: &str, value: &Option<String>| match value {
+            None => Ok(None),
+            Some(value) => chrono::DateTime::parse_from_rfc3339(value)
+                .map(Some)
+                .map_err(|_| format!("Invalid {} format. Must be an RFC 3339 timestamp.", name)),
+        };
+
+        Ok(CartOwnerFilter {
+            user_id: self.user_id.clone(),
+            updated_before: parse("updated_before", &self.updated_before)?,
+            updated_after: parse("updated_after", &self.updated_after)?,
+        })
+    }
+}
+
+/// A user with cart rows, as listed by `fetch_cart_owners`.
+#[derive(Debug, FromQueryResult)]
+pub struct CartOwner {
+    pub user_id: String,
+    pub last_updated_at: DateTimeWithTimeZone,
+}
+
+/// One user's cart in `GET /admin/carts`.
+#[derive(Debug, Serialize)]
+pub struct AdminCartResponse {
+    pub user_id: String,
+    pub last_updated_at: DateTimeWithTimeZone,
+    #[serde(flatten)]
+    pub cart: CartContents,
+}
+
+/// A page of `GET /admin/carts`, most recently changed carts first.
+#[derive(Debug, Serialize)]
+pub struct AdminCartsPage {
+    pub page: u64,
+    pub per_page: u64,
+    pub total_carts: u64,
+    pub carts: Vec<AdminCartResponse>,
+}
+
+/// Query parameters for `GET /admin/carts/abandoned`.
+#[derive(Deserialize)]
+pub struct AbandonedCartsQuery {
+    /// Hours without any cart change; defaults to 24. Also accepted as `hours`.
+    #[serde(alias = "hours")]
+    pub inactive_hours: Option<i64>,
+    /// 1-based; defaults to 1. Ignored by the CSV export.
+    pub page: Option<u64>,
+    /// Defaults to 50, capped at 500. Ignored by the CSV export.
+    pub per_page: Option<u64>,
+}
+
+/// A registered user's cart that hasn't changed for a while; only active lines count.
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct AbandonedCart {
+    pub user_id: String,
+    /// Sum of the lines at their snapshot prices.
+    pub cart_value: Decimal,
+    /// `cart_value` formatted with `format_money`; filled in by `AbandonedCart::with_display`.
+    #[sea_orm(skip)]
+    pub cart_value_display: String,
+    pub item_count: i64,
+    /// Distinct product names, comma-separated, for the email copy.
+    pub product_names: String,
+    pub last_updated_at: DateTimeWithTimeZone,
+}
+
+impl AbandonedCart {
+    pub fn with_display(mut self) -> Self {
+        self.cart_value_display = format_money(f64::try_from(self.cart_value).unwrap_or_default());
+        self
+    }
+}
+
+/// A page of `GET /admin/carts/abandoned`, most valuable carts first.
+#[derive(Debug, Serialize)]
+pub struct AbandonedCartsPage {
+    pub inactive_hours: i64,
+    pub page: u64,
+    pub per_page: u64,
+    pub total_carts: u64,
+    pub carts: Vec<AbandonedCart>,
+}
+
+/// What happened to a cart, as sent on `GET /carts/{user_id}/events`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CartEventKind {
+    ItemAdded,
+    QtyChanged,
+    ItemRemoved,
+}
+
+impl CartEventKind {
+    /// The SSE `event:` name.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CartEventKind::ItemAdded => "item_added",
+            CartEventKind::QtyChanged => "qty_changed",
+            CartEventKind::ItemRemoved => "item_removed",
+        }
+    }
+}
+
+/// A cart change with the cart's totals right after it; totals only count active items.
+#[derive(Debug, Clone, Serialize)]
+pub struct CartEvent {
+    pub kind: CartEventKind,
+    pub product_id: Uuid,
+    pub total_items: i64,
+    pub total_price: Decimal,
+}
+
+/// Selects a variant line for endpoints that address a cart item by product.
+#[derive(Deserialize)]
+pub struct CartItemQuery {
+    /// Targets the base product when omitted.
+    pub variant_id: Option<Uuid>,
+}
+
+/// Outcome of a cart quantity update: the resulting row, or `removed: true` when it was deleted.
+#[derive(Debug, Serialize)]
+pub struct CartItemUpdateResult {
     pub product_id: Uuid,
     pub total_qty: i32,
+    pub removed: bool,
+    pub cart: Option<Model>,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromQueryResult)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CartsResponse {
     pub id: Uuid,
     pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub variant_name: Option<String>,
     pub total_qty: i32,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
     pub product_name: String,
     pub description: String,
+    /// Whether the product can currently be bought, sale window included.
+    pub is_available: bool,
+    /// Stock left for the product, or for the variant on variant lines.
+    pub stock_quantity: Option<i32>,
+    /// Live price of the variant, or of the product when there is no variant override.
     pub product_price: BigDecimal,
+    /// Price snapshotted when the item was added; the subtotal is based on it unless a quantity
+    /// discount applies.
+    pub unit_price: BigDecimal,
+    /// What each unit costs after quantity discounts: `applied_tier`'s price, else `unit_price`.
+    pub effective_unit_price: BigDecimal,
+    /// The quantity discount the subtotal uses, if any. Only lines without a variant get one.
+    pub applied_tier: Option<AppliedPriceTier>,
+    /// Whether the live price differs from the snapshot.
+    pub price_changed: bool,
     pub sub_total_price: BigDecimal,
     pub img_url: String,
+    /// `None` for products sold by the store itself.
+    pub vendor_id: Option<Uuid>,
+    pub vendor_name: Option<String>,
+    pub note: Option<String>,
+}
+
+impl CartsResponse {
+    /// Prices the line with `tier` when there is one, otherwise at its snapshot prices.
+    pub fn apply_price_tier(&mut self, tier: Option<&super::product_price_tiers::Model>) {
+        match tier {
+            Some(tier) => {
+                self.effective_unit_price = BigDecimal::from_str(&tier.unit_price.to_string()).unwrap_or_default();
+                self.sub_total_price = &self.effective_unit_price * BigDecimal::from(self.total_qty);
+                self.applied_tier = Some(AppliedPriceTier {
+                    min_qty: tier.min_qty,
+                    unit_price: tier.unit_price,
+                });
+            }
+            None => {
+                self.effective_unit_price = self.unit_price.clone();
+                self.applied_tier = None;
+            }
+        }
+    }
+
+    /// `unit_price` as a `Decimal`, for comparing against price tiers.
+    pub fn unit_price_decimal(&self) -> Decimal {
+        decimal_from_big(&self.unit_price)
+    }
+}
+
+/// One cart row joined with its product, variant and vendor, before `fetch_cart_with_products`
+/// folds rows for the same product and variant into a `CartsResponse` line.
+///
+/// The variant and vendor columns are `None` when the line has no variant or the product no vendor.
+#[derive(Debug, Clone, FromQueryResult)]
+pub struct CartRowWithProduct {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    pub total_qty: i32,
+    pub unit_price: BigDecimal,
+    pub note: Option<String>,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+    pub product_name: String,
+    pub description: String,
+    pub product_price: BigDecimal,
+    pub product_stock: i32,
+    pub product_is_available: bool,
+    pub available_from: Option<DateTimeWithTimeZone>,
+    pub available_until: Option<DateTimeWithTimeZone>,
+    pub img_url: String,
+    pub vendor_id: Option<Uuid>,
+    pub vendor_name: Option<String>,
+    pub variant_name: Option<String>,
+    pub variant_price: Option<BigDecimal>,
+    pub variant_stock: Option<i32>,
+}
+
+/// The quantity discount applied to a cart line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedPriceTier {
+    pub min_qty: i32,
+    pub unit_price: Decimal,
+}
+
+#[derive(Deserialize)]
+pub struct BulkRemoveCartItems {
+    pub product_ids: Vec<Uuid>,
+}
+
+/// Result of `DELETE /carts/{user_id}/items`.
+#[derive(Debug, Serialize)]
+pub struct BulkRemovedCartItems {
+    /// Cart rows deleted, variant lines included.
+    pub removed: usize,
+    /// Requested product ids that had no row in the cart.
+    pub not_found: Vec<Uuid>,
+}
+
+/// A cart line as it was just before being removed.
+#[derive(Debug, Serialize)]
+pub struct RemovedCartItem {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    /// `None` if the product itself no longer exists.
+    pub product_name: Option<String>,
+    pub total_qty: i32,
+}
+
+impl RemovedCartItem {
+    pub fn from_model(cart: &Model, product: Option<&super::products::Model>) -> Self {
+        Self {
+            product_id: cart.product_id,
+            variant_id: cart.variant_id,
+            product_name: product.map(|product| product.product_name.clone()),
+            total_qty: cart.total_qty,
+        }
+    }
+}
+
+fn decimal_from_big(amount: &BigDecimal) -> Decimal {
+    Decimal::from_str(&amount.to_string()).unwrap_or_default()
+}
+
+/// A cart line with its subtotal formatted for display.
+#[derive(Debug, Serialize)]
+pub struct CartLine {
+    #[serde(flatten)]
+    pub line: CartsResponse,
+    pub sub_total_display: String,
+}
+
+impl CartLine {
+    fn from_response(line: CartsResponse) -> Self {
+        let sub_total_display = format_money(f64::try_from(decimal_from_big(&line.sub_total_price)).unwrap_or_default());
+        Self { line, sub_total_display }
+    }
+}
+
+/// A user's cart lines with their totals.
+///
+/// `grand_total` (also sent as `grand_total_after_discount`) is the total less the attached
+/// coupon's discount, if any.
+#[derive(Debug, Serialize)]
+pub struct CartContents {
+    pub items: Vec<CartLine>,
+    pub total_price: BigDecimal,
+    /// Sum of the items' quantities.
+    pub total_items: i64,
+    pub coupon: Option<CartCouponResponse>,
+    pub discount_amount: BigDecimal,
+    pub grand_total_after_discount: BigDecimal,
+    pub grand_total: Decimal,
+    pub grand_total_display: String,
+}
+
+impl CartContents {
+    pub fn from_lines(lines: Vec<CartsResponse>) -> Self {
+        let total_price = lines
+            .iter()
+            .fold(BigDecimal::from(0), |total, line| total + &line.sub_total_price);
+        let total_items = lines.iter().map(|line| i64::from(line.total_qty)).sum();
+
+        let mut contents = Self {
+            items: lines.into_iter().map(CartLine::from_response).collect(),
+            grand_total_after_discount: total_price.clone(),
+            total_price,
+            total_items,
+            coupon: None,
+            discount_amount: BigDecimal::from(0),
+            grand_total: Decimal::ZERO,
+            grand_total_display: String::new(),
+        };
+        contents.set_grand_total(contents.total_price_decimal());
+        contents
+    }
+
+    /// `total_price` as a `Decimal`, for the coupon rules.
+    pub fn total_price_decimal(&self) -> Decimal {
+        decimal_from_big(&self.total_price)
+    }
+
+    /// Records the attached coupon and takes `discount` off the grand total.
+    pub fn with_coupon(mut self, code: String, discount: Decimal, issue: Option<&'static str>) -> Self {
+        self.discount_amount = BigDecimal::from_str(&discount.to_string()).unwrap_or_default();
+        self.grand_total_after_discount = &self.total_price - &self.discount_amount;
+        self.set_grand_total(self.total_price_decimal() - discount);
+        self.coupon = Some(CartCouponResponse { code, issue });
+        self
+    }
+
+    fn set_grand_total(&mut self, grand_total: Decimal) {
+        self.grand_total = grand_total;
+        self.grand_total_display = format_money(f64::try_from(grand_total).unwrap_or_default());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Splits the lines by vendor, in the order each vendor first appears, and returns the
+    /// groups along with the cart-wide totals.
+    pub fn into_vendor_groups(self) -> (Vec<CartVendorGroup>, CartTotals) {
+        let mut groups: Vec<CartVendorGroup> = Vec::new();
+        for line in self.items {
+            let index = match groups.iter().position(|group| group.vendor_id == line.line.vendor_id) {
+                Some(index) => index,
+                None => {
+                    groups.push(CartVendorGroup {
+                        vendor_id: line.line.vendor_id,
+                        vendor_name: line.line.vendor_name.clone(),
+                        items: Vec::new(),
+                        vendor_subtotal: BigDecimal::from(0),
+                    });
+                    groups.len() - 1
+                }
+            };
+            let group = &mut groups[index];
+            group.vendor_subtotal += &line.line.sub_total_price;
+            group.items.push(line);
+        }
+
+        let totals = CartTotals {
+            total_price: self.total_price,
+            total_items: self.total_items,
+            coupon: self.coupon,
+            discount_amount: self.discount_amount,
+            grand_total: self.grand_total,
+            grand_total_display: self.grand_total_display,
+        };
+        (groups, totals)
+    }
+}
+
+/// One vendor's lines of a cart, as returned by `GET /carts/{user_id}?grouped=true`.
+#[derive(Debug, Serialize)]
+pub struct CartVendorGroup {
+    /// `None` for products sold by the store itself.
+    pub vendor_id: Option<Uuid>,
+    pub vendor_name: Option<String>,
+    pub items: Vec<CartLine>,
+    pub vendor_subtotal: BigDecimal,
+}
+
+/// Cart-wide totals sent in `meta` next to the vendor groups.
+#[derive(Debug, Serialize)]
+pub struct CartTotals {
+    pub total_price: BigDecimal,
+    pub total_items: i64,
+    pub coupon: Option<CartCouponResponse>,
+    pub discount_amount: BigDecimal,
+    pub grand_total: Decimal,
+    pub grand_total_display: String,
 }
\ No newline at end of file
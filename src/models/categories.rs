@@ -11,35 +11,75 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
     pub name: String,
+    pub parent_id: Option<Uuid>,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
+    /// Set when the category is deleted; deleted categories are hidden but can be restored.
+    pub deleted_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "Entity",
+        from = "Column::ParentId",
+        to = "Column::Id",
+        on_delete = "Restrict"
+    )]
+    SelfRef,
+}
 
 impl ActiveModelBehavior for ActiveModel {}
 
 #[derive(Deserialize)]
 pub struct NewCategory {
     pub name: String,
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
 }
+
+#[derive(Deserialize)]
+pub struct DeleteCategoryQuery {
+    /// Re-parent the category's children onto its own parent instead of refusing the delete.
+    #[serde(default)]
+    pub force: bool,
+}
+
 // Category response schema
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CategoryResponse {
     pub id: Uuid,
     pub name: String,
+    pub parent_id: Option<Uuid>,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// A page of `GET /category`, newest first.
+#[derive(Debug, Serialize)]
+pub struct CategoriesPage {
+    pub page: u64,
+    pub per_page: u64,
+    pub total_categories: u64,
+    pub categories: Vec<CategoryResponse>,
+}
+
 impl CategoryResponse {
     pub fn from_model(category: categories::Model) -> Self {
         Self {
             id: category.id,
             name: category.name,
+            parent_id: category.parent_id,
             created_at: format_datetime(category.created_at),
             updated_at: format_datetime(category.updated_at),
         }
     }
 }
+
+// Nested category tree node
+#[derive(Debug, Serialize)]
+pub struct CategoryTreeNode {
+    #[serde(flatten)]
+    pub category: CategoryResponse,
+    pub children: Vec<CategoryTreeNode>,
+}
@@ -2,8 +2,27 @@
 
 pub mod prelude;
 
+pub mod addresses;
+pub mod cart_coupons;
+pub mod cart_deletions;
+pub mod cart_sessions;
+pub mod cart_shares;
 pub mod carts;
 pub mod categories;
+pub mod coupons;
+pub mod order_addresses;
+pub mod order_items;
+pub mod order_status_history;
+pub mod orders;
+pub mod price_history;
+pub mod product_images;
+pub mod product_price_tiers;
+pub mod product_variants;
 pub mod products;
+pub mod restock_subscriptions;
+pub mod saved_items;
+pub mod stock_adjustments;
+pub mod vendors;
+pub mod wishlists;
 
 pub mod responses;
\ No newline at end of file
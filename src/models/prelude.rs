@@ -1,6 +1,26 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
+#![allow(unused_imports)]
 
+pub use super::addresses::Entity as Addresses;
+pub use super::cart_coupons::Entity as CartCoupons;
+pub use super::cart_deletions::Entity as CartDeletions;
+pub use super::cart_sessions::Entity as CartSessions;
+pub use super::cart_shares::Entity as CartShares;
 pub use super::carts::Entity as Carts;
 pub use super::categories::Entity as Categories;
-pub use super::products::Entity as Products;
\ No newline at end of file
+pub use super::coupons::Entity as Coupons;
+pub use super::order_addresses::Entity as OrderAddresses;
+pub use super::order_items::Entity as OrderItems;
+pub use super::order_status_history::Entity as OrderStatusHistory;
+pub use super::orders::Entity as Orders;
+pub use super::price_history::Entity as PriceHistory;
+pub use super::product_images::Entity as ProductImages;
+pub use super::product_price_tiers::Entity as ProductPriceTiers;
+pub use super::product_variants::Entity as ProductVariants;
+pub use super::products::Entity as Products;
+pub use super::restock_subscriptions::Entity as RestockSubscriptions;
+pub use super::saved_items::Entity as SavedItems;
+pub use super::stock_adjustments::Entity as StockAdjustments;
+pub use super::vendors::Entity as Vendors;
+pub use super::wishlists::Entity as Wishlists;
\ No newline at end of file
@@ -1,8 +1,9 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
 use crate::models::products;
-use crate::utils::{format_datetime, format_money};
+use crate::utils::{deserialize_nullable, format_datetime, format_money, local_datetime};
 use sea_orm::entity::prelude::*;
+use sea_orm::FromQueryResult;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Serialize, Deserialize)]
@@ -11,24 +12,117 @@ pub struct Model {
     #[sea_orm(primary_key, auto_increment = false)]
     pub id: Uuid,
     pub product_name: String,
+    /// URL-friendly name, unique; regenerated when the name changes.
+    #[sea_orm(unique)]
+    pub slug: String,
     pub description: String,
     #[sea_orm(column_type = "Decimal(Some((10, 2)))")]
     pub price: Decimal,
     pub category: String,
     pub img_url: String,
     pub is_available: bool,
+    /// Set when a stock decrease hid the product by selling it out, so a restock shows it again.
+    /// Changing `is_available` by hand clears it.
+    pub sold_out: bool,
+    pub stock_quantity: i32,
+    /// Fewest units a cart line may hold, for products sold in packs; at least 1.
+    pub min_order_qty: i32,
+    pub is_featured: bool,
+    pub available_from: Option<DateTimeWithTimeZone>,
+    pub available_until: Option<DateTimeWithTimeZone>,
+    /// Bumped on every edit; updates must send the version they were based on.
+    pub version: i32,
+    /// Product page views counted by `GET /products/{product_id}?track=true`.
+    pub view_count: i64,
+    /// `None` for products sold by the store itself.
+    pub vendor_id: Option<Uuid>,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::cart_deletions::Entity")]
+    CartDeletions,
+    #[sea_orm(has_many = "super::carts::Entity")]
+    Carts,
+    #[sea_orm(has_many = "super::price_history::Entity")]
+    PriceHistory,
+    #[sea_orm(has_many = "super::product_images::Entity")]
+    ProductImages,
+    #[sea_orm(has_many = "super::product_price_tiers::Entity")]
+    ProductPriceTiers,
+    #[sea_orm(has_many = "super::product_variants::Entity")]
+    ProductVariants,
+    #[sea_orm(has_many = "super::restock_subscriptions::Entity")]
+    RestockSubscriptions,
+    #[sea_orm(
+        belongs_to = "super::vendors::Entity",
+        from = "Column::VendorId",
+        to = "super::vendors::Column::Id",
+        on_delete = "SetNull"
+    )]
+    Vendors,
+}
+
+impl Related<super::price_history::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PriceHistory.def()
+    }
+}
+
+impl Related<super::product_price_tiers::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProductPriceTiers.def()
+    }
+}
+
+impl Related<super::restock_subscriptions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RestockSubscriptions.def()
+    }
+}
+
+impl Related<super::cart_deletions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CartDeletions.def()
+    }
+}
+
+impl Related<super::carts::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Carts.def()
+    }
+}
+
+impl Related<super::product_images::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProductImages.def()
+    }
+}
+
+impl Related<super::product_variants::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProductVariants.def()
+    }
+}
+
+impl Related<super::vendors::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Vendors.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
 
-#[derive(Deserialize)]
-pub struct NewProducts {
-    pub name: String,
+impl Model {
+    /// Whether the product can be sold at `now`: flagged available and inside its
+    /// `available_from`/`available_until` window, with a missing bound left open.
+    pub fn is_available_at(&self, now: DateTimeWithTimeZone) -> bool {
+        self.is_available
+            && self.available_from.is_none_or(|from| from <= now)
+            && self.available_until.is_none_or(|until| now < until)
+    }
 }
 
 // Product response schema
@@ -36,25 +130,76 @@ pub struct NewProducts {
 pub struct ProductsResponse {
     pub id: Uuid,
     pub product_name: String,
+    pub slug: String,
     pub description: String,
     pub price: String,
     pub category: String,
     pub img_url: String,
+    /// Gallery URLs, with the legacy `img_url` first when set.
+    pub images: Vec<String>,
     pub is_available: bool,
+    pub stock_quantity: i32,
+    pub min_order_qty: i32,
+    pub is_featured: bool,
+    pub available_from: Option<String>,
+    pub available_until: Option<String>,
+    pub version: i32,
+    pub view_count: i64,
+    pub vendor_id: Option<Uuid>,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// A page of `GET /products`, newest first.
+#[derive(Debug, Serialize)]
+pub struct ProductsPage {
+    pub page: u64,
+    pub per_page: u64,
+    pub total_products: u64,
+    pub products: Vec<ProductsResponse>,
+}
+
 impl crate::models::products::ProductsResponse {
+    /// Appends gallery URLs (already in `sort_order`) after the primary image.
+    pub fn with_gallery(mut self, gallery: Vec<String>) -> Self {
+        for url in gallery {
+            if !self.images.contains(&url) {
+                self.images.push(url);
+            }
+        }
+        self
+    }
+
     pub fn from_model(products: products::Model) -> Self {
+        Self::from_model_at(products, local_datetime())
+    }
+
+    /// Builds the response with `is_available` evaluated against the window at `now`.
+    pub fn from_model_at(products: products::Model, now: DateTimeWithTimeZone) -> Self {
+        let is_available = products.is_available_at(now);
+        let images = if products.img_url.is_empty() {
+            Vec::new()
+        } else {
+            vec![products.img_url.clone()]
+        };
         Self {
             id: products.id,
             product_name: products.product_name,
+            slug: products.slug,
             description: products.description,
-            price: format_money(f64::try_from(products.price).unwrap()),
+            price: format_money(f64::try_from(products.price).unwrap_or_default()),
             category: products.category,
             img_url: products.img_url,
-            is_available: products.is_available,
+            images,
+            is_available,
+            stock_quantity: products.stock_quantity,
+            min_order_qty: products.min_order_qty,
+            is_featured: products.is_featured,
+            available_from: products.available_from.map(format_datetime),
+            available_until: products.available_until.map(format_datetime),
+            version: products.version,
+            view_count: products.view_count,
+            vendor_id: products.vendor_id,
             created_at: format_datetime(products.created_at),
             updated_at: format_datetime(products.updated_at),
         }
@@ -69,4 +214,313 @@ pub struct NewProduct {
     pub category: String,
     pub img_url: String,
     pub is_available: bool,
+    /// Defaults to 0 on create. Updates can't change it (see `POST /products/{product_id}/stock`),
+    /// so on update it must be omitted or match the stored stock.
+    pub stock_quantity: Option<i32>,
+    /// Defaults to 1 on create; left unchanged on update when omitted. Must be at least 1.
+    pub min_order_qty: Option<i32>,
+    /// Defaults to false on create; left unchanged on update when omitted.
+    pub is_featured: Option<bool>,
+    /// Start of the sale window; open-ended when null. Left unchanged on update when omitted.
+    pub available_from: Option<DateTimeWithTimeZone>,
+    /// End of the sale window; open-ended when null. Left unchanged on update when omitted.
+    pub available_until: Option<DateTimeWithTimeZone>,
+    /// Seller of the product; none when omitted or null on create. On update, left unchanged
+    /// when omitted and cleared by `null`.
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub vendor_id: Option<Option<Uuid>>,
+    /// Ignored on create. On update, the version the edit was based on, which must match the
+    /// stored one; without it the update applies over whatever is stored.
+    pub version: Option<i32>,
+}
+
+/// Body of `POST /products/compare`.
+#[derive(Debug, Deserialize)]
+pub struct CompareProducts {
+    pub ids: Vec<Uuid>,
+}
+
+/// One row of the comparison matrix: an attribute's value for each product, in request order.
+#[derive(Debug, Serialize)]
+pub struct ComparedAttribute {
+    pub attribute: &'static str,
+    pub values: Vec<serde_json::Value>,
+    /// Whether the products disagree on this attribute, so the UI can highlight it.
+    pub differs: bool,
+}
+
+/// Result of `POST /products/compare`.
+#[derive(Debug, Serialize)]
+pub struct ProductComparison {
+    pub products: Vec<ProductsResponse>,
+    pub attributes: Vec<ComparedAttribute>,
+}
+
+impl ComparedAttribute {
+    /// Builds the matrix from the products, already in request order, with availability at `now`.
+    pub fn matrix(products: &[products::Model], now: DateTimeWithTimeZone) -> Vec<Self> {
+        let row = |attribute: &'static str, value: &dyn Fn(&products::Model) -> serde_json::Value| {
+            let values: Vec<serde_json::Value> = products.iter().map(value).collect();
+            let differs = values.windows(2).any(|pair| pair[0] != pair[1]);
+            Self { attribute, values, differs }
+        };
+
+        vec![
+            row("price", &|product| serde_json::json!(product.price)),
+            row("category", &|product| serde_json::json!(product.category)),
+            row("is_available", &|product| serde_json::json!(product.is_available_at(now))),
+            row("stock_quantity", &|product| serde_json::json!(product.stock_quantity)),
+            row("min_order_qty", &|product| serde_json::json!(product.min_order_qty)),
+        ]
+    }
+}
+
+/// Body of `PATCH /products/availability`.
+#[derive(Debug, Deserialize)]
+pub struct BulkProductAvailability {
+    pub ids: Vec<Uuid>,
+    pub is_available: bool,
+}
+
+/// Result of `PATCH /products/availability`.
+#[derive(Debug, Serialize)]
+pub struct ProductsAvailabilityUpdated {
+    /// Products that exist among the requested ids; unknown ids are ignored.
+    pub updated: u64,
+}
+
+/// Body of `POST /products/price-update`: either a list of `{ "id", "price" }` pairs or a
+/// `{ "category", "percent_change" }` object.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PriceUpdateRequest {
+    Prices(Vec<ProductPriceChange>),
+    /// `percent_change` of -20 takes 20% off every product in the category; 10 adds 10%.
+    Percent {
+        category: String,
+        percent_change: Decimal,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProductPriceChange {
+    pub id: Uuid,
+    pub price: Decimal,
+}
+
+/// One product changed by `POST /products/price-update`.
+#[derive(Debug, Serialize)]
+pub struct PriceChange {
+    pub id: Uuid,
+    pub product_name: String,
+    pub old_price: Decimal,
+    pub new_price: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProductListQuery {
+    /// Only return products in this category (case-insensitive).
+    pub category: Option<String>,
+    /// RFC 3339 timestamp used instead of the current time when evaluating availability windows.
+    pub now: Option<String>,
+    /// RFC 3339 bounds on `created_at` / `updated_at`; `_after` is inclusive, `_before` exclusive.
+    /// Only `GET /products` applies them.
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub updated_after: Option<String>,
+    pub updated_before: Option<String>,
+}
+
+/// Parsed `created_*` / `updated_*` bounds of a `ProductListQuery`.
+#[derive(Debug, Default)]
+pub struct ProductDateRange {
+    pub created_after: Option<DateTimeWithTimeZone>,
+    pub created_before: Option<DateTimeWithTimeZone>,
+    pub updated_after: Option<DateTimeWithTimeZone>,
+    pub updated_before: Option<DateTimeWithTimeZone>,
+}
+
+impl ProductListQuery {
+    /// Parses the date bounds, describing the malformed one on failure.
+    pub fn date_range(&self) -> Result<ProductDateRange, String> {
+        let parse = |name: &str, value: &Option<String>| match value {
+            None => Ok(None),
+            Some(value) => chrono::DateTime::parse_from_rfc3339(value)
+                .map(Some)
+                .map_err(|_| format!("Invalid {} format. Must be an RFC 3339 timestamp.", name)),
+        };
+
+        Ok(ProductDateRange {
+            created_after: parse("created_after", &self.created_after)?,
+            created_before: parse("created_before", &self.created_before)?,
+            updated_after: parse("updated_after", &self.updated_after)?,
+            updated_before: parse("updated_before", &self.updated_before)?,
+        })
+    }
+}
+
+/// Result of `DELETE /products/{product_id}`.
+#[derive(Debug, Serialize)]
+pub struct ProductDeleted {
+    pub product_id: Uuid,
+    /// Cart lines removed along with the product by the foreign key cascade.
+    pub cart_items_removed: u64,
+}
+
+/// A `GET /products/summary` row: only the columns a product grid shows.
+///
+/// `is_available` already accounts for the sale window.
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct ProductSummary {
+    pub id: Uuid,
+    pub product_name: String,
+    pub price: Decimal,
+    pub img_url: String,
+    pub is_available: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProductSuggestQuery {
+    #[serde(default)]
+    pub q: String,
+}
+
+/// A `GET /products/suggest` entry; kept to what a search box dropdown needs.
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct ProductSuggestion {
+    pub id: Uuid,
+    pub product_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProductSearchQuery {
+    pub q: String,
+    pub limit: Option<u64>,
+}
+
+/// A `GET /products/search` hit; higher `rank` means more relevant.
+#[derive(Debug, Serialize)]
+pub struct ProductSearchResult {
+    #[serde(flatten)]
+    pub product: ProductsResponse,
+    pub rank: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProductViewQuery {
+    /// Count this fetch as a product page view; sent by the storefront only.
+    #[serde(default)]
+    pub track: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NewProductsQuery {
+    /// How many days back to look; defaults to 7.
+    pub days: Option<i64>,
+}
+
+// Stock totals at retail price, overall or for one category
+#[derive(Debug, FromQueryResult)]
+pub struct StockValueTotals {
+    pub total_units: Option<i64>,
+    pub total_value: Option<Decimal>,
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct CategoryStockValue {
+    pub category: String,
+    pub total_units: i64,
+    pub total_value: Decimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryValuation {
+    pub category: String,
+    pub total_units: i64,
+    pub total_value: Decimal,
+    pub total_value_display: String,
+}
+
+impl CategoryValuation {
+    pub fn from_stock_value(value: CategoryStockValue) -> Self {
+        Self {
+            total_value_display: format_money(f64::try_from(value.total_value).unwrap_or_default()),
+            category: value.category,
+            total_units: value.total_units,
+            total_value: value.total_value,
+        }
+    }
+}
+
+/// Result of `GET /admin/inventory/valuation`.
+#[derive(Debug, Serialize)]
+pub struct InventoryValuationResponse {
+    pub total_units: i64,
+    pub total_value: Decimal,
+    pub total_value_display: String,
+    /// Most valuable category first.
+    pub categories: Vec<CategoryValuation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LowStockQuery {
+    pub threshold: Option<i32>,
+}
+
+/// How `GET /products/trending` ranks cart activity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrendingRank {
+    /// Total quantity across all carts.
+    #[default]
+    Quantity,
+    /// Number of distinct users who added the product.
+    Users,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrendingQuery {
+    pub limit: Option<u64>,
+    /// RFC 3339 timestamp; only cart rows created at or after it are counted.
+    pub since: Option<String>,
+    #[serde(default)]
+    pub rank_by: TrendingRank,
+}
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    /// Apply the valid rows even when some fail validation.
+    #[serde(default)]
+    pub partial: bool,
+}
+
+/// One data row of a product import CSV; prices are validated after parsing.
+#[derive(Debug, Deserialize)]
+pub struct ProductImportRow {
+    pub product_name: String,
+    #[serde(default)]
+    pub description: String,
+    pub price: String,
+    pub category: String,
+    #[serde(default)]
+    pub img_url: String,
+    pub is_available: Option<bool>,
+    pub stock_quantity: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportRowStatus {
+    Created,
+    Updated,
+    Failed,
+}
+
+/// Per-row result of `POST /products/import`; `row` is 1-based and excludes the header.
+#[derive(Debug, Serialize)]
+pub struct ImportRowOutcome {
+    pub row: usize,
+    pub product_name: String,
+    pub status: ImportRowStatus,
+    pub reason: Option<String>,
 }
@@ -8,8 +8,52 @@ pub struct SuccessResponse<T> {
     pub data: T,
 }
 
+// Success response wrapper with response-wide figures next to the data
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SuccessResponseWithMeta<T, M> {
+    pub success: bool,
+    pub message: String,
+    pub data: T,
+    pub meta: M,
+}
+
 // Error response schema
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub detail: String,
 }
+
+// Error response when a requested quantity exceeds what can be purchased
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockErrorResponse {
+    pub detail: String,
+    pub max_allowed_qty: i32,
+}
+
+// Error response when a cart line would hold fewer units than the product is sold in
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MinimumQuantityResponse {
+    pub detail: String,
+    pub min_order_qty: i32,
+}
+
+// Error response when a cart line would exceed the per-item quantity limit
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuantityLimitResponse {
+    pub detail: String,
+    pub max_qty: i32,
+}
+
+// Error response when a new product would exceed the distinct-products-per-cart limit
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CartItemsLimitResponse {
+    pub detail: String,
+    pub max_items: usize,
+}
+
+// Error response when a coupon can't be applied, with a machine-readable reason
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CouponErrorResponse {
+    pub detail: String,
+    pub reason: String,
+}
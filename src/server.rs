@@ -0,0 +1,46 @@
+use crate::utils::AppLogger;
+use actix_web::{web, App, HttpServer};
+use sea_orm::DatabaseConnection;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Shuttle service that runs the Actix server with a shutdown drain.
+///
+/// Same server as `ShuttleActixWeb`, except that on SIGTERM/SIGINT the workers stop
+/// accepting connections and get `shutdown_timeout` to finish in-flight requests before
+/// being dropped. The database pool is closed once the server has stopped.
+pub struct GracefulActixWeb<F> {
+    pub config: F,
+    pub db: DatabaseConnection,
+    pub shutdown_timeout: Duration,
+}
+
+#[shuttle_runtime::async_trait]
+impl<F> shuttle_runtime::Service for GracefulActixWeb<F>
+where
+    F: FnOnce(&mut web::ServiceConfig) + Send + Clone + 'static,
+{
+    async fn bind(self, addr: SocketAddr) -> Result<(), shuttle_runtime::Error> {
+        let logger = AppLogger::default();
+        let config = self.config;
+        let workers = std::thread::available_parallelism().map_or(1, usize::from);
+
+        // Build the `Server` first: the builder isn't `Send` and must not live across the await
+        let server = HttpServer::new(move || App::new().configure(config.clone()))
+            .workers(workers)
+            .shutdown_timeout(self.shutdown_timeout.as_secs())
+            .bind(addr)
+            .map_err(shuttle_runtime::CustomError::new)?
+            .run();
+        server.await.map_err(shuttle_runtime::CustomError::new)?;
+
+        // `run` only returns after the workers have drained (or timed out)
+        logger.info_single("🛑 Server stopped; in-flight requests drained", "SERVER");
+
+        match self.db.close().await {
+            Ok(()) => logger.info_single("💾 Database pool closed", "SERVER"),
+            Err(e) => eprintln!("❌ Failed to close the database pool: {}", e),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+use crate::models::addresses;
+use crate::models::addresses::NewAddress;
+use crate::models::order_addresses::{self, CheckoutAddress};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Set};
+use uuid::Uuid;
+
+// Function to check the required address fields, returning the first problem found
+pub fn validate_address(address: &NewAddress) -> Result<(), String> {
+    let required = [
+        ("line1", &address.line1),
+        ("city", &address.city),
+        ("province", &address.province),
+        ("postal_code", &address.postal_code),
+    ];
+    match required.iter().find(|(_, value)| value.trim().is_empty()) {
+        Some((field, _)) => Err(format!("{} is required.", field)),
+        None => Ok(()),
+    }
+}
+
+/// Longest delivery note accepted at checkout, in characters.
+pub const MAX_DELIVERY_NOTES_LEN: usize = 500;
+
+// Function to read a Philippine mobile number written as `09XXXXXXXXX`, `639XXXXXXXXX` or
+// `+639XXXXXXXXX` (spaces and dashes allowed), returning it as `+639XXXXXXXXX`
+pub fn normalize_ph_phone(phone: &str) -> Option<String> {
+    let compact: String = phone.chars().filter(|c| !matches!(c, ' ' | '-')).collect();
+    let local = compact
+        .strip_prefix("+63")
+        .or_else(|| compact.strip_prefix("63"))
+        .or_else(|| compact.strip_prefix('0'))?;
+    let valid = local.len() == 10 && local.starts_with('9') && local.chars().all(|c| c.is_ascii_digit());
+    valid.then(|| format!("+63{}", local))
+}
+
+// Function to fill a checkout address from the user's address book when it names a saved
+// address. Returns `None` when that address doesn't exist or belongs to someone else.
+pub async fn complete_checkout_address<C: ConnectionTrait>(
+    user_id: &str,
+    mut address: CheckoutAddress,
+    db: &C,
+) -> Result<Option<CheckoutAddress>, sea_orm::DbErr> {
+    let Some(address_id) = address.address_id else {
+        return Ok(Some(address));
+    };
+    let Some(saved) = find_user_address(user_id, address_id, db).await? else {
+        return Ok(None);
+    };
+
+    address.line1 = Some(saved.line1);
+    address.city = Some(saved.city);
+    if address.barangay.as_deref().map(str::trim).filter(|barangay| !barangay.is_empty()).is_none() {
+        address.barangay = saved.line2;
+    }
+    Ok(Some(address))
+}
+
+// Function to check a completed checkout address and turn it into the copy stored with the
+// order, returning the first problem found
+pub fn build_order_address(
+    order_id: Uuid,
+    address: CheckoutAddress,
+    now: DateTimeWithTimeZone,
+) -> Result<order_addresses::ActiveModel, String> {
+    let required = |field: &str, value: Option<String>| {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| format!("address.{} is required.", field))
+    };
+
+    let recipient_name = required("recipient_name", Some(address.recipient_name))?;
+    let phone = required("phone", Some(address.phone))?;
+    let phone = normalize_ph_phone(&phone)
+        .ok_or_else(|| "address.phone must be a Philippine mobile number like 09171234567.".to_string())?;
+    let line1 = required("line1", address.line1)?;
+    let barangay = required("barangay", address.barangay)?;
+    let city = required("city", address.city)?;
+    let notes = address.notes.map(|notes| notes.trim().to_string()).filter(|notes| !notes.is_empty());
+    if notes.as_ref().is_some_and(|notes| notes.chars().count() > MAX_DELIVERY_NOTES_LEN) {
+        return Err(format!("address.notes must be at most {} characters.", MAX_DELIVERY_NOTES_LEN));
+    }
+
+    Ok(order_addresses::ActiveModel {
+        order_id: Set(order_id),
+        recipient_name: Set(recipient_name),
+        phone: Set(phone),
+        line1: Set(line1),
+        barangay: Set(barangay),
+        city: Set(city),
+        notes: Set(notes),
+        created_at: Set(now),
+    })
+}
+
+pub async fn fetch_user_addresses<C: ConnectionTrait>(
+    user_id: &str,
+    db: &C,
+) -> Result<Vec<addresses::Model>, sea_orm::DbErr> {
+    addresses::Entity::find()
+        .filter(addresses::Column::UserId.eq(user_id))
+        .order_by_desc(addresses::Column::IsDefault)
+        .order_by_asc(addresses::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+pub async fn find_user_address<C: ConnectionTrait>(
+    user_id: &str,
+    address_id: Uuid,
+    db: &C,
+) -> Result<Option<addresses::Model>, sea_orm::DbErr> {
+    addresses::Entity::find_by_id(address_id)
+        .filter(addresses::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+}
+
+// Function to clear the default flag on every address of a user; run it in the same
+// transaction that sets the new default
+pub async fn clear_default_address<C: ConnectionTrait>(
+    user_id: &str,
+    db: &C,
+) -> Result<(), sea_orm::DbErr> {
+    addresses::Entity::update_many()
+        .col_expr(addresses::Column::IsDefault, Expr::value(false))
+        .filter(addresses::Column::UserId.eq(user_id))
+        .filter(addresses::Column::IsDefault.eq(true))
+        .exec(db)
+        .await
+        .map(|_| ())
+}
+
+// Function to save a new address; it becomes the default when asked to or when it's the user's first
+pub async fn create_address<C: ConnectionTrait>(
+    user_id: &str,
+    address: NewAddress,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<addresses::Model, sea_orm::DbErr> {
+    let is_first = addresses::Entity::find()
+        .filter(addresses::Column::UserId.eq(user_id))
+        .count(db)
+        .await?
+        == 0;
+    let is_default = address.is_default || is_first;
+    if is_default {
+        clear_default_address(user_id, db).await?;
+    }
+
+    addresses::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id.to_string()),
+        line1: Set(address.line1.trim().to_string()),
+        line2: Set(address.line2.map(|line2| line2.trim().to_string()).filter(|line2| !line2.is_empty())),
+        city: Set(address.city.trim().to_string()),
+        province: Set(address.province.trim().to_string()),
+        postal_code: Set(address.postal_code.trim().to_string()),
+        is_default: Set(is_default),
+        created_at: Set(now),
+    }
+    .insert(db)
+    .await
+}
+
+// Function to replace an address's fields; setting `is_default` moves the default to it.
+// Unsetting it on the current default is ignored so the user keeps a default address.
+pub async fn update_address<C: ConnectionTrait>(
+    existing: addresses::Model,
+    address: NewAddress,
+    db: &C,
+) -> Result<addresses::Model, sea_orm::DbErr> {
+    let is_default = address.is_default || existing.is_default;
+    if is_default && !existing.is_default {
+        clear_default_address(&existing.user_id, db).await?;
+    }
+
+    let mut address_active_model: addresses::ActiveModel = existing.into();
+    address_active_model.line1 = Set(address.line1.trim().to_string());
+    address_active_model.line2 = Set(address.line2.map(|line2| line2.trim().to_string()).filter(|line2| !line2.is_empty()));
+    address_active_model.city = Set(address.city.trim().to_string());
+    address_active_model.province = Set(address.province.trim().to_string());
+    address_active_model.postal_code = Set(address.postal_code.trim().to_string());
+    address_active_model.is_default = Set(is_default);
+    address_active_model.update(db).await
+}
+
+// Function to delete an address; when it was the default, the oldest remaining address takes over
+pub async fn delete_address<C: ConnectionTrait>(
+    existing: addresses::Model,
+    db: &C,
+) -> Result<(), sea_orm::DbErr> {
+    let user_id = existing.user_id.clone();
+    let was_default = existing.is_default;
+    addresses::Entity::delete_by_id(existing.id).exec(db).await?;
+
+    if was_default
+        && let Some(next_default) = addresses::Entity::find()
+            .filter(addresses::Column::UserId.eq(user_id))
+            .order_by_asc(addresses::Column::CreatedAt)
+            .one(db)
+            .await?
+    {
+        let mut address_active_model: addresses::ActiveModel = next_default.into();
+        address_active_model.is_default = Set(true);
+        address_active_model.update(db).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use crate::models::carts;
+use crate::models::carts::{CartContents, CartEvent, CartEventKind, CartListOptions};
+use crate::services::{fetch_cart_total, CartStore};
+use actix_web::web::Bytes;
+use futures_util::stream::{self, Stream, StreamExt};
+use sea_orm::sea_query::Expr;
+use sea_orm::prelude::Decimal;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QuerySelect};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Events buffered per user before a slow listener starts missing some.
+const CART_EVENT_BUFFER: usize = 32;
+
+/// How often an idle stream sends a comment, so dropped connections are noticed.
+const CART_EVENT_HEARTBEAT: Duration = Duration::from_secs(15);
+
+/// In-process fan-out of cart changes to `GET /carts/{user_id}/events` listeners.
+///
+/// A user's channel exists only while someone listens: the last subscription to go away
+/// removes it, and publishing to a user without listeners does nothing.
+#[derive(Default)]
+pub struct CartEventHub {
+    channels: Mutex<HashMap<String, broadcast::Sender<CartEvent>>>,
+}
+
+impl CartEventHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn has_listeners(&self, user_id: &str) -> bool {
+        self.channels
+            .lock()
+            .map(|channels| channels.contains_key(user_id))
+            .unwrap_or(false)
+    }
+
+    pub fn subscribe(self: Arc<Self>, user_id: &str) -> CartEventSubscription {
+        let receiver = {
+            let mut channels = self.channels.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            channels
+                .entry(user_id.to_string())
+                .or_insert_with(|| broadcast::channel(CART_EVENT_BUFFER).0)
+                .subscribe()
+        };
+        CartEventSubscription {
+            hub: self,
+            user_id: user_id.to_string(),
+            receiver: Some(receiver),
+        }
+    }
+
+    pub fn publish(&self, user_id: &str, event: CartEvent) {
+        if let Ok(channels) = self.channels.lock()
+            && let Some(sender) = channels.get(user_id)
+        {
+            // Only fails when nobody listens, which is fine
+            let _ = sender.send(event);
+        }
+    }
+
+    fn release(&self, user_id: &str) {
+        if let Ok(mut channels) = self.channels.lock()
+            && channels.get(user_id).is_some_and(|sender| sender.receiver_count() == 0)
+        {
+            channels.remove(user_id);
+        }
+    }
+}
+
+/// One listener's end of a user's channel; dropping it closes the channel once nobody else listens.
+pub struct CartEventSubscription {
+    hub: Arc<CartEventHub>,
+    user_id: String,
+    receiver: Option<broadcast::Receiver<CartEvent>>,
+}
+
+impl CartEventSubscription {
+    async fn next(&mut self) -> Option<CartEvent> {
+        let receiver = self.receiver.as_mut()?;
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some(event),
+                // A slow listener skips what it missed; the next event carries fresh totals
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    // Function to turn the subscription into a `text/event-stream` body with periodic heartbeats.
+    pub fn into_sse_stream(self) -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+        let events = stream::unfold(self, |mut subscription| async move {
+            let event = subscription.next().await?;
+            let data = serde_json::to_string(&event).unwrap_or_default();
+            let frame = format!("event: {}\ndata: {}\n\n", event.kind.as_str(), data);
+            Some((Bytes::from(frame), subscription))
+        });
+        let heartbeats = stream::unfold(tokio::time::interval(CART_EVENT_HEARTBEAT), |mut interval| async move {
+            interval.tick().await;
+            Some((Bytes::from_static(b": keep-alive\n\n"), interval))
+        });
+
+        stream::select(events, heartbeats).map(Ok)
+    }
+}
+
+impl Drop for CartEventSubscription {
+    fn drop(&mut self) {
+        // Drop the receiver first so it no longer counts as a listener
+        self.receiver.take();
+        self.hub.release(&self.user_id);
+    }
+}
+
+// Function to publish a change to a user's cart with the cart's totals as they are now. Skips the
+// totals query when nobody listens; a failing query is logged and the event dropped, so
+// the change itself is never affected.
+pub async fn publish_cart_event<C: ConnectionTrait>(
+    hub: &CartEventHub,
+    user_id: &str,
+    kind: CartEventKind,
+    product_id: Uuid,
+    db: &C,
+) {
+    if !hub.has_listeners(user_id) {
+        return;
+    }
+
+    let totals = async {
+        let total_price = fetch_cart_total(user_id, db).await?;
+        let total_items = carts::Entity::find()
+            .select_only()
+            .column_as(Expr::col(carts::Column::TotalQty).sum(), "total_items")
+            .filter(carts::Column::UserId.eq(user_id))
+            .into_tuple::<Option<i64>>()
+            .one(db)
+            .await?
+            .flatten()
+            .unwrap_or(0);
+        Ok::<_, sea_orm::DbErr>((total_price, total_items))
+    };
+
+    publish_with_totals(hub, user_id, kind, product_id, totals.await);
+}
+
+// Function to publish like `publish_cart_event`, with the totals read through `store`, for changes
+// made through a `CartStore` that may not have reached the `carts` table yet.
+pub async fn publish_cart_store_event<S: CartStore>(
+    hub: &CartEventHub,
+    user_id: &str,
+    kind: CartEventKind,
+    product_id: Uuid,
+    store: &S,
+) {
+    if !hub.has_listeners(user_id) {
+        return;
+    }
+
+    let totals = store
+        .cart_lines(user_id, &CartListOptions::default())
+        .await
+        .map(|lines| {
+            let contents = CartContents::from_lines(lines);
+            let total_price = Decimal::from_str(&contents.total_price.to_string()).unwrap_or_default();
+            (total_price, contents.total_items)
+        });
+    publish_with_totals(hub, user_id, kind, product_id, totals);
+}
+
+fn publish_with_totals(
+    hub: &CartEventHub,
+    user_id: &str,
+    kind: CartEventKind,
+    product_id: Uuid,
+    totals: Result<(Decimal, i64), sea_orm::DbErr>,
+) {
+    match totals {
+        Ok((total_price, total_items)) => hub.publish(
+            user_id,
+            CartEvent {
+                kind,
+                product_id,
+                total_items,
+                total_price,
+            },
+        ),
+        Err(e) => eprintln!("❌ Error computing cart totals for event: {}", e),
+    }
+}
@@ -0,0 +1,195 @@
+use crate::middleware::has_admin_key;
+use crate::models::cart_sessions;
+use crate::models::responses::ErrorResponse;
+use actix_web::{HttpRequest, HttpResponse};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ActiveModelTrait, ConnectionTrait, EntityTrait, Set};
+use uuid::Uuid;
+
+/// Header carrying a cart's secret token.
+pub const CART_TOKEN_HEADER: &str = "x-cart-token";
+
+/// Header carrying the token of the cart `POST /carts/merge` merges into.
+pub const TARGET_CART_TOKEN_HEADER: &str = "x-target-cart-token";
+
+// Function to create a guest cart with a random id and a separate random token.
+pub async fn create_cart_session<C: ConnectionTrait>(
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<cart_sessions::Model, sea_orm::DbErr> {
+    cart_sessions::ActiveModel {
+        user_id: Set(Uuid::new_v4().to_string()),
+        token: Set(Uuid::new_v4().simple().to_string()),
+        created_at: Set(now),
+    }
+    .insert(db)
+    .await
+}
+
+// Function to return the cart session of a signed-in user's id, creating one with a fresh token the
+// first time. Only trusted servers holding the admin key may call this.
+pub async fn issue_user_cart_session<C: ConnectionTrait>(
+    user_id: &str,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<cart_sessions::Model, sea_orm::DbErr> {
+    if let Some(session) = cart_sessions::Entity::find_by_id(user_id.to_string()).one(db).await? {
+        return Ok(session);
+    }
+    cart_sessions::Entity::insert(cart_sessions::ActiveModel {
+        user_id: Set(user_id.to_string()),
+        token: Set(Uuid::new_v4().simple().to_string()),
+        created_at: Set(now),
+    })
+    .on_conflict(OnConflict::column(cart_sessions::Column::UserId).do_nothing().to_owned())
+    .exec_without_returning(db)
+    .await?;
+    // Re-read so a concurrent first call ends up with the same token
+    cart_sessions::Entity::find_by_id(user_id.to_string())
+        .one(db)
+        .await?
+        .ok_or(sea_orm::DbErr::RecordNotFound(format!("cart session for {}", user_id)))
+}
+
+// Function to check the request may act on `user_id`'s cart, using the token in the `X-Cart-Token`
+// header. See `authorize_cart_token`.
+pub async fn authorize_cart_access<C: ConnectionTrait>(
+    user_id: &str,
+    req: &HttpRequest,
+    db: &C,
+) -> Result<(), HttpResponse> {
+    let token = req
+        .headers()
+        .get(CART_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok());
+    authorize_cart_token(user_id, token, req, db).await
+}
+
+// Function to check `token` unlocks `user_id`'s cart.
+// Guest carts, and users' carts once `POST /carts/session` has issued them a token, need the
+// matching token and answer `403 Forbidden` without it. Ids without a cart session, like
+// registered users who were never issued one or the seeded `demo-user`, stay open. Requests
+// carrying the admin key may act on any cart, so trusted servers can work on a user's behalf.
+// Ids with control characters, which Postgres can't store, answer `400 Bad Request`.
+pub async fn authorize_cart_token<C: ConnectionTrait>(
+    user_id: &str,
+    token: Option<&str>,
+    req: &HttpRequest,
+    db: &C,
+) -> Result<(), HttpResponse> {
+    if user_id.chars().any(char::is_control) {
+        return Err(HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Invalid user_id.".to_string(),
+        }));
+    }
+
+    if has_admin_key(req) {
+        return Ok(());
+    }
+
+    let session = match cart_sessions::Entity::find_by_id(user_id.to_string()).one(db).await {
+        Ok(session) => session,
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while checking cart session: {}", e),
+            }));
+        }
+    };
+
+    match session {
+        Some(session) if token != Some(session.token.as_str()) => Err(HttpResponse::Forbidden().json(ErrorResponse {
+            detail: "Missing or invalid cart token for this cart.".to_string(),
+        })),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::test_utils::test_db;
+    use crate::utils::local_datetime;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+    use actix_web::web;
+
+    fn request_with_admin_key(configured_key: &str, sent_key: &str) -> HttpRequest {
+        TestRequest::default()
+            .app_data(web::Data::new(AppConfig {
+                admin_api_key: Some(configured_key.to_string()),
+                ..AppConfig::from_env()
+            }))
+            .insert_header(("x-admin-key", sent_key))
+            .to_http_request()
+    }
+
+    #[actix_web::test]
+    async fn only_the_matching_token_unlocks_a_cart() {
+        let Some(db) = test_db().await else { return };
+        let session = create_cart_session(local_datetime(), &db).await.unwrap();
+        let req = TestRequest::default().to_http_request();
+
+        assert!(authorize_cart_token(&session.user_id, Some(&session.token), &req, &db).await.is_ok());
+
+        let wrong = authorize_cart_token(&session.user_id, Some("not-the-token"), &req, &db).await;
+        assert_eq!(wrong.unwrap_err().status(), StatusCode::FORBIDDEN);
+        let missing = authorize_cart_token(&session.user_id, None, &req, &db).await;
+        assert_eq!(missing.unwrap_err().status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn reads_the_token_from_the_cart_token_header() {
+        let Some(db) = test_db().await else { return };
+        let session = create_cart_session(local_datetime(), &db).await.unwrap();
+
+        let req = TestRequest::default()
+            .insert_header((CART_TOKEN_HEADER, session.token.clone()))
+            .to_http_request();
+        assert!(authorize_cart_access(&session.user_id, &req, &db).await.is_ok());
+
+        let req = TestRequest::default().to_http_request();
+        let refused = authorize_cart_access(&session.user_id, &req, &db).await;
+        assert_eq!(refused.unwrap_err().status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn carts_without_a_session_need_no_token() {
+        let Some(db) = test_db().await else { return };
+        let req = TestRequest::default().to_http_request();
+
+        assert!(authorize_cart_token("demo-user", None, &req, &db).await.is_ok());
+        assert!(authorize_cart_token("registered-user", Some("any-token"), &req, &db).await.is_ok());
+        let refused = authorize_cart_token("nul\0byte", None, &req, &db).await;
+        assert_eq!(refused.unwrap_err().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn the_admin_key_unlocks_any_cart() {
+        let Some(db) = test_db().await else { return };
+        let session = create_cart_session(local_datetime(), &db).await.unwrap();
+
+        let req = request_with_admin_key("secret", "secret");
+        assert!(authorize_cart_token(&session.user_id, None, &req, &db).await.is_ok());
+        assert!(authorize_cart_token("user-without-session", None, &req, &db).await.is_ok());
+
+        let req = request_with_admin_key("secret", "guess");
+        let refused = authorize_cart_token(&session.user_id, None, &req, &db).await;
+        assert_eq!(refused.unwrap_err().status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_web::test]
+    async fn issuing_a_user_session_twice_returns_the_same_token() {
+        let Some(db) = test_db().await else { return };
+        let now = local_datetime();
+
+        let first = issue_user_cart_session("user-42", now, &db).await.unwrap();
+        let second = issue_user_cart_session("user-42", now, &db).await.unwrap();
+        assert_eq!(first.user_id, "user-42");
+        assert_eq!(first.token, second.token);
+
+        let req = TestRequest::default().to_http_request();
+        assert!(authorize_cart_token("user-42", Some(&first.token), &req, &db).await.is_ok());
+    }
+}
@@ -0,0 +1,62 @@
+use crate::models::cart_shares;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, Set};
+use uuid::Uuid;
+
+/// Default lifetime of a share link.
+pub const DEFAULT_CART_SHARE_HOURS: i64 = 72;
+/// Longest lifetime a share link may be given.
+pub const MAX_CART_SHARE_HOURS: i64 = 30 * 24;
+
+// Function to create a share link for `user_id`'s cart that expires `expires_in_hours` after `now`.
+pub async fn create_cart_share<C: ConnectionTrait>(
+    user_id: &str,
+    expires_in_hours: i64,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<cart_shares::Model, sea_orm::DbErr> {
+    cart_shares::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id.to_string()),
+        token: Set(Uuid::new_v4().simple().to_string()),
+        expires_at: Set(now + chrono::Duration::hours(expires_in_hours)),
+        revoked_at: Set(None),
+        created_at: Set(now),
+    }
+    .insert(db)
+    .await
+}
+
+// Function to find a share link by its token, whether or not it is still active.
+pub async fn find_cart_share_by_token<C: ConnectionTrait>(
+    token: &str,
+    db: &C,
+) -> Result<Option<cart_shares::Model>, sea_orm::DbErr> {
+    cart_shares::Entity::find()
+        .filter(cart_shares::Column::Token.eq(token))
+        .one(db)
+        .await
+}
+
+// Function to revoke one of `user_id`'s share links. Returns `None` when the user has no such link;
+// revoking an already revoked link keeps the original `revoked_at`.
+pub async fn revoke_cart_share<C: ConnectionTrait>(
+    user_id: &str,
+    share_id: Uuid,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<Option<cart_shares::Model>, sea_orm::DbErr> {
+    let share = cart_shares::Entity::find_by_id(share_id)
+        .filter(cart_shares::Column::UserId.eq(user_id))
+        .one(db)
+        .await?;
+
+    match share {
+        Some(share) if share.revoked_at.is_none() => {
+            let mut share_active_model: cart_shares::ActiveModel = share.into();
+            share_active_model.revoked_at = Set(Some(now));
+            share_active_model.update(db).await.map(Some)
+        }
+        share => Ok(share),
+    }
+}
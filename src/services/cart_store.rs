@@ -0,0 +1,1131 @@
+use crate::config::{AppConfig, CartStoreKind};
+use crate::models::carts::{CartAddMode, CartListOptions, CartRowWithProduct, CartsResponse, NewCart};
+use crate::models::{carts, product_variants, products, vendors};
+use crate::models::responses::ErrorResponse;
+use crate::services::{check_cart_items_limit, check_quantity_limit, create_new_cart_item, fetch_cart_product_ids, fetch_cart_with_products, find_existing_cart_item, list_cart_rows, lock_existing_cart_item, set_cart_note, set_cart_quantity, validate_cart_line_purchasable};
+use crate::utils::AppLogger;
+use actix_web::HttpResponse;
+use redis::AsyncCommands;
+use sea_orm::prelude::{BigDecimal, DateTimeWithTimeZone, Decimal};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter, Set, TransactionTrait};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::str::FromStr;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Prefix of every Redis key the cart store writes.
+const REDIS_CART_NAMESPACE: &str = "talipapaup";
+
+/// Hash field marking a cached cart as loaded from Postgres.
+const LOADED_FIELD: &str = "loaded";
+
+/// Hash field counting writes to a cached cart, so a flush only drops the copy it wrote back.
+const VERSION_FIELD: &str = "version";
+
+/// How long a cached cart lives after its last use. Far longer than the flush interval, so
+/// only carts that were already written back expire.
+const CART_CACHE_TTL_SECS: i64 = 60 * 60;
+
+/// Most dirty carts written back per round trip of the background flush.
+const FLUSH_BATCH_SIZE: usize = 100;
+
+/// How long a cart stays with Postgres after `sync`, covering the SQL the caller runs next.
+const SQL_LEASE_SECS: u64 = 10;
+
+/// How long a flush may hold a cart before its lock lapses, should the process die mid-flush.
+const FLUSH_LOCK_MS: u64 = 30_000;
+
+/// How long `sync` waits for a flush of the same cart that is already running.
+const FLUSH_LOCK_WAIT_MS: u64 = 5_000;
+const FLUSH_LOCK_POLL_MS: u64 = 20;
+
+/// Times a cart write is recomputed when another request changed the cart first.
+const MAX_WRITE_ATTEMPTS: usize = 10;
+
+/// A cart line written by `CartStore::add_item`.
+#[derive(Debug)]
+pub struct CartAddOutcome {
+    pub cart: carts::Model,
+    /// Quantity of the line before the add; `None` when the line was created.
+    pub previous_qty: Option<i32>,
+}
+
+/// Where the hot cart endpoints (`POST /carts/`, `PUT /carts/qty/...` and `GET /carts/{user_id}`)
+/// read and write cart lines.
+///
+/// Every other cart feature works on the `carts` table directly and calls `sync` first, so a
+/// store that holds changes outside Postgres writes them back before SQL sees the cart.
+pub trait CartStore {
+    /// Adds `new_cart` to its cart, or applies its `mode` to the line already there.
+    ///
+    /// Answers with the same `400`/`409` responses as the handlers when the cart is full or
+    /// the product can't be bought in the resulting quantity.
+    fn add_item(
+        &self,
+        new_cart: &NewCart,
+        note: Option<String>,
+        max_qty: i32,
+        max_items: usize,
+        now: DateTimeWithTimeZone,
+    ) -> impl Future<Output = Result<CartAddOutcome, HttpResponse>>;
+
+    /// Sets the quantity of the base-product line; `None` when the product isn't in the cart.
+    fn set_item_qty(
+        &self,
+        user_id: &str,
+        product_id: Uuid,
+        qty: i32,
+        now: DateTimeWithTimeZone,
+    ) -> impl Future<Output = Result<Option<carts::Model>, sea_orm::DbErr>>;
+
+    /// Lists the cart joined with product details, like `fetch_cart_with_products`.
+    fn cart_lines(
+        &self,
+        user_id: &str,
+        options: &CartListOptions,
+    ) -> impl Future<Output = Result<Vec<CartsResponse>, sea_orm::DbErr>>;
+
+    /// Makes the `carts` table current for `user_id`.
+    fn sync(&self, user_id: &str) -> impl Future<Output = Result<(), sea_orm::DbErr>>;
+}
+
+/// Keeps carts in the `carts` table; every call is a Postgres round trip.
+#[derive(Clone)]
+pub struct PostgresCartStore {
+    db: DatabaseConnection,
+}
+
+impl PostgresCartStore {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+}
+
+impl CartStore for PostgresCartStore {
+    async fn add_item(
+        &self,
+        new_cart: &NewCart,
+        note: Option<String>,
+        max_qty: i32,
+        max_items: usize,
+        now: DateTimeWithTimeZone,
+    ) -> Result<CartAddOutcome, HttpResponse> {
+        let user_id = new_cart.user_id.to_string();
+        let txn = self.db.begin().await.map_err(|e| {
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while starting transaction: {}", e),
+            })
+        })?;
+
+        // Lock the line so concurrent adds see each other's quantity
+        let existing_cart = lock_existing_cart_item(user_id.clone(), new_cart.product_id, new_cart.variant_id, &txn)
+            .await
+            .map_err(|e| {
+                HttpResponse::InternalServerError().json(ErrorResponse {
+                    detail: format!("Database error while checking existing cart: {}", e),
+                })
+            })?;
+
+        if existing_cart.is_none() {
+            let cart_product_ids = fetch_cart_product_ids(&user_id, &txn).await.map_err(|e| {
+                HttpResponse::InternalServerError().json(ErrorResponse {
+                    detail: format!("Database error while counting cart items: {}", e),
+                })
+            })?;
+            check_cart_items_limit(&cart_product_ids, new_cart.product_id, max_items)?;
+        }
+
+        let existing_qty = existing_cart.as_ref().map(|cart| cart.total_qty);
+        let resulting_qty = resulting_cart_qty(new_cart, existing_qty.unwrap_or(0));
+        check_quantity_limit(resulting_qty, max_qty)?;
+        let unit_price =
+            validate_cart_line_purchasable(new_cart.product_id, new_cart.variant_id, resulting_qty, &txn).await?;
+
+        let written = match (new_cart.mode, existing_cart) {
+            (CartAddMode::Set, Some(existing_cart)) => match set_cart_quantity(existing_cart, new_cart.total_qty, now, &txn).await {
+                Ok(cart) if note.is_some() => set_cart_note(cart, note, now, &txn).await,
+                result => result,
+            },
+            // Insert, or add to the existing row atomically if one appeared in the meantime
+            _ => {
+                create_new_cart_item(
+                    user_id,
+                    new_cart.product_id,
+                    new_cart.variant_id,
+                    new_cart.total_qty,
+                    unit_price,
+                    note,
+                    max_qty,
+                    now,
+                    &txn,
+                )
+                .await
+            }
+        };
+        let written = match written {
+            Ok(cart) => txn.commit().await.map(|_| cart),
+            Err(e) => Err(e),
+        };
+
+        match written {
+            Ok(cart) => Ok(CartAddOutcome {
+                cart,
+                previous_qty: existing_qty,
+            }),
+            Err(e) => Err(HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Unable to add product to cart: {}", e),
+            })),
+        }
+    }
+
+    async fn set_item_qty(
+        &self,
+        user_id: &str,
+        product_id: Uuid,
+        qty: i32,
+        now: DateTimeWithTimeZone,
+    ) -> Result<Option<carts::Model>, sea_orm::DbErr> {
+        match find_existing_cart_item(user_id.to_string(), product_id, None, &self.db).await? {
+            Some(cart_item) => set_cart_quantity(cart_item, qty, now, &self.db).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn cart_lines(&self, user_id: &str, options: &CartListOptions) -> Result<Vec<CartsResponse>, sea_orm::DbErr> {
+        fetch_cart_with_products(user_id, options, &self.db).await
+    }
+
+    async fn sync(&self, _user_id: &str) -> Result<(), sea_orm::DbErr> {
+        Ok(())
+    }
+}
+
+/// Keeps hot carts in Redis and writes them back to Postgres lazily.
+///
+/// A cart is loaded from Postgres on first use into one Redis hash: a JSON copy of each line,
+/// plus its quantity in a separate field. Every write is one Lua script that only applies if
+/// the quantity it was computed from is still current, so concurrent adds are retried against
+/// each other instead of both passing the stock check. Changed carts are flagged as dirty and
+/// written back by `spawn_cart_store_flush`; the cached copy is dropped once Postgres holds it.
+///
+/// `sync` writes the cart back and then hands it to SQL for `SQL_LEASE_SECS`: meanwhile every
+/// call goes straight to Postgres, so a request racing the handler's own SQL can't reload the
+/// old lines into Redis and have them written back over a checkout or delete.
+///
+/// Admin listings and the stale-cart cleanup read Postgres, so they lag by up to one flush
+/// interval.
+#[derive(Clone)]
+pub struct RedisCartStore {
+    redis: redis::aio::MultiplexedConnection,
+    db: DatabaseConnection,
+    namespace: String,
+}
+
+// What reading a cart from Redis found
+enum CachedCart {
+    Lines(Vec<carts::Model>),
+    // `sync` handed the cart to SQL; Postgres is the copy to use
+    Leased,
+    // Written back and dropped since it was loaded; the next read loads it again
+    Dropped,
+}
+
+// What `WRITE_LINE_SCRIPT` did with a line
+enum LineWrite {
+    Written,
+    // The cart changed since the line was computed
+    Stale,
+    Leased,
+}
+
+impl RedisCartStore {
+    pub async fn connect(redis_url: &str, db: DatabaseConnection) -> Result<Self, redis::RedisError> {
+        Self::connect_in(redis_url, REDIS_CART_NAMESPACE, db).await
+    }
+
+    // Keys are prefixed with `namespace`, so stores sharing a Redis never see each other's carts
+    async fn connect_in(redis_url: &str, namespace: &str, db: DatabaseConnection) -> Result<Self, redis::RedisError> {
+        let client = redis::Client::open(redis_url)?;
+        let redis = client.get_multiplexed_async_connection().await?;
+        Ok(Self {
+            redis,
+            db,
+            namespace: namespace.to_string(),
+        })
+    }
+
+    fn cart_key(&self, user_id: &str) -> String {
+        format!("{}:cart:{}", self.namespace, user_id)
+    }
+
+    // Set of user ids whose cached cart has changes not yet written to Postgres
+    fn dirty_key(&self) -> String {
+        format!("{}:carts:dirty", self.namespace)
+    }
+
+    // Present while `sync` has handed the cart to SQL
+    fn lease_key(&self, user_id: &str) -> String {
+        format!("{}:cart-lease:{}", self.namespace, user_id)
+    }
+
+    // Held by the one flush allowed to write the cart back at a time
+    fn flush_lock_key(&self, user_id: &str) -> String {
+        format!("{}:cart-flush:{}", self.namespace, user_id)
+    }
+
+    fn line_field(product_id: Uuid, variant_id: Option<Uuid>) -> String {
+        match variant_id {
+            Some(variant_id) => format!("line:{}:{}", product_id, variant_id),
+            None => format!("line:{}", product_id),
+        }
+    }
+
+    fn qty_field(product_id: Uuid, variant_id: Option<Uuid>) -> String {
+        format!("qty{}", &Self::line_field(product_id, variant_id)["line".len()..])
+    }
+
+    // Used for every call while the cart is leased to SQL
+    fn postgres(&self) -> PostgresCartStore {
+        PostgresCartStore::new(self.db.clone())
+    }
+
+    // Copies the user's Postgres rows into Redis unless they're already there; `false` when the
+    // cart is leased to SQL. `HSETNX` never overwrites, so a racing load is harmless.
+    async fn ensure_loaded(&self, user_id: &str) -> Result<bool, sea_orm::DbErr> {
+        let key = self.cart_key(user_id);
+        let lease_key = self.lease_key(user_id);
+        let mut redis = self.redis.clone();
+        let (leased, loaded): (bool, bool) = redis::pipe()
+            .exists(&lease_key)
+            .hexists(&key, LOADED_FIELD)
+            .query_async(&mut redis)
+            .await
+            .map_err(redis_error)?;
+        if leased {
+            return Ok(false);
+        }
+        if loaded {
+            return Ok(true);
+        }
+
+        let rows = carts::Entity::find()
+            .filter(carts::Column::UserId.eq(user_id))
+            .all(&self.db)
+            .await?;
+        let script = redis::Script::new(LOAD_CART_SCRIPT);
+        let mut invocation = script.key(&key);
+        invocation.key(&lease_key).arg(CART_CACHE_TTL_SECS);
+        for row in rows {
+            invocation
+                .arg(Self::qty_field(row.product_id, row.variant_id))
+                .arg(row.total_qty)
+                .arg(Self::line_field(row.product_id, row.variant_id))
+                .arg(line_json(&row)?);
+        }
+        invocation.invoke_async(&mut redis).await.map_err(redis_error)
+    }
+
+    // Reads the cached cart, oldest line first like the `carts` table listing
+    async fn cached_cart(&self, user_id: &str) -> Result<CachedCart, sea_orm::DbErr> {
+        if !self.ensure_loaded(user_id).await? {
+            return Ok(CachedCart::Leased);
+        }
+        let mut redis = self.redis.clone();
+        let fields: HashMap<String, String> = redis.hgetall(self.cart_key(user_id)).await.map_err(redis_error)?;
+        if !fields.contains_key(LOADED_FIELD) {
+            return Ok(CachedCart::Dropped);
+        }
+        parse_cached_lines(&fields).map(CachedCart::Lines)
+    }
+
+    // Writes `line` and marks the cart dirty, unless the cart is leased, was dropped, or the
+    // line's quantity is no longer `expected_qty`
+    async fn write_line(&self, line: &carts::Model, expected_qty: Option<i32>) -> Result<LineWrite, sea_orm::DbErr> {
+        let mut redis = self.redis.clone();
+        let written: i32 = redis::Script::new(WRITE_LINE_SCRIPT)
+            .key(self.cart_key(&line.user_id))
+            .key(self.lease_key(&line.user_id))
+            .key(self.dirty_key())
+            .arg(Self::qty_field(line.product_id, line.variant_id))
+            .arg(expected_qty.map(|qty| qty.to_string()).unwrap_or_default())
+            .arg(line.total_qty)
+            .arg(Self::line_field(line.product_id, line.variant_id))
+            .arg(line_json(line)?)
+            .arg(CART_CACHE_TTL_SECS)
+            .arg(&line.user_id)
+            .invoke_async(&mut redis)
+            .await
+            .map_err(redis_error)?;
+        Ok(match written {
+            1 => LineWrite::Written,
+            -1 => LineWrite::Leased,
+            _ => LineWrite::Stale,
+        })
+    }
+
+    // Takes the cart's flush lock, answering the token that releases it; `None` when another
+    // flush holds it
+    async fn try_lock_flush(&self, user_id: &str) -> Result<Option<String>, sea_orm::DbErr> {
+        let token = Uuid::new_v4().simple().to_string();
+        let mut redis = self.redis.clone();
+        let locked: Option<String> = redis::cmd("SET")
+            .arg(self.flush_lock_key(user_id))
+            .arg(&token)
+            .arg("NX")
+            .arg("PX")
+            .arg(FLUSH_LOCK_MS)
+            .query_async(&mut redis)
+            .await
+            .map_err(redis_error)?;
+        Ok(locked.map(|_| token))
+    }
+
+    async fn unlock_flush(&self, user_id: &str, token: &str) -> Result<(), sea_orm::DbErr> {
+        let mut redis = self.redis.clone();
+        redis::Script::new(UNLOCK_SCRIPT)
+            .key(self.flush_lock_key(user_id))
+            .arg(token)
+            .invoke_async::<()>(&mut redis)
+            .await
+            .map_err(redis_error)
+    }
+
+    // Upserts the cached cart into Postgres, then drops it unless it changed meanwhile, in which
+    // case it stays dirty for the next flush. Callers hold the flush lock.
+    async fn write_back(&self, user_id: &str) -> Result<(), sea_orm::DbErr> {
+        let key = self.cart_key(user_id);
+        let mut redis = self.redis.clone();
+
+        redis.srem::<_, _, ()>(self.dirty_key(), user_id).await.map_err(redis_error)?;
+        let fields: HashMap<String, String> = redis.hgetall(&key).await.map_err(redis_error)?;
+        if fields.is_empty() {
+            return Ok(());
+        }
+
+        let persisted = match parse_cached_lines(&fields) {
+            Ok(lines) => persist_cart_lines(lines, &self.db).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = persisted {
+            redis.sadd::<_, _, ()>(self.dirty_key(), user_id).await.map_err(redis_error)?;
+            return Err(e);
+        }
+        redis::Script::new(DROP_FLUSHED_SCRIPT)
+            .key(&key)
+            .key(self.dirty_key())
+            .arg(fields.get(VERSION_FIELD).map(String::as_str).unwrap_or_default())
+            .arg(user_id)
+            .invoke_async::<()>(&mut redis)
+            .await
+            .map_err(redis_error)
+    }
+
+    // Function to write the cached cart of `user_id` to Postgres and drop it from Redis.
+    // Answers `false` without writing when another flush of the same cart is running.
+    pub async fn flush_cart(&self, user_id: &str) -> Result<bool, sea_orm::DbErr> {
+        let Some(token) = self.try_lock_flush(user_id).await? else {
+            return Ok(false);
+        };
+        let written = self.write_back(user_id).await;
+        self.unlock_flush(user_id, &token).await?;
+        written.map(|()| true)
+    }
+
+    // Function to write every cart changed since the last flush back to Postgres; returns how many.
+    // Carts another flush is busy with stay dirty for the next run.
+    pub async fn flush_dirty_carts(&self) -> Result<usize, sea_orm::DbErr> {
+        let mut redis = self.redis.clone();
+        let mut flushed = 0;
+        let mut busy = Vec::new();
+        loop {
+            let user_ids: Vec<String> = redis::cmd("SPOP")
+                .arg(self.dirty_key())
+                .arg(FLUSH_BATCH_SIZE)
+                .query_async(&mut redis)
+                .await
+                .map_err(redis_error)?;
+            if user_ids.is_empty() {
+                break;
+            }
+            for user_id in user_ids {
+                if self.flush_cart(&user_id).await? {
+                    flushed += 1;
+                } else {
+                    busy.push(user_id);
+                }
+            }
+        }
+        if !busy.is_empty() {
+            redis.sadd::<_, _, ()>(self.dirty_key(), busy).await.map_err(redis_error)?;
+        }
+        Ok(flushed)
+    }
+}
+
+impl CartStore for RedisCartStore {
+    async fn add_item(
+        &self,
+        new_cart: &NewCart,
+        note: Option<String>,
+        max_qty: i32,
+        max_items: usize,
+        now: DateTimeWithTimeZone,
+    ) -> Result<CartAddOutcome, HttpResponse> {
+        let user_id = new_cart.user_id.to_string();
+        let write_error = |e: sea_orm::DbErr| {
+            HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Unable to add product to cart: {}", e),
+            })
+        };
+
+        for _ in 0..MAX_WRITE_ATTEMPTS {
+            let lines = match self.cached_cart(&user_id).await {
+                Ok(CachedCart::Lines(lines)) => lines,
+                Ok(CachedCart::Leased) => return self.postgres().add_item(new_cart, note, max_qty, max_items, now).await,
+                Ok(CachedCart::Dropped) => continue,
+                Err(e) => {
+                    return Err(HttpResponse::InternalServerError().json(ErrorResponse {
+                        detail: format!("Database error while checking existing cart: {}", e),
+                    }));
+                }
+            };
+
+            let existing_cart = lines
+                .iter()
+                .find(|line| line.product_id == new_cart.product_id && line.variant_id == new_cart.variant_id)
+                .cloned();
+            if existing_cart.is_none() {
+                let cart_product_ids: HashSet<Uuid> = lines.iter().map(|line| line.product_id).collect();
+                check_cart_items_limit(&cart_product_ids, new_cart.product_id, max_items)?;
+            }
+
+            let existing_qty = existing_cart.as_ref().map(|cart| cart.total_qty);
+            let resulting_qty = resulting_cart_qty(new_cart, existing_qty.unwrap_or(0));
+            check_quantity_limit(resulting_qty, max_qty)?;
+            let unit_price =
+                validate_cart_line_purchasable(new_cart.product_id, new_cart.variant_id, resulting_qty, &self.db).await?;
+
+            let mut line = existing_cart.unwrap_or_else(|| carts::Model {
+                id: Uuid::new_v4(),
+                user_id: user_id.clone(),
+                product_id: new_cart.product_id,
+                variant_id: new_cart.variant_id,
+                total_qty: 0,
+                unit_price,
+                note: None,
+                created_at: now,
+                updated_at: now,
+            });
+            line.total_qty = resulting_qty;
+            if note.is_some() {
+                line.note = note.clone();
+            }
+            line.updated_at = now;
+
+            // Only lands if no other request changed the line since the stock check above
+            match self.write_line(&line, Some(existing_qty.unwrap_or(0))).await.map_err(write_error)? {
+                LineWrite::Written => {
+                    return Ok(CartAddOutcome {
+                        cart: line,
+                        previous_qty: existing_qty,
+                    });
+                }
+                LineWrite::Stale => continue,
+                LineWrite::Leased => return self.postgres().add_item(new_cart, note, max_qty, max_items, now).await,
+            }
+        }
+
+        Err(HttpResponse::Conflict().json(ErrorResponse {
+            detail: "The cart is being changed by another request; please try again.".to_string(),
+        }))
+    }
+
+    async fn set_item_qty(
+        &self,
+        user_id: &str,
+        product_id: Uuid,
+        qty: i32,
+        now: DateTimeWithTimeZone,
+    ) -> Result<Option<carts::Model>, sea_orm::DbErr> {
+        for _ in 0..MAX_WRITE_ATTEMPTS {
+            let lines = match self.cached_cart(user_id).await? {
+                CachedCart::Lines(lines) => lines,
+                CachedCart::Leased => return self.postgres().set_item_qty(user_id, product_id, qty, now).await,
+                CachedCart::Dropped => continue,
+            };
+            let line = lines
+                .into_iter()
+                .find(|line| line.product_id == product_id && line.variant_id.is_none());
+            let Some(mut line) = line else {
+                return Ok(None);
+            };
+
+            line.total_qty = qty;
+            line.updated_at = now;
+            match self.write_line(&line, None).await? {
+                LineWrite::Written => return Ok(Some(line)),
+                LineWrite::Stale => continue,
+                LineWrite::Leased => return self.postgres().set_item_qty(user_id, product_id, qty, now).await,
+            }
+        }
+        Err(sea_orm::DbErr::Custom("The cart kept changing while it was being updated".to_string()))
+    }
+
+    async fn cart_lines(&self, user_id: &str, options: &CartListOptions) -> Result<Vec<CartsResponse>, sea_orm::DbErr> {
+        let lines = match self.cached_cart(user_id).await? {
+            CachedCart::Lines(lines) => lines,
+            // Postgres is current: the cart is leased, or was written back before it was dropped
+            CachedCart::Leased | CachedCart::Dropped => return self.postgres().cart_lines(user_id, options).await,
+        };
+        let rows = cart_rows_with_products(lines, &self.db).await?;
+        list_cart_rows(rows, options, &self.db).await
+    }
+
+    async fn sync(&self, user_id: &str) -> Result<(), sea_orm::DbErr> {
+        let mut redis = self.redis.clone();
+        // Lease first, so nothing is written to the cached copy once it's been read back
+        redis::cmd("SET")
+            .arg(self.lease_key(user_id))
+            .arg(1)
+            .arg("EX")
+            .arg(SQL_LEASE_SECS)
+            .query_async::<()>(&mut redis)
+            .await
+            .map_err(redis_error)?;
+
+        // Wait out a flush already writing this cart, or it could land after the caller's SQL
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(FLUSH_LOCK_WAIT_MS);
+        let written = loop {
+            match self.try_lock_flush(user_id).await {
+                Ok(Some(token)) => {
+                    let written = self.write_back(user_id).await;
+                    break self.unlock_flush(user_id, &token).await.and(written);
+                }
+                Ok(None) if tokio::time::Instant::now() < deadline => {
+                    tokio::time::sleep(Duration::from_millis(FLUSH_LOCK_POLL_MS)).await;
+                }
+                Ok(None) => break Err(sea_orm::DbErr::Custom("Timed out waiting for the cart to be written back".to_string())),
+                Err(e) => break Err(e),
+            }
+        };
+
+        if written.is_err() {
+            // The cached copy is still the newest; keep serving it
+            redis.del::<_, ()>(self.lease_key(user_id)).await.map_err(redis_error)?;
+        }
+        written
+    }
+}
+
+// Loads rows into an empty cart hash. KEYS: cart, lease. ARGV: TTL, then qty field, qty, line
+// field, line JSON for each row. Answers 0 without loading when the cart is leased to SQL.
+const LOAD_CART_SCRIPT: &str = r"
+-- Loads a cart from Postgres rows unless it is leased to SQL
+if redis.call('EXISTS', KEYS[2]) == 1 then
+    return 0
+end
+for i = 2, #ARGV, 4 do
+    redis.call('HSETNX', KEYS[1], ARGV[i], ARGV[i + 1])
+    redis.call('HSETNX', KEYS[1], ARGV[i + 2], ARGV[i + 3])
+end
+redis.call('HSET', KEYS[1], 'loaded', 1)
+redis.call('EXPIRE', KEYS[1], ARGV[1])
+return 1
+";
+
+// KEYS: cart, lease, dirty set. ARGV: qty field, expected qty ('' to skip the check), qty, line
+// field, line JSON, TTL, user id. Answers 1 when written, 0 when stale, -1 when leased.
+const WRITE_LINE_SCRIPT: &str = r"
+-- Writes a cart line if the cached cart is still the one it was computed from
+if redis.call('EXISTS', KEYS[2]) == 1 then
+    return -1
+end
+if redis.call('HEXISTS', KEYS[1], 'loaded') == 0 then
+    return 0
+end
+local current = tonumber(redis.call('HGET', KEYS[1], ARGV[1]) or '0')
+if ARGV[2] ~= '' and current ~= tonumber(ARGV[2]) then
+    return 0
+end
+redis.call('HSET', KEYS[1], ARGV[1], ARGV[3], ARGV[4], ARGV[5])
+redis.call('HINCRBY', KEYS[1], 'version', 1)
+redis.call('EXPIRE', KEYS[1], ARGV[6])
+redis.call('SADD', KEYS[3], ARGV[7])
+return 1
+";
+
+// KEYS: cart, dirty set. ARGV: version that was written back, user id.
+const DROP_FLUSHED_SCRIPT: &str = r"
+-- Drops a written-back cart unless it changed while it was being written
+if (redis.call('HGET', KEYS[1], 'version') or '') == ARGV[1] then
+    redis.call('DEL', KEYS[1])
+    return 1
+end
+redis.call('SADD', KEYS[2], ARGV[2])
+return 0
+";
+
+// KEYS: lock. ARGV: token of the holder.
+const UNLOCK_SCRIPT: &str = r"
+-- Releases a lock only if the caller still holds it
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+";
+
+/// The configured `CartStore`, picked by `CART_STORE`.
+#[derive(Clone)]
+pub enum CartBackend {
+    Postgres(PostgresCartStore),
+    Redis(RedisCartStore),
+}
+
+impl CartBackend {
+    // Function to build the store `config` asks for. `CART_STORE=redis` needs `REDIS_URL`.
+    pub async fn from_config(config: &AppConfig, db: DatabaseConnection) -> Result<Self, redis::RedisError> {
+        match (config.cart_store, config.redis_url.as_deref()) {
+            (CartStoreKind::Redis, Some(redis_url)) => Ok(Self::Redis(RedisCartStore::connect(redis_url, db).await?)),
+            (CartStoreKind::Redis, None) => Err(redis::RedisError::from((
+                redis::ErrorKind::InvalidClientConfig,
+                "CART_STORE=redis needs REDIS_URL",
+            ))),
+            (CartStoreKind::Postgres, _) => Ok(Self::Postgres(PostgresCartStore::new(db))),
+        }
+    }
+}
+
+impl CartStore for CartBackend {
+    async fn add_item(
+        &self,
+        new_cart: &NewCart,
+        note: Option<String>,
+        max_qty: i32,
+        max_items: usize,
+        now: DateTimeWithTimeZone,
+    ) -> Result<CartAddOutcome, HttpResponse> {
+        match self {
+            Self::Postgres(store) => store.add_item(new_cart, note, max_qty, max_items, now).await,
+            Self::Redis(store) => store.add_item(new_cart, note, max_qty, max_items, now).await,
+        }
+    }
+
+    async fn set_item_qty(
+        &self,
+        user_id: &str,
+        product_id: Uuid,
+        qty: i32,
+        now: DateTimeWithTimeZone,
+    ) -> Result<Option<carts::Model>, sea_orm::DbErr> {
+        match self {
+            Self::Postgres(store) => store.set_item_qty(user_id, product_id, qty, now).await,
+            Self::Redis(store) => store.set_item_qty(user_id, product_id, qty, now).await,
+        }
+    }
+
+    async fn cart_lines(&self, user_id: &str, options: &CartListOptions) -> Result<Vec<CartsResponse>, sea_orm::DbErr> {
+        match self {
+            Self::Postgres(store) => store.cart_lines(user_id, options).await,
+            Self::Redis(store) => store.cart_lines(user_id, options).await,
+        }
+    }
+
+    async fn sync(&self, user_id: &str) -> Result<(), sea_orm::DbErr> {
+        match self {
+            Self::Postgres(store) => store.sync(user_id).await,
+            Self::Redis(store) => store.sync(user_id).await,
+        }
+    }
+}
+
+// Function to sync the cart before a handler works on the `carts` table, answering `500` on
+// failure.
+pub async fn sync_cart_store<S: CartStore>(store: &S, user_id: &str) -> Result<(), HttpResponse> {
+    store.sync(user_id).await.map_err(|e| {
+        HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Failed to sync cart: {}", e),
+        })
+    })
+}
+
+// Function to spawn a task that writes carts changed in Redis back to Postgres, every `every`.
+// A failed run is logged and the remaining carts are retried on the next tick.
+pub fn spawn_cart_store_flush(store: RedisCartStore, every: Duration) {
+    tokio::spawn(async move {
+        let logger = AppLogger::default();
+        let mut interval = tokio::time::interval(every);
+
+        loop {
+            interval.tick().await;
+
+            match store.flush_dirty_carts().await {
+                Ok(0) => {}
+                Ok(flushed) => logger.info_single(&format!("💾 Wrote {} cached carts to Postgres", flushed), "CART STORE"),
+                Err(e) => eprintln!("❌ Cart store flush failed: {}", e),
+            }
+        }
+    });
+}
+
+// Quantity a line ends up with once `new_cart` is applied on top of `existing_qty`
+fn resulting_cart_qty(new_cart: &NewCart, existing_qty: i32) -> i32 {
+    match new_cart.mode {
+        CartAddMode::Add => existing_qty.saturating_add(new_cart.total_qty),
+        CartAddMode::Set => new_cart.total_qty,
+    }
+}
+
+fn redis_error(e: redis::RedisError) -> sea_orm::DbErr {
+    sea_orm::DbErr::Custom(format!("Redis error: {}", e))
+}
+
+fn line_json(line: &carts::Model) -> Result<String, sea_orm::DbErr> {
+    serde_json::to_string(line).map_err(|e| sea_orm::DbErr::Custom(format!("Failed to encode cart line: {}", e)))
+}
+
+// Pairs every `line:*` field with its `qty:*` field; the quantity in the JSON copy is stale
+fn parse_cached_lines(fields: &HashMap<String, String>) -> Result<Vec<carts::Model>, sea_orm::DbErr> {
+    let mut lines = Vec::new();
+    for (field, value) in fields {
+        let Some(line_key) = field.strip_prefix("line") else {
+            continue;
+        };
+        let mut line: carts::Model = serde_json::from_str(value)
+            .map_err(|e| sea_orm::DbErr::Custom(format!("Failed to decode cart line: {}", e)))?;
+        line.total_qty = fields
+            .get(&format!("qty{}", line_key))
+            .and_then(|qty| qty.parse().ok())
+            .unwrap_or(0);
+        lines.push(line);
+    }
+    lines.sort_by_key(|line| (line.created_at, line.id));
+    Ok(lines)
+}
+
+// Pairs cached lines with the product, vendor and variant columns the `carts` join would return.
+// Lines whose product was deleted while cached are left out, as the foreign key cascade will do.
+async fn cart_rows_with_products<C: ConnectionTrait>(
+    lines: Vec<carts::Model>,
+    db: &C,
+) -> Result<Vec<CartRowWithProduct>, sea_orm::DbErr> {
+    let product_ids: HashSet<Uuid> = lines.iter().map(|line| line.product_id).collect();
+    let variant_ids: HashSet<Uuid> = lines.iter().filter_map(|line| line.variant_id).collect();
+
+    let products: HashMap<Uuid, (products::Model, Option<vendors::Model>)> = products::Entity::find()
+        .find_also_related(vendors::Entity)
+        .filter(products::Column::Id.is_in(product_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(product, vendor)| (product.id, (product, vendor)))
+        .collect();
+    let variants: HashMap<Uuid, product_variants::Model> = product_variants::Entity::find()
+        .filter(product_variants::Column::Id.is_in(variant_ids))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|variant| (variant.id, variant))
+        .collect();
+
+    Ok(lines
+        .into_iter()
+        .filter_map(|line| {
+            let (product, vendor) = products.get(&line.product_id)?;
+            let variant = line.variant_id.and_then(|variant_id| variants.get(&variant_id));
+            Some(CartRowWithProduct {
+                id: line.id,
+                product_id: line.product_id,
+                variant_id: line.variant_id,
+                total_qty: line.total_qty,
+                unit_price: big_decimal(line.unit_price),
+                note: line.note,
+                created_at: line.created_at,
+                updated_at: line.updated_at,
+                product_name: product.product_name.clone(),
+                description: product.description.clone(),
+                product_price: big_decimal(product.price),
+                product_stock: product.stock_quantity,
+                product_is_available: product.is_available,
+                available_from: product.available_from,
+                available_until: product.available_until,
+                img_url: product.img_url.clone(),
+                vendor_id: product.vendor_id,
+                vendor_name: vendor.as_ref().map(|vendor| vendor.name.clone()),
+                variant_name: variant.map(|variant| variant.name.clone()),
+                variant_price: variant.and_then(|variant| variant.price).map(big_decimal),
+                variant_stock: variant.map(|variant| variant.stock_quantity),
+            })
+        })
+        .collect())
+}
+
+fn big_decimal(amount: Decimal) -> BigDecimal {
+    BigDecimal::from_str(&amount.to_string()).unwrap_or_default()
+}
+
+// Upserts cached lines into `carts`. Lines whose product or variant was deleted while cached
+// are dropped, as the foreign key cascade would have done.
+async fn persist_cart_lines(lines: Vec<carts::Model>, db: &DatabaseConnection) -> Result<(), sea_orm::DbErr> {
+    if lines.is_empty() {
+        return Ok(());
+    }
+
+    let txn = db.begin().await?;
+    let product_ids: HashSet<Uuid> = products::Entity::find()
+        .filter(products::Column::Id.is_in(lines.iter().map(|line| line.product_id)))
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|product| product.id)
+        .collect();
+    let variant_ids: HashSet<Uuid> = product_variants::Entity::find()
+        .filter(product_variants::Column::Id.is_in(lines.iter().filter_map(|line| line.variant_id)))
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|variant| variant.id)
+        .collect();
+
+    let rows: Vec<carts::ActiveModel> = lines
+        .into_iter()
+        .filter(|line| product_ids.contains(&line.product_id))
+        .filter(|line| line.variant_id.is_none_or(|variant_id| variant_ids.contains(&variant_id)))
+        .map(|line| carts::ActiveModel {
+            id: Set(line.id),
+            user_id: Set(line.user_id),
+            product_id: Set(line.product_id),
+            variant_id: Set(line.variant_id),
+            total_qty: Set(line.total_qty),
+            unit_price: Set(line.unit_price),
+            note: Set(line.note),
+            created_at: Set(line.created_at),
+            updated_at: Set(line.updated_at),
+        })
+        .collect();
+    if !rows.is_empty() {
+        carts::Entity::insert_many(rows)
+            .on_conflict(
+                OnConflict::columns([carts::Column::UserId, carts::Column::ProductId, carts::Column::VariantId])
+                    .update_columns([
+                        carts::Column::TotalQty,
+                        carts::Column::UnitPrice,
+                        carts::Column::Note,
+                        carts::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec_without_returning(&txn)
+            .await?;
+    }
+    txn.commit().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{insert_cart_item, insert_product, test_db, test_service_url};
+    use crate::utils::local_datetime;
+    use actix_web::http::StatusCode;
+
+    fn new_cart(user_id: Uuid, product_id: Uuid, total_qty: i32, mode: CartAddMode) -> NewCart {
+        NewCart {
+            user_id,
+            product_id,
+            variant_id: None,
+            total_qty,
+            note: None,
+            mode,
+        }
+    }
+
+    async fn carts_in_postgres(user_id: Uuid, db: &DatabaseConnection) -> Vec<(Uuid, i32)> {
+        let mut rows: Vec<(Uuid, i32)> = carts::Entity::find()
+            .filter(carts::Column::UserId.eq(user_id.to_string()))
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|cart| (cart.product_id, cart.total_qty))
+            .collect();
+        rows.sort();
+        rows
+    }
+
+    // The behavior every store must share, whatever keeps the carts
+    async fn check_cart_store<S: CartStore>(store: &S, db: &DatabaseConnection) {
+        let now = local_datetime();
+        let user_id = Uuid::new_v4();
+        let bangus = insert_product(db, "Bangus", Decimal::new(15000, 2), 50).await;
+        let tilapia = insert_product(db, "Tilapia", Decimal::new(9000, 2), 50).await;
+        let pusit = insert_product(db, "Pusit", Decimal::new(32000, 2), 50).await;
+
+        let created = store.add_item(&new_cart(user_id, bangus.id, 2, CartAddMode::Add), None, 10, 2, now).await.unwrap();
+        assert_eq!(created.previous_qty, None);
+        assert_eq!(created.cart.total_qty, 2);
+        assert_eq!(created.cart.unit_price, bangus.price);
+
+        let added = store.add_item(&new_cart(user_id, bangus.id, 3, CartAddMode::Add), None, 10, 2, now).await.unwrap();
+        assert_eq!(added.previous_qty, Some(2));
+        assert_eq!(added.cart.total_qty, 5);
+        assert_eq!(added.cart.id, created.cart.id);
+
+        let set = store
+            .add_item(&new_cart(user_id, bangus.id, 4, CartAddMode::Set), Some("Linisin po".to_string()), 10, 2, now)
+            .await
+            .unwrap();
+        assert_eq!(set.previous_qty, Some(5));
+        assert_eq!(set.cart.total_qty, 4);
+        assert_eq!(set.cart.note.as_deref(), Some("Linisin po"));
+
+        // Over the per-line cap, and a third product in a cart limited to two
+        let over_cap = store.add_item(&new_cart(user_id, bangus.id, 7, CartAddMode::Add), None, 10, 2, now).await;
+        assert_eq!(over_cap.unwrap_err().status(), StatusCode::BAD_REQUEST);
+        store.add_item(&new_cart(user_id, tilapia.id, 1, CartAddMode::Add), None, 10, 2, now).await.unwrap();
+        let over_limit = store.add_item(&new_cart(user_id, pusit.id, 1, CartAddMode::Add), None, 10, 2, now).await;
+        assert_eq!(over_limit.unwrap_err().status(), StatusCode::BAD_REQUEST);
+
+        let updated = store.set_item_qty(&user_id.to_string(), tilapia.id, 6, now).await.unwrap();
+        assert_eq!(updated.map(|cart| cart.total_qty), Some(6));
+        assert!(store.set_item_qty(&user_id.to_string(), pusit.id, 1, now).await.unwrap().is_none());
+
+        let lines = store.cart_lines(&user_id.to_string(), &CartListOptions::default()).await.unwrap();
+        let mut listed: Vec<(String, i32)> =
+            lines.iter().map(|line| (line.product_name.clone(), line.total_qty)).collect();
+        listed.sort();
+        assert_eq!(listed, [("Bangus".to_string(), 4), ("Tilapia".to_string(), 6)]);
+
+        store.sync(&user_id.to_string()).await.unwrap();
+        let mut expected = vec![(bangus.id, 4), (tilapia.id, 6)];
+        expected.sort();
+        assert_eq!(carts_in_postgres(user_id, db).await, expected);
+    }
+
+    async fn redis_store(db: &DatabaseConnection) -> Option<RedisCartStore> {
+        let url = test_service_url("TEST_REDIS_URL")?;
+        let namespace = format!("test_{}", Uuid::new_v4().simple());
+        Some(RedisCartStore::connect_in(&url, &namespace, db.clone()).await.unwrap())
+    }
+
+    #[actix_web::test]
+    async fn postgres_store_keeps_carts_in_the_carts_table() {
+        let Some(db) = test_db().await else { return };
+        check_cart_store(&PostgresCartStore::new(db.clone()), &db).await;
+    }
+
+    #[actix_web::test]
+    async fn redis_store_behaves_like_the_postgres_store() {
+        let Some(db) = test_db().await else { return };
+        let Some(store) = redis_store(&db).await else { return };
+        check_cart_store(&store, &db).await;
+    }
+
+    #[actix_web::test]
+    async fn redis_store_writes_back_only_on_flush() {
+        let Some(db) = test_db().await else { return };
+        let Some(store) = redis_store(&db).await else { return };
+        let user_id = Uuid::new_v4();
+        let bangus = insert_product(&db, "Bangus", Decimal::new(15000, 2), 50).await;
+
+        store.add_item(&new_cart(user_id, bangus.id, 3, CartAddMode::Add), None, 10, 50, local_datetime()).await.unwrap();
+        assert_eq!(carts_in_postgres(user_id, &db).await, []);
+
+        assert_eq!(store.flush_dirty_carts().await.unwrap(), 1);
+        assert_eq!(carts_in_postgres(user_id, &db).await, [(bangus.id, 3)]);
+        // Nothing changed since, so there is nothing left to write
+        assert_eq!(store.flush_dirty_carts().await.unwrap(), 0);
+    }
+
+    #[actix_web::test]
+    async fn redis_store_loads_existing_rows_and_drops_deleted_products_on_flush() {
+        let Some(db) = test_db().await else { return };
+        let Some(store) = redis_store(&db).await else { return };
+        let user_id = Uuid::new_v4();
+        let bangus = insert_product(&db, "Bangus", Decimal::new(15000, 2), 50).await;
+        let tilapia = insert_product(&db, "Tilapia", Decimal::new(9000, 2), 50).await;
+        insert_cart_item(&db, &user_id.to_string(), &bangus, 2).await;
+
+        // The row already in Postgres is picked up on first use
+        let added = store
+            .add_item(&new_cart(user_id, bangus.id, 1, CartAddMode::Add), None, 10, 50, local_datetime())
+            .await
+            .unwrap();
+        assert_eq!(added.previous_qty, Some(2));
+        store.add_item(&new_cart(user_id, tilapia.id, 1, CartAddMode::Add), None, 10, 50, local_datetime()).await.unwrap();
+
+        // Deleted while the cart sat in Redis; the cascade already removed its Postgres row
+        products::Entity::delete_by_id(tilapia.id).exec(&db).await.unwrap();
+        store.sync(&user_id.to_string()).await.unwrap();
+        assert_eq!(carts_in_postgres(user_id, &db).await, [(bangus.id, 3)]);
+    }
+
+    #[actix_web::test]
+    async fn redis_store_leaves_the_cart_to_sql_after_sync() {
+        let Some(db) = test_db().await else { return };
+        let Some(store) = redis_store(&db).await else { return };
+        let user_id = Uuid::new_v4();
+        let bangus = insert_product(&db, "Bangus", Decimal::new(15000, 2), 50).await;
+        let tilapia = insert_product(&db, "Tilapia", Decimal::new(9000, 2), 50).await;
+        store.add_item(&new_cart(user_id, bangus.id, 2, CartAddMode::Add), None, 10, 50, local_datetime()).await.unwrap();
+
+        // A checkout syncs, then deletes the rows; a request lands in between
+        store.sync(&user_id.to_string()).await.unwrap();
+        let lines = store.cart_lines(&user_id.to_string(), &CartListOptions::default()).await.unwrap();
+        assert_eq!(lines.len(), 1);
+        carts::Entity::delete_many()
+            .filter(carts::Column::UserId.eq(user_id.to_string()))
+            .exec(&db)
+            .await
+            .unwrap();
+
+        // The old line wasn't cached again, so nothing writes it back
+        store.add_item(&new_cart(user_id, tilapia.id, 1, CartAddMode::Add), None, 10, 50, local_datetime()).await.unwrap();
+        store.flush_dirty_carts().await.unwrap();
+        assert_eq!(carts_in_postgres(user_id, &db).await, [(tilapia.id, 1)]);
+        let lines = store.cart_lines(&user_id.to_string(), &CartListOptions::default()).await.unwrap();
+        assert_eq!(lines.iter().map(|line| line.product_id).collect::<Vec<_>>(), [tilapia.id]);
+    }
+
+    #[actix_web::test]
+    async fn redis_store_never_adds_past_the_stock() {
+        let Some(db) = test_db().await else { return };
+        let Some(store) = redis_store(&db).await else { return };
+        let user_id = Uuid::new_v4();
+        let bangus = insert_product(&db, "Bangus", Decimal::new(15000, 2), 3).await;
+        let new_carts: Vec<NewCart> = (0..10).map(|_| new_cart(user_id, bangus.id, 1, CartAddMode::Add)).collect();
+
+        // Ten shoppers' worth of adds race for a stock of 3
+        let adds = new_carts.iter().map(|new_cart| store.add_item(new_cart, None, 10, 50, local_datetime()));
+        let added = futures_util::future::join_all(adds).await;
+        assert_eq!(added.iter().filter(|add| add.is_ok()).count(), 3);
+
+        store.sync(&user_id.to_string()).await.unwrap();
+        assert_eq!(carts_in_postgres(user_id, &db).await, [(bangus.id, 3)]);
+    }
+
+    #[test]
+    fn parses_cached_lines_with_their_own_quantity_field() {
+        let line = carts::Model {
+            id: Uuid::new_v4(),
+            user_id: "shopper".to_string(),
+            product_id: Uuid::new_v4(),
+            variant_id: None,
+            total_qty: 1,
+            unit_price: Decimal::new(15000, 2),
+            note: None,
+            created_at: local_datetime(),
+            updated_at: local_datetime(),
+        };
+        let fields = HashMap::from([
+            (LOADED_FIELD.to_string(), "1".to_string()),
+            (RedisCartStore::line_field(line.product_id, None), line_json(&line).unwrap()),
+            (RedisCartStore::qty_field(line.product_id, None), "7".to_string()),
+        ]);
+
+        let lines = parse_cached_lines(&fields).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].id, line.id);
+        assert_eq!(lines[0].total_qty, 7);
+    }
+}
@@ -1,52 +1,1056 @@
 use sea_orm::ColumnTrait;
 use sea_orm::QueryFilter;
-use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
-use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::{Alias, Expr, Func, OnConflict, SimpleExpr};
+use sea_orm::{ActiveModelTrait, ConnectionTrait, EntityTrait, ModelTrait, Set};
+use sea_orm::{JoinType, Order, PaginatorTrait, QueryOrder, QuerySelect, QueryTrait, RelationTrait, Select, SelectModel, Selector};
+use sea_orm::prelude::{BigDecimal, DateTimeWithTimeZone, Decimal};
 use uuid::Uuid;
-use crate::models::carts;
+use crate::models::{cart_deletions, carts};
+use crate::models::carts::{AbandonedCart, CartLineChange, CartListOptions, CartOwner, CartOwnerFilter, CartRowWithProduct, CartSortKey, CartLineFix, CartLineStatus, CartLineValidation, CartsResponse, RemovedCartItem};
+use crate::models::{product_variants, products, vendors};
+use crate::models::responses::{CartItemsLimitResponse, ErrorResponse, QuantityLimitResponse};
+use actix_web::HttpResponse;
+use crate::services::{applicable_price_tier, apply_cart_price_tiers, fetch_price_tiers_by_product};
+use crate::utils::{format_datetime, format_money, local_datetime, AppLogger};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+// Function to load a user's cart joined with product details, one line per product.
+// Duplicate rows for the same product and variant are folded together: quantities are summed and
+// the earliest row's id, `created_at` and `unit_price` are kept. The subtotal uses the
+// snapshotted `unit_price`, or the product's quantity discount for the folded `total_qty` (see
+// `apply_cart_price_tiers`); `price_changed` is set when the snapshot differs from the live price,
+// which is the variant's price override when there is one and the product price otherwise.
+// `is_available` includes the sale window.
+// Lines are ordered by `options.sort_by` on the folded values, so `added` uses the earliest row's
+// `created_at` and `updated` the latest `updated_at`; ties fall back to product and variant id.
+pub async fn fetch_cart_with_products<C: ConnectionTrait>(
+    user_id: &str,
+    options: &CartListOptions,
+    db: &C,
+) -> Result<Vec<CartsResponse>, sea_orm::DbErr> {
+    let rows = carts::Entity::find()
+        .select_only()
+        .columns([
+            carts::Column::Id,
+            carts::Column::ProductId,
+            carts::Column::VariantId,
+            carts::Column::TotalQty,
+            carts::Column::UnitPrice,
+            carts::Column::Note,
+            carts::Column::CreatedAt,
+            carts::Column::UpdatedAt,
+        ])
+        .column(products::Column::ProductName)
+        .column(products::Column::Description)
+        .column_as(products::Column::Price, "product_price")
+        .column_as(products::Column::StockQuantity, "product_stock")
+        .column_as(products::Column::IsAvailable, "product_is_available")
+        .column(products::Column::AvailableFrom)
+        .column(products::Column::AvailableUntil)
+        .column(products::Column::ImgUrl)
+        .column(products::Column::VendorId)
+        .column_as(vendors::Column::Name, "vendor_name")
+        .column_as(product_variants::Column::Name, "variant_name")
+        .column_as(product_variants::Column::Price, "variant_price")
+        .column_as(product_variants::Column::StockQuantity, "variant_stock")
+        .join(JoinType::InnerJoin, carts::Relation::Products.def())
+        .join(JoinType::LeftJoin, carts::Relation::ProductVariants.def())
+        .join(JoinType::LeftJoin, products::Relation::Vendors.def())
+        .filter(carts::Column::UserId.eq(user_id))
+        .order_by_asc(carts::Column::CreatedAt)
+        .order_by_asc(carts::Column::Id)
+        .into_model::<CartRowWithProduct>()
+        .all(db)
+        .await?;
+
+    list_cart_rows(rows, options, db).await
+}
+
+// Function to fold cart rows joined with their products into lines, then filter, sort and price
+// them as `options` asks.
+pub async fn list_cart_rows<C: ConnectionTrait>(
+    rows: Vec<CartRowWithProduct>,
+    options: &CartListOptions,
+    db: &C,
+) -> Result<Vec<CartsResponse>, sea_orm::DbErr> {
+    let mut lines = fold_cart_rows(rows, local_datetime());
+    sort_cart_lines(&mut lines, options.sort_by, options.descending);
+
+    apply_cart_price_tiers(&mut lines, options.sort_by, options.descending, db).await?;
+    Ok(lines)
+}
+
+// Function to fold cart rows into one line per product and variant. `rows` must be ordered oldest
+// first, so each line keeps its earliest row's id, `created_at`, `unit_price` and note.
+fn fold_cart_rows(rows: Vec<CartRowWithProduct>, now: DateTimeWithTimeZone) -> Vec<CartsResponse> {
+    let mut lines: Vec<CartsResponse> = Vec::new();
+    let mut line_index: HashMap<(Uuid, Option<Uuid>), usize> = HashMap::new();
+
+    for row in rows {
+        let product_price = row.variant_price.clone().unwrap_or_else(|| row.product_price.clone());
+        let price_changed = product_price != row.unit_price;
+        let sub_total_price = &row.unit_price * BigDecimal::from(row.total_qty);
+
+        if let Some(&index) = line_index.get(&(row.product_id, row.variant_id)) {
+            let line = &mut lines[index];
+            line.total_qty += row.total_qty;
+            line.updated_at = line.updated_at.max(row.updated_at);
+            line.sub_total_price += sub_total_price;
+            line.price_changed |= price_changed;
+            continue;
+        }
+
+        let is_available = row.product_is_available
+            && row.available_from.is_none_or(|from| from <= now)
+            && row.available_until.is_none_or(|until| until > now);
+        let stock_quantity = match row.variant_id {
+            Some(_) => row.variant_stock,
+            None => Some(row.product_stock),
+        };
+
+        line_index.insert((row.product_id, row.variant_id), lines.len());
+        lines.push(CartsResponse {
+            id: row.id,
+            product_id: row.product_id,
+            variant_id: row.variant_id,
+            variant_name: row.variant_name,
+            total_qty: row.total_qty,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            product_name: row.product_name,
+            description: row.description,
+            is_available,
+            stock_quantity,
+            product_price,
+            effective_unit_price: row.unit_price.clone(),
+            unit_price: row.unit_price,
+            applied_tier: None,
+            price_changed,
+            sub_total_price,
+            img_url: row.img_url,
+            vendor_id: row.vendor_id,
+            vendor_name: row.vendor_name,
+            note: row.note,
+        });
+    }
+    lines
+}
+
+// Function to order cart lines by `sort_by`, breaking ties by product and then variant id. Base
+// product lines sort after variant lines.
+fn sort_cart_lines(lines: &mut [CartsResponse], sort_by: CartSortKey, descending: bool) {
+    let name_key = |line: &CartsResponse| line.product_name.to_lowercase();
+
+    lines.sort_by(|a, b| {
+        let ordering = match sort_by {
+            CartSortKey::Added => a.created_at.cmp(&b.created_at),
+            CartSortKey::Updated => a.updated_at.cmp(&b.updated_at),
+            CartSortKey::Name => name_key(a).cmp(&name_key(b)),
+            CartSortKey::Subtotal => a.sub_total_price.cmp(&b.sub_total_price),
+        };
+        let ordering = if descending { ordering.reverse() } else { ordering };
+        ordering
+            .then_with(|| a.product_id.cmp(&b.product_id))
+            .then_with(|| (a.variant_id.is_none(), a.variant_id).cmp(&(b.variant_id.is_none(), b.variant_id)))
+    });
+}
+
+// Function to list one page of the users that have cart rows, most recently changed cart first,
+// along with the number of matching users. `page` is 0-based; load each cart's lines with
+// `fetch_cart_with_products`.
+pub async fn fetch_cart_owners<C: ConnectionTrait>(
+    filter: &CartOwnerFilter,
+    page: u64,
+    per_page: u64,
+    db: &C,
+) -> Result<(Vec<CartOwner>, u64), sea_orm::DbErr> {
+    let last_updated_at = || Expr::col((carts::Entity, carts::Column::UpdatedAt)).max();
+
+    let paginator = carts::Entity::find()
+        .select_only()
+        .column(carts::Column::UserId)
+        .column_as(last_updated_at(), "last_updated_at")
+        .apply_if(filter.user_id.clone(), |query, user_id| {
+            query.filter(carts::Column::UserId.eq(user_id))
+        })
+        .group_by(carts::Column::UserId)
+        .apply_if(filter.updated_before, |query, before| {
+            query.having(Expr::expr(last_updated_at()).lt(before))
+        })
+        .apply_if(filter.updated_after, |query, after| {
+            query.having(Expr::expr(last_updated_at()).gte(after))
+        })
+        .order_by(last_updated_at(), Order::Desc)
+        .order_by_asc(carts::Column::UserId)
+        .into_model::<CartOwner>()
+        .paginate(db, per_page);
+
+    let total = paginator.num_items().await?;
+    let owners = paginator.fetch_page(page).await?;
+    Ok((owners, total))
+}
+
+/// Columns of the abandoned cart CSV export, in order.
+const ABANDONED_CART_CSV_HEADER: [&str; 5] = ["user_id", "cart_value", "item_count", "product_names", "last_updated_at"];
+
+fn abandoned_carts_query(cutoff: DateTimeWithTimeZone) -> Selector<SelectModel<AbandonedCart>> {
+    let last_updated_at = || Expr::col((carts::Entity, carts::Column::UpdatedAt)).max();
+    let cart_value = || {
+        SimpleExpr::from(Func::sum(
+            Expr::col((carts::Entity, carts::Column::TotalQty))
+                .mul(Expr::col((carts::Entity, carts::Column::UnitPrice))),
+        ))
+    };
+
+    carts::Entity::find()
+        .select_only()
+        .column(carts::Column::UserId)
+        .column_as(cart_value(), "cart_value")
+        .column_as(Expr::col((carts::Entity, carts::Column::TotalQty)).sum(), "item_count")
+        .column_as(
+            Expr::cust("string_agg(DISTINCT products.product_name, ', ' ORDER BY products.product_name)"),
+            "product_names",
+        )
+        .column_as(last_updated_at(), "last_updated_at")
+        .join(JoinType::InnerJoin, carts::Relation::Products.def())
+        // Guest carts have nobody to email
+        .filter(Expr::cust(
+            "NOT EXISTS (SELECT 1 FROM cart_sessions WHERE cart_sessions.user_id = carts.user_id)",
+        ))
+        .group_by(carts::Column::UserId)
+        .having(Expr::expr(last_updated_at()).lt(cutoff))
+        .order_by(cart_value(), Order::Desc)
+        .order_by_asc(carts::Column::UserId)
+        .into_model::<AbandonedCart>()
+}
+
+// Function to list one page (0-based) of the registered users' carts that haven't changed since
+// `cutoff`, most valuable first, along with the number of such carts.
+pub async fn fetch_abandoned_cart_page<C: ConnectionTrait>(
+    cutoff: DateTimeWithTimeZone,
+    page: u64,
+    per_page: u64,
+    db: &C,
+) -> Result<(Vec<AbandonedCart>, u64), sea_orm::DbErr> {
+    let paginator = abandoned_carts_query(cutoff).paginate(db, per_page);
+    let total = paginator.num_items().await?;
+    let carts = paginator.fetch_page(page).await?;
+    Ok((carts.into_iter().map(AbandonedCart::with_display).collect(), total))
+}
+
+// Function to list every matching cart at once, like `fetch_abandoned_cart_page`, for the CSV
+// export.
+pub async fn fetch_all_abandoned_carts<C: ConnectionTrait>(
+    cutoff: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<Vec<AbandonedCart>, sea_orm::DbErr> {
+    let carts = abandoned_carts_query(cutoff).all(db).await?;
+    Ok(carts.into_iter().map(AbandonedCart::with_display).collect())
+}
+
+// Function to serialize abandoned carts as CSV, header row first.
+pub fn write_abandoned_carts_csv(carts: &[AbandonedCart]) -> Result<Vec<u8>, csv::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(ABANDONED_CART_CSV_HEADER)?;
+
+    for cart in carts {
+        writer.write_record([
+            cart.user_id.clone(),
+            format_money(f64::try_from(cart.cart_value).unwrap_or_default()),
+            cart.item_count.to_string(),
+            cart.product_names.clone(),
+            format_datetime(cart.last_updated_at),
+        ])?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| csv::Error::from(e.into_error()))
+}
+
+// Function to sum the lines of a user's cart at their snapshot prices, with
+// quantity discounts applied the same way as in `fetch_cart_with_products`.
+pub async fn fetch_cart_total<C: ConnectionTrait>(
+    user_id: &str,
+    db: &C,
+) -> Result<Decimal, sea_orm::DbErr> {
+    let rows = carts::Entity::find()
+        .filter(carts::Column::UserId.eq(user_id))
+        .order_by_asc(carts::Column::CreatedAt)
+        .order_by_asc(carts::Column::Id)
+        .all(db)
+        .await?;
+
+    // Folded like `fold_cart_rows`: summed quantities, priced by the earliest row's snapshot for tiers
+    let mut lines: Vec<(Uuid, Option<Uuid>, i32, Decimal, Decimal)> = Vec::new();
+    for cart in rows {
+        let subtotal = cart.unit_price * Decimal::from(cart.total_qty);
+        match lines
+            .iter_mut()
+            .find(|(product_id, variant_id, ..)| (*product_id, *variant_id) == (cart.product_id, cart.variant_id))
+        {
+            Some((_, _, total_qty, _, line_subtotal)) => {
+                *total_qty += cart.total_qty;
+                *line_subtotal += subtotal;
+            }
+            None => lines.push((cart.product_id, cart.variant_id, cart.total_qty, cart.unit_price, subtotal)),
+        }
+    }
+
+    let product_ids = lines
+        .iter()
+        .filter(|(_, variant_id, ..)| variant_id.is_none())
+        .map(|(product_id, ..)| *product_id)
+        .collect();
+    let tiers = fetch_price_tiers_by_product(product_ids, db).await?;
+
+    Ok(lines
+        .into_iter()
+        .map(|(product_id, variant_id, total_qty, unit_price, subtotal)| {
+            let tier = match (variant_id, tiers.get(&product_id)) {
+                (None, Some(tiers)) => applicable_price_tier(tiers, total_qty, unit_price),
+                _ => None,
+            };
+            tier.map_or(subtotal, |tier| tier.unit_price * Decimal::from(total_qty))
+        })
+        .sum())
+}
+
+fn existing_cart_item_query(user_id: String, product_id: Uuid, variant_id: Option<Uuid>) -> Select<carts::Entity> {
+    let variant_filter = match variant_id {
+        Some(variant_id) => carts::Column::VariantId.eq(variant_id),
+        None => carts::Column::VariantId.is_null(),
+    };
 
-pub async fn find_existing_cart_item(
-    user_id: String,
-    product_id: Uuid,
-    db: &DatabaseConnection,
-) -> Result<Option<carts::Model>, sea_orm::DbErr> {
     carts::Entity::find()
         .filter(carts::Column::UserId.eq(user_id))
         .filter(carts::Column::ProductId.eq(product_id))
+        .filter(variant_filter)
+}
+
+// Function to find the cart row for a product, or for one of its variants when `variant_id` is set.
+pub async fn find_existing_cart_item<C: ConnectionTrait>(
+    user_id: String,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    db: &C,
+) -> Result<Option<carts::Model>, sea_orm::DbErr> {
+    existing_cart_item_query(user_id, product_id, variant_id)
+        .one(db)
+        .await
+}
+
+// Function to find the row like `find_existing_cart_item`, but lock it (`FOR UPDATE`) until the
+// surrounding transaction ends, so concurrent adds to the same line queue up behind each other.
+pub async fn lock_existing_cart_item<C: ConnectionTrait>(
+    user_id: String,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    db: &C,
+) -> Result<Option<carts::Model>, sea_orm::DbErr> {
+    existing_cart_item_query(user_id, product_id, variant_id)
+        .lock_exclusive()
         .one(db)
         .await
 }
 
-pub async fn update_cart_quantity(
+// Function to reject a cart line quantity above the configured per-item limit with
+// `400 Bad Request`.
+pub fn check_quantity_limit(requested_qty: i32, max_qty: i32) -> Result<(), HttpResponse> {
+    if requested_qty > max_qty {
+        return Err(HttpResponse::BadRequest().json(QuantityLimitResponse {
+            detail: format!("You can have at most {} of a product in your cart.", max_qty),
+            max_qty,
+        }));
+    }
+    Ok(())
+}
+
+// Function to collect the ids of the distinct products in a user's cart.
+pub async fn fetch_cart_product_ids<C: ConnectionTrait>(
+    user_id: &str,
+    db: &C,
+) -> Result<HashSet<Uuid>, sea_orm::DbErr> {
+    let product_ids = carts::Entity::find()
+        .select_only()
+        .column(carts::Column::ProductId)
+        .distinct()
+        .filter(carts::Column::UserId.eq(user_id))
+        .into_tuple::<Uuid>()
+        .all(db)
+        .await?;
+    Ok(product_ids.into_iter().collect())
+}
+
+pub fn cart_items_limit_detail(max_items: usize) -> String {
+    format!("Your cart can hold at most {} different products.", max_items)
+}
+
+// Function to reject adding `product_id` with `400 Bad Request` when it isn't in the cart yet and
+// the cart already holds `max_items` distinct products. Products already in the cart always pass.
+pub fn check_cart_items_limit(
+    cart_product_ids: &HashSet<Uuid>,
+    product_id: Uuid,
+    max_items: usize,
+) -> Result<(), HttpResponse> {
+    if !cart_product_ids.contains(&product_id) && cart_product_ids.len() >= max_items {
+        return Err(HttpResponse::BadRequest().json(CartItemsLimitResponse {
+            detail: cart_items_limit_detail(max_items),
+            max_items,
+        }));
+    }
+    Ok(())
+}
+
+/// Longest note accepted on a cart line, in characters.
+pub const MAX_CART_NOTE_LEN: usize = 250;
+
+// Function to trim a cart note, treating a blank one as no note. Longer than
+// `MAX_CART_NOTE_LEN` characters is rejected with `400 Bad Request`.
+pub fn normalize_cart_note(note: Option<&str>) -> Result<Option<String>, HttpResponse> {
+    let Some(note) = note.map(str::trim).filter(|note| !note.is_empty()) else {
+        return Ok(None);
+    };
+    if note.chars().count() > MAX_CART_NOTE_LEN {
+        return Err(HttpResponse::BadRequest().json(ErrorResponse {
+            detail: format!("Notes are limited to {} characters.", MAX_CART_NOTE_LEN),
+        }));
+    }
+    Ok(Some(note.to_string()))
+}
+
+// Function to add `additional_qty` to an existing cart row in a single `UPDATE`.
+// The increment happens in the database (`total_qty = LEAST(total_qty + $1, $max)`),
+// so concurrent adds to the same row are never lost and the result never exceeds
+// `max_qty`.
+// Returns the row as it is after the update.
+pub async fn update_cart_quantity<C: ConnectionTrait>(
     existing_cart: carts::Model,
     additional_qty: i32,
+    max_qty: i32,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<carts::Model, sea_orm::DbErr> {
+    carts::Entity::update_many()
+        .col_expr(
+            carts::Column::TotalQty,
+            Func::least([
+                Expr::col(carts::Column::TotalQty).add(additional_qty),
+                Expr::value(max_qty),
+            ])
+            .into(),
+        )
+        .col_expr(carts::Column::UpdatedAt, Expr::value(now))
+        .filter(carts::Column::Id.eq(existing_cart.id))
+        .exec_with_returning(db)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(sea_orm::DbErr::RecordNotUpdated)
+}
+
+pub async fn set_cart_quantity<C: ConnectionTrait>(
+    existing_cart: carts::Model,
+    qty: i32,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<carts::Model, sea_orm::DbErr> {
+    let mut cart_active_model: carts::ActiveModel = existing_cart.into();
+
+    cart_active_model.total_qty = Set(qty);
+    cart_active_model.updated_at = Set(now);
+
+    cart_active_model.update(db).await
+}
+
+pub async fn set_cart_note<C: ConnectionTrait>(
+    existing_cart: carts::Model,
+    note: Option<String>,
     now: DateTimeWithTimeZone,
-    db: &DatabaseConnection,
+    db: &C,
 ) -> Result<carts::Model, sea_orm::DbErr> {
     let mut cart_active_model: carts::ActiveModel = existing_cart.into();
-    let current_qty = cart_active_model.total_qty.clone().unwrap();
 
-    cart_active_model.total_qty = Set(current_qty + additional_qty);
+    cart_active_model.note = Set(note);
     cart_active_model.updated_at = Set(now);
 
     cart_active_model.update(db).await
 }
 
-pub async fn create_new_cart_item(
+pub async fn remove_cart_item<C: ConnectionTrait>(
+    existing_cart: carts::Model,
+    db: &C,
+) -> Result<(), sea_orm::DbErr> {
+    existing_cart.delete(db).await.map(|_| ())
+}
+
+/// How long, in minutes, a line removed with `remove_cart_item_with_undo` can be restored.
+pub const CART_RESTORE_WINDOW_MINUTES: i64 = 10;
+
+// Function to delete a cart row and keep a copy of it in `cart_deletions` so the removal can be
+// undone. Run it in a transaction so the copy and the delete land together. Copies of the user's
+// removals older than the restore window are dropped along the way.
+pub async fn remove_cart_item_with_undo<C: ConnectionTrait>(
+    existing_cart: carts::Model,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<(), sea_orm::DbErr> {
+    cart_deletions::Entity::delete_many()
+        .filter(cart_deletions::Column::UserId.eq(existing_cart.user_id.as_str()))
+        .filter(cart_deletions::Column::DeletedAt.lt(now - chrono::Duration::minutes(CART_RESTORE_WINDOW_MINUTES)))
+        .exec(db)
+        .await?;
+
+    cart_deletions::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(existing_cart.user_id.clone()),
+        product_id: Set(existing_cart.product_id),
+        variant_id: Set(existing_cart.variant_id),
+        total_qty: Set(existing_cart.total_qty),
+        unit_price: Set(existing_cart.unit_price),
+        note: Set(existing_cart.note.clone()),
+        deleted_at: Set(now),
+    }
+    .insert(db)
+    .await?;
+
+    remove_cart_item(existing_cart, db).await
+}
+
+// Function to find the most recent recorded removal of a product line, or of one of its variant
+// lines when `variant_id` is set.
+pub async fn find_latest_cart_deletion<C: ConnectionTrait>(
+    user_id: &str,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    db: &C,
+) -> Result<Option<cart_deletions::Model>, sea_orm::DbErr> {
+    let variant_filter = match variant_id {
+        Some(variant_id) => cart_deletions::Column::VariantId.eq(variant_id),
+        None => cart_deletions::Column::VariantId.is_null(),
+    };
+
+    cart_deletions::Entity::find()
+        .filter(cart_deletions::Column::UserId.eq(user_id))
+        .filter(cart_deletions::Column::ProductId.eq(product_id))
+        .filter(variant_filter)
+        .order_by_desc(cart_deletions::Column::DeletedAt)
+        .one(db)
+        .await
+}
+
+// Function to put a removed line back with its snapshot price and note, and forget the removal. If
+// the product was added again in the meantime the quantities are summed, capped at `max_qty`.
+pub async fn restore_cart_deletion<C: ConnectionTrait>(
+    deletion: cart_deletions::Model,
+    max_qty: i32,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<carts::Model, sea_orm::DbErr> {
+    let restored = create_new_cart_item(
+        deletion.user_id.clone(),
+        deletion.product_id,
+        deletion.variant_id,
+        deletion.total_qty,
+        deletion.unit_price,
+        deletion.note.clone(),
+        max_qty,
+        now,
+        db,
+    )
+    .await?;
+    deletion.delete(db).await?;
+    Ok(restored)
+}
+
+// Function to delete every line (base product and variants) of the given products from a user's
+// cart in one statement and return the deleted rows.
+pub async fn remove_cart_items<C: ConnectionTrait>(
+    user_id: &str,
+    product_ids: &[Uuid],
+    db: &C,
+) -> Result<Vec<carts::Model>, sea_orm::DbErr> {
+    carts::Entity::delete_many()
+        .filter(carts::Column::UserId.eq(user_id))
+        .filter(carts::Column::ProductId.is_in(product_ids.iter().copied()))
+        .exec_with_returning(db)
+        .await
+}
+
+// Function to insert a cart row, or add `total_qty` to the existing row for the same user, product
+// and variant. Relies on the unique `(user_id, product_id, variant_id)` index so concurrent adds
+// can't create duplicate rows:
+// `ON CONFLICT ... DO UPDATE SET total_qty = carts.total_qty + EXCLUDED.total_qty`.
+// `unit_price` is the effective price snapshotted on insert; an existing row keeps its original
+// snapshot. The summed quantity is capped at `max_qty`, and the existing note is kept unless a new
+// one is given.
+#[allow(clippy::too_many_arguments)]
+pub async fn create_new_cart_item<C: ConnectionTrait>(
     user_id: String,
     product_id: Uuid,
+    variant_id: Option<Uuid>,
     total_qty: i32,
+    unit_price: Decimal,
+    note: Option<String>,
+    max_qty: i32,
     now: DateTimeWithTimeZone,
-    db: &DatabaseConnection,
+    db: &C,
 ) -> Result<carts::Model, sea_orm::DbErr> {
     let new_cart_model = carts::ActiveModel {
         id: Set(Uuid::new_v4()),
         user_id: Set(user_id.to_string()),
         product_id: Set(product_id),
+        variant_id: Set(variant_id),
         total_qty: Set(total_qty),
+        unit_price: Set(unit_price),
+        note: Set(note),
         created_at: Set(now),
         updated_at: Set(now),
     };
 
-    new_cart_model.insert(db).await
-}
\ No newline at end of file
+    carts::Entity::insert(new_cart_model)
+        .on_conflict(
+            OnConflict::columns([carts::Column::UserId, carts::Column::ProductId, carts::Column::VariantId])
+                .value(
+                    carts::Column::TotalQty,
+                    Func::least([
+                        Expr::col((carts::Entity, carts::Column::TotalQty))
+                            .add(Expr::col((Alias::new("excluded"), carts::Column::TotalQty))),
+                        Expr::value(max_qty),
+                    ]),
+                )
+                .value(
+                    carts::Column::Note,
+                    Func::coalesce([
+                        Expr::col((Alias::new("excluded"), carts::Column::Note)).into(),
+                        Expr::col((carts::Entity, carts::Column::Note)).into(),
+                    ]),
+                )
+                .value(
+                    carts::Column::UpdatedAt,
+                    Expr::col((Alias::new("excluded"), carts::Column::UpdatedAt)),
+                )
+                .to_owned(),
+        )
+        .exec_with_returning(db)
+        .await
+}
+
+// Function to move every cart row from `from_user_id` into `to_user_id`'s cart.
+// Products (and variants) already in the target cart have the source quantity added to the
+// existing row; everything else is re-assigned to the target user. Returns the
+// number of source rows that were merged. Merged quantities are capped at `max_qty`.
+pub async fn merge_cart_items<C: ConnectionTrait>(
+    from_user_id: &str,
+    to_user_id: &str,
+    max_qty: i32,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<usize, sea_orm::DbErr> {
+    let source_items = carts::Entity::find()
+        .filter(carts::Column::UserId.eq(from_user_id))
+        .all(db)
+        .await?;
+
+    for source_item in &source_items {
+        let target_item = find_existing_cart_item(
+            to_user_id.to_string(),
+            source_item.product_id,
+            source_item.variant_id,
+            db,
+        )
+        .await?;
+
+        match target_item {
+            Some(target_item) => {
+                update_cart_quantity(target_item, source_item.total_qty, max_qty, now, db).await?;
+
+                source_item.clone().delete(db).await?;
+            }
+            None => {
+                let mut source_active_model: carts::ActiveModel = source_item.clone().into();
+                source_active_model.user_id = Set(to_user_id.to_string());
+                source_active_model.updated_at = Set(now);
+                source_active_model.update(db).await?;
+            }
+        }
+    }
+
+    Ok(source_items.len())
+}
+
+// Function to delete every cart row whose `updated_at` is older than `cutoff`. Returns the number
+// of rows removed.
+pub async fn delete_stale_carts<C: ConnectionTrait>(
+    cutoff: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<u64, sea_orm::DbErr> {
+    carts::Entity::delete_many()
+        .filter(carts::Column::UpdatedAt.lt(cutoff))
+        .exec(db)
+        .await
+        .map(|result| result.rows_affected)
+}
+
+// Function to spawn a task that purges carts idle for more than `retention_days`, every `every`.
+// A failed run (e.g. the database is briefly unreachable) is logged and retried on the next tick.
+pub fn spawn_stale_cart_cleanup(db: sea_orm::DatabaseConnection, retention_days: i64, every: Duration) {
+    tokio::spawn(async move {
+        let logger = AppLogger::default();
+        let mut interval = tokio::time::interval(every);
+
+        loop {
+            interval.tick().await;
+
+            let cutoff = local_datetime() - chrono::Duration::days(retention_days);
+            match delete_stale_carts(cutoff, &db).await {
+                Ok(removed) => logger.info_single(
+                    &format!("🧹 Removed {} cart rows idle for more than {} days", removed, retention_days),
+                    "CLEANUP",
+                ),
+                Err(e) => eprintln!("❌ Stale cart cleanup failed: {}", e),
+            }
+        }
+    });
+}
+
+// Function to delete every row of a user's cart and return what was removed.
+pub async fn clear_cart<C: ConnectionTrait>(
+    user_id: &str,
+    db: &C,
+) -> Result<Vec<RemovedCartItem>, sea_orm::DbErr> {
+    let removed_items = carts::Entity::find()
+        .filter(carts::Column::UserId.eq(user_id))
+        .find_also_related(products::Entity)
+        .order_by_asc(carts::Column::CreatedAt)
+        .all(db)
+        .await?
+        .iter()
+        .map(|(cart, product)| RemovedCartItem::from_model(cart, product.as_ref()))
+        .collect();
+
+    carts::Entity::delete_many()
+        .filter(carts::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+
+    Ok(removed_items)
+}
+
+// Function to check one cart line against the live product (and variant, if any).
+// Unavailability wins over stock, and stock over a changed price, so each line
+// reports the issue that matters most.
+pub fn check_cart_line(
+    cart: &carts::Model,
+    product: &products::Model,
+    variant: Option<&product_variants::Model>,
+    now: DateTimeWithTimeZone,
+) -> CartLineStatus {
+    if !product.is_available_at(now) {
+        return CartLineStatus::Unavailable;
+    }
+    let (stock_quantity, live_price) = match (cart.variant_id, variant) {
+        (None, _) => (product.stock_quantity, product.price),
+        (Some(_), Some(variant)) => (variant.stock_quantity, variant.effective_price(product)),
+        (Some(_), None) => return CartLineStatus::Unavailable,
+    };
+
+    if cart.total_qty > stock_quantity {
+        CartLineStatus::InsufficientStock {
+            available_qty: stock_quantity.max(0),
+        }
+    } else if cart.unit_price != live_price {
+        CartLineStatus::PriceChanged {
+            old_price: cart.unit_price,
+            new_price: live_price,
+        }
+    } else {
+        CartLineStatus::Ok
+    }
+}
+
+// Function to check every line of a user's cart, oldest first.
+pub async fn validate_cart_lines<C: ConnectionTrait>(
+    user_id: &str,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<Vec<(carts::Model, CartLineValidation)>, sea_orm::DbErr> {
+    let lines: Vec<(carts::Model, products::Model)> = carts::Entity::find()
+        .filter(carts::Column::UserId.eq(user_id))
+        .inner_join(products::Entity)
+        .select_also(products::Entity)
+        .order_by_asc(carts::Column::CreatedAt)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(cart, product)| Some((cart, product?)))
+        .collect();
+
+    let variant_ids: Vec<Uuid> = lines.iter().filter_map(|(cart, _)| cart.variant_id).collect();
+    let variants = if variant_ids.is_empty() {
+        Vec::new()
+    } else {
+        product_variants::Entity::find()
+            .filter(product_variants::Column::Id.is_in(variant_ids))
+            .all(db)
+            .await?
+    };
+
+    Ok(lines
+        .into_iter()
+        .map(|(cart, product)| {
+            let variant = cart
+                .variant_id
+                .and_then(|variant_id| variants.iter().find(|variant| variant.id == variant_id));
+            let validation = CartLineValidation {
+                product_id: cart.product_id,
+                variant_id: cart.variant_id,
+                product_name: product.product_name.clone(),
+                total_qty: cart.total_qty,
+                status: check_cart_line(&cart, &product, variant, now),
+            };
+            (cart, validation)
+        })
+        .collect())
+}
+
+// Function to resolve the issues found by `validate_cart_lines`: unavailable lines are removed,
+// quantities are clamped to the available stock (removing the line at zero) and
+// changed prices are accepted. Returns what was changed.
+pub async fn fix_cart_lines<C: ConnectionTrait>(
+    lines: &[(carts::Model, CartLineValidation)],
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<Vec<CartLineChange>, sea_orm::DbErr> {
+    let mut changes = Vec::new();
+
+    for (cart, validation) in lines {
+        let fix = match validation.status {
+            CartLineStatus::Ok => continue,
+            CartLineStatus::Unavailable | CartLineStatus::InsufficientStock { available_qty: 0 } => {
+                remove_cart_item(cart.clone(), db).await?;
+                CartLineFix::Removed
+            }
+            CartLineStatus::InsufficientStock { available_qty } => {
+                set_cart_quantity(cart.clone(), available_qty, now, db).await?;
+                CartLineFix::QuantityClamped {
+                    from_qty: cart.total_qty,
+                    to_qty: available_qty,
+                }
+            }
+            CartLineStatus::PriceChanged { old_price, new_price } => {
+                let mut cart_active_model: carts::ActiveModel = cart.clone().into();
+                cart_active_model.unit_price = Set(new_price);
+                cart_active_model.updated_at = Set(now);
+                cart_active_model.update(db).await?;
+                CartLineFix::PriceUpdated { old_price, new_price }
+            }
+        };
+
+        changes.push(CartLineChange {
+            product_id: cart.product_id,
+            variant_id: cart.variant_id,
+            fix,
+        });
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{insert_cart_item, insert_product, test_db};
+    use chrono::{Duration as ChronoDuration, TimeZone, Utc};
+    use futures_util::future::join_all;
+    use std::str::FromStr;
+
+    fn at(minutes: i64) -> DateTimeWithTimeZone {
+        (Utc.with_ymd_and_hms(2026, 1, 5, 8, 0, 0).unwrap() + ChronoDuration::minutes(minutes)).fixed_offset()
+    }
+
+    fn money(amount: &str) -> BigDecimal {
+        BigDecimal::from_str(amount).unwrap()
+    }
+
+    fn row(product_id: Uuid, total_qty: i32, unit_price: &str, minutes: i64) -> CartRowWithProduct {
+        CartRowWithProduct {
+            id: Uuid::new_v4(),
+            product_id,
+            variant_id: None,
+            total_qty,
+            unit_price: money(unit_price),
+            note: None,
+            created_at: at(minutes),
+            updated_at: at(minutes),
+            product_name: "Bangus".to_string(),
+            description: "Milkfish".to_string(),
+            product_price: money(unit_price),
+            product_stock: 40,
+            product_is_available: true,
+            available_from: None,
+            available_until: None,
+            img_url: String::new(),
+            vendor_id: None,
+            vendor_name: None,
+            variant_name: None,
+            variant_price: None,
+            variant_stock: None,
+        }
+    }
+
+    #[test]
+    fn folds_duplicate_rows_keeping_the_earliest_snapshot() {
+        let product_id = Uuid::new_v4();
+        let first = row(product_id, 2, "150.00", 0);
+        let first_id = first.id;
+        let mut second = row(product_id, 3, "160.00", 10);
+        second.product_price = money("150.00");
+        second.note = Some("fillet please".to_string());
+
+        let lines = fold_cart_rows(vec![first, second], at(60));
+
+        assert_eq!(lines.len(), 1);
+        let line = &lines[0];
+        assert_eq!(line.id, first_id);
+        assert_eq!(line.total_qty, 5);
+        assert_eq!(line.unit_price, money("150.00"));
+        assert_eq!(line.sub_total_price, money("780.00"));
+        assert_eq!(line.created_at, at(0));
+        assert_eq!(line.updated_at, at(10));
+        assert_eq!(line.note, None);
+        assert!(line.price_changed);
+    }
+
+    #[test]
+    fn keeps_variant_lines_apart_from_the_base_product() {
+        let product_id = Uuid::new_v4();
+        let mut variant = row(product_id, 1, "210.00", 5);
+        variant.variant_id = Some(Uuid::new_v4());
+        variant.variant_price = Some(money("210.00"));
+        variant.variant_stock = Some(3);
+
+        let lines = fold_cart_rows(vec![row(product_id, 2, "150.00", 0), variant], at(60));
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].stock_quantity, Some(40));
+        assert_eq!(lines[1].stock_quantity, Some(3));
+        assert_eq!(lines[1].product_price, money("210.00"));
+        assert!(!lines[1].price_changed);
+    }
+
+    #[test]
+    fn flags_hidden_products_and_closed_sale_windows() {
+        let mut hidden = row(Uuid::new_v4(), 1, "90.00", 0);
+        hidden.product_is_available = false;
+        let mut not_yet_on_sale = row(Uuid::new_v4(), 1, "90.00", 1);
+        not_yet_on_sale.available_from = Some(at(120));
+        let mut sale_ended = row(Uuid::new_v4(), 1, "90.00", 2);
+        sale_ended.available_until = Some(at(30));
+
+        let lines = fold_cart_rows(vec![hidden, not_yet_on_sale, sale_ended, row(Uuid::new_v4(), 1, "90.00", 3)], at(60));
+
+        assert_eq!(
+            lines.iter().map(|line| line.is_available).collect::<Vec<_>>(),
+            [false, false, false, true]
+        );
+    }
+
+    #[test]
+    fn sorts_by_the_folded_values_with_stable_tie_breaks() {
+        let mut cheap = row(Uuid::new_v4(), 1, "20.00", 0);
+        cheap.product_name = "tilapia".to_string();
+        let mut dear = row(Uuid::new_v4(), 4, "50.00", 5);
+        dear.product_name = "Alimasag".to_string();
+        let mut middle = row(Uuid::new_v4(), 2, "30.00", 10);
+        middle.product_name = "Pusit".to_string();
+        let mut lines = fold_cart_rows(vec![cheap, dear, middle], at(60));
+        let names = |lines: &[CartsResponse]| lines.iter().map(|line| line.product_name.clone()).collect::<Vec<_>>();
+
+        sort_cart_lines(&mut lines, CartSortKey::Name, false);
+        assert_eq!(names(&lines), ["Alimasag", "Pusit", "tilapia"]);
+
+        sort_cart_lines(&mut lines, CartSortKey::Subtotal, true);
+        assert_eq!(lines.iter().map(|line| line.total_qty).collect::<Vec<_>>(), [4, 2, 1]);
+
+        sort_cart_lines(&mut lines, CartSortKey::Added, true);
+        assert_eq!(lines.iter().map(|line| line.created_at).collect::<Vec<_>>(), [at(10), at(5), at(0)]);
+
+        let product_id = Uuid::new_v4();
+        let mut variant = row(product_id, 1, "20.00", 0);
+        variant.variant_id = Some(Uuid::new_v4());
+        let mut lines = fold_cart_rows(vec![row(product_id, 1, "20.00", 0), variant], at(60));
+        sort_cart_lines(&mut lines, CartSortKey::Added, false);
+        assert!(lines[0].variant_id.is_some());
+        assert!(lines[1].variant_id.is_none());
+    }
+
+    #[actix_web::test]
+    async fn concurrent_increments_are_all_kept() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Tilapia", Decimal::new(9000, 2), 100).await;
+        let now = local_datetime();
+        let cart = create_new_cart_item("user-1".to_string(), product.id, None, 1, product.price, None, 99, now, &db)
+            .await
+            .unwrap();
+
+        // Every caller starts from the same stale row, as concurrent requests would
+        let increments = (0..10).map(|_| update_cart_quantity(cart.clone(), 2, 99, now, &db));
+        for result in join_all(increments).await {
+            result.unwrap();
+        }
+
+        let cart = carts::Entity::find_by_id(cart.id).one(&db).await.unwrap().unwrap();
+        assert_eq!(cart.total_qty, 21);
+    }
+
+    #[actix_web::test]
+    async fn increments_stop_at_the_line_limit() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Tilapia", Decimal::new(9000, 2), 100).await;
+        let now = local_datetime();
+        let cart = create_new_cart_item("user-1".to_string(), product.id, None, 8, product.price, None, 10, now, &db)
+            .await
+            .unwrap();
+
+        let cart = update_cart_quantity(cart, 5, 10, now, &db).await.unwrap();
+        assert_eq!(cart.total_qty, 10);
+    }
+
+    #[actix_web::test]
+    async fn a_full_cart_refuses_new_products_with_the_limit() {
+        let in_cart = HashSet::from([Uuid::new_v4(), Uuid::new_v4()]);
+
+        let response = check_cart_items_limit(&in_cart, Uuid::new_v4(), 2).unwrap_err();
+        assert_eq!(response.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let body = actix_web::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["max_items"], 2);
+        assert_eq!(body["detail"], cart_items_limit_detail(2));
+    }
+
+    #[test]
+    fn products_already_in_the_cart_pass_the_limit() {
+        let product_id = Uuid::new_v4();
+        let in_cart = HashSet::from([product_id, Uuid::new_v4()]);
+
+        assert!(check_cart_items_limit(&in_cart, product_id, 2).is_ok());
+        assert!(check_cart_items_limit(&in_cart, Uuid::new_v4(), 3).is_ok());
+        assert!(check_cart_items_limit(&HashSet::new(), product_id, 1).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn counts_only_the_users_distinct_products() {
+        let Some(db) = test_db().await else { return };
+        let bangus = insert_product(&db, "Bangus", Decimal::new(15000, 2), 100).await;
+        let tilapia = insert_product(&db, "Tilapia", Decimal::new(9000, 2), 100).await;
+        let user_id = Uuid::new_v4().to_string();
+        insert_cart_item(&db, &user_id, &bangus, 1).await;
+        insert_cart_item(&db, &user_id, &tilapia, 1).await;
+        insert_cart_item(&db, "someone-else", &bangus, 1).await;
+
+        let product_ids = fetch_cart_product_ids(&user_id, &db).await.unwrap();
+        assert_eq!(product_ids, HashSet::from([bangus.id, tilapia.id]));
+    }
+}
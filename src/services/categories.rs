@@ -1,11 +1,87 @@
 use crate::models::categories;
+use crate::models::categories::{CategoryResponse, CategoryTreeNode};
 use crate::models::prelude::Categories;
 use actix_web::web;
 use sea_orm::ColumnTrait;
 use sea_orm::EntityTrait;
-use sea_orm::QueryFilter;
+use sea_orm::{QueryFilter, Select};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+/// In-memory copy of the category list, served until `ttl` elapses or a write invalidates it.
+///
+/// Every invalidation bumps a generation counter. A list loaded before the bump is stale, so
+/// `set` drops it instead of caching it over the invalidation.
+pub struct CategoryCache {
+    ttl: Duration,
+    entry: RwLock<CacheEntry>,
+}
+
+struct CacheEntry {
+    generation: u64,
+    categories: Option<(Instant, Vec<CategoryResponse>)>,
+}
+
+impl CategoryCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entry: RwLock::new(CacheEntry {
+                generation: 0,
+                categories: None,
+            }),
+        }
+    }
+
+    // Function to read the current generation; call it before loading the list and pass it to
+    // `set`.
+    pub fn generation(&self) -> u64 {
+        match self.entry.read() {
+            Ok(entry) => entry.generation,
+            Err(poisoned) => poisoned.into_inner().generation,
+        }
+    }
+
+    // Function to return the cached categories if they are still fresh.
+    pub fn get(&self) -> Option<Vec<CategoryResponse>> {
+        let entry = self.entry.read().ok()?;
+        match entry.categories.as_ref() {
+            Some((stored_at, categories)) if stored_at.elapsed() < self.ttl => {
+                Some(categories.clone())
+            }
+            _ => None,
+        }
+    }
+
+    // Function to cache `categories`, loaded when the cache was at `generation`, unless it has been
+    // invalidated since.
+    pub fn set(&self, generation: u64, categories: Vec<CategoryResponse>) {
+        if let Ok(mut entry) = self.entry.write()
+            && entry.generation == generation
+        {
+            entry.categories = Some((Instant::now(), categories));
+        }
+    }
+
+    // Function to drop the cached list so the next read goes to the database.
+    pub fn invalidate(&self) {
+        let mut entry = match self.entry.write() {
+            Ok(entry) => entry,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        entry.generation += 1;
+        entry.categories = None;
+    }
+}
+
+// Function to select the categories that haven't been deleted.
+pub fn find_active_categories() -> Select<categories::Entity> {
+    Categories::find().filter(categories::Column::DeletedAt.is_null())
+}
+
+#[allow(dead_code)]
 pub async fn fetch_category_by_id(
     db: web::Data<sea_orm::DatabaseConnection>,
     category_id: String,
@@ -15,7 +91,7 @@ pub async fn fetch_category_by_id(
         Err(_) => return "".to_string(), // invalid UUID
     };
 
-    match Categories::find()
+    match find_active_categories()
         .filter(categories::Column::Id.eq(category_uuid))
         .one(db.get_ref())
         .await
@@ -24,3 +100,106 @@ pub async fn fetch_category_by_id(
         _ => "".to_string(),
     }
 }
+
+// Function to return true if making `new_parent_id` the parent of `category_id` would create a
+// cycle, i.e. `category_id` is `new_parent_id` itself or one of its ancestors.
+pub fn creates_category_cycle(
+    categories: &[categories::Model],
+    category_id: Uuid,
+    new_parent_id: Uuid,
+) -> bool {
+    let parents: HashMap<Uuid, Option<Uuid>> = categories
+        .iter()
+        .map(|category| (category.id, category.parent_id))
+        .collect();
+
+    let mut current = Some(new_parent_id);
+    let mut steps = 0;
+    while let Some(id) = current {
+        if id == category_id || steps > parents.len() {
+            return true;
+        }
+        current = parents.get(&id).copied().flatten();
+        steps += 1;
+    }
+    false
+}
+
+// Function to build the nested category tree. Categories whose parent is missing are treated as
+// roots.
+pub fn build_category_tree(categories: Vec<categories::Model>) -> Vec<CategoryTreeNode> {
+    let known_ids: Vec<Uuid> = categories.iter().map(|category| category.id).collect();
+    let mut children_by_parent: HashMap<Option<Uuid>, Vec<categories::Model>> = HashMap::new();
+    for category in categories {
+        let parent = category.parent_id.filter(|id| known_ids.contains(id));
+        children_by_parent.entry(parent).or_default().push(category);
+    }
+
+    fn attach(
+        parent: Option<Uuid>,
+        children_by_parent: &mut HashMap<Option<Uuid>, Vec<categories::Model>>,
+    ) -> Vec<CategoryTreeNode> {
+        let mut children = children_by_parent.remove(&parent).unwrap_or_default();
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+        children
+            .into_iter()
+            .map(|category| {
+                let id = category.id;
+                CategoryTreeNode {
+                    category: CategoryResponse::from_model(category),
+                    children: attach(Some(id), children_by_parent),
+                }
+            })
+            .collect()
+    }
+
+    attach(None, &mut children_by_parent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn category(name: &str) -> CategoryResponse {
+        CategoryResponse {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            parent_id: None,
+            created_at: "2026-01-05 08:00:00 AM".to_string(),
+            updated_at: "2026-01-05 08:00:00 AM".to_string(),
+        }
+    }
+
+    #[test]
+    fn serves_the_cached_list_until_invalidated() {
+        let cache = CategoryCache::new(Duration::from_secs(60));
+        assert!(cache.get().is_none());
+
+        cache.set(cache.generation(), vec![category("Seafood")]);
+        assert_eq!(cache.get().unwrap()[0].name, "Seafood");
+
+        cache.invalidate();
+        assert!(cache.get().is_none());
+    }
+
+    #[test]
+    fn drops_a_list_loaded_before_an_invalidation() {
+        let cache = CategoryCache::new(Duration::from_secs(60));
+
+        // A read starts loading, then a write invalidates before the read stores its result
+        let generation = cache.generation();
+        cache.invalidate();
+        cache.set(generation, vec![category("Stale")]);
+        assert!(cache.get().is_none());
+
+        cache.set(cache.generation(), vec![category("Fresh")]);
+        assert_eq!(cache.get().unwrap()[0].name, "Fresh");
+    }
+
+    #[test]
+    fn expires_after_the_ttl() {
+        let cache = CategoryCache::new(Duration::ZERO);
+        cache.set(cache.generation(), vec![category("Seafood")]);
+        assert!(cache.get().is_none());
+    }
+}
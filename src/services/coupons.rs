@@ -0,0 +1,309 @@
+use crate::models::carts::CartContents;
+use crate::models::coupons;
+use crate::models::coupons::DiscountType;
+use crate::models::responses::CouponErrorResponse;
+use crate::models::{cart_coupons, prelude::Coupons};
+use actix_web::HttpResponse;
+use sea_orm::prelude::{DateTimeWithTimeZone, Decimal};
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::{ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter, Set};
+
+// Reason a coupon can't be applied right now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CouponError {
+    Inactive,
+    Expired,
+    Exhausted,
+    BelowMinimum { min_subtotal: Decimal },
+}
+
+impl CouponError {
+    pub fn detail(&self) -> String {
+        match self {
+            CouponError::Inactive => "This coupon is not active.".to_string(),
+            CouponError::Expired => "This coupon has expired.".to_string(),
+            CouponError::Exhausted => "This coupon has no uses left.".to_string(),
+            CouponError::BelowMinimum { min_subtotal } => format!(
+                "This coupon needs a cart total of at least {}.",
+                min_subtotal
+            ),
+        }
+    }
+
+    // Stable code the frontend maps to its own copy
+    pub fn reason(&self) -> &'static str {
+        match self {
+            CouponError::Inactive => "inactive",
+            CouponError::Expired => "expired",
+            CouponError::Exhausted => "exhausted",
+            CouponError::BelowMinimum { .. } => "below_minimum",
+        }
+    }
+
+    pub fn into_response(self) -> HttpResponse {
+        HttpResponse::BadRequest().json(CouponErrorResponse {
+            detail: self.detail(),
+            reason: self.reason().to_string(),
+        })
+    }
+}
+
+// Coupon codes are stored and matched upper-case
+pub fn normalize_coupon_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+pub async fn find_coupon_by_code<C: ConnectionTrait>(
+    code: &str,
+    db: &C,
+) -> Result<Option<coupons::Model>, sea_orm::DbErr> {
+    coupons::Entity::find()
+        .filter(coupons::Column::Code.eq(normalize_coupon_code(code)))
+        .one(db)
+        .await
+}
+
+// Function to check a coupon is active and neither expired nor used up at `now`
+pub fn check_coupon_usable(coupon: &coupons::Model, now: DateTimeWithTimeZone) -> Result<(), CouponError> {
+    if !coupon.is_active {
+        return Err(CouponError::Inactive);
+    }
+    if coupon.expires_at.is_some_and(|expires_at| expires_at <= now) {
+        return Err(CouponError::Expired);
+    }
+    if coupon.max_uses.is_some_and(|max_uses| coupon.used_count >= max_uses) {
+        return Err(CouponError::Exhausted);
+    }
+    Ok(())
+}
+
+// Function to check a coupon can be used at `now` on a cart whose active items total `subtotal`
+pub fn check_coupon_applies(
+    coupon: &coupons::Model,
+    subtotal: Decimal,
+    now: DateTimeWithTimeZone,
+) -> Result<(), CouponError> {
+    check_coupon_usable(coupon, now)?;
+    if let Some(min_subtotal) = coupon.min_subtotal
+        && subtotal < min_subtotal
+    {
+        return Err(CouponError::BelowMinimum { min_subtotal });
+    }
+    Ok(())
+}
+
+// Function to compute the discount a coupon gives on `total`.
+// The result is rounded to cents and never exceeds the total itself.
+pub fn calculate_discount(total: Decimal, discount_type: DiscountType, value: Decimal) -> Decimal {
+    if total <= Decimal::ZERO || value <= Decimal::ZERO {
+        return Decimal::ZERO;
+    }
+
+    let discount = match discount_type {
+        DiscountType::Percentage => total * value.min(Decimal::ONE_HUNDRED) / Decimal::ONE_HUNDRED,
+        DiscountType::Fixed => value,
+    };
+
+    discount.round_dp(2).min(total)
+}
+
+// Function to attach a coupon to a user's cart, replacing any coupon already attached
+pub async fn attach_cart_coupon<C: ConnectionTrait>(
+    user_id: &str,
+    coupon_id: uuid::Uuid,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<(), sea_orm::DbErr> {
+    cart_coupons::Entity::insert(cart_coupons::ActiveModel {
+        user_id: Set(user_id.to_string()),
+        coupon_id: Set(coupon_id),
+        applied_at: Set(now),
+    })
+    .on_conflict(
+        OnConflict::column(cart_coupons::Column::UserId)
+            .update_columns([cart_coupons::Column::CouponId, cart_coupons::Column::AppliedAt])
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+    Ok(())
+}
+
+// Function to detach the coupon from a user's cart. Returns false when none was attached.
+pub async fn detach_cart_coupon<C: ConnectionTrait>(
+    user_id: &str,
+    db: &C,
+) -> Result<bool, sea_orm::DbErr> {
+    let result = cart_coupons::Entity::delete_by_id(user_id.to_string())
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+// Function to count one use of a coupon at checkout. The UPDATE only matches while uses are left,
+// so concurrent checkouts can't push it past `max_uses`; returns false when none were left
+pub async fn redeem_coupon<C: ConnectionTrait>(
+    coupon_id: uuid::Uuid,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<bool, sea_orm::DbErr> {
+    let result = coupons::Entity::update_many()
+        .col_expr(coupons::Column::UsedCount, Expr::col(coupons::Column::UsedCount).add(1))
+        .col_expr(coupons::Column::UpdatedAt, Expr::value(now))
+        .filter(coupons::Column::Id.eq(coupon_id))
+        .filter(
+            Condition::any()
+                .add(coupons::Column::MaxUses.is_null())
+                .add(Expr::col(coupons::Column::UsedCount).lt(Expr::col(coupons::Column::MaxUses))),
+        )
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+// Function to find the coupon attached to a user's cart
+pub async fn find_cart_coupon<C: ConnectionTrait>(
+    user_id: &str,
+    db: &C,
+) -> Result<Option<coupons::Model>, sea_orm::DbErr> {
+    let attached = cart_coupons::Entity::find_by_id(user_id.to_string())
+        .find_also_related(Coupons)
+        .one(db)
+        .await?;
+    Ok(attached.and_then(|(_, coupon)| coupon))
+}
+
+// Function to apply the coupon attached to a user's cart, if any, to its contents. A coupon that
+// no longer applies stays attached, gives no discount and reports why in `coupon.issue`.
+pub async fn apply_cart_coupon<C: ConnectionTrait>(
+    user_id: &str,
+    contents: CartContents,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<CartContents, sea_orm::DbErr> {
+    let Some(coupon) = find_cart_coupon(user_id, db).await? else {
+        return Ok(contents);
+    };
+
+    let subtotal = contents.total_price_decimal();
+    let (discount, issue) = match check_coupon_applies(&coupon, subtotal, now) {
+        Ok(()) => (calculate_discount(subtotal, coupon.discount_type, coupon.value), None),
+        Err(e) => (Decimal::ZERO, Some(e.reason())),
+    };
+    Ok(contents.with_coupon(coupon.code, discount, issue))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_db;
+    use crate::utils::local_datetime;
+    use chrono::Duration;
+    use futures_util::future::join_all;
+    use sea_orm::ActiveModelTrait;
+    use uuid::Uuid;
+
+    fn coupon(discount_type: DiscountType, value: Decimal) -> coupons::Model {
+        let now = local_datetime();
+        coupons::Model {
+            id: Uuid::new_v4(),
+            code: "SAVE10".to_string(),
+            discount_type,
+            value,
+            expires_at: None,
+            max_uses: None,
+            used_count: 0,
+            created_at: now,
+            updated_at: now,
+            min_subtotal: None,
+            is_active: true,
+        }
+    }
+
+    #[test]
+    fn percentage_discounts_are_rounded_to_cents() {
+        let discount = calculate_discount(Decimal::new(33333, 2), DiscountType::Percentage, Decimal::new(15, 0));
+        assert_eq!(discount, Decimal::new(5000, 2));
+
+        let discount = calculate_discount(Decimal::new(999, 2), DiscountType::Percentage, Decimal::new(125, 1));
+        assert_eq!(discount, Decimal::new(125, 2));
+    }
+
+    #[test]
+    fn discounts_never_exceed_the_total() {
+        let total = Decimal::new(8000, 2);
+        assert_eq!(calculate_discount(total, DiscountType::Fixed, Decimal::new(100, 0)), total);
+        assert_eq!(calculate_discount(total, DiscountType::Percentage, Decimal::new(150, 0)), total);
+        assert_eq!(calculate_discount(total, DiscountType::Fixed, Decimal::new(25, 0)), Decimal::new(25, 0));
+    }
+
+    #[test]
+    fn empty_carts_and_non_positive_values_get_no_discount() {
+        assert_eq!(calculate_discount(Decimal::ZERO, DiscountType::Fixed, Decimal::TEN), Decimal::ZERO);
+        assert_eq!(calculate_discount(Decimal::TEN, DiscountType::Fixed, Decimal::NEGATIVE_ONE), Decimal::ZERO);
+        assert_eq!(calculate_discount(Decimal::TEN, DiscountType::Percentage, Decimal::ZERO), Decimal::ZERO);
+    }
+
+    #[test]
+    fn expired_coupons_are_rejected_from_their_expiry_on() {
+        let now = local_datetime();
+        let mut coupon = coupon(DiscountType::Fixed, Decimal::TEN);
+
+        coupon.expires_at = Some(now + Duration::minutes(1));
+        assert_eq!(check_coupon_usable(&coupon, now), Ok(()));
+
+        coupon.expires_at = Some(now);
+        assert_eq!(check_coupon_usable(&coupon, now), Err(CouponError::Expired));
+
+        coupon.expires_at = Some(now - Duration::days(1));
+        assert_eq!(check_coupon_usable(&coupon, now), Err(CouponError::Expired));
+    }
+
+    #[test]
+    fn inactive_and_used_up_coupons_are_rejected() {
+        let now = local_datetime();
+        let mut coupon = coupon(DiscountType::Fixed, Decimal::TEN);
+
+        coupon.max_uses = Some(3);
+        coupon.used_count = 2;
+        assert_eq!(check_coupon_usable(&coupon, now), Ok(()));
+        coupon.used_count = 3;
+        assert_eq!(check_coupon_usable(&coupon, now), Err(CouponError::Exhausted));
+
+        coupon.is_active = false;
+        assert_eq!(check_coupon_usable(&coupon, now), Err(CouponError::Inactive));
+    }
+
+    #[test]
+    fn minimum_subtotal_is_inclusive() {
+        let now = local_datetime();
+        let mut coupon = coupon(DiscountType::Percentage, Decimal::TEN);
+        coupon.min_subtotal = Some(Decimal::new(500, 0));
+
+        assert_eq!(check_coupon_applies(&coupon, Decimal::new(500, 0), now), Ok(()));
+        assert_eq!(
+            check_coupon_applies(&coupon, Decimal::new(49999, 2), now),
+            Err(CouponError::BelowMinimum { min_subtotal: Decimal::new(500, 0) })
+        );
+    }
+
+    #[test]
+    fn codes_are_matched_trimmed_and_upper_case() {
+        assert_eq!(normalize_coupon_code("  save10 "), "SAVE10");
+    }
+
+    #[actix_web::test]
+    async fn concurrent_redemptions_stop_at_max_uses() {
+        let Some(db) = test_db().await else { return };
+        let mut coupon = coupon(DiscountType::Fixed, Decimal::TEN);
+        coupon.max_uses = Some(3);
+        let coupon = coupons::ActiveModel::from(coupon).insert(&db).await.unwrap();
+
+        let redemptions = (0..8).map(|_| redeem_coupon(coupon.id, local_datetime(), &db));
+        let redeemed = join_all(redemptions).await.into_iter().filter(|result| *result.as_ref().unwrap()).count();
+
+        assert_eq!(redeemed, 3);
+        let stored = coupons::Entity::find_by_id(coupon.id).one(&db).await.unwrap().unwrap();
+        assert_eq!(stored.used_count, 3);
+    }
+}
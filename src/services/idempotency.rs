@@ -0,0 +1,128 @@
+use actix_web::body::{to_bytes, BoxBody};
+use actix_web::http::header::HeaderValue;
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use actix_web::HttpResponse;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Header clients send to make a POST safe to retry.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Header set on responses replayed from the store.
+const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// A key is scoped to the user it was sent for, so two users can't collide on the same key.
+pub type IdempotencyScope = (String, String);
+
+/// A finished response kept so a retry with the same key gets the same answer.
+#[derive(Clone)]
+pub struct StoredResponse {
+    status: StatusCode,
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+}
+
+impl StoredResponse {
+    pub fn into_response(self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status);
+        if let Some(content_type) = self.content_type {
+            response.content_type(content_type);
+        }
+        response
+            .insert_header((IDEMPOTENT_REPLAYED_HEADER, "true"))
+            .body(self.body)
+    }
+}
+
+enum Entry {
+    InProgress(Instant),
+    Done(Instant, StoredResponse),
+}
+
+impl Entry {
+    fn stored_at(&self) -> Instant {
+        match self {
+            Entry::InProgress(stored_at) | Entry::Done(stored_at, _) => *stored_at,
+        }
+    }
+}
+
+/// What to do with a request carrying an idempotency key.
+pub enum IdempotencyCheck {
+    /// First time this key is seen; the caller must hand its response to `finish`.
+    Started,
+    /// Another request with the same key hasn't finished yet.
+    InProgress,
+    /// The key was already processed; send this response back unchanged.
+    Replay(StoredResponse),
+}
+
+/// In-memory record of processed idempotency keys, each kept for `ttl`.
+pub struct IdempotencyStore {
+    ttl: Duration,
+    entries: Mutex<HashMap<IdempotencyScope, Entry>>,
+}
+
+impl IdempotencyStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Function to claim `scope` for the current request unless it is already claimed or completed.
+    pub fn begin(&self, scope: &IdempotencyScope) -> IdempotencyCheck {
+        let mut entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        entries.retain(|_, entry| entry.stored_at().elapsed() < self.ttl);
+
+        match entries.get(scope) {
+            Some(Entry::InProgress(_)) => IdempotencyCheck::InProgress,
+            Some(Entry::Done(_, stored)) => IdempotencyCheck::Replay(stored.clone()),
+            None => {
+                entries.insert(scope.clone(), Entry::InProgress(Instant::now()));
+                IdempotencyCheck::Started
+            }
+        }
+    }
+
+    // Function to record the response for a claimed `scope` and return it to be sent.
+    // Server errors release the key instead, so the client can retry them.
+    pub async fn finish(&self, scope: IdempotencyScope, response: HttpResponse) -> HttpResponse {
+        if response.status().is_server_error() {
+            self.release(&scope);
+            return response;
+        }
+
+        let (response, body) = response.into_parts();
+        let body = match to_bytes(body).await {
+            Ok(body) => body,
+            Err(_) => {
+                self.release(&scope);
+                return HttpResponse::InternalServerError().finish();
+            }
+        };
+
+        let stored = StoredResponse {
+            status: response.status(),
+            content_type: response.headers().get(actix_web::http::header::CONTENT_TYPE).cloned(),
+            body: body.clone(),
+        };
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(scope, Entry::Done(Instant::now(), stored));
+        }
+
+        response.set_body(BoxBody::new(body))
+    }
+
+    fn release(&self, scope: &IdempotencyScope) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.remove(scope);
+        }
+    }
+}
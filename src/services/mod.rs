@@ -1,16 +1,49 @@
+mod addresses;
 mod categories;
 mod products;
+mod cart_events;
+mod cart_sessions;
+mod cart_store;
+mod cart_shares;
 mod carts;
-
+mod coupons;
+mod idempotency;
+mod orders;
+mod pricing;
+mod price_tiers;
+mod rate_limit;
+mod receipts;
+mod restock;
+mod saved_items;
+mod seed;
+mod wishlists;
+
+pub use addresses::*;
+#[allow(unused_imports)]
 pub use categories::*;
 pub use products::*;
+pub use cart_events::*;
+pub use cart_sessions::*;
+pub use cart_store::*;
+pub use cart_shares::*;
 pub use carts::*;
-
-use colourful_logger::Logger;
+pub use coupons::*;
+pub use idempotency::*;
+pub use orders::*;
+pub use pricing::*;
+pub use price_tiers::*;
+pub use rate_limit::*;
+pub use receipts::*;
+pub use restock::*;
+pub use saved_items::*;
+pub use seed::*;
+pub use wishlists::*;
+
+use crate::utils::AppLogger;
 use sea_orm::{Database, DatabaseConnection};
 
 pub async fn establish_connection() -> DatabaseConnection {
-    let logger = Logger::default();
+    let logger = AppLogger::default();
 
     logger.info_single("🔌 Initializing database connection...", "DATABASE");
 
@@ -0,0 +1,958 @@
+use crate::models::carts::{CartAddMode, CartLineStatus, CartLineValidation, CartListOptions, NewCart};
+use crate::models::orders::{OrderCancellation, OrderChargesDisplay, OrderDetail, OrderFilter, OrderItemDetail, OrderReceipt, OrderSortKey, OrderStatus, OrderSummary, OrderTimelineEntry, PaymentMethod, PaymentStatus, OrderWithItems, ReceiptLine, ReceiptStore, ReceiptVat, ReorderSkipReason, ReorderSummary, ReorderedItem, SkippedReorderItem};
+use crate::models::order_addresses::CheckoutAddress;
+use crate::models::responses::ErrorResponse;
+use crate::models::{carts, order_addresses, order_items, order_status_history, orders, products};
+use crate::services::{adjust_product_stock, adjust_variant_stock, applicable_price_tier, build_order_address, complete_checkout_address, calculate_discount, check_coupon_applies, detach_cart_coupon, find_cart_coupon, redeem_coupon, fetch_price_tiers_by_product, check_cart_items_limit, compute_checkout_summary, find_product_by_id, find_product_variant, validate_cart_lines, CartAddOutcome, CartStore, CheckoutRates, StockAdjustmentOutcome, VariantStockOutcome};
+use crate::utils::{format_datetime_in, DateTimeFormat, STORE_TIMEZONE};
+use actix_web::HttpResponse;
+use sea_orm::prelude::{DateTimeWithTimeZone, Decimal};
+use sea_orm::sea_query::{Expr, Func, SimpleExpr};
+use sea_orm::ActiveValue::NotSet;
+use sea_orm::{ActiveEnum, ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, JoinType, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, QueryTrait, RelationTrait, Set};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+// Reason an order status change was refused
+#[derive(Debug)]
+pub enum OrderStatusError {
+    NotFound,
+    IllegalTransition { from: OrderStatus, to: OrderStatus },
+    // Prepaid orders can't be prepared before their payment is recorded
+    AwaitingPayment(PaymentMethod),
+    Db(sea_orm::DbErr),
+}
+
+impl OrderStatusError {
+    pub fn detail(&self) -> String {
+        match self {
+            OrderStatusError::NotFound => "Order not found.".to_string(),
+            OrderStatusError::IllegalTransition { from, to } => format!(
+                "An order can't move from '{}' to '{}'.",
+                from.to_value(),
+                to.to_value()
+            ),
+            OrderStatusError::AwaitingPayment(method) => format!(
+                "This order is paid by '{}' and can't be prepared until the payment is recorded.",
+                method.to_value()
+            ),
+            OrderStatusError::Db(e) => format!("Database error while updating order status: {}", e),
+        }
+    }
+}
+
+impl std::fmt::Display for OrderStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.detail())
+    }
+}
+
+impl std::error::Error for OrderStatusError {}
+
+impl From<sea_orm::DbErr> for OrderStatusError {
+    fn from(e: sea_orm::DbErr) -> Self {
+        OrderStatusError::Db(e)
+    }
+}
+
+// Function to list one page (0-based) of the orders matching `filter`, with how many units
+// each holds, along with the number of matching orders. Backs both the customer and admin listings
+pub async fn fetch_orders<C: ConnectionTrait>(
+    filter: &OrderFilter,
+    page: u64,
+    per_page: u64,
+    db: &C,
+) -> Result<(Vec<OrderSummary>, u64), sea_orm::DbErr> {
+    let query = orders::Entity::find()
+        .select_only()
+        .columns([
+            orders::Column::Id,
+            orders::Column::OrderNumber,
+            orders::Column::UserId,
+            orders::Column::Status,
+            orders::Column::PaymentStatus,
+            orders::Column::PaymentMethod,
+            orders::Column::Subtotal,
+            orders::Column::Tax,
+            orders::Column::DeliveryFee,
+            orders::Column::Discount,
+            orders::Column::Total,
+            orders::Column::CreatedAt,
+            orders::Column::UpdatedAt,
+        ])
+        .column_as(
+            SimpleExpr::from(Func::coalesce([
+                Func::sum(Expr::col((order_items::Entity, order_items::Column::Quantity))).into(),
+                Expr::val(0).into(),
+            ])),
+            "item_count",
+        )
+        .join(JoinType::LeftJoin, orders::Relation::OrderItems.def())
+        .apply_if(filter.user_id.as_deref(), |query, user_id| {
+            query.filter(orders::Column::UserId.eq(user_id))
+        })
+        .apply_if(filter.status, |query, status| query.filter(orders::Column::Status.eq(status)))
+        .apply_if(filter.created_from, |query, from| query.filter(orders::Column::CreatedAt.gte(from)))
+        .apply_if(filter.created_before, |query, before| {
+            query.filter(orders::Column::CreatedAt.lt(before))
+        })
+        .apply_if(filter.min_total, |query, min| query.filter(orders::Column::Total.gte(min)))
+        .group_by(orders::Column::Id);
+
+    let query = match filter.sort_by {
+        OrderSortKey::CreatedAt => query.order_by_desc(orders::Column::CreatedAt),
+        OrderSortKey::Total => query
+            .order_by_desc(orders::Column::Total)
+            .order_by_desc(orders::Column::CreatedAt),
+    };
+
+    let paginator = query
+        .order_by_asc(orders::Column::Id)
+        .into_model::<OrderSummary>()
+        .paginate(db, per_page);
+
+    let total = paginator.num_items().await?;
+    let orders = paginator.fetch_page(page).await?;
+    Ok((orders.into_iter().map(OrderSummary::with_display).collect(), total))
+}
+
+// Function to find an order by its human-readable number, ignoring case and surrounding spaces
+pub async fn find_order_by_number<C: ConnectionTrait>(
+    order_number: &str,
+    db: &C,
+) -> Result<Option<orders::Model>, sea_orm::DbErr> {
+    orders::Entity::find()
+        .filter(orders::Column::OrderNumber.eq(order_number.trim().to_uppercase()))
+        .one(db)
+        .await
+}
+
+// Function to load one of a user's orders with its items, each with the product's current image.
+// Returns `None` if the order doesn't exist or belongs to someone else
+pub async fn fetch_order_detail<C: ConnectionTrait>(
+    user_id: &str,
+    order_id: Uuid,
+    db: &C,
+) -> Result<Option<OrderDetail>, sea_orm::DbErr> {
+    let Some(order) = orders::Entity::find_by_id(order_id)
+        .filter(orders::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let items = order_items::Entity::find()
+        .filter(order_items::Column::OrderId.eq(order_id))
+        .order_by_asc(order_items::Column::CreatedAt)
+        .all(db)
+        .await?;
+
+    // Items keep their own name and price, so a deleted product only loses its image
+    let product_ids: Vec<Uuid> = items.iter().map(|item| item.product_id).collect();
+    let images: HashMap<Uuid, String> = products::Entity::find()
+        .select_only()
+        .column(products::Column::Id)
+        .column(products::Column::ImgUrl)
+        .filter(products::Column::Id.is_in(product_ids))
+        .into_tuple::<(Uuid, String)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+
+    let delivery_address = order_addresses::Entity::find_by_id(order_id).one(db).await?;
+
+    let item_count = items.iter().map(|item| i64::from(item.quantity)).sum();
+    let items_total = items.iter().map(|item| item.line_total).sum();
+    let items = items
+        .into_iter()
+        .map(|item| {
+            let img_url = images.get(&item.product_id).cloned();
+            OrderItemDetail::new(item, img_url)
+        })
+        .collect();
+
+    Ok(Some(OrderDetail {
+        display: OrderChargesDisplay::from_order(&order),
+        awaiting_payment: order.awaiting_payment(),
+        delivery_address,
+        order,
+        items,
+        item_count,
+        items_total,
+    }))
+}
+
+// Function to list an order's statuses oldest first, starting with `pending` at checkout, or
+// `None` when the order doesn't exist or belongs to another user
+pub async fn fetch_order_timeline<C: ConnectionTrait>(
+    user_id: &str,
+    order_id: Uuid,
+    db: &C,
+) -> Result<Option<Vec<OrderTimelineEntry>>, sea_orm::DbErr> {
+    let Some(order) = orders::Entity::find_by_id(order_id)
+        .filter(orders::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let transitions = order_status_history::Entity::find()
+        .filter(order_status_history::Column::OrderId.eq(order_id))
+        .order_by_asc(order_status_history::Column::ChangedAt)
+        .all(db)
+        .await?;
+
+    // Checkout doesn't record a transition, so the placed order is the first entry
+    let placed = OrderTimelineEntry {
+        status: OrderStatus::Pending,
+        changed_at: order.created_at,
+        note: None,
+    };
+    let timeline = std::iter::once(placed)
+        .chain(transitions.into_iter().map(|transition| OrderTimelineEntry {
+            status: transition.to_status,
+            changed_at: transition.changed_at,
+            note: transition.reason,
+        }))
+        .collect();
+    Ok(Some(timeline))
+}
+
+// Function to build an order's receipt from what checkout stored, or `None` when the order
+// doesn't exist or belongs to another user. Dates are shown in store time.
+pub async fn fetch_order_receipt<C: ConnectionTrait>(
+    user_id: &str,
+    order_id: Uuid,
+    store: ReceiptStore,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<Option<OrderReceipt>, sea_orm::DbErr> {
+    let Some(order) = orders::Entity::find_by_id(order_id)
+        .filter(orders::Column::UserId.eq(user_id))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let items = order_items::Entity::find()
+        .filter(order_items::Column::OrderId.eq(order_id))
+        .order_by_asc(order_items::Column::CreatedAt)
+        .all(db)
+        .await?;
+    let delivery_address = order_addresses::Entity::find_by_id(order_id).one(db).await?;
+
+    let store_time = |at: DateTimeWithTimeZone| format_datetime_in(at, STORE_TIMEZONE, DateTimeFormat::DateTime);
+    Ok(Some(OrderReceipt {
+        store,
+        order_id: order.id,
+        placed_at: store_time(order.created_at),
+        paid_at: order.paid_at.map(store_time),
+        issued_at: store_time(now),
+        status: order.status,
+        payment_method: order.payment_method,
+        payment_status: order.payment_status,
+        items: items.into_iter().map(ReceiptLine::new).collect(),
+        subtotal: order.subtotal,
+        vat: ReceiptVat::from_order(&order),
+        delivery_fee: order.delivery_fee,
+        discount: order.discount,
+        total: order.total,
+        display: OrderChargesDisplay::from_order(&order),
+        delivery_address,
+        order_number: order.order_number,
+        payment_reference: order.payment_reference,
+    }))
+}
+
+// Reason a cart couldn't be turned into an order
+#[derive(Debug)]
+pub enum CheckoutError {
+    EmptyCart,
+    // The cart lines that failed validation or whose stock ran out while reserving
+    Blocked(Vec<CartLineValidation>),
+    // The attached coupon's last use was taken by another checkout
+    CouponExhausted,
+    // The delivery address is incomplete or malformed
+    InvalidAddress(String),
+    // `address_id` isn't one of the user's saved addresses
+    AddressNotFound,
+    Db(sea_orm::DbErr),
+}
+
+impl CheckoutError {
+    pub fn detail(&self) -> String {
+        match self {
+            CheckoutError::EmptyCart => "The cart is empty.".to_string(),
+            CheckoutError::Blocked(items) => format!(
+                "{} cart item(s) need attention before checkout. See POST /carts/{{user_id}}/validate.",
+                items.len()
+            ),
+            CheckoutError::CouponExhausted => {
+                "The coupon on this cart has just run out. Remove it or try another one.".to_string()
+            }
+            CheckoutError::InvalidAddress(reason) => reason.clone(),
+            CheckoutError::AddressNotFound => "Address not found.".to_string(),
+            CheckoutError::Db(e) => format!("Database error during checkout: {}", e),
+        }
+    }
+}
+
+impl std::fmt::Display for CheckoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.detail())
+    }
+}
+
+impl std::error::Error for CheckoutError {}
+
+impl From<sea_orm::DbErr> for CheckoutError {
+    fn from(e: sea_orm::DbErr) -> Self {
+        CheckoutError::Db(e)
+    }
+}
+
+// Function to turn a user's cart lines into a pending order: every line must pass the
+// same checks as `POST /carts/{user_id}/validate`, names and prices are copied into the order
+// items, stock is reserved and the lines are removed from the cart.
+// The delivery address is copied into `order_addresses`, filled from the address book when it
+// names a saved address.
+// Stock is taken from the variant for variant lines and from the product otherwise, with
+// conditional UPDATEs (see `adjust_product_stock`), so when two checkouts
+// race for the last units the loser gets `Blocked` with every line that ran short.
+// Returns an error instead of a partial order, so run this inside a transaction that rolls back
+// on error.
+pub async fn checkout_cart<C: ConnectionTrait>(
+    user_id: &str,
+    payment_method: PaymentMethod,
+    address: CheckoutAddress,
+    rates: &CheckoutRates,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<OrderWithItems, CheckoutError> {
+    let lines = validate_cart_lines(user_id, now, db).await?;
+    if lines.is_empty() {
+        return Err(CheckoutError::EmptyCart);
+    }
+
+    if lines.iter().any(|(_, line)| line.status != CartLineStatus::Ok) {
+        return Err(CheckoutError::Blocked(
+            lines
+                .into_iter()
+                .map(|(_, line)| line)
+                .filter(|line| line.status != CartLineStatus::Ok)
+                .collect(),
+        ));
+    }
+
+    let address_id = address.address_id;
+    let address = complete_checkout_address(user_id, address, db)
+        .await?
+        .ok_or(CheckoutError::AddressNotFound)?;
+    let order_id = Uuid::new_v4();
+    let delivery_address = build_order_address(order_id, address, now).map_err(CheckoutError::InvalidAddress)?;
+
+    // Quantity discounts apply to lines without a variant, as in the cart view
+    let product_ids = lines
+        .iter()
+        .filter(|(cart, _)| cart.variant_id.is_none())
+        .map(|(cart, _)| cart.product_id)
+        .collect();
+    let tiers = fetch_price_tiers_by_product(product_ids, db).await?;
+    let lines: Vec<_> = lines
+        .into_iter()
+        .map(|(cart, line)| {
+            let unit_price = match (cart.variant_id, tiers.get(&cart.product_id)) {
+                (None, Some(tiers)) => applicable_price_tier(tiers, cart.total_qty, cart.unit_price)
+                    .map_or(cart.unit_price, |tier| tier.unit_price),
+                _ => cart.unit_price,
+            };
+            (cart, line, unit_price)
+        })
+        .collect();
+
+    let subtotal: Decimal = lines
+        .iter()
+        .map(|(cart, _, unit_price)| *unit_price * Decimal::from(cart.total_qty))
+        .sum();
+    let summary = compute_checkout_summary(subtotal, rates);
+
+    // The attached coupon counts as on the cart: one that no longer applies just gives nothing
+    let coupon = find_cart_coupon(user_id, db).await?;
+    let discount = match &coupon {
+        Some(coupon) if check_coupon_applies(coupon, summary.subtotal, now).is_ok() => {
+            calculate_discount(summary.subtotal, coupon.discount_type, coupon.value)
+        }
+        _ => Decimal::ZERO,
+    };
+    if let Some(coupon) = coupon.filter(|_| discount > Decimal::ZERO)
+        && !redeem_coupon(coupon.id, now, db).await?
+    {
+        return Err(CheckoutError::CouponExhausted);
+    }
+
+    let order = orders::ActiveModel {
+        id: Set(order_id),
+        // Left to the column default, which draws from `order_number_seq`
+        order_number: NotSet,
+        user_id: Set(user_id.to_string()),
+        status: Set(OrderStatus::Pending),
+        address_id: Set(address_id),
+        subtotal: Set(summary.subtotal),
+        tax: Set(summary.tax),
+        delivery_fee: Set(summary.shipping_fee),
+        discount: Set(discount),
+        total: Set((summary.grand_total - discount).max(Decimal::ZERO)),
+        payment_status: Set(PaymentStatus::Unpaid),
+        payment_method: Set(payment_method),
+        paid_at: Set(None),
+        payment_reference: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(db)
+    .await?;
+    let delivery_address = delivery_address.insert(db).await?;
+
+    let reason = format!("Reserved for order {}", order.id);
+    let mut items = Vec::with_capacity(lines.len());
+    let mut out_of_stock = Vec::new();
+    for (cart, line, unit_price) in lines {
+        // Variant lines take stock from the variant, everything else from the product
+        let taken = match cart.variant_id {
+            Some(variant_id) => match adjust_variant_stock(variant_id, -cart.total_qty, now, db).await? {
+                VariantStockOutcome::Applied => Ok(()),
+                VariantStockOutcome::VariantNotFound => Err(CartLineStatus::Unavailable),
+                VariantStockOutcome::InsufficientStock(available_qty) => Err(CartLineStatus::InsufficientStock {
+                    available_qty: available_qty.max(0),
+                }),
+            },
+            None => match adjust_product_stock(cart.product_id, -cart.total_qty, reason.clone(), now, db).await? {
+                StockAdjustmentOutcome::Applied(_) => Ok(()),
+                StockAdjustmentOutcome::ProductNotFound => Err(CartLineStatus::Unavailable),
+                StockAdjustmentOutcome::InsufficientStock(available_qty) => Err(CartLineStatus::InsufficientStock {
+                    available_qty: available_qty.max(0),
+                }),
+            },
+        };
+        if let Err(status) = taken {
+            out_of_stock.push(CartLineValidation { status, ..line });
+            continue;
+        }
+
+        let item = order_items::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            order_id: Set(order.id),
+            product_id: Set(cart.product_id),
+            variant_id: Set(cart.variant_id),
+            product_name: Set(line.product_name),
+            unit_price: Set(unit_price),
+            quantity: Set(cart.total_qty),
+            line_total: Set(unit_price * Decimal::from(cart.total_qty)),
+            created_at: Set(now),
+        }
+        .insert(db)
+        .await?;
+        items.push(item);
+    }
+    if !out_of_stock.is_empty() {
+        return Err(CheckoutError::Blocked(out_of_stock));
+    }
+
+    carts::Entity::delete_many()
+        .filter(carts::Column::UserId.eq(user_id))
+        .exec(db)
+        .await?;
+    detach_cart_coupon(user_id, db).await?;
+
+    Ok(OrderWithItems {
+        display: OrderChargesDisplay::from_order(&order),
+        order,
+        delivery_address,
+        items,
+    })
+}
+
+// Function to put the stock of every item of an order back where checkout took it from: the
+// variant for variant items, otherwise the product, recording product changes in the stock
+// audit trail. A variant deleted since the order was placed has nothing to restore.
+pub async fn release_order_stock<C: ConnectionTrait>(
+    order_id: Uuid,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<(), sea_orm::DbErr> {
+    let items = order_items::Entity::find()
+        .filter(order_items::Column::OrderId.eq(order_id))
+        .all(db)
+        .await?;
+
+    let reason = format!("Released by cancelled order {}", order_id);
+    for item in items {
+        match item.variant_id {
+            Some(variant_id) => {
+                adjust_variant_stock(variant_id, item.quantity, now, db).await?;
+            }
+            None => {
+                adjust_product_stock(item.product_id, item.quantity, reason.clone(), now, db).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Function to move an order to `next` and record the transition. Unpaid orders that aren't
+// cash on delivery can't move to preparing. Cancelling an order releases its stock. The order row is locked for the duration, so run this inside a transaction.
+pub async fn transition_order_status<C: ConnectionTrait>(
+    order_id: Uuid,
+    next: OrderStatus,
+    reason: Option<String>,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<(orders::Model, order_status_history::Model), OrderStatusError> {
+    let order = orders::Entity::find_by_id(order_id)
+        .lock_exclusive()
+        .one(db)
+        .await?
+        .ok_or(OrderStatusError::NotFound)?;
+
+    let current = order.status;
+    if !current.can_transition_to(next) {
+        return Err(OrderStatusError::IllegalTransition {
+            from: current,
+            to: next,
+        });
+    }
+    if next == OrderStatus::Preparing && order.awaiting_payment() {
+        return Err(OrderStatusError::AwaitingPayment(order.payment_method));
+    }
+
+    let mut order_active_model: orders::ActiveModel = order.into();
+    order_active_model.status = Set(next);
+    order_active_model.updated_at = Set(now);
+    let order = order_active_model.update(db).await?;
+
+    if next == OrderStatus::Cancelled {
+        release_order_stock(order_id, now, db).await?;
+    }
+
+    let transition = order_status_history::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        order_id: Set(order_id),
+        from_status: Set(current),
+        to_status: Set(next),
+        changed_at: Set(now),
+        reason: Set(reason),
+    }
+    .insert(db)
+    .await?;
+
+    Ok((order, transition))
+}
+
+// Outcome of marking an order as paid
+pub enum MarkPaidOutcome {
+    Paid(orders::Model),
+    // Already paid; returned untouched so retries are harmless
+    AlreadyPaid(orders::Model),
+    NotFound,
+    Refunded,
+    Cancelled,
+}
+
+// Longest external payment reference kept on an order
+const MAX_PAYMENT_REFERENCE_LEN: usize = 200;
+
+// Function to trim a payment reference, treating a blank one as none
+pub fn normalize_payment_reference(reference: Option<&str>) -> Result<Option<String>, String> {
+    let Some(reference) = reference.map(str::trim).filter(|reference| !reference.is_empty()) else {
+        return Ok(None);
+    };
+    if reference.chars().count() > MAX_PAYMENT_REFERENCE_LEN {
+        return Err(format!("Payment references are limited to {} characters.", MAX_PAYMENT_REFERENCE_LEN));
+    }
+    Ok(Some(reference.to_string()))
+}
+
+// Function to record an order's payment with its time and optional external reference.
+// The order row is locked, so run this inside a transaction
+pub async fn mark_order_paid<C: ConnectionTrait>(
+    order_id: Uuid,
+    reference: Option<String>,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<MarkPaidOutcome, sea_orm::DbErr> {
+    let Some(order) = orders::Entity::find_by_id(order_id).lock_exclusive().one(db).await? else {
+        return Ok(MarkPaidOutcome::NotFound);
+    };
+
+    match order.payment_status {
+        PaymentStatus::Paid => return Ok(MarkPaidOutcome::AlreadyPaid(order)),
+        PaymentStatus::Refunded => return Ok(MarkPaidOutcome::Refunded),
+        PaymentStatus::Unpaid if order.status == OrderStatus::Cancelled => return Ok(MarkPaidOutcome::Cancelled),
+        PaymentStatus::Unpaid => {}
+    }
+
+    let mut order_active_model: orders::ActiveModel = order.into();
+    order_active_model.payment_status = Set(PaymentStatus::Paid);
+    order_active_model.paid_at = Set(Some(now));
+    order_active_model.payment_reference = Set(reference);
+    order_active_model.updated_at = Set(now);
+    Ok(MarkPaidOutcome::Paid(order_active_model.update(db).await?))
+}
+
+// Longest cancellation reason a customer can leave
+const MAX_CANCEL_REASON_LEN: usize = 500;
+
+// Function to trim a cancellation reason, treating a blank one as none
+pub fn normalize_cancel_reason(reason: Option<&str>) -> Result<Option<String>, String> {
+    let Some(reason) = reason.map(str::trim).filter(|reason| !reason.is_empty()) else {
+        return Ok(None);
+    };
+    if reason.chars().count() > MAX_CANCEL_REASON_LEN {
+        return Err(format!("Cancellation reasons are limited to {} characters.", MAX_CANCEL_REASON_LEN));
+    }
+    Ok(Some(reason.to_string()))
+}
+
+// Function to cancel one of a user's own orders while it is still pending or confirmed,
+// releasing its stock. An order that is already cancelled is returned as it is, so retries
+// are harmless. Orders of other users are reported as not found. Run inside a transaction.
+pub async fn cancel_customer_order<C: ConnectionTrait>(
+    user_id: &str,
+    order_id: Uuid,
+    reason: Option<String>,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<OrderCancellation, OrderStatusError> {
+    let order = orders::Entity::find_by_id(order_id)
+        .filter(orders::Column::UserId.eq(user_id))
+        .lock_exclusive()
+        .one(db)
+        .await?
+        .ok_or(OrderStatusError::NotFound)?;
+
+    match order.status {
+        OrderStatus::Cancelled => {
+            let transition = order_status_history::Entity::find()
+                .filter(order_status_history::Column::OrderId.eq(order_id))
+                .filter(order_status_history::Column::ToStatus.eq(OrderStatus::Cancelled))
+                .order_by_desc(order_status_history::Column::ChangedAt)
+                .one(db)
+                .await?;
+            Ok(OrderCancellation {
+                order,
+                transition,
+                already_cancelled: true,
+            })
+        }
+        OrderStatus::Pending | OrderStatus::Confirmed => {
+            let (order, transition) = transition_order_status(order_id, OrderStatus::Cancelled, reason, now, db).await?;
+            Ok(OrderCancellation {
+                order,
+                transition: Some(transition),
+                already_cancelled: false,
+            })
+        }
+        from => Err(OrderStatusError::IllegalTransition {
+            from,
+            to: OrderStatus::Cancelled,
+        }),
+    }
+}
+
+// Function to copy an order's items back into its owner's cart at today's prices. Each item goes
+// through `CartStore::add_item` like `POST /carts/`, variant included. Quantities are cut down to
+// the stock left over after what's already in the cart and to `max_qty`; items that can't be
+// added at all, or only below the product's `min_order_qty` or past `max_items`, are skipped.
+pub async fn reorder_into_cart<S: CartStore, C: ConnectionTrait>(
+    order: &orders::Model,
+    store: &S,
+    max_qty: i32,
+    max_items: usize,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<ReorderSummary, HttpResponse> {
+    let db_error = |e: sea_orm::DbErr| {
+        HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while reordering: {}", e),
+        })
+    };
+
+    // `POST /carts/` only takes UUID cart ids
+    let Ok(user_id) = Uuid::parse_str(&order.user_id) else {
+        return Err(HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "Invalid user_id.".to_string(),
+        }));
+    };
+
+    let items = order_items::Entity::find()
+        .filter(order_items::Column::OrderId.eq(order.id))
+        .order_by_asc(order_items::Column::CreatedAt)
+        .all(db)
+        .await
+        .map_err(db_error)?;
+
+    // An order can list the same product or variant more than once; add it to the cart as one line
+    let mut ordered: Vec<(Uuid, Option<Uuid>, String, i32)> = Vec::with_capacity(items.len());
+    for item in items {
+        match ordered
+            .iter_mut()
+            .find(|(product_id, variant_id, _, _)| *product_id == item.product_id && *variant_id == item.variant_id)
+        {
+            Some((_, _, _, qty)) => *qty += item.quantity,
+            None => ordered.push((item.product_id, item.variant_id, item.product_name, item.quantity)),
+        }
+    }
+
+    let lines = store
+        .cart_lines(&order.user_id, &CartListOptions::default())
+        .await
+        .map_err(db_error)?;
+    let mut cart_qty: HashMap<(Uuid, Option<Uuid>), i32> =
+        lines.iter().map(|line| ((line.product_id, line.variant_id), line.total_qty)).collect();
+    let mut cart_product_ids: HashSet<Uuid> = lines.iter().map(|line| line.product_id).collect();
+
+    let mut summary = ReorderSummary {
+        order_id: order.id,
+        added: Vec::new(),
+        skipped: Vec::new(),
+    };
+
+    for (product_id, variant_id, product_name, ordered_qty) in ordered {
+        let existing_qty = cart_qty.get(&(product_id, variant_id)).copied();
+        let outcome = match reorder_qty(product_id, variant_id, ordered_qty, existing_qty.unwrap_or(0), max_qty, now, db)
+            .await
+            .map_err(db_error)?
+        {
+            Ok(_) if existing_qty.is_none() && check_cart_items_limit(&cart_product_ids, product_id, max_items).is_err() => {
+                Err(ReorderSkipReason::CartFull)
+            }
+            outcome => outcome,
+        };
+
+        let added_qty = match outcome {
+            Ok(added_qty) => added_qty,
+            Err(reason) => {
+                summary.skipped.push(SkippedReorderItem {
+                    product_id,
+                    variant_id,
+                    product_name,
+                    ordered_qty,
+                    reason,
+                });
+                continue;
+            }
+        };
+
+        let new_cart = NewCart {
+            user_id,
+            product_id,
+            variant_id,
+            total_qty: added_qty,
+            note: None,
+            mode: CartAddMode::Add,
+        };
+        match store.add_item(&new_cart, None, max_qty, max_items, now).await {
+            Ok(CartAddOutcome { cart, .. }) => {
+                cart_qty.insert((product_id, variant_id), cart.total_qty);
+                cart_product_ids.insert(product_id);
+                summary.added.push(ReorderedItem {
+                    product_id,
+                    variant_id,
+                    product_name,
+                    ordered_qty,
+                    added_qty,
+                    total_qty: cart.total_qty,
+                });
+            }
+            Err(response) if response.status().is_server_error() => return Err(response),
+            // Stock or the cart changed since the checks above
+            Err(_) => summary.skipped.push(SkippedReorderItem {
+                product_id,
+                variant_id,
+                product_name,
+                ordered_qty,
+                reason: ReorderSkipReason::Conflict,
+            }),
+        }
+    }
+
+    Ok(summary)
+}
+
+// Function to work out how much of an ordered line can go back into a cart already holding
+// `existing_qty` of it, or why none of it can
+async fn reorder_qty<C: ConnectionTrait>(
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    ordered_qty: i32,
+    existing_qty: i32,
+    max_qty: i32,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<Result<i32, ReorderSkipReason>, sea_orm::DbErr> {
+    let Some(product) = find_product_by_id(product_id, db).await? else {
+        return Ok(Err(ReorderSkipReason::Deleted));
+    };
+    let stock = match variant_id {
+        Some(variant_id) => match find_product_variant(product_id, variant_id, db).await? {
+            Some(variant) => variant.stock_quantity,
+            None => return Ok(Err(ReorderSkipReason::Deleted)),
+        },
+        None => product.stock_quantity,
+    };
+    if !product.is_available_at(now) {
+        return Ok(Err(ReorderSkipReason::Unavailable));
+    }
+
+    let in_stock = stock - existing_qty;
+    let room = max_qty - existing_qty;
+    let added_qty = ordered_qty.min(in_stock).min(room);
+    Ok(if added_qty <= 0 && room <= 0 {
+        Err(ReorderSkipReason::QuantityLimit)
+    } else if added_qty <= 0 {
+        Err(ReorderSkipReason::OutOfStock)
+    } else if existing_qty + added_qty < product.min_order_qty {
+        Err(ReorderSkipReason::BelowMinimum)
+    } else {
+        Ok(added_qty)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::product_variants;
+    use crate::test_utils::{insert_cart_item, insert_product, test_db};
+    use crate::utils::local_datetime;
+    use sea_orm::{DatabaseConnection, IntoActiveModel, TransactionTrait};
+
+    fn rates() -> CheckoutRates {
+        CheckoutRates {
+            tax_rate: Decimal::ZERO,
+            shipping_fee: Decimal::ZERO,
+            free_shipping_threshold: None,
+        }
+    }
+
+    fn address() -> CheckoutAddress {
+        CheckoutAddress {
+            address_id: None,
+            recipient_name: "Juan dela Cruz".to_string(),
+            phone: "09171234567".to_string(),
+            line1: Some("12 Mabini St.".to_string()),
+            barangay: Some("Poblacion".to_string()),
+            city: Some("Makati".to_string()),
+            notes: None,
+        }
+    }
+
+    async fn insert_variant(db: &DatabaseConnection, product: &products::Model, stock: i32) -> product_variants::Model {
+        let now = local_datetime();
+        product_variants::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            product_id: Set(product.id),
+            name: Set("1kg".to_string()),
+            sku: Set(format!("SKU-{}", Uuid::new_v4().simple())),
+            price: Set(None),
+            stock_quantity: Set(stock),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(db)
+        .await
+        .unwrap()
+    }
+
+    async fn stock_of(db: &DatabaseConnection, product_id: Uuid, variant_id: Uuid) -> (i32, i32) {
+        let product = find_product_by_id(product_id, db).await.unwrap().unwrap();
+        let variant = product_variants::Entity::find_by_id(variant_id).one(db).await.unwrap().unwrap();
+        (product.stock_quantity, variant.stock_quantity)
+    }
+
+    #[actix_web::test]
+    async fn checkout_takes_variant_stock_and_cancelling_puts_it_back() {
+        let Some(db) = test_db().await else { return };
+        // The base product is sold out; only the variant has stock
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 0).await;
+        let variant = insert_variant(&db, &product, 5).await;
+        let mut line = insert_cart_item(&db, "variant-buyer", &product, 3).await.into_active_model();
+        line.variant_id = Set(Some(variant.id));
+        line.update(&db).await.unwrap();
+        let now = local_datetime();
+
+        let placed = checkout_cart("variant-buyer", PaymentMethod::Cod, address(), &rates(), now, &db)
+            .await
+            .unwrap();
+        assert_eq!(placed.items[0].variant_id, Some(variant.id));
+        assert_eq!(stock_of(&db, product.id, variant.id).await, (0, 2));
+
+        cancel_customer_order("variant-buyer", placed.order.id, None, now, &db).await.unwrap();
+        assert_eq!(stock_of(&db, product.id, variant.id).await, (0, 5));
+    }
+
+    #[actix_web::test]
+    async fn base_product_stock_does_not_cover_a_sold_out_variant() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Tilapia", Decimal::new(9000, 2), 50).await;
+        let variant = insert_variant(&db, &product, 1).await;
+        for user_id in ["first-buyer", "second-buyer"] {
+            let mut line = insert_cart_item(&db, user_id, &product, 1).await.into_active_model();
+            line.variant_id = Set(Some(variant.id));
+            line.update(&db).await.unwrap();
+        }
+        let now = local_datetime();
+
+        checkout_cart("first-buyer", PaymentMethod::Cod, address(), &rates(), now, &db)
+            .await
+            .unwrap();
+        assert_eq!(stock_of(&db, product.id, variant.id).await, (50, 0));
+
+        let refused = checkout_cart("second-buyer", PaymentMethod::Cod, address(), &rates(), now, &db).await;
+        assert!(matches!(refused, Err(CheckoutError::Blocked(_))));
+        assert_eq!(stock_of(&db, product.id, variant.id).await, (50, 0));
+    }
+
+    #[actix_web::test]
+    async fn only_one_of_two_racing_checkouts_gets_the_last_unit() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 1).await;
+        insert_cart_item(&db, "first-buyer", &product, 1).await;
+        insert_cart_item(&db, "second-buyer", &product, 1).await;
+        let now = local_datetime();
+
+        // Each in its own transaction, as the checkout handler runs it
+        let checkout = |user_id: &'static str| {
+            db.transaction::<_, OrderWithItems, CheckoutError>(move |txn| {
+                Box::pin(async move { checkout_cart(user_id, PaymentMethod::Cod, address(), &rates(), now, txn).await })
+            })
+        };
+        let outcomes = futures_util::future::join_all([checkout("first-buyer"), checkout("second-buyer")]).await;
+
+        let placed = outcomes.iter().filter(|outcome| outcome.is_ok()).count();
+        let blocked: Vec<_> = outcomes
+            .iter()
+            .filter_map(|outcome| match outcome {
+                Err(sea_orm::TransactionError::Transaction(CheckoutError::Blocked(lines))) => Some(lines),
+                _ => None,
+            })
+            .collect();
+        assert_eq!((placed, blocked.len()), (1, 1));
+        // The loser either ran short taking stock or, if it validated after the winner
+        // committed, already saw the product sold out and hidden
+        assert!(matches!(
+            blocked[0][0].status,
+            CartLineStatus::InsufficientStock { available_qty: 0 } | CartLineStatus::Unavailable
+        ));
+
+        let stored = find_product_by_id(product.id, &db).await.unwrap().unwrap();
+        assert_eq!(stored.stock_quantity, 0);
+        assert!(!stored.is_available);
+        assert!(stored.sold_out);
+        let orders = orders::Entity::find().all(&db).await.unwrap();
+        assert_eq!(orders.len(), 1);
+    }
+}
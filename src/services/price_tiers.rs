@@ -0,0 +1,224 @@
+use crate::models::carts::{CartSortKey, CartsResponse};
+use crate::models::product_price_tiers::{self, NewPriceTier};
+use crate::services::validate_product_price;
+use sea_orm::prelude::{DateTimeWithTimeZone, Decimal};
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Most tiers a single product can have.
+pub const MAX_PRICE_TIERS: usize = 20;
+
+// Function to check a product's full set of tiers before it replaces the current one.
+// Every tier must start at 2 units or more (a tier at 1 would just be the product price), have a
+// valid price below `product_price`, and start at a quantity no other tier starts at.
+pub fn validate_price_tiers(tiers: &[NewPriceTier], product_price: Decimal) -> Result<(), String> {
+    if tiers.len() > MAX_PRICE_TIERS {
+        return Err(format!("A product can have at most {} price tiers.", MAX_PRICE_TIERS));
+    }
+
+    let mut seen = HashSet::new();
+    for tier in tiers {
+        if tier.min_qty < 2 {
+            return Err("Tier min_qty must be at least 2.".to_string());
+        }
+        if !seen.insert(tier.min_qty) {
+            return Err(format!("More than one tier starts at min_qty {}.", tier.min_qty));
+        }
+        if let Err(reason) = validate_product_price(tier.unit_price) {
+            return Err(format!("Tier unit_price for min_qty {} {}.", tier.min_qty, reason));
+        }
+        if tier.unit_price >= product_price {
+            return Err(format!(
+                "Tier unit_price for min_qty {} must be below the product price of {}.",
+                tier.min_qty, product_price
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Function to pick the tier for `qty` units: the one with the highest `min_qty` not above `qty`.
+pub fn select_price_tier(tiers: &[product_price_tiers::Model], qty: i32) -> Option<&product_price_tiers::Model> {
+    tiers
+        .iter()
+        .filter(|tier| tier.min_qty <= qty)
+        .max_by_key(|tier| tier.min_qty)
+}
+
+// Function to pick the tier a cart line is priced with, if any.
+// A tier only applies when it is cheaper than the line's snapshot `unit_price`, so lowering the
+// product price below a tier never makes buying in bulk more expensive.
+pub fn applicable_price_tier(
+    tiers: &[product_price_tiers::Model],
+    qty: i32,
+    unit_price: Decimal,
+) -> Option<&product_price_tiers::Model> {
+    select_price_tier(tiers, qty).filter(|tier| tier.unit_price < unit_price)
+}
+
+// Function to list a product's tiers, lowest `min_qty` first.
+pub async fn fetch_price_tiers<C: ConnectionTrait>(
+    product_id: Uuid,
+    db: &C,
+) -> Result<Vec<product_price_tiers::Model>, sea_orm::DbErr> {
+    product_price_tiers::Entity::find()
+        .filter(product_price_tiers::Column::ProductId.eq(product_id))
+        .order_by_asc(product_price_tiers::Column::MinQty)
+        .all(db)
+        .await
+}
+
+// Function to load the tiers of several products at once, keyed by product id.
+pub async fn fetch_price_tiers_by_product<C: ConnectionTrait>(
+    product_ids: Vec<Uuid>,
+    db: &C,
+) -> Result<HashMap<Uuid, Vec<product_price_tiers::Model>>, sea_orm::DbErr> {
+    if product_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let tiers = product_price_tiers::Entity::find()
+        .filter(product_price_tiers::Column::ProductId.is_in(product_ids))
+        .all(db)
+        .await?;
+
+    let mut by_product: HashMap<Uuid, Vec<product_price_tiers::Model>> = HashMap::new();
+    for tier in tiers {
+        by_product.entry(tier.product_id).or_default().push(tier);
+    }
+    Ok(by_product)
+}
+
+// Function to replace all of a product's tiers with `tiers`, which must already be validated.
+// Run it in a transaction so readers never see the product without tiers.
+pub async fn replace_price_tiers<C: ConnectionTrait>(
+    product_id: Uuid,
+    tiers: &[NewPriceTier],
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<Vec<product_price_tiers::Model>, sea_orm::DbErr> {
+    product_price_tiers::Entity::delete_many()
+        .filter(product_price_tiers::Column::ProductId.eq(product_id))
+        .exec(db)
+        .await?;
+
+    if !tiers.is_empty() {
+        product_price_tiers::Entity::insert_many(tiers.iter().map(|tier| product_price_tiers::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            product_id: Set(product_id),
+            min_qty: Set(tier.min_qty),
+            unit_price: Set(tier.unit_price),
+            created_at: Set(now),
+        }))
+        .exec(db)
+        .await?;
+    }
+
+    fetch_price_tiers(product_id, db).await
+}
+
+// Function to price cart lines with their products' quantity discounts, based on each line's
+// `total_qty`. Variant lines keep their snapshot prices, since tiers are set against the product
+// price. When the lines are sorted by subtotal they are re-sorted, as discounts can change the
+// order.
+pub async fn apply_cart_price_tiers<C: ConnectionTrait>(
+    lines: &mut [CartsResponse],
+    sort_by: CartSortKey,
+    descending: bool,
+    db: &C,
+) -> Result<(), sea_orm::DbErr> {
+    let product_ids = lines
+        .iter()
+        .filter(|line| line.variant_id.is_none())
+        .map(|line| line.product_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let tiers = fetch_price_tiers_by_product(product_ids, db).await?;
+
+    for line in lines.iter_mut() {
+        let tier = match (line.variant_id, tiers.get(&line.product_id)) {
+            (None, Some(tiers)) => applicable_price_tier(tiers, line.total_qty, line.unit_price_decimal()).cloned(),
+            _ => None,
+        };
+        line.apply_price_tier(tier.as_ref());
+    }
+
+    if sort_by == CartSortKey::Subtotal && !tiers.is_empty() {
+        lines.sort_by(|a, b| {
+            let ordering = a.sub_total_price.cmp(&b.sub_total_price);
+            if descending { ordering.reverse() } else { ordering }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::local_datetime;
+
+    fn tier(min_qty: i32, unit_price: i64) -> product_price_tiers::Model {
+        product_price_tiers::Model {
+            id: Uuid::new_v4(),
+            product_id: Uuid::nil(),
+            min_qty,
+            unit_price: Decimal::new(unit_price, 0),
+            created_at: local_datetime(),
+        }
+    }
+
+    fn new_tier(min_qty: i32, unit_price: i64) -> NewPriceTier {
+        NewPriceTier {
+            min_qty,
+            unit_price: Decimal::new(unit_price, 0),
+        }
+    }
+
+    #[test]
+    fn picks_the_highest_tier_the_quantity_reaches() {
+        // Out of order on purpose; tiers aren't always loaded sorted
+        let tiers = [tier(20, 120), tier(5, 140), tier(10, 130)];
+        let min_qty = |qty| select_price_tier(&tiers, qty).map(|tier| tier.min_qty);
+
+        assert_eq!(min_qty(1), None);
+        assert_eq!(min_qty(4), None);
+        assert_eq!(min_qty(5), Some(5));
+        assert_eq!(min_qty(19), Some(10));
+        assert_eq!(min_qty(20), Some(20));
+        assert_eq!(min_qty(500), Some(20));
+        assert_eq!(select_price_tier(&[], 50), None);
+    }
+
+    #[test]
+    fn skips_a_tier_that_is_not_cheaper_than_the_snapshot_price() {
+        let tiers = [tier(5, 140), tier(10, 130)];
+
+        let applied = applicable_price_tier(&tiers, 12, Decimal::new(150, 0));
+        assert_eq!(applied.map(|tier| tier.min_qty), Some(10));
+        // The line was added after a price drop to 125, below every tier
+        assert_eq!(applicable_price_tier(&tiers, 12, Decimal::new(125, 0)), None);
+        assert_eq!(applicable_price_tier(&tiers, 12, Decimal::new(130, 0)), None);
+    }
+
+    #[test]
+    fn accepts_distinct_tiers_below_the_product_price() {
+        assert!(validate_price_tiers(&[new_tier(5, 140), new_tier(10, 130)], Decimal::new(150, 0)).is_ok());
+        assert!(validate_price_tiers(&[], Decimal::new(150, 0)).is_ok());
+    }
+
+    #[test]
+    fn rejects_overlapping_or_pointless_tiers() {
+        let price = Decimal::new(150, 0);
+
+        let repeated = validate_price_tiers(&[new_tier(5, 140), new_tier(5, 130)], price);
+        assert_eq!(repeated.unwrap_err(), "More than one tier starts at min_qty 5.");
+        assert!(validate_price_tiers(&[new_tier(1, 140)], price).is_err());
+        assert!(validate_price_tiers(&[new_tier(5, 150)], price).is_err());
+        assert!(validate_price_tiers(&[new_tier(5, 0)], price).is_err());
+
+        let too_many: Vec<NewPriceTier> = (2..=22).map(|min_qty| new_tier(min_qty, 100)).collect();
+        assert!(validate_price_tiers(&too_many, price).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use crate::config::AppConfig;
+use sea_orm::prelude::Decimal;
+
+/// Tax and shipping rates applied to a cart at checkout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckoutRates {
+    pub tax_rate: Decimal,
+    pub shipping_fee: Decimal,
+    pub free_shipping_threshold: Option<Decimal>,
+}
+
+impl CheckoutRates {
+    // Function to pick the rates for a delivery region. Regions listed in `REGIONAL_SHIPPING_FEES`
+    // get their own shipping fee, matched case-insensitively; everything else uses the configured
+    // defaults.
+    pub fn for_region(config: &AppConfig, region: Option<&str>) -> Self {
+        let shipping_fee = region
+            .and_then(|region| config.regional_shipping_fees.get(&region.trim().to_lowercase()))
+            .copied()
+            .unwrap_or(config.shipping_fee);
+        Self {
+            tax_rate: config.tax_rate,
+            shipping_fee,
+            free_shipping_threshold: config.free_shipping_threshold,
+        }
+    }
+}
+
+/// Money breakdown of a cart at checkout, all rounded to cents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckoutSummary {
+    pub subtotal: Decimal,
+    pub tax: Decimal,
+    pub shipping_fee: Decimal,
+    pub grand_total: Decimal,
+    /// Subtotal at which shipping becomes free, when free shipping is offered.
+    pub free_shipping_threshold: Option<Decimal>,
+    /// How much more the cart needs for free shipping; zero once it qualifies.
+    pub amount_to_free_shipping: Option<Decimal>,
+}
+
+// Function to compute tax, shipping and grand total for a cart subtotal.
+// An empty cart ships for free, as does any subtotal at or above the free-shipping threshold.
+// The distance to that threshold is returned so the UI can show "₱120 away from free delivery".
+pub fn compute_checkout_summary(subtotal: Decimal, rates: &CheckoutRates) -> CheckoutSummary {
+    let subtotal = subtotal.max(Decimal::ZERO).round_dp(2);
+    let tax = (subtotal * rates.tax_rate).round_dp(2);
+
+    let ships_free = subtotal.is_zero()
+        || rates
+            .free_shipping_threshold
+            .is_some_and(|threshold| subtotal >= threshold);
+    let shipping_fee = if ships_free {
+        Decimal::ZERO
+    } else {
+        rates.shipping_fee.round_dp(2)
+    };
+    let free_shipping_threshold = rates.free_shipping_threshold.map(|threshold| threshold.round_dp(2));
+
+    CheckoutSummary {
+        subtotal,
+        tax,
+        shipping_fee,
+        grand_total: subtotal + tax + shipping_fee,
+        free_shipping_threshold,
+        amount_to_free_shipping: free_shipping_threshold
+            .map(|threshold| (threshold - subtotal).max(Decimal::ZERO)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn ph_rates() -> CheckoutRates {
+        CheckoutRates {
+            tax_rate: Decimal::new(12, 2),
+            shipping_fee: Decimal::new(50, 0),
+            free_shipping_threshold: Some(Decimal::new(1000, 0)),
+        }
+    }
+
+    #[test]
+    fn adds_vat_and_shipping_to_the_subtotal() {
+        let summary = compute_checkout_summary(Decimal::new(500, 0), &ph_rates());
+
+        assert_eq!(summary.subtotal, Decimal::new(500, 0));
+        assert_eq!(summary.tax, Decimal::new(60, 0));
+        assert_eq!(summary.shipping_fee, Decimal::new(50, 0));
+        assert_eq!(summary.grand_total, Decimal::new(610, 0));
+        assert_eq!(summary.amount_to_free_shipping, Some(Decimal::new(500, 0)));
+    }
+
+    #[test]
+    fn ships_free_from_the_threshold_on() {
+        let summary = compute_checkout_summary(Decimal::new(1000, 0), &ph_rates());
+
+        assert_eq!(summary.shipping_fee, Decimal::ZERO);
+        assert_eq!(summary.grand_total, Decimal::new(1120, 0));
+        assert_eq!(summary.amount_to_free_shipping, Some(Decimal::ZERO));
+    }
+
+    #[test]
+    fn rounds_every_amount_to_cents() {
+        let rates = CheckoutRates {
+            shipping_fee: Decimal::new(49999, 3),
+            free_shipping_threshold: Some(Decimal::new(999995, 3)),
+            ..ph_rates()
+        };
+        // 333.335 rounds half to even, to 333.34; 12% of it is 40.0008
+        let summary = compute_checkout_summary(Decimal::new(333335, 3), &rates);
+
+        assert_eq!(summary.subtotal, Decimal::new(33334, 2));
+        assert_eq!(summary.tax, Decimal::new(4000, 2));
+        assert_eq!(summary.shipping_fee, Decimal::new(5000, 2));
+        assert_eq!(summary.grand_total, Decimal::new(42334, 2));
+        assert_eq!(summary.free_shipping_threshold, Some(Decimal::new(100000, 2)));
+        assert_eq!(summary.amount_to_free_shipping, Some(Decimal::new(66666, 2)));
+    }
+
+    #[test]
+    fn an_empty_cart_costs_nothing() {
+        let summary = compute_checkout_summary(Decimal::ZERO, &ph_rates());
+
+        assert_eq!(summary.tax, Decimal::ZERO);
+        assert_eq!(summary.shipping_fee, Decimal::ZERO);
+        assert_eq!(summary.grand_total, Decimal::ZERO);
+        assert_eq!(summary.amount_to_free_shipping, Some(Decimal::new(1000, 0)));
+
+        // A negative subtotal is treated as empty rather than producing a refund
+        let summary = compute_checkout_summary(Decimal::new(-20, 0), &ph_rates());
+        assert_eq!(summary.subtotal, Decimal::ZERO);
+        assert_eq!(summary.grand_total, Decimal::ZERO);
+    }
+
+    #[test]
+    fn regions_use_their_own_shipping_fee() {
+        let config = AppConfig {
+            tax_rate: Decimal::new(12, 2),
+            shipping_fee: Decimal::new(80, 0),
+            free_shipping_threshold: Some(Decimal::new(1500, 0)),
+            regional_shipping_fees: HashMap::from([
+                ("ncr".to_string(), Decimal::new(50, 0)),
+                ("visayas".to_string(), Decimal::new(150, 0)),
+                ("mindanao".to_string(), Decimal::new(180, 0)),
+            ]),
+            ..AppConfig::from_env()
+        };
+
+        let fee = |region: Option<&str>| CheckoutRates::for_region(&config, region).shipping_fee;
+        assert_eq!(fee(Some("ncr")), Decimal::new(50, 0));
+        assert_eq!(fee(Some(" Visayas ")), Decimal::new(150, 0));
+        assert_eq!(fee(Some("MINDANAO")), Decimal::new(180, 0));
+        assert_eq!(fee(Some("luzon")), Decimal::new(80, 0));
+        assert_eq!(fee(None), Decimal::new(80, 0));
+
+        let rates = CheckoutRates::for_region(&config, Some("visayas"));
+        assert_eq!(rates.tax_rate, Decimal::new(12, 2));
+        assert_eq!(rates.free_shipping_threshold, Some(Decimal::new(1500, 0)));
+    }
+
+    #[test]
+    fn always_charges_shipping_without_a_threshold() {
+        let rates = CheckoutRates {
+            free_shipping_threshold: None,
+            ..ph_rates()
+        };
+        let summary = compute_checkout_summary(Decimal::new(5000, 0), &rates);
+
+        assert_eq!(summary.shipping_fee, Decimal::new(50, 0));
+        assert_eq!(summary.free_shipping_threshold, None);
+        assert_eq!(summary.amount_to_free_shipping, None);
+    }
+}
@@ -1,15 +1,23 @@
-use actix_web::{web, HttpResponse};
-use sea_orm::{DatabaseConnection, QueryFilter};
+use actix_web::HttpResponse;
+use sea_orm::{ActiveModelTrait, ConnectionTrait, QueryFilter, QueryOrder, QuerySelect, Set};
 use sea_orm::ColumnTrait;
 use sea_orm::EntityTrait;
+use sea_orm::prelude::{DateTimeWithTimeZone, Decimal};
+use sea_orm::sea_query::{Expr, Func};
+use sea_orm::Condition;
+use rust_decimal::RoundingStrategy;
+use std::collections::HashMap;
 use uuid::Uuid;
-use crate::models::products;
-use crate::models::responses::ErrorResponse;
+use crate::models::{categories, price_history, product_images, product_variants, products, stock_adjustments, vendors};
+use crate::models::products::{ImportRowStatus, PriceChange, PriceUpdateRequest, ProductImportRow, ProductSuggestion, ProductsResponse};
+use crate::models::responses::{ErrorResponse, MinimumQuantityResponse, StockErrorResponse};
+use crate::services::{is_restock, queue_restock_notifications};
+use crate::utils::{format_datetime, format_money, local_datetime};
 
 // Function to find a product by ID
-pub async fn find_product_by_id(
+pub async fn find_product_by_id<C: ConnectionTrait>(
     product_id: Uuid,
-    db: &DatabaseConnection,
+    db: &C,
 ) -> Result<Option<products::Model>, sea_orm::DbErr> {
     products::Entity::find()
         .filter(products::Column::Id.eq(product_id))
@@ -17,10 +25,40 @@ pub async fn find_product_by_id(
         .await
 }
 
+// Function to count a product page view. The increment happens in the `UPDATE` itself, so
+// concurrent views are never lost; returns the product as it is afterwards, or `None`
+pub async fn record_product_view<C: ConnectionTrait>(
+    product_id: Uuid,
+    db: &C,
+) -> Result<Option<products::Model>, sea_orm::DbErr> {
+    let updated = products::Entity::update_many()
+        .col_expr(products::Column::ViewCount, Expr::col(products::Column::ViewCount).add(1))
+        .filter(products::Column::Id.eq(product_id))
+        .exec_with_returning(db)
+        .await?;
+    Ok(updated.into_iter().next())
+}
+
+// Filter matching products that are flagged available and inside their sale window at `now`
+pub fn available_at(now: DateTimeWithTimeZone) -> Condition {
+    Condition::all()
+        .add(products::Column::IsAvailable.eq(true))
+        .add(
+            Condition::any()
+                .add(products::Column::AvailableFrom.is_null())
+                .add(products::Column::AvailableFrom.lte(now)),
+        )
+        .add(
+            Condition::any()
+                .add(products::Column::AvailableUntil.is_null())
+                .add(products::Column::AvailableUntil.gt(now)),
+        )
+}
+
 // Function to handle product validation and return the appropriate HTTP response
-pub async fn validate_product_exists(
+pub async fn validate_product_exists<C: ConnectionTrait>(
     product_id: Uuid,
-    db: &DatabaseConnection,
+    db: &C,
 ) -> Result<(), HttpResponse> {
     match find_product_by_id(product_id, db).await {
         Ok(None) => {
@@ -36,3 +74,1087 @@ pub async fn validate_product_exists(
         }
     }
 }
+
+// Function to reject a product's vendor id that doesn't match a vendor with 400 Bad Request
+pub async fn validate_vendor_exists<C: ConnectionTrait>(
+    vendor_id: Option<Uuid>,
+    db: &C,
+) -> Result<(), HttpResponse> {
+    let Some(vendor_id) = vendor_id else {
+        return Ok(());
+    };
+    match vendors::Entity::find_by_id(vendor_id).one(db).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(HttpResponse::BadRequest().json(ErrorResponse {
+            detail: "No vendor found with this ID.".to_string(),
+        })),
+        Err(e) => Err(HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while checking vendor: {}", e),
+        })),
+    }
+}
+
+// Reason a product can't be added to a cart in the requested quantity
+pub enum PurchaseError {
+    Unavailable,
+    BelowMinimum { min_order_qty: i32 },
+    InsufficientStock { max_allowed_qty: i32 },
+}
+
+impl PurchaseError {
+    pub fn detail(&self) -> String {
+        match self {
+            PurchaseError::Unavailable => "This product is currently unavailable.".to_string(),
+            PurchaseError::BelowMinimum { min_order_qty } => format!(
+                "This product is sold in quantities of at least {}.",
+                min_order_qty
+            ),
+            PurchaseError::InsufficientStock { max_allowed_qty } => format!(
+                "Not enough stock. You can have at most {} of this product in your cart.",
+                max_allowed_qty
+            ),
+        }
+    }
+
+    pub fn into_response(self) -> HttpResponse {
+        let detail = self.detail();
+        match self {
+            PurchaseError::Unavailable => HttpResponse::Conflict().json(ErrorResponse { detail }),
+            PurchaseError::BelowMinimum { min_order_qty } => {
+                HttpResponse::BadRequest().json(MinimumQuantityResponse {
+                    detail,
+                    min_order_qty,
+                })
+            }
+            PurchaseError::InsufficientStock { max_allowed_qty } => {
+                HttpResponse::Conflict().json(StockErrorResponse {
+                    detail,
+                    max_allowed_qty,
+                })
+            }
+        }
+    }
+}
+
+// Function to check a product can be bought in the requested total quantity
+pub fn check_purchasable(product: &products::Model, requested_qty: i32) -> Result<(), PurchaseError> {
+    if !product.is_available_at(local_datetime()) {
+        return Err(PurchaseError::Unavailable);
+    }
+    if requested_qty < product.min_order_qty {
+        return Err(PurchaseError::BelowMinimum {
+            min_order_qty: product.min_order_qty,
+        });
+    }
+    if requested_qty > product.stock_quantity {
+        return Err(PurchaseError::InsufficientStock {
+            max_allowed_qty: product.stock_quantity.max(0),
+        });
+    }
+    Ok(())
+}
+
+// Function to validate that a product exists, is available and has enough stock
+// for the requested total quantity (what the cart row would hold afterwards)
+pub async fn validate_product_purchasable<C: ConnectionTrait>(
+    product_id: Uuid,
+    requested_qty: i32,
+    db: &C,
+) -> Result<products::Model, HttpResponse> {
+    match find_product_by_id(product_id, db).await {
+        Ok(None) => Err(HttpResponse::Conflict().json(ErrorResponse {
+            detail: "No product found with this ID.".to_string(),
+        })),
+        Ok(Some(product)) => match check_purchasable(&product, requested_qty) {
+            Ok(()) => Ok(product),
+            Err(e) => Err(e.into_response()),
+        },
+        Err(e) => Err(HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while checking product: {}", e),
+        })),
+    }
+}
+
+// Function to find one of a product's variants; variants of other products are not matched
+pub async fn find_product_variant<C: ConnectionTrait>(
+    product_id: Uuid,
+    variant_id: Uuid,
+    db: &C,
+) -> Result<Option<product_variants::Model>, sea_orm::DbErr> {
+    product_variants::Entity::find()
+        .filter(product_variants::Column::Id.eq(variant_id))
+        .filter(product_variants::Column::ProductId.eq(product_id))
+        .one(db)
+        .await
+}
+
+// Function to check a variant can be bought: availability comes from the product, stock from the variant
+pub fn check_variant_purchasable(
+    product: &products::Model,
+    variant: &product_variants::Model,
+    requested_qty: i32,
+) -> Result<(), PurchaseError> {
+    if !product.is_available_at(local_datetime()) {
+        return Err(PurchaseError::Unavailable);
+    }
+    if requested_qty < product.min_order_qty {
+        return Err(PurchaseError::BelowMinimum {
+            min_order_qty: product.min_order_qty,
+        });
+    }
+    if requested_qty > variant.stock_quantity {
+        return Err(PurchaseError::InsufficientStock {
+            max_allowed_qty: variant.stock_quantity.max(0),
+        });
+    }
+    Ok(())
+}
+
+// Function to validate a cart line (the base product, or one of its variants) for the
+// requested total quantity. Returns the price to snapshot on the cart row.
+pub async fn validate_cart_line_purchasable<C: ConnectionTrait>(
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    requested_qty: i32,
+    db: &C,
+) -> Result<Decimal, HttpResponse> {
+    let Some(variant_id) = variant_id else {
+        return validate_product_purchasable(product_id, requested_qty, db)
+            .await
+            .map(|product| product.price);
+    };
+
+    let product = match find_product_by_id(product_id, db).await {
+        Ok(Some(product)) => product,
+        Ok(None) => {
+            return Err(HttpResponse::Conflict().json(ErrorResponse {
+                detail: "No product found with this ID.".to_string(),
+            }));
+        }
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError().json(ErrorResponse {
+                detail: format!("Database error while checking product: {}", e),
+            }));
+        }
+    };
+
+    match find_product_variant(product_id, variant_id, db).await {
+        Ok(Some(variant)) => match check_variant_purchasable(&product, &variant, requested_qty) {
+            Ok(()) => Ok(variant.effective_price(&product)),
+            Err(e) => Err(e.into_response()),
+        },
+        Ok(None) => Err(HttpResponse::Conflict().json(ErrorResponse {
+            detail: "No variant found with this ID for this product.".to_string(),
+        })),
+        Err(e) => Err(HttpResponse::InternalServerError().json(ErrorResponse {
+            detail: format!("Database error while checking product variant: {}", e),
+        })),
+    }
+}
+
+// Function to record a product price change in `price_history`; does nothing when the price
+// is unchanged. Call it in the same transaction as the update
+pub async fn record_price_change<C: ConnectionTrait>(
+    product_id: Uuid,
+    old_price: Decimal,
+    new_price: Decimal,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<(), sea_orm::DbErr> {
+    if old_price == new_price {
+        return Ok(());
+    }
+
+    price_history::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        product_id: Set(Some(product_id)),
+        old_price: Set(old_price),
+        new_price: Set(new_price),
+        changed_at: Set(now),
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+// Function to list a product's price changes, newest first
+pub async fn fetch_price_history<C: ConnectionTrait>(
+    product_id: Uuid,
+    db: &C,
+) -> Result<Vec<price_history::Model>, sea_orm::DbErr> {
+    price_history::Entity::find()
+        .filter(price_history::Column::ProductId.eq(product_id))
+        .order_by_desc(price_history::Column::ChangedAt)
+        .all(db)
+        .await
+}
+
+// Largest price a `numeric(10, 2)` column holds
+fn max_product_price() -> Decimal {
+    Decimal::new(9_999_999_999, 2)
+}
+
+// Function to check a price is positive, has at most two decimal places and fits the
+// `numeric(10, 2)` price column
+pub fn validate_product_price(price: Decimal) -> Result<(), String> {
+    if price <= Decimal::ZERO {
+        return Err("must be greater than 0".to_string());
+    }
+    if price.normalize().scale() > 2 {
+        return Err("can have at most two decimal places".to_string());
+    }
+    if price > max_product_price() {
+        return Err(format!("can be at most {}", max_product_price()));
+    }
+    Ok(())
+}
+
+// Function to change a price by a percentage, rounded to cents (half away from zero).
+// Returns `None` if the result overflows
+pub fn apply_percent_change(price: Decimal, percent_change: Decimal) -> Option<Decimal> {
+    let factor = Decimal::ONE_HUNDRED
+        .checked_add(percent_change)?
+        .checked_div(Decimal::ONE_HUNDRED)?;
+    price
+        .checked_mul(factor)
+        .map(|price| price.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero))
+}
+
+// Outcome of a bulk price update
+pub enum PriceUpdateOutcome {
+    Applied(Vec<PriceChange>),
+    ProductsNotFound(Vec<Uuid>),
+    CategoryEmpty,
+    Invalid(String),
+}
+
+// Function to reprice several products at once. Every new price is worked out and validated
+// before anything is written, so one bad price rejects the whole batch; the products are
+// locked until the surrounding transaction ends
+pub async fn apply_price_update<C: ConnectionTrait>(
+    request: PriceUpdateRequest,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<PriceUpdateOutcome, sea_orm::DbErr> {
+    let mut planned = Vec::new();
+
+    match request {
+        PriceUpdateRequest::Prices(prices) => {
+            let ids: Vec<Uuid> = prices.iter().map(|change| change.id).collect();
+            let found: HashMap<Uuid, products::Model> = products::Entity::find()
+                .filter(products::Column::Id.is_in(ids.clone()))
+                .lock_exclusive()
+                .all(db)
+                .await?
+                .into_iter()
+                .map(|product| (product.id, product))
+                .collect();
+
+            let missing: Vec<Uuid> = ids.into_iter().filter(|id| !found.contains_key(id)).collect();
+            if !missing.is_empty() {
+                return Ok(PriceUpdateOutcome::ProductsNotFound(missing));
+            }
+
+            for change in prices {
+                planned.push((found[&change.id].clone(), change.price));
+            }
+        }
+        PriceUpdateRequest::Percent { category, percent_change } => {
+            let products = products::Entity::find()
+                .filter(
+                    Expr::expr(Func::lower(Expr::col(products::Column::Category)))
+                        .eq(category.trim().to_lowercase()),
+                )
+                .order_by_asc(products::Column::ProductName)
+                .lock_exclusive()
+                .all(db)
+                .await?;
+
+            if products.is_empty() {
+                return Ok(PriceUpdateOutcome::CategoryEmpty);
+            }
+
+            for product in products {
+                let Some(new_price) = apply_percent_change(product.price, percent_change) else {
+                    return Ok(PriceUpdateOutcome::Invalid(format!(
+                        "The new price of '{}' is out of range.",
+                        product.product_name
+                    )));
+                };
+                planned.push((product, new_price));
+            }
+        }
+    }
+
+    for (product, new_price) in &planned {
+        if let Err(reason) = validate_product_price(*new_price) {
+            return Ok(PriceUpdateOutcome::Invalid(format!(
+                "The new price of '{}' ({}) {}.",
+                product.product_name, new_price, reason
+            )));
+        }
+    }
+
+    let mut changes = Vec::with_capacity(planned.len());
+    for (product, new_price) in planned {
+        products::Entity::update_many()
+            .col_expr(products::Column::Price, Expr::value(new_price))
+            .col_expr(products::Column::Version, Expr::col(products::Column::Version).add(1))
+            .col_expr(products::Column::UpdatedAt, Expr::value(now))
+            .filter(products::Column::Id.eq(product.id))
+            .exec(db)
+            .await?;
+        record_price_change(product.id, product.price, new_price, now, db).await?;
+
+        changes.push(PriceChange {
+            id: product.id,
+            product_name: product.product_name,
+            old_price: product.price,
+            new_price,
+        });
+    }
+
+    Ok(PriceUpdateOutcome::Applied(changes))
+}
+
+// Outcome of applying a stock delta to a product
+pub enum StockAdjustmentOutcome {
+    Applied(stock_adjustments::Model),
+    ProductNotFound,
+    InsufficientStock(i32),
+}
+
+// Function to atomically apply a stock delta and record it in the audit trail.
+// The UPDATE only matches when the resulting stock stays non-negative, so
+// concurrent adjustments can never drive it below zero. A decrease that sells
+// out an available product also hides it and marks it sold out, in the same
+// statement; an increase shows a sold-out product again. Products hidden by
+// hand stay hidden either way.
+pub async fn adjust_product_stock<C: ConnectionTrait>(
+    product_id: Uuid,
+    delta: i32,
+    reason: String,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<StockAdjustmentOutcome, sea_orm::DbErr> {
+    // SET expressions see the row as it was before the update
+    let resulting_qty = || Expr::expr(Expr::col(products::Column::StockQuantity).add(delta));
+    let mut update = products::Entity::update_many()
+        .col_expr(
+            products::Column::StockQuantity,
+            Expr::col(products::Column::StockQuantity).add(delta),
+        )
+        .col_expr(products::Column::UpdatedAt, Expr::value(now));
+    if delta < 0 {
+        let sells_out = || resulting_qty().lte(0).and(Expr::col(products::Column::IsAvailable).eq(true));
+        update = update
+            .col_expr(
+                products::Column::IsAvailable,
+                Expr::case(sells_out(), false)
+                    .finally(Expr::col(products::Column::IsAvailable))
+                    .into(),
+            )
+            .col_expr(
+                products::Column::SoldOut,
+                Expr::case(sells_out(), true)
+                    .finally(Expr::col(products::Column::SoldOut))
+                    .into(),
+            );
+    } else if delta > 0 {
+        let restocks = || resulting_qty().gt(0).and(Expr::col(products::Column::SoldOut).eq(true));
+        update = update
+            .col_expr(
+                products::Column::IsAvailable,
+                Expr::case(restocks(), true)
+                    .finally(Expr::col(products::Column::IsAvailable))
+                    .into(),
+            )
+            .col_expr(
+                products::Column::SoldOut,
+                Expr::case(restocks(), false)
+                    .finally(Expr::col(products::Column::SoldOut))
+                    .into(),
+            );
+    }
+
+    let updated = update
+        .filter(products::Column::Id.eq(product_id))
+        .filter(products::Column::StockQuantity.gte(-delta))
+        .exec_with_returning(db)
+        .await?;
+
+    let product = match updated.into_iter().next() {
+        Some(product) => product,
+        None => {
+            return Ok(match find_product_by_id(product_id, db).await? {
+                Some(product) => StockAdjustmentOutcome::InsufficientStock(product.stock_quantity),
+                None => StockAdjustmentOutcome::ProductNotFound,
+            });
+        }
+    };
+
+    let adjustment = stock_adjustments::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        product_id: Set(Some(product_id)),
+        delta: Set(delta),
+        reason: Set(reason),
+        resulting_qty: Set(product.stock_quantity),
+        created_at: Set(now),
+    }
+    .insert(db)
+    .await?;
+
+    if is_restock(product.stock_quantity - delta, product.stock_quantity) {
+        queue_restock_notifications(&product, now, db).await?;
+    }
+
+    Ok(StockAdjustmentOutcome::Applied(adjustment))
+}
+
+// Outcome of applying a stock delta to a product variant
+pub enum VariantStockOutcome {
+    Applied,
+    VariantNotFound,
+    InsufficientStock(i32),
+}
+
+// Function to atomically apply a stock delta to a variant, with the same conditional UPDATE as
+// `adjust_product_stock`, so concurrent checkouts can't drive it below zero. Variants carry
+// their own stock, so the base product's stock and availability are left alone.
+pub async fn adjust_variant_stock<C: ConnectionTrait>(
+    variant_id: Uuid,
+    delta: i32,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<VariantStockOutcome, sea_orm::DbErr> {
+    let updated = product_variants::Entity::update_many()
+        .col_expr(
+            product_variants::Column::StockQuantity,
+            Expr::col(product_variants::Column::StockQuantity).add(delta),
+        )
+        .col_expr(product_variants::Column::UpdatedAt, Expr::value(now))
+        .filter(product_variants::Column::Id.eq(variant_id))
+        .filter(product_variants::Column::StockQuantity.gte(-delta))
+        .exec_with_returning(db)
+        .await?;
+
+    if !updated.is_empty() {
+        return Ok(VariantStockOutcome::Applied);
+    }
+    Ok(match product_variants::Entity::find_by_id(variant_id).one(db).await? {
+        Some(variant) => VariantStockOutcome::InsufficientStock(variant.stock_quantity),
+        None => VariantStockOutcome::VariantNotFound,
+    })
+}
+
+// Columns written by `GET /products/export.csv`, in order
+const PRODUCT_CSV_HEADER: [&str; 8] = [
+    "id",
+    "product_name",
+    "description",
+    "price",
+    "category",
+    "is_available",
+    "img_url",
+    "created_at",
+];
+
+// Function to serialize a page of products as CSV rows, optionally preceded by the header row
+pub fn write_products_csv(products: &[products::Model], with_header: bool) -> Result<Vec<u8>, csv::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    if with_header {
+        writer.write_record(PRODUCT_CSV_HEADER)?;
+    }
+
+    for product in products {
+        writer.write_record([
+            product.id.to_string(),
+            product.product_name.clone(),
+            product.description.clone(),
+            format_money(f64::try_from(product.price).unwrap_or_default()),
+            product.category.clone(),
+            product.is_available.to_string(),
+            product.img_url.clone(),
+            format_datetime(product.created_at),
+        ])?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| csv::Error::from(e.into_error()))
+}
+
+// A CSV row that passed validation, ready to be written
+pub struct ValidatedImportRow {
+    pub product_name: String,
+    pub description: String,
+    pub price: Decimal,
+    pub category: String,
+    pub img_url: String,
+    pub is_available: bool,
+    pub stock_quantity: Option<i32>,
+}
+
+// Function to load every category name, lowercased, mapped to its stored spelling
+pub async fn fetch_category_names<C: ConnectionTrait>(db: &C) -> Result<HashMap<String, String>, sea_orm::DbErr> {
+    Ok(categories::Entity::find()
+        .filter(categories::Column::DeletedAt.is_null())
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|category| (category.name.to_lowercase(), category.name))
+        .collect())
+}
+
+// Function to check an import row has a name, a positive price and a known category
+pub fn validate_import_row(
+    row: ProductImportRow,
+    category_names: &HashMap<String, String>,
+) -> Result<ValidatedImportRow, String> {
+    let product_name = row.product_name.trim().to_string();
+    if product_name.is_empty() {
+        return Err("Product name is required.".to_string());
+    }
+
+    let price = match row.price.trim().parse::<Decimal>() {
+        Ok(price) if price > Decimal::ZERO => price,
+        Ok(_) => return Err("Price must be greater than 0.".to_string()),
+        Err(_) => return Err(format!("Invalid price '{}'.", row.price.trim())),
+    };
+
+    let category = match category_names.get(&row.category.trim().to_lowercase()) {
+        Some(category) => category.clone(),
+        None => return Err(format!("Unknown category '{}'.", row.category.trim())),
+    };
+
+    if row.stock_quantity.is_some_and(|qty| qty < 0) {
+        return Err("Stock quantity cannot be negative.".to_string());
+    }
+
+    Ok(ValidatedImportRow {
+        product_name,
+        description: row.description,
+        price,
+        category,
+        img_url: row.img_url,
+        is_available: row.is_available.unwrap_or(true),
+        stock_quantity: row.stock_quantity,
+    })
+}
+
+// Function to create a product from an import row, or update the one with the same name
+pub async fn upsert_product_by_name<C: ConnectionTrait>(
+    row: ValidatedImportRow,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<ImportRowStatus, sea_orm::DbErr> {
+    let existing_product = products::Entity::find()
+        .filter(products::Column::ProductName.eq(row.product_name.as_str()))
+        .one(db)
+        .await?;
+
+    match existing_product {
+        Some(existing_product) => {
+            let version = existing_product.version;
+            record_price_change(existing_product.id, existing_product.price, row.price, now, db).await?;
+            // Matched by name, so the slug can stay as it is
+            let mut product_active_model: products::ActiveModel = existing_product.into();
+            product_active_model.description = Set(row.description);
+            product_active_model.price = Set(row.price);
+            product_active_model.category = Set(row.category);
+            product_active_model.img_url = Set(row.img_url);
+            if product_active_model.is_available.as_ref() != &row.is_available {
+                product_active_model.sold_out = Set(false);
+            }
+            product_active_model.is_available = Set(row.is_available);
+            if let Some(stock_quantity) = row.stock_quantity {
+                product_active_model.stock_quantity = Set(stock_quantity);
+            }
+            product_active_model.version = Set(version + 1);
+            product_active_model.updated_at = Set(now);
+            product_active_model.update(db).await?;
+            Ok(ImportRowStatus::Updated)
+        }
+        None => {
+            let slug = slugify_unique(&row.product_name, None, db).await?;
+            products::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                product_name: Set(row.product_name),
+                slug: Set(slug),
+                description: Set(row.description),
+                price: Set(row.price),
+                category: Set(row.category),
+                img_url: Set(row.img_url),
+                is_available: Set(row.is_available),
+                sold_out: Set(false),
+                stock_quantity: Set(row.stock_quantity.unwrap_or(0)),
+                min_order_qty: Set(1),
+                is_featured: Set(false),
+                available_from: Set(None),
+                available_until: Set(None),
+                version: Set(1),
+                view_count: Set(0),
+                vendor_id: Set(None),
+                created_at: Set(now),
+                updated_at: Set(now),
+            }
+            .insert(db)
+            .await?;
+            Ok(ImportRowStatus::Created)
+        }
+    }
+}
+
+// Function to turn a product name into a URL slug: lowercase, with every run of characters
+// other than letters and digits collapsed into a single dash. Unicode letters are kept as-is.
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    if slug.ends_with('-') {
+        slug.pop();
+    }
+    if slug.is_empty() {
+        slug.push_str("product");
+    }
+    slug
+}
+
+// Function to pick a free slug for a product name, appending `-2`, `-3`, ... on collision.
+// `exclude_id` is the product being renamed, so it doesn't collide with its own slug.
+pub async fn slugify_unique<C: ConnectionTrait>(
+    name: &str,
+    exclude_id: Option<Uuid>,
+    db: &C,
+) -> Result<String, sea_orm::DbErr> {
+    let base = slugify(name);
+
+    let mut query = products::Entity::find()
+        .select_only()
+        .column(products::Column::Slug)
+        .filter(
+            Condition::any()
+                .add(products::Column::Slug.eq(base.as_str()))
+                .add(products::Column::Slug.starts_with(format!("{}-", base))),
+        );
+    if let Some(exclude_id) = exclude_id {
+        query = query.filter(products::Column::Id.ne(exclude_id));
+    }
+    let taken: Vec<String> = query.into_tuple().all(db).await?;
+
+    if !taken.contains(&base) {
+        return Ok(base);
+    }
+    let mut suffix = 2;
+    while taken.contains(&format!("{}-{}", base, suffix)) {
+        suffix += 1;
+    }
+    Ok(format!("{}-{}", base, suffix))
+}
+
+// Function to list a product's gallery images in display order
+pub async fn fetch_product_images<C: ConnectionTrait>(
+    product_id: Uuid,
+    db: &C,
+) -> Result<Vec<product_images::Model>, sea_orm::DbErr> {
+    product_images::Entity::find()
+        .filter(product_images::Column::ProductId.eq(product_id))
+        .order_by_asc(product_images::Column::SortOrder)
+        .order_by_asc(product_images::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+// Function to fill in the gallery of each product response with a single query
+pub async fn attach_product_galleries<C: ConnectionTrait>(
+    responses: Vec<ProductsResponse>,
+    db: &C,
+) -> Result<Vec<ProductsResponse>, sea_orm::DbErr> {
+    if responses.is_empty() {
+        return Ok(responses);
+    }
+
+    let product_ids: Vec<Uuid> = responses.iter().map(|response| response.id).collect();
+    let mut galleries: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for image in product_images::Entity::find()
+        .filter(product_images::Column::ProductId.is_in(product_ids))
+        .order_by_asc(product_images::Column::SortOrder)
+        .order_by_asc(product_images::Column::CreatedAt)
+        .all(db)
+        .await?
+    {
+        galleries.entry(image.product_id).or_default().push(image.url);
+    }
+
+    Ok(responses
+        .into_iter()
+        .map(|response| {
+            let gallery = galleries.remove(&response.id).unwrap_or_default();
+            response.with_gallery(gallery)
+        })
+        .collect())
+}
+
+// Function to append an image to the end of a product's gallery
+pub async fn add_product_image<C: ConnectionTrait>(
+    product_id: Uuid,
+    url: String,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<product_images::Model, sea_orm::DbErr> {
+    let last_sort_order = product_images::Entity::find()
+        .select_only()
+        .column_as(Expr::col(product_images::Column::SortOrder).max(), "last_sort_order")
+        .filter(product_images::Column::ProductId.eq(product_id))
+        .into_tuple::<Option<i32>>()
+        .one(db)
+        .await?
+        .flatten();
+
+    product_images::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        product_id: Set(product_id),
+        url: Set(url),
+        sort_order: Set(last_sort_order.map_or(0, |sort_order| sort_order + 1)),
+        created_at: Set(now),
+    }
+    .insert(db)
+    .await
+}
+
+// Function to rewrite a product's gallery order; `image_ids` must be that product's images
+pub async fn set_product_image_order<C: ConnectionTrait>(
+    product_id: Uuid,
+    image_ids: &[Uuid],
+    db: &C,
+) -> Result<(), sea_orm::DbErr> {
+    for (sort_order, image_id) in image_ids.iter().enumerate() {
+        product_images::Entity::update_many()
+            .col_expr(product_images::Column::SortOrder, Expr::value(sort_order as i32))
+            .filter(product_images::Column::Id.eq(*image_id))
+            .filter(product_images::Column::ProductId.eq(product_id))
+            .exec(db)
+            .await?;
+    }
+    Ok(())
+}
+
+// Function to list a product's variants, oldest first
+pub async fn fetch_product_variants<C: ConnectionTrait>(
+    product_id: Uuid,
+    db: &C,
+) -> Result<Vec<product_variants::Model>, sea_orm::DbErr> {
+    product_variants::Entity::find()
+        .filter(product_variants::Column::ProductId.eq(product_id))
+        .order_by_asc(product_variants::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+// Function to check whether a SKU is taken, ignoring `exclude_id` (the variant being updated)
+pub async fn sku_exists<C: ConnectionTrait>(
+    sku: &str,
+    exclude_id: Option<Uuid>,
+    db: &C,
+) -> Result<bool, sea_orm::DbErr> {
+    let mut query = product_variants::Entity::find()
+        .filter(Expr::expr(Func::lower(Expr::col(product_variants::Column::Sku))).eq(sku.to_lowercase()));
+    if let Some(exclude_id) = exclude_id {
+        query = query.filter(product_variants::Column::Id.ne(exclude_id));
+    }
+    Ok(query.one(db).await?.is_some())
+}
+
+// Queries shorter than this skip full-text search; stemming makes 1-2 letter terms useless
+const MIN_FULL_TEXT_QUERY_LEN: usize = 3;
+
+// Most suggestions `GET /products/suggest` returns
+const MAX_PRODUCT_SUGGESTIONS: u64 = 10;
+
+// Function to escape `%`, `_` and `\` so user input matches literally in a LIKE pattern
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Function to suggest available products whose name starts with `prefix` (case-insensitive),
+// most viewed first. Matches `LOWER(product_name) LIKE 'prefix%'`, which the
+// `idx_products_product_name_prefix` index serves
+pub async fn suggest_products<C: ConnectionTrait>(
+    prefix: &str,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<Vec<ProductSuggestion>, sea_orm::DbErr> {
+    products::Entity::find()
+        .select_only()
+        .column(products::Column::Id)
+        .column(products::Column::ProductName)
+        .filter(
+            Expr::expr(Func::lower(Expr::col(products::Column::ProductName)))
+                .like(format!("{}%", escape_like(&prefix.to_lowercase()))),
+        )
+        .filter(available_at(now))
+        .order_by_desc(products::Column::ViewCount)
+        .order_by_asc(products::Column::ProductName)
+        .limit(MAX_PRODUCT_SUGGESTIONS)
+        .into_model::<ProductSuggestion>()
+        .all(db)
+        .await
+}
+
+// Function to turn free text into a `to_tsquery` expression that ANDs prefix matches of each word.
+// Anything but letters and digits is dropped, so user input can't produce tsquery syntax errors.
+pub fn build_tsquery(q: &str) -> Option<String> {
+    let terms: Vec<String> = q
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("{}:*", term.to_lowercase()))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" & "))
+    }
+}
+
+// Function to search products by name and description, most relevant first.
+// Very short queries fall back to a product name prefix match with a rank of 0.
+pub async fn search_products<C: ConnectionTrait>(
+    q: &str,
+    limit: u64,
+    db: &C,
+) -> Result<Vec<(products::Model, f64)>, sea_orm::DbErr> {
+    let q = q.trim();
+    let tsquery = build_tsquery(q).filter(|_| q.chars().count() >= MIN_FULL_TEXT_QUERY_LEN);
+
+    let Some(tsquery) = tsquery else {
+        return Ok(products::Entity::find()
+            .filter(products::Column::ProductName.starts_with(q.to_lowercase()))
+            .order_by_asc(products::Column::ProductName)
+            .limit(limit)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|product| (product, 0.0))
+            .collect());
+    };
+
+    let ranked: Vec<(Uuid, f64)> = products::Entity::find()
+        .select_only()
+        .column(products::Column::Id)
+        .column_as(
+            Expr::cust_with_values(
+                "ts_rank(search_vector, to_tsquery('english', $1))::float8",
+                [tsquery.clone()],
+            ),
+            "rank",
+        )
+        .filter(Expr::cust_with_values(
+            "search_vector @@ to_tsquery('english', $1)",
+            [tsquery],
+        ))
+        .order_by_desc(Expr::cust("rank"))
+        .order_by_asc(products::Column::ProductName)
+        .limit(limit)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let mut products_by_id: HashMap<Uuid, products::Model> = products::Entity::find()
+        .filter(products::Column::Id.is_in(ranked.iter().map(|(id, _)| *id)))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|product| (product.id, product))
+        .collect();
+
+    Ok(ranked
+        .into_iter()
+        .filter_map(|(id, rank)| products_by_id.remove(&id).map(|product| (product, rank)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{insert_product, test_db};
+    use sea_orm::{DatabaseConnection, IntoActiveModel, TransactionTrait};
+
+    async fn insert_product_with_slug(db: &DatabaseConnection, name: &str, slug: &str) -> products::Model {
+        let mut product = insert_product(db, name, Decimal::new(10000, 2), 10).await.into_active_model();
+        product.slug = Set(slug.to_string());
+        product.update(db).await.unwrap()
+    }
+
+    #[test]
+    fn slugify_kebab_cases_names() {
+        assert_eq!(slugify("Fresh Bangus  (1kg)"), "fresh-bangus-1kg");
+        assert_eq!(slugify("  --Tilapia--  "), "tilapia");
+        assert_eq!(slugify("!!!"), "product");
+    }
+
+    #[test]
+    fn slugify_keeps_unicode_letters() {
+        assert_eq!(slugify("Ube Halayá"), "ube-halayá");
+        assert_eq!(slugify("Ñame ÁRABE"), "ñame-árabe");
+        assert_eq!(slugify("魚 市場"), "魚-市場");
+    }
+
+    #[actix_web::test]
+    async fn slugify_unique_uses_the_plain_slug_when_free() {
+        let Some(db) = test_db().await else { return };
+        insert_product_with_slug(&db, "Bangus", "bangus").await;
+
+        assert_eq!(slugify_unique("Tilapia", None, &db).await.unwrap(), "tilapia");
+        // Only exact matches and numbered variants of the same base count as collisions
+        insert_product_with_slug(&db, "Bangus Belly", "bangus-belly").await;
+        assert_eq!(slugify_unique("Bangus", None, &db).await.unwrap(), "bangus-2");
+    }
+
+    #[actix_web::test]
+    async fn slugify_unique_appends_the_next_free_suffix() {
+        let Some(db) = test_db().await else { return };
+        insert_product_with_slug(&db, "Galunggong", "galunggong").await;
+        insert_product_with_slug(&db, "Galunggong", "galunggong-2").await;
+        insert_product_with_slug(&db, "Galunggong", "galunggong-4").await;
+
+        assert_eq!(slugify_unique("Galunggong", None, &db).await.unwrap(), "galunggong-3");
+    }
+
+    #[actix_web::test]
+    async fn slugify_unique_ignores_the_product_being_renamed() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product_with_slug(&db, "Dalagang Bukid", "dalagang-bukid").await;
+
+        assert_eq!(
+            slugify_unique("Dalagang Bukid", Some(product.id), &db).await.unwrap(),
+            "dalagang-bukid"
+        );
+        assert_eq!(slugify_unique("Dalagang Bukid", None, &db).await.unwrap(), "dalagang-bukid-2");
+    }
+
+    #[actix_web::test]
+    async fn slugify_unique_dedupes_unicode_names() {
+        let Some(db) = test_db().await else { return };
+        insert_product_with_slug(&db, "Ube Halayá", "ube-halayá").await;
+
+        assert_eq!(slugify_unique("UBE halayá", None, &db).await.unwrap(), "ube-halayá-2");
+    }
+
+    async fn stored_product(product_id: Uuid, db: &DatabaseConnection) -> products::Model {
+        find_product_by_id(product_id, db).await.unwrap().unwrap()
+    }
+
+    async fn adjustments_of(product_id: Uuid, db: &DatabaseConnection) -> Vec<stock_adjustments::Model> {
+        stock_adjustments::Entity::find()
+            .filter(stock_adjustments::Column::ProductId.eq(product_id))
+            .all(db)
+            .await
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn only_one_of_two_racing_decrements_takes_the_last_unit() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Bangus", Decimal::new(15000, 2), 1).await;
+        let now = local_datetime();
+
+        // Each in its own transaction, as two checkouts would be
+        let take_one = || {
+            db.transaction::<_, StockAdjustmentOutcome, sea_orm::DbErr>(move |txn| {
+                Box::pin(async move { adjust_product_stock(product.id, -1, "Checkout".to_string(), now, txn).await })
+            })
+        };
+        let outcomes = futures_util::future::join_all([take_one(), take_one()]).await;
+
+        let applied = outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome, Ok(StockAdjustmentOutcome::Applied(_))))
+            .count();
+        let refused = outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome, Ok(StockAdjustmentOutcome::InsufficientStock(0))))
+            .count();
+        assert_eq!((applied, refused), (1, 1));
+
+        let stored = stored_product(product.id, &db).await;
+        assert_eq!(stored.stock_quantity, 0);
+        assert!(!stored.is_available);
+        assert_eq!(adjustments_of(product.id, &db).await.len(), 1);
+    }
+
+    #[actix_web::test]
+    async fn refuses_a_decrement_that_would_go_negative() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Tilapia", Decimal::new(9000, 2), 3).await;
+        let now = local_datetime();
+
+        let outcome = adjust_product_stock(product.id, -5, "Spoilage".to_string(), now, &db).await.unwrap();
+        assert!(matches!(outcome, StockAdjustmentOutcome::InsufficientStock(3)));
+        let stored = stored_product(product.id, &db).await;
+        assert_eq!(stored.stock_quantity, 3);
+        assert!(stored.is_available);
+        assert!(adjustments_of(product.id, &db).await.is_empty());
+
+        let missing = adjust_product_stock(Uuid::new_v4(), -1, "Spoilage".to_string(), now, &db).await.unwrap();
+        assert!(matches!(missing, StockAdjustmentOutcome::ProductNotFound));
+    }
+
+    #[actix_web::test]
+    async fn records_each_applied_adjustment_with_the_resulting_stock() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Pusit", Decimal::new(32000, 2), 3).await;
+        let now = local_datetime();
+
+        let StockAdjustmentOutcome::Applied(sold) =
+            adjust_product_stock(product.id, -3, "Checkout".to_string(), now, &db).await.unwrap()
+        else {
+            panic!("the decrement should apply");
+        };
+        assert_eq!(sold.resulting_qty, 0);
+        assert!(!stored_product(product.id, &db).await.is_available);
+
+        let StockAdjustmentOutcome::Applied(restocked) =
+            adjust_product_stock(product.id, 10, "Delivery".to_string(), now, &db).await.unwrap()
+        else {
+            panic!("the restock should apply");
+        };
+        assert_eq!(restocked.resulting_qty, 10);
+        let stored = stored_product(product.id, &db).await;
+        assert_eq!(stored.stock_quantity, 10);
+        assert!(stored.is_available);
+        assert!(!stored.sold_out);
+    }
+
+    #[actix_web::test]
+    async fn a_restock_leaves_a_product_hidden_by_hand_hidden() {
+        let Some(db) = test_db().await else { return };
+        let product = insert_product(&db, "Alimango", Decimal::new(65000, 2), 0).await;
+        let mut hidden = product.into_active_model();
+        hidden.is_available = Set(false);
+        let product = hidden.update(&db).await.unwrap();
+        let now = local_datetime();
+
+        let StockAdjustmentOutcome::Applied(_) =
+            adjust_product_stock(product.id, 4, "Delivery".to_string(), now, &db).await.unwrap()
+        else {
+            panic!("the restock should apply");
+        };
+        let stored = stored_product(product.id, &db).await;
+        assert_eq!(stored.stock_quantity, 4);
+        assert!(!stored.is_available);
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Length of a rate limit window; limits are per minute
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+// Who a request is counted against
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    // An authenticated user, wherever they connect from
+    User(String),
+    // An anonymous client, by IP address
+    Ip(IpAddr),
+    // Anonymous requests whose address isn't known; they share one quota
+    UnknownClient,
+}
+
+// Request counts of the current window for each key
+struct RateLimitWindows {
+    counts: HashMap<RateLimitKey, (Instant, u32)>,
+    // When expired windows were last dropped from `counts`
+    swept_at: Instant,
+}
+
+// In-memory request counts per key, in fixed one-minute windows
+pub struct RateLimiter {
+    windows: Mutex<RateLimitWindows>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self {
+            windows: Mutex::new(RateLimitWindows {
+                counts: HashMap::new(),
+                swept_at: Instant::now(),
+            }),
+        }
+    }
+
+    // Function to count a request against `key`, allowing at most `limit` per window. Returns how
+    // long until the window resets when the limit is already used up; the refused request isn't
+    // counted. Expired windows are dropped at most once per window rather than on every call.
+    pub fn check(&self, key: RateLimitKey, limit: u32) -> Result<(), Duration> {
+        self.check_at(key, limit, Instant::now())
+    }
+
+    fn check_at(&self, key: RateLimitKey, limit: u32, now: Instant) -> Result<(), Duration> {
+        let mut windows = match self.windows.lock() {
+            Ok(windows) => windows,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if now.duration_since(windows.swept_at) >= RATE_LIMIT_WINDOW {
+            windows.counts.retain(|_, (started_at, _)| now.duration_since(*started_at) < RATE_LIMIT_WINDOW);
+            windows.swept_at = now;
+        }
+
+        let (started_at, count) = windows.counts.entry(key).or_insert((now, 0));
+        // A window left over from before the last sweep is reset in place
+        if now.duration_since(*started_at) >= RATE_LIMIT_WINDOW {
+            *started_at = now;
+            *count = 0;
+        }
+        if *count >= limit {
+            return Err(RATE_LIMIT_WINDOW.saturating_sub(now.duration_since(*started_at)));
+        }
+        *count += 1;
+        Ok(())
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(last: u8) -> RateLimitKey {
+        RateLimitKey::Ip(IpAddr::from([203, 0, 113, last]))
+    }
+
+    #[test]
+    fn refuses_requests_over_the_limit_until_the_window_resets() {
+        let limiter = RateLimiter::new();
+        let start = Instant::now();
+
+        assert!(limiter.check_at(ip(1), 2, start).is_ok());
+        assert!(limiter.check_at(ip(1), 2, start + Duration::from_secs(1)).is_ok());
+        assert_eq!(
+            limiter.check_at(ip(1), 2, start + Duration::from_secs(15)),
+            Err(Duration::from_secs(45))
+        );
+        // Other clients have their own quota
+        assert!(limiter.check_at(ip(2), 2, start + Duration::from_secs(15)).is_ok());
+
+        assert!(limiter.check_at(ip(1), 2, start + RATE_LIMIT_WINDOW).is_ok());
+    }
+
+    #[test]
+    fn counts_users_apart_from_the_ip_they_share() {
+        let limiter = RateLimiter::new();
+        let start = Instant::now();
+        let user = |user_id: &str| RateLimitKey::User(user_id.to_string());
+
+        assert!(limiter.check_at(user("ana"), 1, start).is_ok());
+        assert!(limiter.check_at(user("ana"), 1, start).is_err());
+        assert!(limiter.check_at(user("ben"), 1, start).is_ok());
+        assert!(limiter.check_at(ip(1), 1, start).is_ok());
+    }
+
+    #[test]
+    fn drops_expired_windows_once_per_window() {
+        let limiter = RateLimiter::new();
+        let start = limiter.windows.lock().unwrap().swept_at;
+        let clients = |limiter: &RateLimiter| limiter.windows.lock().unwrap().counts.len();
+
+        limiter.check_at(ip(1), 10, start).unwrap();
+        limiter.check_at(ip(2), 10, start + Duration::from_secs(30)).unwrap();
+        assert_eq!(clients(&limiter), 2);
+
+        // The first sweep drops ip(1) and keeps ip(2), whose window is still running
+        limiter.check_at(ip(3), 10, start + Duration::from_secs(61)).unwrap();
+        assert_eq!(clients(&limiter), 2);
+
+        // ip(2)'s window has run out, but the next sweep isn't due until 121s
+        limiter.check_at(ip(4), 10, start + Duration::from_secs(100)).unwrap();
+        assert_eq!(clients(&limiter), 3);
+
+        // Only ip(4) is still inside its window at the second sweep
+        limiter.check_at(ip(5), 10, start + Duration::from_secs(121)).unwrap();
+        assert_eq!(clients(&limiter), 2);
+    }
+}
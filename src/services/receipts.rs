@@ -0,0 +1,100 @@
+use crate::models::orders::OrderReceipt;
+use crate::utils::escape_html;
+use sea_orm::prelude::Decimal;
+use sea_orm::ActiveEnum;
+
+/// Printable receipt page; `{{name}}` placeholders are filled in by `render_receipt_html`.
+const RECEIPT_TEMPLATE: &str = include_str!("../templates/receipt.html");
+
+// Function to render a receipt as a standalone HTML page for printing. Every value is escaped.
+pub fn render_receipt_html(receipt: &OrderReceipt) -> String {
+    let store_details = [receipt.store.address.clone(), receipt.store.tin.as_ref().map(|tin| format!("TIN {}", tin))]
+        .into_iter()
+        .flatten()
+        .map(|detail| escape_html(&detail))
+        .collect::<Vec<_>>()
+        .join("<br>");
+
+    let payment = match &receipt.payment_reference {
+        Some(reference) => format!(
+            "{} ({}, ref. {})",
+            receipt.payment_method.to_value(),
+            receipt.payment_status.to_value(),
+            reference
+        ),
+        None => format!("{} ({})", receipt.payment_method.to_value(), receipt.payment_status.to_value()),
+    };
+
+    let delivery_address = receipt
+        .delivery_address
+        .as_ref()
+        .map(|address| {
+            let mut lines = vec![
+                format!("{} · {}", address.recipient_name, address.phone),
+                address.line1.clone(),
+                format!("{}, {}", address.barangay, address.city),
+            ];
+            lines.extend(address.notes.clone());
+            let lines: Vec<String> = lines.iter().map(|line| escape_html(line)).collect();
+            format!("<p>Deliver to:<br>{}</p>", lines.join("<br>"))
+        })
+        .unwrap_or_default();
+
+    let items = receipt
+        .items
+        .iter()
+        .map(|item| {
+            format!(
+                "    <tr><td>{}</td><td class=\"amount\">{}</td><td class=\"amount\">{}</td><td class=\"amount\">{}</td></tr>",
+                escape_html(&item.product_name),
+                item.quantity,
+                escape_html(&item.unit_price_display),
+                escape_html(&item.line_total_display)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let vat_rate = (receipt.vat.rate * Decimal::ONE_HUNDRED).normalize().to_string();
+
+    let values = [
+        ("store_name", escape_html(&receipt.store.name)),
+        ("store_details", store_details),
+        ("order_number", escape_html(&receipt.order_number)),
+        ("placed_at", escape_html(&receipt.placed_at)),
+        ("payment", escape_html(&payment)),
+        ("issued_at", escape_html(&receipt.issued_at)),
+        ("delivery_address", delivery_address),
+        ("items", items),
+        ("subtotal", escape_html(&receipt.display.subtotal_display)),
+        ("vat_rate", vat_rate),
+        ("vat", escape_html(&receipt.vat.amount_display)),
+        ("delivery_fee", escape_html(&receipt.display.delivery_fee_display)),
+        ("discount", escape_html(&receipt.display.discount_display)),
+        ("total", escape_html(&receipt.display.total_display)),
+    ];
+
+    // One pass over the template, so placeholder-like text inside a value is left alone
+    let mut html = String::with_capacity(RECEIPT_TEMPLATE.len() * 2);
+    let mut rest = RECEIPT_TEMPLATE;
+    while let Some(start) = rest.find("{{") {
+        html.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = &after[..end];
+                match values.iter().find(|(key, _)| *key == name) {
+                    Some((_, value)) => html.push_str(value),
+                    None => html.push_str(&rest[start..start + end + 4]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                html.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    html.push_str(rest);
+    html
+}
@@ -0,0 +1,103 @@
+use crate::models::{products, restock_subscriptions};
+use crate::utils::AppLogger;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, Set};
+use uuid::Uuid;
+
+/// Outcome of `POST /products/{product_id}/restock-subscriptions`.
+pub enum RestockSubscribeOutcome {
+    Subscribed(restock_subscriptions::Model),
+    AlreadySubscribed,
+}
+
+// Function to sign a user up to hear when a product is back in stock.
+// A user who was already notified about this product gets their subscription re-armed
+// (with the new email) instead of a second row.
+pub async fn subscribe_to_restock<C: ConnectionTrait>(
+    user_id: &str,
+    product_id: Uuid,
+    email: &str,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<RestockSubscribeOutcome, sea_orm::DbErr> {
+    let existing = restock_subscriptions::Entity::find()
+        .filter(restock_subscriptions::Column::UserId.eq(user_id))
+        .filter(restock_subscriptions::Column::ProductId.eq(product_id))
+        .one(db)
+        .await?;
+
+    let subscription = match existing {
+        Some(existing) if existing.notified_at.is_none() => return Ok(RestockSubscribeOutcome::AlreadySubscribed),
+        Some(existing) => {
+            let mut subscription: restock_subscriptions::ActiveModel = existing.into();
+            subscription.email = Set(email.to_string());
+            subscription.created_at = Set(now);
+            subscription.notified_at = Set(None);
+            subscription.update(db).await?
+        }
+        None => {
+            restock_subscriptions::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                user_id: Set(user_id.to_string()),
+                product_id: Set(product_id),
+                email: Set(email.to_string()),
+                created_at: Set(now),
+                notified_at: Set(None),
+            }
+            .insert(db)
+            .await?
+        }
+    };
+
+    Ok(RestockSubscribeOutcome::Subscribed(subscription))
+}
+
+// Function to remove a user's restock subscription for a product. Returns whether there was one.
+pub async fn unsubscribe_from_restock<C: ConnectionTrait>(
+    user_id: &str,
+    product_id: Uuid,
+    db: &C,
+) -> Result<bool, sea_orm::DbErr> {
+    let result = restock_subscriptions::Entity::delete_many()
+        .filter(restock_subscriptions::Column::UserId.eq(user_id))
+        .filter(restock_subscriptions::Column::ProductId.eq(product_id))
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+// Function to queue a restock notice for everyone still waiting on `product`, stamping
+// `notified_at` so nobody is told twice. Returns how many notices were queued. Notices are only
+// logged for now; an email integration can pick them up from the log or from the stamped rows.
+pub async fn queue_restock_notifications<C: ConnectionTrait>(
+    product: &products::Model,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<usize, sea_orm::DbErr> {
+    let notified = restock_subscriptions::Entity::update_many()
+        .col_expr(restock_subscriptions::Column::NotifiedAt, Expr::value(now))
+        .filter(restock_subscriptions::Column::ProductId.eq(product.id))
+        .filter(restock_subscriptions::Column::NotifiedAt.is_null())
+        .exec_with_returning(db)
+        .await?;
+
+    let logger = AppLogger::default();
+    for subscription in &notified {
+        logger.info_single(
+            &format!(
+                "📬 Restock notice queued for {} ({}): '{}' is back with {} in stock",
+                subscription.email, subscription.user_id, product.product_name, product.stock_quantity
+            ),
+            "RESTOCK",
+        );
+    }
+
+    Ok(notified.len())
+}
+
+// Function to tell whether a stock change from `previous_qty` to `new_qty` brings a product back in
+// stock.
+pub fn is_restock(previous_qty: i32, new_qty: i32) -> bool {
+    previous_qty <= 0 && new_qty > 0
+}
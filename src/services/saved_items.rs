@@ -0,0 +1,79 @@
+use crate::models::{carts, products, saved_items};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+use uuid::Uuid;
+
+// Function to save a cart line for later with its quantity, price and note. Saving a line that is
+// already saved replaces the earlier copy. The caller removes the cart row in the same transaction.
+pub async fn insert_saved_item<C: ConnectionTrait>(
+    cart_item: &carts::Model,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<saved_items::Model, sea_orm::DbErr> {
+    saved_items::Entity::insert(saved_items::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(cart_item.user_id.clone()),
+        product_id: Set(cart_item.product_id),
+        variant_id: Set(cart_item.variant_id),
+        total_qty: Set(cart_item.total_qty),
+        unit_price: Set(cart_item.unit_price),
+        note: Set(cart_item.note.clone()),
+        created_at: Set(now),
+    })
+    .on_conflict(
+        OnConflict::columns([
+            saved_items::Column::UserId,
+            saved_items::Column::ProductId,
+            saved_items::Column::VariantId,
+        ])
+        .update_columns([
+            saved_items::Column::TotalQty,
+            saved_items::Column::UnitPrice,
+            saved_items::Column::Note,
+            saved_items::Column::CreatedAt,
+        ])
+        .to_owned(),
+    )
+    .exec_with_returning(db)
+    .await
+}
+
+// Function to delete a saved item and return it, or `None` if it isn't saved. Of two concurrent
+// calls only one gets the row back.
+pub async fn take_saved_item<C: ConnectionTrait>(
+    user_id: &str,
+    product_id: Uuid,
+    variant_id: Option<Uuid>,
+    db: &C,
+) -> Result<Option<saved_items::Model>, sea_orm::DbErr> {
+    let variant_filter = match variant_id {
+        Some(variant_id) => saved_items::Column::VariantId.eq(variant_id),
+        None => saved_items::Column::VariantId.is_null(),
+    };
+
+    Ok(saved_items::Entity::delete_many()
+        .filter(saved_items::Column::UserId.eq(user_id))
+        .filter(saved_items::Column::ProductId.eq(product_id))
+        .filter(variant_filter)
+        .exec_with_returning(db)
+        .await?
+        .into_iter()
+        .next())
+}
+
+// Function to list a user's saved items with product details, most recently saved first.
+pub async fn fetch_saved_items<C: ConnectionTrait>(
+    user_id: &str,
+    db: &C,
+) -> Result<Vec<(saved_items::Model, products::Model)>, sea_orm::DbErr> {
+    Ok(saved_items::Entity::find()
+        .filter(saved_items::Column::UserId.eq(user_id))
+        .find_also_related(products::Entity)
+        .order_by_desc(saved_items::Column::CreatedAt)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(item, product)| product.map(|product| (item, product)))
+        .collect())
+}
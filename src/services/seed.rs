@@ -0,0 +1,131 @@
+use crate::models::{categories, products};
+use crate::services::{create_new_cart_item, find_existing_cart_item, slugify_unique};
+use sea_orm::prelude::{DateTimeWithTimeZone, Decimal};
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, Set};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Cart owner the demo cart rows are created for.
+pub const DEMO_CART_USER_ID: &str = "demo-user";
+
+const DEMO_CATEGORIES: [&str; 4] = ["Seafood", "Meat", "Vegetables", "Fruits"];
+
+// (name, description, price in centavos, category, stock)
+const DEMO_PRODUCTS: [(&str, &str, i64, &str, i32); 9] = [
+    ("Bangus", "Fresh milkfish from Dagupan, cleaned on request. Price per kilo.", 18000, "Seafood", 40),
+    ("Tilapia", "Live-harvested tilapia, about 3 pieces per kilo.", 14000, "Seafood", 50),
+    ("Galunggong", "Round scad, perfect for frying. Price per kilo.", 22000, "Seafood", 30),
+    ("Pork Liempo", "Skin-on pork belly, sliced for grilling. Price per kilo.", 38000, "Meat", 25),
+    ("Whole Chicken", "Dressed native chicken, about 1.2 kg each.", 22000, "Meat", 20),
+    ("Kangkong", "Water spinach, one bundle.", 2500, "Vegetables", 100),
+    ("Ampalaya", "Bitter gourd. Price per kilo.", 8000, "Vegetables", 60),
+    ("Calamansi", "Philippine lime, 250 g pack.", 6000, "Fruits", 80),
+    ("Carabao Mango", "Sweet Guimaras mangoes. Price per kilo.", 15000, "Fruits", 45),
+];
+
+// (product name, quantity)
+const DEMO_CART: [(&str, i32); 3] = [("Bangus", 2), ("Kangkong", 3), ("Calamansi", 1)];
+
+/// What `seed_demo_data` inserted; anything already present is skipped and not counted.
+#[derive(Debug, Default, Serialize)]
+pub struct SeedSummary {
+    pub categories_created: usize,
+    pub products_created: usize,
+    pub cart_items_created: usize,
+}
+
+// Function to insert demo categories, products and a cart for `DEMO_CART_USER_ID`.
+// Idempotent: categories and products are matched by name and cart rows by product,
+// and existing rows are left untouched, so running it again creates nothing.
+pub async fn seed_demo_data<C: ConnectionTrait>(
+    now: DateTimeWithTimeZone,
+    max_cart_item_qty: i32,
+    db: &C,
+) -> Result<SeedSummary, sea_orm::DbErr> {
+    let mut summary = SeedSummary::default();
+
+    for name in DEMO_CATEGORIES {
+        let existing = categories::Entity::find()
+            .filter(categories::Column::Name.eq(name))
+            .one(db)
+            .await?;
+        if existing.is_none() {
+            categories::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                name: Set(name.to_string()),
+                parent_id: Set(None),
+                created_at: Set(now),
+                updated_at: Set(now),
+                deleted_at: Set(None),
+            }
+            .insert(db)
+            .await?;
+            summary.categories_created += 1;
+        }
+    }
+
+    for (name, description, price_centavos, category, stock_quantity) in DEMO_PRODUCTS {
+        let existing = products::Entity::find()
+            .filter(products::Column::ProductName.eq(name))
+            .one(db)
+            .await?;
+        if existing.is_some() {
+            continue;
+        }
+
+        let slug = slugify_unique(name, None, db).await?;
+        products::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            product_name: Set(name.to_string()),
+            slug: Set(slug),
+            description: Set(description.to_string()),
+            price: Set(Decimal::new(price_centavos, 2)),
+            category: Set(category.to_string()),
+            img_url: Set(format!("https://placehold.co/600x400?text={}", name.replace(' ', "+"))),
+            is_available: Set(true),
+            sold_out: Set(false),
+            stock_quantity: Set(stock_quantity),
+            min_order_qty: Set(1),
+            is_featured: Set(false),
+            available_from: Set(None),
+            available_until: Set(None),
+            version: Set(1),
+            view_count: Set(0),
+            vendor_id: Set(None),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(db)
+        .await?;
+        summary.products_created += 1;
+    }
+
+    for (name, qty) in DEMO_CART {
+        let Some(product) = products::Entity::find()
+            .filter(products::Column::ProductName.eq(name))
+            .one(db)
+            .await?
+        else {
+            continue;
+        };
+
+        let existing_cart = find_existing_cart_item(DEMO_CART_USER_ID.to_string(), product.id, None, db).await?;
+        if existing_cart.is_none() {
+            create_new_cart_item(
+                DEMO_CART_USER_ID.to_string(),
+                product.id,
+                None,
+                qty,
+                product.price,
+                None,
+                max_cart_item_qty,
+                now,
+                db,
+            )
+            .await?;
+            summary.cart_items_created += 1;
+        }
+    }
+
+    Ok(summary)
+}
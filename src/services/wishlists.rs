@@ -0,0 +1,60 @@
+use crate::models::{products, wishlists};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+use uuid::Uuid;
+
+// Function to add a product to a user's wishlist; a product already on it is left as-is.
+// Returns the wishlist entry either way.
+pub async fn add_to_wishlist<C: ConnectionTrait>(
+    user_id: &str,
+    product_id: Uuid,
+    now: DateTimeWithTimeZone,
+    db: &C,
+) -> Result<wishlists::Model, sea_orm::DbErr> {
+    wishlists::Entity::insert(wishlists::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id.to_string()),
+        product_id: Set(product_id),
+        created_at: Set(now),
+    })
+    .on_conflict(
+        OnConflict::columns([wishlists::Column::UserId, wishlists::Column::ProductId])
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(db)
+    .await?;
+
+    find_wishlist_item(user_id, product_id, db)
+        .await?
+        .ok_or(sea_orm::DbErr::RecordNotFound("wishlist item".to_string()))
+}
+
+pub async fn find_wishlist_item<C: ConnectionTrait>(
+    user_id: &str,
+    product_id: Uuid,
+    db: &C,
+) -> Result<Option<wishlists::Model>, sea_orm::DbErr> {
+    wishlists::Entity::find()
+        .filter(wishlists::Column::UserId.eq(user_id))
+        .filter(wishlists::Column::ProductId.eq(product_id))
+        .one(db)
+        .await
+}
+
+// Function to list a user's wishlist with product details, most recently added first.
+pub async fn fetch_wishlist<C: ConnectionTrait>(
+    user_id: &str,
+    db: &C,
+) -> Result<Vec<(wishlists::Model, products::Model)>, sea_orm::DbErr> {
+    Ok(wishlists::Entity::find()
+        .filter(wishlists::Column::UserId.eq(user_id))
+        .find_also_related(products::Entity)
+        .order_by_desc(wishlists::Column::CreatedAt)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(item, product)| product.map(|product| (item, product)))
+        .collect())
+}
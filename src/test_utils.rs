@@ -0,0 +1,91 @@
+use crate::models::{carts, products};
+use crate::utils::local_datetime;
+use migration::{Migrator, MigratorTrait};
+use sea_orm::prelude::Decimal;
+use sea_orm::{ActiveModelTrait, ConnectOptions, ConnectionTrait, Database, DatabaseConnection, Set};
+use uuid::Uuid;
+
+/// Connects to a fresh schema in the `TEST_DATABASE_URL` database with every migration applied,
+/// so tests can run side by side without seeing each other's rows. The schemas are left behind,
+/// so point the variable at a throwaway database.
+///
+/// Returns `None` when `TEST_DATABASE_URL` isn't set; database tests return early in that case,
+/// except on CI (see `test_service_url`).
+pub async fn test_db() -> Option<DatabaseConnection> {
+    let url = test_service_url("TEST_DATABASE_URL")?;
+    let schema = format!("test_{}", Uuid::new_v4().simple());
+
+    let admin = Database::connect(&url).await.expect("connect to TEST_DATABASE_URL");
+    admin
+        .execute_unprepared(&format!("CREATE SCHEMA {}", schema))
+        .await
+        .expect("create test schema");
+    admin.close().await.ok();
+
+    let mut options = ConnectOptions::new(url);
+    options.set_schema_search_path(schema).max_connections(10).sqlx_logging(false);
+    let db = Database::connect(options).await.expect("connect to the test schema");
+    Migrator::up(&db, None).await.expect("run migrations");
+    Some(db)
+}
+
+/// Reads the URL of a service the tests need. Unset, the test is skipped locally but fails on CI
+/// (where `CI` is set), so a misconfigured pipeline can't pass by running nothing.
+pub fn test_service_url(name: &str) -> Option<String> {
+    match std::env::var(name) {
+        Ok(url) => Some(url),
+        Err(_) if std::env::var_os("CI").is_some() => panic!("{} must be set on CI", name),
+        Err(_) => {
+            eprintln!("{} is not set; skipping test", name);
+            None
+        }
+    }
+}
+
+/// Inserts an available product with `stock` units at `price`.
+pub async fn insert_product(db: &DatabaseConnection, name: &str, price: Decimal, stock: i32) -> products::Model {
+    let now = local_datetime();
+    products::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        product_name: Set(name.to_string()),
+        slug: Set(format!("{}-{}", name.to_lowercase().replace(' ', "-"), Uuid::new_v4().simple())),
+        description: Set(format!("Fresh {}", name)),
+        price: Set(price),
+        category: Set("Seafood".to_string()),
+        img_url: Set(String::new()),
+        is_available: Set(true),
+        sold_out: Set(false),
+        stock_quantity: Set(stock),
+        min_order_qty: Set(1),
+        is_featured: Set(false),
+        available_from: Set(None),
+        available_until: Set(None),
+        version: Set(1),
+        view_count: Set(0),
+        vendor_id: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(db)
+    .await
+    .expect("insert product")
+}
+
+/// Puts `qty` of `product` in `user_id`'s active cart at the product's current price.
+pub async fn insert_cart_item(db: &DatabaseConnection, user_id: &str, product: &products::Model, qty: i32) -> carts::Model {
+    let now = local_datetime();
+    carts::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id.to_string()),
+        product_id: Set(product.id),
+        variant_id: Set(None),
+        total_qty: Set(qty),
+        unit_price: Set(product.price),
+        note: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(db)
+    .await
+    .expect("insert cart item")
+}
@@ -1,24 +1,186 @@
 use chrono::{DateTime, Utc};
-use num_format::{Locale, ToFormattedString};
+use chrono_tz::Tz;
+use num_format::Locale;
+use serde::{Deserialize, Deserializer};
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::str::FromStr;
 
+/// Formats an amount with thousands separators and exactly two decimals, e.g. `1,234.50`.
+///
+/// Rounds half away from zero on the amount's shortest decimal form (what it prints as), so
+/// `1.005` gives `1.01` even though its binary value is slightly below, and a carry moves into
+/// the whole part (`1.999` gives `2.00`). Amounts that round to zero never get a minus sign.
+///
+/// The digits are grouped as text, so amounts of any size format without overflowing. `NaN`
+/// and infinities can't be shown as money and come back as `"NaN"`, `"∞"` and `"-∞"`.
 pub fn format_money(amount: f64) -> String {
-    let is_negative = amount < 0.0;
+    if amount.is_nan() {
+        return "NaN".to_string();
+    }
+    if amount.is_infinite() {
+        return if amount > 0.0 { "∞" } else { "-∞" }.to_string();
+    }
+
     let abs_amount = amount.abs();
-    let whole_part = abs_amount.trunc() as u64;
-    let decimal_part = (abs_amount.fract() * 100.0).round() as u64;
+    // Beyond `Decimal`'s range every representable f64 is whole, so there is nothing to round
+    let rounded = match Decimal::from_str(&abs_amount.to_string()) {
+        Ok(decimal) => format!(
+            "{:.2}",
+            decimal.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+        ),
+        Err(_) => format!("{:.2}", abs_amount),
+    };
+    let (whole_part, decimal_part) = rounded.split_once('.').unwrap_or((&rounded, "00"));
 
-    let formatted = format!(
-        "{}.{:02}",
-        whole_part.to_formatted_string(&Locale::en),
-        decimal_part
-    );
+    let formatted = format!("{}.{}", group_thousands(whole_part), decimal_part);
 
-    if is_negative {
+    let is_zero = rounded.bytes().all(|b| b == b'0' || b == b'.');
+    if amount < 0.0 && !is_zero {
         format!("-{}", formatted)
     } else {
         formatted
     }
 }
+
+fn group_thousands(digits: &str) -> String {
+    let separator = Locale::en.separator();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// For `#[serde(default, deserialize_with = "deserialize_nullable")]` fields that tell an
+/// omitted value (`None`) apart from an explicit `null` (`Some(None)`).
+pub fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 pub fn format_datetime<T: Into<DateTime<Utc>>>(datetime: T) -> String {
-    datetime.into().format("%Y-%m-%d %I:%M:%S %p").to_string()
+    datetime.into().format(DateTimeFormat::DateTime.pattern()).to_string()
+}
+
+/// Named patterns for rendering timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateTimeFormat {
+    /// `2025-06-01`
+    Date,
+    /// `2025-06-01 02:30:00 PM`, the pattern `format_datetime` uses.
+    DateTime,
+}
+
+impl DateTimeFormat {
+    pub fn pattern(self) -> &'static str {
+        match self {
+            DateTimeFormat::Date => "%Y-%m-%d",
+            DateTimeFormat::DateTime => "%Y-%m-%d %I:%M:%S %p",
+        }
+    }
+}
+
+/// Like `format_datetime`, but renders the instant in `tz` (e.g. `STORE_TIMEZONE`) whatever
+/// offset it was stored with.
+pub fn format_datetime_in<T: Into<DateTime<Utc>>>(datetime: T, tz: Tz, format: DateTimeFormat) -> String {
+    datetime.into().with_timezone(&tz).format(format.pattern()).to_string()
+}
+
+/// Escapes text for use in HTML element content or a quoted attribute.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Checks an email address has the `local@domain.tld` shape, without whitespace.
+pub fn is_valid_email(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.split('.').count() >= 2
+                && domain.split('.').all(|label| !label.is_empty())
+                && email.len() <= 254
+                && !email.chars().any(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+/// Checks an image URL is an absolute `http(s)` URL without whitespace.
+pub fn is_valid_image_url(url: &str) -> bool {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"));
+
+    match rest {
+        Some(rest) => {
+            let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+            !host.is_empty() && url.len() <= 2048 && !url.chars().any(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounding_carries_into_the_whole_part() {
+        assert_eq!(format_money(0.999), "1.00");
+        assert_eq!(format_money(1.999), "2.00");
+        assert_eq!(format_money(999.995), "1,000.00");
+    }
+
+    #[test]
+    fn rounds_half_cents_away_from_zero_as_printed() {
+        assert_eq!(format_money(1.005), "1.01");
+        assert_eq!(format_money(-1.005), "-1.01");
+        assert_eq!(format_money(0.1 + 0.2), "0.30");
+    }
+
+    #[test]
+    fn amounts_rounding_to_zero_have_no_sign() {
+        assert_eq!(format_money(-0.001), "0.00");
+        assert_eq!(format_money(-0.0), "0.00");
+        assert_eq!(format_money(0.0), "0.00");
+    }
+
+    #[test]
+    fn groups_thousands_in_large_amounts() {
+        assert_eq!(format_money(1234567.891), "1,234,567.89");
+        assert_eq!(format_money(-98765432.1), "-98,765,432.10");
+        assert_eq!(format_money(100.0), "100.00");
+    }
+
+    #[test]
+    fn non_finite_amounts_come_back_as_sentinels() {
+        assert_eq!(format_money(f64::NAN), "NaN");
+        assert_eq!(format_money(f64::INFINITY), "∞");
+        assert_eq!(format_money(f64::NEG_INFINITY), "-∞");
+    }
+
+    #[test]
+    fn amounts_past_u64_format_without_overflowing() {
+        assert_eq!(format_money(1e20), "100,000,000,000,000,000,000.00");
+        // Past `Decimal`'s range too, so the f64 digits are grouped as they are
+        assert_eq!(format_money(-1e30), "-1,000,000,000,000,000,019,884,624,838,656.00");
+    }
 }
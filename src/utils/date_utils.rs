@@ -1,12 +1,15 @@
 use chrono::{FixedOffset, Offset, TimeZone, Utc};
-use chrono_tz::Asia::Manila;
+use chrono_tz::Tz;
 use sea_orm::prelude::DateTimeWithTimeZone;
 
+/// The store's local time zone, used for `local_datetime` and for displaying timestamps.
+pub const STORE_TIMEZONE: Tz = chrono_tz::Asia::Manila;
+
 pub fn local_datetime() -> DateTimeWithTimeZone {
-    let manila_time = Utc::now().with_timezone(&Manila);
+    let manila_time = Utc::now().with_timezone(&STORE_TIMEZONE);
     let offset_seconds = manila_time.offset().fix().local_minus_utc();
     let manila_offset = FixedOffset::east_opt(offset_seconds).unwrap();
-    let now: DateTimeWithTimeZone = manila_offset.from_utc_datetime(&manila_time.naive_local()).into();
+    let now: DateTimeWithTimeZone = manila_offset.from_utc_datetime(&manila_time.naive_local());
 
     now
 }
\ No newline at end of file
@@ -0,0 +1,67 @@
+use colourful_logger::Logger;
+use std::env;
+use tracing_subscriber::EnvFilter;
+
+/// Output format for application logs, selected with the `LOG_FORMAT` env var.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable colourful lines, meant for local development.
+    Pretty,
+    /// One JSON object per line, meant for log aggregators.
+    Json,
+}
+
+impl LogFormat {
+    pub fn from_env() -> Self {
+        match env::var("LOG_FORMAT") {
+            Ok(value) if value.eq_ignore_ascii_case("json") => LogFormat::Json,
+            _ => LogFormat::Pretty,
+        }
+    }
+}
+
+/// Installs the global `tracing` subscriber for the given format.
+///
+/// `RUST_LOG` controls the filter and defaults to `info`.
+pub fn init_tracing(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let result = match format {
+        LogFormat::Json => tracing_subscriber::fmt()
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_env_filter(filter)
+            .try_init(),
+        LogFormat::Pretty => tracing_subscriber::fmt().with_env_filter(filter).try_init(),
+    };
+
+    if let Err(e) = result {
+        eprintln!("❌ Failed to initialize tracing subscriber: {}", e);
+    }
+}
+
+/// Thin wrapper over `colourful_logger` that emits structured `tracing`
+/// events instead when `LOG_FORMAT=json`.
+pub struct AppLogger {
+    format: LogFormat,
+    logger: Logger,
+}
+
+impl Default for AppLogger {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::from_env(),
+            logger: Logger::default(),
+        }
+    }
+}
+
+impl AppLogger {
+    pub fn info_single(&self, message: &str, tag: &str) {
+        match self.format {
+            LogFormat::Json => tracing::info!(tag, "{}", message),
+            LogFormat::Pretty => self.logger.info_single(message, tag),
+        }
+    }
+}
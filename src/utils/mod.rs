@@ -1,5 +1,7 @@
 pub mod common_utils;
 mod date_utils;
+mod logger;
 
 pub use common_utils::*;
 pub use date_utils::*;
+pub use logger::*;